@@ -0,0 +1,37 @@
+use ross_dsl::decompiler::decompile_with_devices;
+use ross_dsl::lower::Lowerer;
+use ross_dsl::Parser;
+
+fn main() {
+    let text = "
+        device kitchen_switch = 0x0003;
+        device kitchen_light = 0x000a;
+
+        send BUTTON_PRESSED_EVENT_CODE from kitchen_switch to kitchen_light;
+    ";
+
+    let program = match Parser::parse_program(text) {
+        Ok(program) => program,
+        Err(err) => {
+            println!("{}", err.render(text));
+            return;
+        }
+    };
+
+    let mut lowerer = Lowerer::new();
+
+    for statement in program.statements.iter() {
+        if let Err(err) = lowerer.lower_statement(statement) {
+            println!("{:?}", err);
+            return;
+        }
+    }
+
+    let devices = lowerer.devices().clone();
+    let config = lowerer.finish();
+
+    match decompile_with_devices(&config, &devices) {
+        Ok(source) => println!("{}", source),
+        Err(err) => println!("{}", err),
+    }
+}
@@ -1,3 +1,4 @@
+use ross_dsl::simulator::{simulate, Event};
 use ross_dsl::Parser;
 
 fn main() {
@@ -27,8 +28,25 @@ fn main() {
         }
     ";
 
-    match Parser::parse(text) {
-        Ok(event_processors) => println!("{:?}", event_processors),
+    let config = match Parser::parse(text) {
+        Ok(config) => config,
+        Err(err) => return println!("{}", err.render(text)),
+    };
+
+    let events = [
+        Event::button_pressed(0x0002, 0),
+        Event::button_released(0x0002, 0),
+        Event::button_pressed(0x0002, 0),
+    ];
+
+    match simulate(&config, 0x0001, &events) {
+        Ok(steps) => {
+            for (event, step) in events.iter().zip(steps) {
+                println!("{:?}", event);
+                println!("  packets: {:?}", step.packets);
+                println!("  state: {:?}", step.state);
+            }
+        }
         Err(err) => println!("{}", err),
     }
 }
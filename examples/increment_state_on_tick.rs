@@ -15,6 +15,6 @@ fn main() {
 
     match Parser::parse(text) {
         Ok(event_processors) => println!("{:?}", event_processors),
-        Err(err) => println!("{}", err),
+        Err(err) => println!("{}", err.render(text)),
     }
 }
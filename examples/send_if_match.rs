@@ -3,7 +3,7 @@ use ross_dsl::Parser;
 fn main() {
     let text = "
         const device_address = 0x0003~u16;
-        let button_index = 0x00~u8;
+        const button_index = 0x00~u8;
         const receiver_address = 0x000a~u16;
 
 
@@ -15,6 +15,6 @@ fn main() {
 
     match Parser::parse(text) {
         Ok(event_processors) => println!("{:?}", event_processors),
-        Err(err) => println!("{}", err),
+        Err(err) => println!("{}", err.render(text)),
     }
 }
@@ -0,0 +1,245 @@
+use std::collections::BTreeSet;
+
+use ross_config::matcher::Matcher;
+
+use crate::decode::value_equal_to_u16;
+
+/// Whether a matcher can match an event and whether it can fail to.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Satisfiability {
+    Never,
+    Sometimes,
+    Always,
+}
+
+/// The fields of an event that checks can be compared on. `None` stands for any value that no
+/// check compares with.
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Event {
+    event_code: Option<u16>,
+    producer_address: Option<u16>,
+}
+
+/// Decides whether `matcher` can be true and whether it can be false on the events that
+/// `context` can match, or on any event without one.
+///
+/// Only checks of the event code and the producer address are related to each other. Every
+/// other check is assumed to have either result, as it may read a state that other checks
+/// change, so this never reports a condition that depends on the state.
+pub fn satisfiability(matcher: &Matcher, context: Option<&Matcher>) -> Satisfiability {
+    let mut event_codes = BTreeSet::new();
+    let mut producer_addresses = BTreeSet::new();
+
+    for matcher in Some(matcher).into_iter().chain(context) {
+        collect_values(matcher, &mut event_codes, &mut producer_addresses);
+    }
+
+    let event_codes = event_codes.into_iter().map(Some).chain(Some(None));
+    let producer_addresses: Vec<Option<u16>> = producer_addresses
+        .into_iter()
+        .map(Some)
+        .chain(Some(None))
+        .collect();
+
+    let mut can_be_true = false;
+    let mut can_be_false = false;
+
+    for event_code in event_codes {
+        for producer_address in producer_addresses.iter() {
+            let event = Event {
+                event_code,
+                producer_address: *producer_address,
+            };
+
+            if let Some(context) = context {
+                if !outcomes(context, &event).0 {
+                    continue;
+                }
+            }
+
+            let (is_true, is_false) = outcomes(matcher, &event);
+            can_be_true |= is_true;
+            can_be_false |= is_false;
+        }
+    }
+
+    match (can_be_true, can_be_false) {
+        (true, true) => Satisfiability::Sometimes,
+        (true, false) => Satisfiability::Always,
+        // Only when `context` can never be true, which is reported on its own
+        (false, false) => Satisfiability::Sometimes,
+        (false, true) => Satisfiability::Never,
+    }
+}
+
+fn collect_values(
+    matcher: &Matcher,
+    event_codes: &mut BTreeSet<u16>,
+    producer_addresses: &mut BTreeSet<u16>,
+) {
+    match matcher {
+        Matcher::Single { .. } => {
+            if let Some(event_code) = value_equal_to_u16(matcher, "EventCodeExtractor") {
+                event_codes.insert(event_code);
+            }
+
+            if let Some(address) = value_equal_to_u16(matcher, "EventProducerAddressExtractor") {
+                producer_addresses.insert(address);
+            }
+        }
+        Matcher::Not(matcher) => collect_values(matcher, event_codes, producer_addresses),
+        Matcher::And(matcher1, matcher2) | Matcher::Or(matcher1, matcher2) => {
+            collect_values(matcher1, event_codes, producer_addresses);
+            collect_values(matcher2, event_codes, producer_addresses);
+        }
+    }
+}
+
+/// Returns whether `matcher` can be true and whether it can be false on `event`. Every check
+/// that is not on `event` can have either result, independently of the others.
+fn outcomes(matcher: &Matcher, event: &Event) -> (bool, bool) {
+    match matcher {
+        Matcher::Single { .. } => {
+            let result = if let Some(event_code) = value_equal_to_u16(matcher, "EventCodeExtractor")
+            {
+                Some(event.event_code == Some(event_code))
+            } else {
+                value_equal_to_u16(matcher, "EventProducerAddressExtractor")
+                    .map(|address| event.producer_address == Some(address))
+            };
+
+            match result {
+                Some(result) => (result, !result),
+                None => (true, true),
+            }
+        }
+        Matcher::Not(matcher) => {
+            let (is_true, is_false) = outcomes(matcher, event);
+            (is_false, is_true)
+        }
+        Matcher::And(matcher1, matcher2) => {
+            let (is_true1, is_false1) = outcomes(matcher1, event);
+            let (is_true2, is_false2) = outcomes(matcher2, event);
+            (is_true1 && is_true2, is_false1 || is_false2)
+        }
+        Matcher::Or(matcher1, matcher2) => {
+            let (is_true1, is_false1) = outcomes(matcher1, event);
+            let (is_true2, is_false2) = outcomes(matcher2, event);
+            (is_true1 || is_true2, is_false1 && is_false2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ross_config::extractor::{
+        EventCodeExtractor, EventProducerAddressExtractor, NoneExtractor,
+    };
+    use ross_config::filter::{FlipStateFilter, ValueEqualToConstFilter};
+    use ross_config::Value;
+
+    fn event(event_code: u16) -> Matcher {
+        Matcher::Single {
+            extractor: Box::new(EventCodeExtractor::new()),
+            filter: Box::new(ValueEqualToConstFilter::new(Value::U16(event_code))),
+        }
+    }
+
+    fn producer(address: u16) -> Matcher {
+        Matcher::Single {
+            extractor: Box::new(EventProducerAddressExtractor::new()),
+            filter: Box::new(ValueEqualToConstFilter::new(Value::U16(address))),
+        }
+    }
+
+    fn flip() -> Matcher {
+        Matcher::Single {
+            extractor: Box::new(NoneExtractor::new()),
+            filter: Box::new(FlipStateFilter::new(0)),
+        }
+    }
+
+    fn and(matcher1: Matcher, matcher2: Matcher) -> Matcher {
+        Matcher::And(Box::new(matcher1), Box::new(matcher2))
+    }
+
+    fn or(matcher1: Matcher, matcher2: Matcher) -> Matcher {
+        Matcher::Or(Box::new(matcher1), Box::new(matcher2))
+    }
+
+    fn not(matcher: Matcher) -> Matcher {
+        Matcher::Not(Box::new(matcher))
+    }
+
+    #[test]
+    fn sometimes_test() {
+        assert_eq!(
+            satisfiability(&and(event(0x0001), producer(0x0002)), None),
+            Satisfiability::Sometimes
+        );
+        assert_eq!(
+            satisfiability(&or(event(0x0001), event(0x0002)), None),
+            Satisfiability::Sometimes
+        );
+    }
+
+    #[test]
+    fn different_event_codes_test() {
+        assert_eq!(
+            satisfiability(&and(event(0x0001), event(0x0002)), None),
+            Satisfiability::Never
+        );
+        assert_eq!(
+            satisfiability(&and(producer(0x0001), and(flip(), producer(0x0002))), None),
+            Satisfiability::Never
+        );
+    }
+
+    #[test]
+    fn negation_test() {
+        assert_eq!(
+            satisfiability(&and(event(0x0001), not(event(0x0001))), None),
+            Satisfiability::Never
+        );
+        assert_eq!(
+            satisfiability(&or(not(event(0x0001)), event(0x0001)), None),
+            Satisfiability::Always
+        );
+        assert_eq!(
+            satisfiability(&or(not(event(0x0001)), not(event(0x0002))), None),
+            Satisfiability::Always
+        );
+    }
+
+    #[test]
+    fn state_test() {
+        assert_eq!(
+            satisfiability(&and(flip(), not(flip())), None),
+            Satisfiability::Sometimes
+        );
+        assert_eq!(
+            satisfiability(&or(flip(), not(flip())), None),
+            Satisfiability::Sometimes
+        );
+    }
+
+    #[test]
+    fn context_test() {
+        let context = and(event(0x0001), producer(0x0002));
+
+        assert_eq!(
+            satisfiability(&event(0x0001), Some(&context)),
+            Satisfiability::Always
+        );
+        assert_eq!(
+            satisfiability(&producer(0x0003), Some(&context)),
+            Satisfiability::Never
+        );
+        assert_eq!(
+            satisfiability(&flip(), Some(&context)),
+            Satisfiability::Sometimes
+        );
+    }
+}
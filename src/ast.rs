@@ -0,0 +1,537 @@
+use crate::literal::Literal;
+use crate::location::{slice_offset, Location};
+
+/// A byte range in the source text.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Creates a span that starts at `text` and ends at `input`, which are both subslices of
+    /// `source`. The usual use is a parser's input before and after parsing.
+    pub fn new(source: &str, text: &str, input: &str) -> Self {
+        let start = slice_offset(source, text).unwrap_or(source.len());
+        let end = slice_offset(source, input).unwrap_or(source.len());
+
+        Self { start, end }
+    }
+
+    /// Creates a span covering `slice`, which has to be a subslice of `source`.
+    pub fn of(source: &str, slice: &str) -> Self {
+        let start = slice_offset(source, slice).unwrap_or(source.len());
+        let end = (start + slice.len()).min(source.len());
+
+        Self { start, end }
+    }
+
+    pub fn location(&self, file: Option<&str>, source: &str) -> Location {
+        Location::new(file, source, self.start)
+    }
+
+    /// Returns the smallest span covering both `self` and `other`.
+    pub fn join(&self, other: Span) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Name {
+    pub value: String,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expression {
+    Literal {
+        value: Literal,
+        span: Span,
+    },
+    Constant(Name),
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expression>,
+        span: Span,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+        span: Span,
+    },
+    Parenthesized {
+        expression: Box<Expression>,
+        span: Span,
+    },
+}
+
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Literal { span, .. } => *span,
+            Expression::Constant(name) => name.span,
+            Expression::Unary { span, .. } => *span,
+            Expression::Binary { span, .. } => *span,
+            Expression::Parenthesized { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum UnaryOperator {
+    Not,
+}
+
+impl UnaryOperator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOperator::Not => "!",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum BinaryOperator {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    BitAnd,
+    BitXor,
+    BitOr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Rem => "%",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Shl => "<<",
+            BinaryOperator::Shr => ">>",
+            BinaryOperator::BitAnd => "&",
+            BinaryOperator::BitXor => "^",
+            BinaryOperator::BitOr => "|",
+            BinaryOperator::Eq => "==",
+            BinaryOperator::Ne => "!=",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::Le => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::Ge => ">=",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+        }
+    }
+
+    /// Operators with a higher precedence bind tighter. The order is the same as in Rust.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Rem => 9,
+            BinaryOperator::Add | BinaryOperator::Sub => 8,
+            BinaryOperator::Shl | BinaryOperator::Shr => 7,
+            BinaryOperator::BitAnd => 6,
+            BinaryOperator::BitXor => 5,
+            BinaryOperator::BitOr => 4,
+            BinaryOperator::Eq
+            | BinaryOperator::Ne
+            | BinaryOperator::Lt
+            | BinaryOperator::Le
+            | BinaryOperator::Gt
+            | BinaryOperator::Ge => 3,
+            BinaryOperator::And => 2,
+            BinaryOperator::Or => 1,
+        }
+    }
+}
+
+/// A call of an extractor, filter or producer, like `PacketProducer(receiver_address);`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ItemCall {
+    pub name: Name,
+    pub arguments: Vec<Expression>,
+    pub span: Span,
+}
+
+/// A condition of `do`, `send`, `set` and `fire`. `Not`, `And`, `Or` and `Parenthesized` come
+/// from the operator syntax, like `match (event a && producer b) || !tick;`. The outermost
+/// condition spans the whole `match` statement, the ones in it only their own text.
+#[derive(Debug, PartialEq, Clone)]
+pub enum MatchExpr {
+    Event {
+        event_code: Expression,
+        span: Span,
+    },
+    Producer {
+        address: Expression,
+        span: Span,
+    },
+    Tick {
+        span: Span,
+    },
+    Block {
+        block: BlockMatch,
+        span: Span,
+    },
+    Not {
+        operand: Box<MatchExpr>,
+        span: Span,
+    },
+    And {
+        left: Box<MatchExpr>,
+        right: Box<MatchExpr>,
+        span: Span,
+    },
+    Or {
+        left: Box<MatchExpr>,
+        right: Box<MatchExpr>,
+        span: Span,
+    },
+    Parenthesized {
+        match_expr: Box<MatchExpr>,
+        span: Span,
+    },
+}
+
+impl MatchExpr {
+    pub fn span(&self) -> Span {
+        match self {
+            MatchExpr::Event { span, .. } => *span,
+            MatchExpr::Producer { span, .. } => *span,
+            MatchExpr::Tick { span } => *span,
+            MatchExpr::Block { span, .. } => *span,
+            MatchExpr::Not { span, .. } => *span,
+            MatchExpr::And { span, .. } => *span,
+            MatchExpr::Or { span, .. } => *span,
+            MatchExpr::Parenthesized { span, .. } => *span,
+        }
+    }
+
+    pub(crate) fn with_span(mut self, new_span: Span) -> Self {
+        match &mut self {
+            MatchExpr::Event { span, .. }
+            | MatchExpr::Producer { span, .. }
+            | MatchExpr::Tick { span }
+            | MatchExpr::Block { span, .. }
+            | MatchExpr::Not { span, .. }
+            | MatchExpr::And { span, .. }
+            | MatchExpr::Or { span, .. }
+            | MatchExpr::Parenthesized { span, .. } => *span = new_span,
+        }
+
+        self
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum BlockMatch {
+    Single {
+        extractor: Option<ItemCall>,
+        filter: ItemCall,
+        span: Span,
+    },
+    Not {
+        extractor: Option<ItemCall>,
+        filter: ItemCall,
+        span: Span,
+    },
+    Or {
+        children: Vec<BlockMatch>,
+        span: Span,
+    },
+    And {
+        children: Vec<BlockMatch>,
+        span: Span,
+    },
+}
+
+impl BlockMatch {
+    pub fn span(&self) -> Span {
+        match self {
+            BlockMatch::Single { span, .. } => *span,
+            BlockMatch::Not { span, .. } => *span,
+            BlockMatch::Or { span, .. } => *span,
+            BlockMatch::And { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FireExpr {
+    pub extractor: Option<ItemCall>,
+    pub producer: ItemCall,
+    pub condition: Option<MatchExpr>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LetStatement {
+    pub name: Name,
+    /// The type after `:`, which is otherwise that of `value`.
+    pub value_type: Option<Name>,
+    pub value: Expression,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ConstStatement {
+    pub name: Name,
+    pub value: Expression,
+    pub span: Span,
+}
+
+/// `device name = address;`, which names the address of a device.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DeviceStatement {
+    pub name: Name,
+    pub address: Expression,
+    pub span: Span,
+}
+
+/// `event name = event_code;`, which declares an event code next to those of `ross_protocol`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EventStatement {
+    pub name: Name,
+    pub event_code: Expression,
+    pub span: Span,
+}
+
+/// `pub(gateway_addresses) peripheral index family kind(arguments);`, where `family` is `bcm` or
+/// `relay` and `kind` is one of `single`, `rgb`, `rgbw` or `double_exclusive`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PeripheralStatement {
+    pub gateway_addresses: Option<Vec<Expression>>,
+    pub index: Expression,
+    pub family: Name,
+    pub kind: Name,
+    pub arguments: Vec<Expression>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SendStatement {
+    pub event_code: Expression,
+    pub from_address: Expression,
+    pub to_address: Expression,
+    pub condition: Option<MatchExpr>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SetStatement {
+    pub state_variable: Name,
+    pub value: Expression,
+    pub event_code: Expression,
+    pub from_address: Expression,
+    pub condition: Option<MatchExpr>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DoStatement {
+    pub matches: Vec<MatchExpr>,
+    pub fires: Vec<FireExpr>,
+    pub span: Span,
+}
+
+/// `toggle state_variable on event_code from address { on: fire {..}; off: fire {..}; }`, which
+/// flips a `bool` state variable on the event and fires `on` if it became `true` and `off` if it
+/// became `false`. It is the same as a `do` with a `FlipStateFilter` and `fire`s on
+/// `StateEqualToConstFilter`s.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ToggleStatement {
+    pub state_variable: Name,
+    pub event_code: Expression,
+    pub from_address: Expression,
+    pub on: FireExpr,
+    pub off: FireExpr,
+    pub span: Span,
+}
+
+/// `import "path";`, which brings the `const`, `device`, `event` and `peripheral` statements of
+/// another file in.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImportStatement {
+    pub path: String,
+    pub path_span: Span,
+    pub span: Span,
+}
+
+/// `test "name" at address { steps }`, which runs the program on the host and checks what it
+/// does. The device under test has `address`, or 0x0000 without `at`. Tests are not part of the
+/// config.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TestStatement {
+    pub name: String,
+    pub name_span: Span,
+    pub device_address: Option<Expression>,
+    pub steps: Vec<TestStep>,
+    pub span: Span,
+}
+
+/// A step of a `test`. `inject event_code from address with data;` feeds the program an event
+/// whose data fields are `data`, in the order the event code defines them, or that carries no data
+/// without `with`. `expect producer(arguments);` checks that the last injected event made it send
+/// what the producer would and `expect state name == value;` checks a state variable.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TestStep {
+    Inject {
+        event_code: Expression,
+        from_address: Expression,
+        data: Vec<Expression>,
+        span: Span,
+    },
+    ExpectPacket {
+        producer: ItemCall,
+        span: Span,
+    },
+    ExpectState {
+        state_variable: Name,
+        value: Expression,
+        span: Span,
+    },
+}
+
+impl TestStep {
+    pub fn span(&self) -> Span {
+        match self {
+            TestStep::Inject { span, .. } => *span,
+            TestStep::ExpectPacket { span, .. } => *span,
+            TestStep::ExpectState { span, .. } => *span,
+        }
+    }
+
+    pub(crate) fn with_span(mut self, new_span: Span) -> Self {
+        match &mut self {
+            TestStep::Inject { span, .. }
+            | TestStep::ExpectPacket { span, .. }
+            | TestStep::ExpectState { span, .. } => *span = new_span,
+        }
+
+        self
+    }
+}
+
+/// `#![level(lints)]`, which sets the level of `lints` in the whole file, or `#[level(lints)]`,
+/// which sets it in the next statement only. `level` is `allow`, `warn` or `deny`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AttributeStatement {
+    pub inner: bool,
+    pub level: Name,
+    pub lints: Vec<Name>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Statement {
+    Attribute(AttributeStatement),
+    Import(ImportStatement),
+    Let(LetStatement),
+    Const(ConstStatement),
+    Device(DeviceStatement),
+    Event(EventStatement),
+    Peripheral(PeripheralStatement),
+    Send(SendStatement),
+    Set(SetStatement),
+    Do(DoStatement),
+    Toggle(Box<ToggleStatement>),
+    Test(TestStatement),
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Attribute(statement) => statement.span,
+            Statement::Import(statement) => statement.span,
+            Statement::Let(statement) => statement.span,
+            Statement::Const(statement) => statement.span,
+            Statement::Device(statement) => statement.span,
+            Statement::Event(statement) => statement.span,
+            Statement::Peripheral(statement) => statement.span,
+            Statement::Send(statement) => statement.span,
+            Statement::Set(statement) => statement.span,
+            Statement::Do(statement) => statement.span,
+            Statement::Toggle(statement) => statement.span,
+            Statement::Test(statement) => statement.span,
+        }
+    }
+
+    /// Returns the name that a `let`, `const`, `device` or `event` statement declares.
+    pub fn declared_name(&self) -> Option<&Name> {
+        match self {
+            Statement::Let(statement) => Some(&statement.name),
+            Statement::Const(statement) => Some(&statement.name),
+            Statement::Device(statement) => Some(&statement.name),
+            Statement::Event(statement) => Some(&statement.name),
+            _ => None,
+        }
+    }
+}
+
+/// A `//` comment, which the parser otherwise drops. `text` starts at the slashes and has no
+/// trailing whitespace.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+    /// Whether only whitespace precedes the comment on its line.
+    pub own_line: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Program {
+    pub statements: Vec<Statement>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn span_new_test() {
+        let source = "let a = false;input";
+
+        assert_eq!(
+            Span::new(source, source, &source[14..]),
+            Span { start: 0, end: 14 }
+        );
+    }
+
+    #[test]
+    fn span_of_test() {
+        let source = "let a = false;input";
+
+        assert_eq!(Span::of(source, &source[4..5]), Span { start: 4, end: 5 });
+    }
+
+    #[test]
+    fn span_location_test() {
+        let source = "let a = false;\nconst b = true;";
+        let span = Span { start: 21, end: 22 };
+
+        assert_eq!(span.location(None, source), Location::new(None, source, 21));
+    }
+
+    #[test]
+    fn span_join_test() {
+        let span = Span { start: 4, end: 8 }.join(Span { start: 2, end: 6 });
+
+        assert_eq!(span, Span { start: 2, end: 8 });
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::ast::Span;
+use crate::lower::SourceMap;
+use crate::simulator::Match;
+
+/// How many times each event processor of a config matched and each of its creators ran, over
+/// any number of test runs and trace replays of it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Coverage {
+    source_map: SourceMap,
+    event_processor_hits: Vec<u32>,
+    creator_hits: Vec<Vec<u32>>,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CoverageKind {
+    EventProcessor,
+    Creator,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CoverageEntry {
+    pub kind: CoverageKind,
+    pub span: Span,
+    pub hits: u32,
+}
+
+impl Coverage {
+    /// Creates coverage with no hits for a config that `source_map` comes from.
+    pub fn new(source_map: &SourceMap) -> Self {
+        Self {
+            source_map: source_map.clone(),
+            event_processor_hits: vec![0; source_map.event_processors.len()],
+            creator_hits: source_map
+                .creators
+                .iter()
+                .map(|creators| vec![0; creators.len()])
+                .collect(),
+        }
+    }
+
+    /// Records that the event processor at index `event_processor` matched an event, and that
+    /// its creators at `creators` ran.
+    pub fn record(&mut self, event_processor: usize, creators: &[usize]) {
+        self.event_processor_hits[event_processor] += 1;
+
+        for creator in creators.iter() {
+            self.creator_hits[event_processor][*creator] += 1;
+        }
+    }
+
+    pub fn record_matches(&mut self, matches: &[Match]) {
+        for matched in matches.iter() {
+            self.record(matched.event_processor, &matched.creators);
+        }
+    }
+
+    /// Returns the hits of every event processor and creator, ordered by where they start.
+    pub fn entries(&self) -> Vec<CoverageEntry> {
+        let mut entries = vec![];
+
+        for (index, span) in self.source_map.event_processors.iter().enumerate() {
+            entries.push(CoverageEntry {
+                kind: CoverageKind::EventProcessor,
+                span: *span,
+                hits: self.event_processor_hits[index],
+            });
+
+            for (creator_index, span) in self.source_map.creators[index].iter().enumerate() {
+                entries.push(CoverageEntry {
+                    kind: CoverageKind::Creator,
+                    span: *span,
+                    hits: self.creator_hits[index][creator_index],
+                });
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.span.start);
+        entries
+    }
+
+    /// Lists every event processor and creator with its hits, followed by how many of them were
+    /// hit. `source` is the text of the file named `file_name` that the config comes from.
+    pub fn text_report(&self, file_name: &str, source: &str) -> String {
+        let entries = self.entries();
+        let mut report = String::new();
+
+        for entry in entries.iter() {
+            let location = entry.span.location(Some(file_name), source);
+            let verb = match entry.kind {
+                CoverageKind::EventProcessor => "matched",
+                CoverageKind::Creator => "ran",
+            };
+
+            writeln!(
+                report,
+                "{}:{}:{}: {} {} {} {}",
+                file_name,
+                location.line,
+                location.column,
+                keyword(source, entry.span),
+                verb,
+                entry.hits,
+                if entry.hits == 1 { "time" } else { "times" }
+            )
+            .unwrap();
+        }
+
+        for (kind, name, verb) in [
+            (CoverageKind::EventProcessor, "event processors", "matched"),
+            (CoverageKind::Creator, "creators", "ran"),
+        ]
+        .iter()
+        {
+            let total = entries.iter().filter(|entry| entry.kind == *kind).count();
+            let hit = entries
+                .iter()
+                .filter(|entry| entry.kind == *kind && entry.hits > 0)
+                .count();
+
+            writeln!(report, "{}: {} of {} {}", name, hit, total, verb).unwrap();
+        }
+
+        report
+    }
+
+    /// Returns the coverage in the tracefile format of lcov. Event processors are functions named
+    /// after the statement and where it starts, and every event processor and creator is a line.
+    pub fn lcov_report(&self, file_name: &str, source: &str) -> String {
+        let entries = self.entries();
+        let mut report = String::new();
+        let mut line_hits = BTreeMap::new();
+        let mut functions = 0;
+        let mut functions_hit = 0;
+
+        writeln!(report, "TN:").unwrap();
+        writeln!(report, "SF:{}", file_name).unwrap();
+
+        for entry in entries.iter() {
+            let location = entry.span.location(Some(file_name), source);
+
+            // A line with several entries on it is only covered if all of them are
+            let hits = line_hits.entry(location.line).or_insert(entry.hits);
+            *hits = (*hits).min(entry.hits);
+
+            if entry.kind == CoverageKind::EventProcessor {
+                let name = format!(
+                    "{}@{}:{}",
+                    keyword(source, entry.span),
+                    location.line,
+                    location.column
+                );
+
+                writeln!(report, "FN:{},{}", location.line, name).unwrap();
+                writeln!(report, "FNDA:{},{}", entry.hits, name).unwrap();
+
+                functions += 1;
+
+                if entry.hits > 0 {
+                    functions_hit += 1;
+                }
+            }
+        }
+
+        writeln!(report, "FNF:{}", functions).unwrap();
+        writeln!(report, "FNH:{}", functions_hit).unwrap();
+
+        for (line, hits) in line_hits.iter() {
+            writeln!(report, "DA:{},{}", line, hits).unwrap();
+        }
+
+        writeln!(report, "LF:{}", line_hits.len()).unwrap();
+        writeln!(
+            report,
+            "LH:{}",
+            line_hits.values().filter(|hits| **hits > 0).count()
+        )
+        .unwrap();
+        writeln!(report, "end_of_record").unwrap();
+
+        report
+    }
+}
+
+/// Returns the keyword that the statement at `span` starts with, like `do` or `fire`.
+fn keyword(source: &str, span: Span) -> &str {
+    let text = source.get(span.start..span.end).unwrap_or("");
+    let end = text
+        .find(|character: char| !character.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::{ParseOptions, Parser};
+    use crate::simulator::{Event, Simulator};
+
+    const TEXT: &str = "let active = false;
+send BUTTON_RELEASED_EVENT_CODE from 0x0002~u16 to 0x0003~u16;
+do {
+    match event BUTTON_PRESSED_EVENT_CODE;
+    match { FlipStateFilter(active); }
+    fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0xff~u8); } if match {
+        StateEqualToConstFilter(active, true);
+    }
+    fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0x00~u8); } if match {
+        StateEqualToConstFilter(active, false);
+    }
+}
+";
+
+    fn coverage(events: &[Event]) -> Coverage {
+        let parsed = Parser::parse_with(ParseOptions::text(TEXT).with_source_map()).unwrap();
+        let (config, source_map) = (parsed.config, parsed.source_map.unwrap());
+        let mut simulator = Simulator::new(&config, 0x0001);
+        let mut coverage = Coverage::new(&source_map);
+
+        for event in events.iter() {
+            coverage.record_matches(&simulator.step_matches(event).unwrap());
+        }
+
+        coverage
+    }
+
+    #[test]
+    fn entries_test() {
+        let coverage = coverage(&[Event::button_pressed(0x0002, 0)]);
+
+        assert_eq!(
+            coverage
+                .entries()
+                .iter()
+                .map(|entry| (entry.kind, keyword(TEXT, entry.span), entry.hits))
+                .collect::<Vec<_>>(),
+            vec![
+                (CoverageKind::EventProcessor, "send", 0),
+                (CoverageKind::Creator, "send", 0),
+                (CoverageKind::EventProcessor, "do", 1),
+                (CoverageKind::Creator, "fire", 1),
+                (CoverageKind::Creator, "fire", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn text_report_test() {
+        let coverage = coverage(&[
+            Event::button_pressed(0x0002, 0),
+            Event::button_pressed(0x0002, 0),
+            Event::button_pressed(0x0002, 0),
+        ]);
+
+        assert_eq!(
+            coverage.text_report("home.ross", TEXT),
+            "home.ross:2:1: send matched 0 times\n\
+             home.ross:2:1: send ran 0 times\n\
+             home.ross:3:1: do matched 3 times\n\
+             home.ross:6:5: fire ran 2 times\n\
+             home.ross:9:5: fire ran 1 time\n\
+             event processors: 1 of 2 matched\n\
+             creators: 2 of 3 ran\n"
+        );
+    }
+
+    #[test]
+    fn lcov_report_test() {
+        let coverage = coverage(&[Event::button_pressed(0x0002, 0)]);
+
+        assert_eq!(
+            coverage.lcov_report("home.ross", TEXT),
+            "TN:\n\
+             SF:home.ross\n\
+             FN:2,send@2:1\n\
+             FNDA:0,send@2:1\n\
+             FN:3,do@3:1\n\
+             FNDA:1,do@3:1\n\
+             FNF:2\n\
+             FNH:1\n\
+             DA:2,0\n\
+             DA:3,1\n\
+             DA:6,1\n\
+             DA:9,0\n\
+             LF:4\n\
+             LH:2\n\
+             end_of_record\n"
+        );
+    }
+}
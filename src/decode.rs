@@ -0,0 +1,555 @@
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{alpha1, alphanumeric1, char, digit1, multispace0};
+use nom::combinator::{all_consuming, map, map_res, opt, recognize};
+use nom::multi::{many0, separated_list0};
+use nom::sequence::{delimited, pair, preceded, terminated};
+use nom::IResult;
+use std::convert::TryFrom;
+use std::fmt::Debug;
+
+use ross_config::matcher::Matcher;
+
+use crate::item::{item_signature, ArgumentKind};
+use crate::literal::Literal;
+
+/// Filters that only compare, so evaluating them any number of times in any order has the same
+/// result as evaluating them once. Every other filter may change the state.
+const PURE_FILTERS: [&str; 7] = [
+    "ValueEqualToConstFilter",
+    "StateEqualToConstFilter",
+    "StateEqualToValueFilter",
+    "StateEqualToStateFilter",
+    "StateMoreThanConstFilter",
+    "StateLessThatConstFilter",
+    "TimeMatchesCronExpressionFilter",
+];
+
+/// An extractor, filter or producer of a config, with the arguments `create_extractor`,
+/// `create_filter` and `create_producer` take to create it again. Addresses are `u16` and state
+/// variables `u32`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct DecodedItem {
+    pub name: String,
+    pub arguments: Vec<Literal>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum DecodeError {
+    /// The item does not exist in the DSL. Holds its `Debug` output.
+    UnknownItem(String),
+    /// The item exists, but its arguments cannot be written as literals.
+    UnexpectedArguments(String),
+}
+
+/// Decodes an extractor, filter or producer. The `ross_config` items are trait objects that can
+/// only be inspected through their `Debug` output, so this parses that output back, and nothing
+/// else does.
+pub(crate) fn decode_item(item: &dyn Debug) -> Result<DecodedItem, DecodeError> {
+    let (name, values) =
+        parse_item(item).ok_or_else(|| DecodeError::UnknownItem(format!("{:?}", item)))?;
+    let signature = item_signature(&name).ok_or_else(|| DecodeError::UnknownItem(name.clone()))?;
+
+    if signature.len() != values.len() {
+        return Err(DecodeError::UnexpectedArguments(name));
+    }
+
+    let arguments = signature
+        .iter()
+        .zip(values.iter())
+        .map(|(argument_kind, value)| argument_literal(*argument_kind, value))
+        .collect::<Option<Vec<_>>>();
+
+    match arguments {
+        Some(arguments) => Ok(DecodedItem { name, arguments }),
+        None => Err(DecodeError::UnexpectedArguments(name)),
+    }
+}
+
+/// Recognizes `Single { extractor_name, ValueEqualToConstFilter(Value::U16(value)) }`, which is
+/// what `match event` and `match producer` lower to.
+pub(crate) fn value_equal_to_u16(matcher: &Matcher, extractor_name: &str) -> Option<u16> {
+    if let Matcher::Single { extractor, filter } = matcher {
+        let extractor = decode_item(extractor).ok()?;
+        let filter = decode_item(filter).ok()?;
+
+        if extractor.name == extractor_name && filter.name == "ValueEqualToConstFilter" {
+            if let [Literal::U16(value)] = filter.arguments.as_slice() {
+                return Some(*value);
+            }
+        }
+    }
+
+    None
+}
+
+/// Whether `matcher` only compares, so that evaluating it any number of times in any order has
+/// the same result as evaluating it once.
+pub(crate) fn is_pure(matcher: &Matcher) -> bool {
+    match matcher {
+        Matcher::Single { filter, .. } => {
+            matches!(decode_item(filter), Ok(filter) if PURE_FILTERS.contains(&filter.name.as_str()))
+        }
+        Matcher::Not(matcher) => is_pure(matcher),
+        Matcher::And(matcher1, matcher2) | Matcher::Or(matcher1, matcher2) => {
+            is_pure(matcher1) && is_pure(matcher2)
+        }
+    }
+}
+
+fn argument_literal(argument_kind: ArgumentKind, value: &DebugValue) -> Option<Literal> {
+    let value = match (argument_kind, value) {
+        (ArgumentKind::U8, DebugValue::Number(value)) => Literal::U8(u8::try_from(*value).ok()?),
+        (ArgumentKind::U16, DebugValue::Number(value))
+        | (ArgumentKind::Address, DebugValue::Number(value)) => {
+            Literal::U16(u16::try_from(*value).ok()?)
+        }
+        (ArgumentKind::U32, DebugValue::Number(value))
+        | (ArgumentKind::StateIndex, DebugValue::Number(value)) => {
+            Literal::U32(u32::try_from(*value).ok()?)
+        }
+        (ArgumentKind::Value, value) => debug_value_to_literal(value)?,
+        (ArgumentKind::BcmValue, DebugValue::Named(variant, fields)) => {
+            match (variant.as_str(), fields.as_slice()) {
+                ("Single", [value]) => debug_value_to_literal(&DebugValue::Named(
+                    "U8".to_string(),
+                    vec![value.clone()],
+                ))?,
+                ("Rgb", _) | ("Rgbw", _) => debug_value_to_literal(value)?,
+                _ => return None,
+            }
+        }
+        (ArgumentKind::RelayValue, DebugValue::Named(variant, fields)) => {
+            match (variant.as_str(), fields.as_slice()) {
+                ("Single", [DebugValue::Named(value, _)]) => match value.as_str() {
+                    "true" => Literal::Bool(true),
+                    "false" => Literal::Bool(false),
+                    _ => return None,
+                },
+                ("DoubleExclusive", [DebugValue::Named(value, _)]) => match value.as_str() {
+                    "FirstChannelOn" => Literal::String("first".to_string()),
+                    "SecondChannelOn" => Literal::String("second".to_string()),
+                    "NoChannelOn" => Literal::String("none".to_string()),
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        (ArgumentKind::CronExpression, DebugValue::Named(_, fields)) => {
+            cron_expression_to_literal(fields)?
+        }
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+/// Converts the `Debug` output of a `Value`, `MessageValue` or `BcmValue` variant.
+fn debug_value_to_literal(value: &DebugValue) -> Option<Literal> {
+    let (variant, fields) = match value {
+        DebugValue::Named(variant, fields) => (variant.as_str(), fields.as_slice()),
+        _ => return None,
+    };
+
+    let mut numbers = vec![];
+
+    for field in fields {
+        match field {
+            DebugValue::Number(number) => numbers.push(*number),
+            DebugValue::Named(value, _) if variant == "Bool" => {
+                return match value.as_str() {
+                    "true" => Some(Literal::Bool(true)),
+                    "false" => Some(Literal::Bool(false)),
+                    _ => None,
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    let byte = |i: usize| u8::try_from(numbers[i]).ok();
+
+    match (variant, numbers.len()) {
+        ("U8", 1) => Some(Literal::U8(byte(0)?)),
+        ("U16", 1) => Some(Literal::U16(u16::try_from(numbers[0]).ok()?)),
+        ("U32", 1) => Some(Literal::U32(u32::try_from(numbers[0]).ok()?)),
+        ("Rgb", 3) => Some(Literal::Rgb(byte(0)?, byte(1)?, byte(2)?)),
+        ("Rgbw", 4) => Some(Literal::Rgbw(byte(0)?, byte(1)?, byte(2)?, byte(3)?)),
+        _ => None,
+    }
+}
+
+/// Writes the second to day of week fields of a `CronExpression` in the format its
+/// `TryFrom<Literal>` parses. The year is not part of that format.
+fn cron_expression_to_literal(fields: &[DebugValue]) -> Option<Literal> {
+    const RANGES: [(u64, u64); 6] = [(0, 59), (0, 59), (0, 23), (1, 31), (1, 12), (1, 7)];
+
+    if fields.len() < RANGES.len() {
+        return None;
+    }
+
+    let mut field_strings = vec![];
+
+    for (field, (min, max)) in fields.iter().zip(RANGES.iter()) {
+        let field_string = match field {
+            DebugValue::Named(variant, values) => match (variant.as_str(), values.as_slice()) {
+                ("Any", []) => "*".to_string(),
+                ("Including", [DebugValue::Set(values)]) => {
+                    let mut numbers = vec![];
+
+                    for value in values {
+                        match value {
+                            DebugValue::Number(number) => numbers.push(*number),
+                            _ => return None,
+                        }
+                    }
+
+                    numbers.sort_unstable();
+
+                    if numbers.is_empty() {
+                        return None;
+                    } else if numbers == (*min..=*max).collect::<Vec<_>>() {
+                        "*".to_string()
+                    } else {
+                        let numbers: Vec<String> =
+                            numbers.iter().map(|number| number.to_string()).collect();
+                        numbers.join(",")
+                    }
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        field_strings.push(field_string);
+    }
+
+    Some(Literal::String(field_strings.join(" ")))
+}
+
+/// A value of the `Debug` output of an item.
+#[derive(Debug, PartialEq, Clone)]
+enum DebugValue {
+    Number(u64),
+    Set(Vec<DebugValue>),
+    /// A struct, tuple struct, unit struct or enum variant with its field values in order.
+    Named(String, Vec<DebugValue>),
+}
+
+/// Returns the name and field values of an extractor, filter or producer.
+fn parse_item(item: &dyn Debug) -> Option<(String, Vec<DebugValue>)> {
+    let text = format!("{:?}", item);
+
+    let result = all_consuming(delimited(multispace0, debug_value, multispace0))(text.as_str());
+
+    match result {
+        Ok((_, DebugValue::Named(name, fields))) => Some((name, fields)),
+        _ => None,
+    }
+}
+
+fn debug_value(text: &str) -> IResult<&str, DebugValue> {
+    let number_parser = map(map_res(digit1, str::parse), DebugValue::Number);
+    let set_parser = map(
+        delimited(
+            char('{'),
+            separated_list0(char(','), delimited(multispace0, debug_value, multispace0)),
+            char('}'),
+        ),
+        DebugValue::Set,
+    );
+    let named_parser = map(
+        pair(identifier, opt(alt((struct_fields, tuple_fields)))),
+        |(name, fields)| DebugValue::Named(name.to_string(), fields.unwrap_or_default()),
+    );
+
+    alt((number_parser, set_parser, named_parser))(text)
+}
+
+fn struct_fields(text: &str) -> IResult<&str, Vec<DebugValue>> {
+    let field_parser = preceded(
+        pair(
+            delimited(multispace0, identifier, multispace0),
+            terminated(char(':'), multispace0),
+        ),
+        terminated(debug_value, multispace0),
+    );
+
+    delimited(
+        preceded(multispace0, char('{')),
+        separated_list0(char(','), field_parser),
+        char('}'),
+    )(text)
+}
+
+fn tuple_fields(text: &str) -> IResult<&str, Vec<DebugValue>> {
+    delimited(
+        char('('),
+        separated_list0(char(','), delimited(multispace0, debug_value, multispace0)),
+        char(')'),
+    )(text)
+}
+
+fn identifier(text: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        alt((alpha1, tag("_"))),
+        many0(alt((alphanumeric1, tag("_")))),
+    ))(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ross_config::Value;
+
+    use crate::extractor::create_extractor;
+    use crate::filter::create_filter;
+    use crate::producer::create_producer;
+
+    fn decoded(name: &str, arguments: &[Literal]) -> Result<DecodedItem, DecodeError> {
+        Ok(DecodedItem {
+            name: name.to_string(),
+            arguments: arguments.to_vec(),
+        })
+    }
+
+    #[test]
+    fn extractors_test() {
+        let names = [
+            "NoneExtractor",
+            "PacketExtractor",
+            "EventCodeExtractor",
+            "EventProducerAddressExtractor",
+            "MessageCodeExtractor",
+            "MessageValueExtractor",
+            "ButtonIndexExtractor",
+        ];
+
+        for name in names.iter() {
+            let extractor = create_extractor(name, &[], (), &[]).unwrap();
+
+            assert_eq!(decode_item(&extractor), decoded(name, &[]));
+        }
+    }
+
+    #[test]
+    fn filters_test() {
+        let filters = vec![
+            ("ValueEqualToConstFilter", vec![Literal::U8(0x01)]),
+            ("ValueEqualToConstFilter", vec![Literal::U16(0xabab)]),
+            ("ValueEqualToConstFilter", vec![Literal::U32(0xabababab)]),
+            ("ValueEqualToConstFilter", vec![Literal::Bool(true)]),
+            ("ValueEqualToConstFilter", vec![Literal::Rgb(1, 2, 3)]),
+            ("ValueEqualToConstFilter", vec![Literal::Rgbw(1, 2, 3, 4)]),
+            (
+                "StateEqualToConstFilter",
+                vec![Literal::U32(1), Literal::Bool(false)],
+            ),
+            ("StateEqualToValueFilter", vec![Literal::U32(1)]),
+            (
+                "IncrementStateByConstFilter",
+                vec![Literal::U32(1), Literal::U8(2)],
+            ),
+            ("IncrementStateByValueFilter", vec![Literal::U32(1)]),
+            (
+                "DecrementStateByConstFilter",
+                vec![Literal::U32(1), Literal::U16(2)],
+            ),
+            ("DecrementStateByValueFilter", vec![Literal::U32(1)]),
+            (
+                "SetStateToConstFilter",
+                vec![Literal::U32(1), Literal::Rgbw(1, 2, 3, 4)],
+            ),
+            ("SetStateToValueFilter", vec![Literal::U32(1)]),
+            ("FlipStateFilter", vec![Literal::U32(1)]),
+            (
+                "TimeMatchesCronExpressionFilter",
+                vec![Literal::String("0 30 7 * * 1,2,3".to_string())],
+            ),
+            (
+                "StateMoreThanConstFilter",
+                vec![Literal::U32(1), Literal::U32(5)],
+            ),
+            (
+                "StateLessThatConstFilter",
+                vec![Literal::U32(1), Literal::U8(5)],
+            ),
+            (
+                "SetStateToStateFilter",
+                vec![Literal::U32(1), Literal::U32(2)],
+            ),
+            (
+                "StateEqualToStateFilter",
+                vec![Literal::U32(1), Literal::U32(2)],
+            ),
+        ];
+
+        for (name, arguments) in filters {
+            let filter = create_filter(name, &arguments, (), &vec![(); arguments.len()]).unwrap();
+
+            assert_eq!(decode_item(&filter), decoded(name, &arguments));
+        }
+    }
+
+    #[test]
+    fn producers_test() {
+        let address = Literal::U16(0x0003);
+        let producers = vec![
+            ("NoneProducer", vec![]),
+            ("PacketProducer", vec![address.clone()]),
+            (
+                "MessageProducer",
+                vec![address.clone(), Literal::U16(0x0100), Literal::U8(1)],
+            ),
+            (
+                "MessageProducer",
+                vec![address.clone(), Literal::U16(0x0100), Literal::U32(1)],
+            ),
+            (
+                "MessageProducer",
+                vec![address.clone(), Literal::U16(0x0100), Literal::Bool(true)],
+            ),
+            (
+                "BcmChangeBrightnessProducer",
+                vec![address.clone(), Literal::U8(0), Literal::U8(0xff)],
+            ),
+            (
+                "BcmChangeBrightnessProducer",
+                vec![address.clone(), Literal::U8(0), Literal::Rgb(1, 2, 3)],
+            ),
+            (
+                "BcmChangeBrightnessProducer",
+                vec![address.clone(), Literal::U8(0), Literal::Rgbw(1, 2, 3, 4)],
+            ),
+            (
+                "BcmChangeBrightnessStateProducer",
+                vec![address.clone(), Literal::U8(0), Literal::U32(1)],
+            ),
+            (
+                "BcmAnimateBrightnessProducer",
+                vec![
+                    address.clone(),
+                    Literal::U8(0),
+                    Literal::U32(1000),
+                    Literal::U8(0xff),
+                ],
+            ),
+            (
+                "BcmAnimateBrightnessStateProducer",
+                vec![
+                    address.clone(),
+                    Literal::U8(0),
+                    Literal::U32(1000),
+                    Literal::U32(1),
+                ],
+            ),
+            (
+                "RelaySetValueProducer",
+                vec![address.clone(), Literal::U8(0), Literal::Bool(true)],
+            ),
+            (
+                "RelaySetValueProducer",
+                vec![
+                    address.clone(),
+                    Literal::U8(0),
+                    Literal::String("first".to_string()),
+                ],
+            ),
+            (
+                "RelaySetValueProducer",
+                vec![
+                    address.clone(),
+                    Literal::U8(0),
+                    Literal::String("second".to_string()),
+                ],
+            ),
+            (
+                "RelaySetValueProducer",
+                vec![address, Literal::U8(0), Literal::String("none".to_string())],
+            ),
+        ];
+
+        for (name, arguments) in producers {
+            let producer =
+                create_producer(name, &arguments, (), &vec![(); arguments.len()]).unwrap();
+
+            assert_eq!(decode_item(&producer), decoded(name, &arguments));
+        }
+    }
+
+    #[test]
+    fn unknown_item_test() {
+        assert_eq!(
+            decode_item(&Value::U8(0x01)),
+            Err(DecodeError::UnknownItem("U8".to_string()))
+        );
+        assert_eq!(
+            decode_item(&"PacketProducer"),
+            Err(DecodeError::UnknownItem("\"PacketProducer\"".to_string()))
+        );
+    }
+
+    #[test]
+    fn value_equal_to_u16_test() {
+        let matcher = |extractor: &str, value: Literal| Matcher::Single {
+            extractor: create_extractor(extractor, &[], (), &[]).unwrap(),
+            filter: create_filter("ValueEqualToConstFilter", &[value], (), &[()]).unwrap(),
+        };
+
+        assert_eq!(
+            value_equal_to_u16(
+                &matcher("EventCodeExtractor", Literal::U16(0x0001)),
+                "EventCodeExtractor"
+            ),
+            Some(0x0001)
+        );
+        assert_eq!(
+            value_equal_to_u16(
+                &matcher("EventCodeExtractor", Literal::U16(0x0001)),
+                "EventProducerAddressExtractor"
+            ),
+            None
+        );
+        assert_eq!(
+            value_equal_to_u16(
+                &matcher("EventCodeExtractor", Literal::U8(0x01)),
+                "EventCodeExtractor"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn debug_value_test() {
+        assert_eq!(
+            debug_value("StateEqualToConstFilter { state_index: 1, value: Bool(true) }"),
+            Ok((
+                "",
+                DebugValue::Named(
+                    "StateEqualToConstFilter".to_string(),
+                    vec![
+                        DebugValue::Number(1),
+                        DebugValue::Named(
+                            "Bool".to_string(),
+                            vec![DebugValue::Named("true".to_string(), vec![])]
+                        ),
+                    ]
+                )
+            ))
+        );
+        assert_eq!(
+            debug_value("Including({1, 2})"),
+            Ok((
+                "",
+                DebugValue::Named(
+                    "Including".to_string(),
+                    vec![DebugValue::Set(vec![
+                        DebugValue::Number(1),
+                        DebugValue::Number(2)
+                    ])]
+                )
+            ))
+        );
+    }
+}
@@ -0,0 +1,1029 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use ross_config::config::Config;
+use ross_config::creator::Creator;
+use ross_config::event_processor::EventProcessor;
+use ross_config::matcher::Matcher;
+use ross_config::peripheral::{BcmPeripheral, Peripheral, RelayPeripheral};
+use ross_config::Value;
+use ross_protocol::event::event_code::INTERNAL_SYSTEM_TICK_EVENT_CODE;
+
+use crate::ast::*;
+use crate::decode::{decode_item, is_pure, value_equal_to_u16, DecodeError};
+use crate::item::{item_signature, ArgumentKind};
+use crate::literal::Literal;
+use crate::lower::prepare_constants;
+use crate::printer::Printer;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DecompileError {
+    /// `let` statements number state variables from zero, so a gap in the initial state cannot
+    /// be written back.
+    NonContiguousInitialState(u32),
+    /// The extractor, filter or producer does not exist in the DSL.
+    UnknownItem(String),
+    /// The item exists, but its arguments cannot be written as literals.
+    UnexpectedItemArguments(String),
+    /// `not` only wraps a single extractor and filter, and `!` only `event`, `producer` and
+    /// `tick`.
+    UnsupportedNot,
+}
+
+impl From<DecodeError> for DecompileError {
+    fn from(err: DecodeError) -> Self {
+        match err {
+            DecodeError::UnknownItem(name) => DecompileError::UnknownItem(name),
+            DecodeError::UnexpectedArguments(name) => DecompileError::UnexpectedItemArguments(name),
+        }
+    }
+}
+
+impl Display for DecompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecompileError::NonContiguousInitialState(index) => {
+                write!(f, "state index {} leaves a gap in the initial state", index)
+            }
+            DecompileError::UnknownItem(name) => write!(f, "unknown item {}", name),
+            DecompileError::UnexpectedItemArguments(name) => {
+                write!(f, "unexpected arguments of {}", name)
+            }
+            DecompileError::UnsupportedNot => {
+                write!(f, "not of anything but a single matcher is not supported")
+            }
+        }
+    }
+}
+
+impl Error for DecompileError {}
+
+/// Renders `config` back into source that parses to an equivalent `Config`.
+pub fn decompile(config: &Config) -> Result<String, DecompileError> {
+    Ok(Printer::print(&decompile_program(config)?))
+}
+
+/// Same as `decompile`, but declares `devices`, which are names by address, and writes their
+/// names instead of the addresses.
+pub fn decompile_with_devices(
+    config: &Config,
+    devices: &BTreeMap<u16, String>,
+) -> Result<String, DecompileError> {
+    Ok(Printer::print(&decompile_program_with_devices(
+        config, devices,
+    )?))
+}
+
+/// Turns `config` into a syntax tree. State variables are named `state_<index>`, event codes
+/// of `ross_protocol` use their constant names and every event processor that has the shape
+/// of a `send` or `set` statement is written as one. Spans in the tree are all empty.
+pub fn decompile_program(config: &Config) -> Result<Program, DecompileError> {
+    decompile_program_with_devices(config, &BTreeMap::new())
+}
+
+/// Same as `decompile_program`, but with a `device` statement for each of `devices`.
+pub fn decompile_program_with_devices(
+    config: &Config,
+    devices: &BTreeMap<u16, String>,
+) -> Result<Program, DecompileError> {
+    let decompiler = Decompiler::new(config, devices);
+    let mut statements = vec![];
+
+    for (address, device) in devices.iter() {
+        statements.push(Statement::Device(DeviceStatement {
+            name: name(device),
+            address: literal(Literal::U16(*address)),
+            span: Span::default(),
+        }));
+    }
+
+    for (i, (index, value)) in config.initial_state.iter().enumerate() {
+        if *index != i as u32 {
+            return Err(DecompileError::NonContiguousInitialState(*index));
+        }
+
+        statements.push(Statement::Let(LetStatement {
+            name: name(&decompiler.state_variables[index]),
+            value_type: None,
+            value: literal(value_to_literal(value)),
+            span: Span::default(),
+        }));
+    }
+
+    for (index, peripheral) in config.peripherals.iter() {
+        statements.push(Statement::Peripheral(
+            decompiler.peripheral_statement(*index, peripheral),
+        ));
+    }
+
+    for event_processor in config.event_processors.iter() {
+        statements.push(decompiler.event_processor(event_processor)?);
+    }
+
+    Ok(Program { statements })
+}
+
+struct Decompiler<'a> {
+    state_variables: BTreeMap<u32, String>,
+    event_codes: BTreeMap<u16, String>,
+    devices: &'a BTreeMap<u16, String>,
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(config: &Config, devices: &'a BTreeMap<u16, String>) -> Self {
+        let state_variables = config
+            .initial_state
+            .keys()
+            .map(|index| (*index, format!("state_{}", index)))
+            .collect();
+
+        let mut constants = BTreeMap::new();
+        prepare_constants(&mut constants);
+
+        let event_codes = constants
+            .into_iter()
+            .filter_map(|(name, value)| match value {
+                Literal::Event(event_code) => Some((event_code, name)),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            state_variables,
+            event_codes,
+            devices,
+        }
+    }
+
+    fn peripheral_statement(&self, index: u32, peripheral: &Peripheral) -> PeripheralStatement {
+        let (family, kind, channels, gateway_addresses) = match peripheral {
+            Peripheral::Bcm(BcmPeripheral::Single(channel), gateway_addresses) => {
+                ("bcm", "single", vec![*channel], gateway_addresses)
+            }
+            Peripheral::Bcm(BcmPeripheral::Rgb(r, g, b), gateway_addresses) => {
+                ("bcm", "rgb", vec![*r, *g, *b], gateway_addresses)
+            }
+            Peripheral::Bcm(BcmPeripheral::Rgbw(r, g, b, w), gateway_addresses) => {
+                ("bcm", "rgbw", vec![*r, *g, *b, *w], gateway_addresses)
+            }
+            Peripheral::Relay(RelayPeripheral::Single(channel), gateway_addresses) => {
+                ("relay", "single", vec![*channel], gateway_addresses)
+            }
+            Peripheral::Relay(
+                RelayPeripheral::DoubleExclusive(channel1, channel2),
+                gateway_addresses,
+            ) => (
+                "relay",
+                "double_exclusive",
+                vec![*channel1, *channel2],
+                gateway_addresses,
+            ),
+        };
+
+        let gateway_addresses = if gateway_addresses.is_empty() {
+            None
+        } else {
+            Some(
+                gateway_addresses
+                    .iter()
+                    .map(|address| self.address(*address))
+                    .collect(),
+            )
+        };
+
+        PeripheralStatement {
+            gateway_addresses,
+            index: literal(Literal::U32(index)),
+            family: name(family),
+            kind: name(kind),
+            arguments: channels
+                .into_iter()
+                .map(|channel| literal(Literal::U8(channel)))
+                .collect(),
+            span: Span::default(),
+        }
+    }
+
+    fn event_processor(
+        &self,
+        event_processor: &EventProcessor,
+    ) -> Result<Statement, DecompileError> {
+        if let Some(statement) = self.send_statement(event_processor)? {
+            Ok(Statement::Send(statement))
+        } else if let Some(statement) = self.set_statement(event_processor)? {
+            Ok(Statement::Set(statement))
+        } else {
+            Ok(Statement::Do(self.do_statement(event_processor)?))
+        }
+    }
+
+    /// Recognizes an `And` of a check of the event code, a check of the producer address and
+    /// optionally a condition, with a single packet creator. The checks may be nested and
+    /// ordered in any way that evaluates to the same, like after `optimize`.
+    fn send_statement(
+        &self,
+        event_processor: &EventProcessor,
+    ) -> Result<Option<SendStatement>, DecompileError> {
+        let to_address = match event_processor.creators.as_slice() {
+            [Creator {
+                extractor,
+                producer,
+                matcher: None,
+            }] => match (decode_item(extractor), decode_item(producer)) {
+                (Ok(extractor), Ok(producer))
+                    if extractor.name == "PacketExtractor" && producer.name == "PacketProducer" =>
+                {
+                    match producer.arguments.as_slice() {
+                        [Literal::U16(address)] => *address,
+                        _ => return Ok(None),
+                    }
+                }
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+
+        let operands = and_operands(&event_processor.matcher);
+        let checks = (
+            u16_checks(&operands, "EventCodeExtractor").next_back(),
+            u16_checks(&operands, "EventProducerAddressExtractor").next_back(),
+        );
+
+        let ((event, event_code), (producer, from_address)) = match checks {
+            (Some(event), Some(producer)) => (event, producer),
+            _ => return Ok(None),
+        };
+
+        // `send` evaluates its condition first
+        let condition: Vec<usize> = (0..operands.len())
+            .filter(|i| *i != event && *i != producer)
+            .collect();
+        let order: Vec<usize> = condition.iter().copied().chain([event, producer]).collect();
+
+        if !keeps_outcome(&operands, &order) {
+            return Ok(None);
+        }
+
+        let condition = match self.condition(&operands, &condition) {
+            Err(DecompileError::UnsupportedNot) => return Ok(None),
+            condition => condition?,
+        };
+
+        Ok(Some(SendStatement {
+            event_code: self.event_code(event_code),
+            from_address: self.address(from_address),
+            to_address: self.address(to_address),
+            condition,
+            span: Span::default(),
+        }))
+    }
+
+    /// Recognizes an `And` of a check of the event code, a check of the producer address,
+    /// optionally a condition and last a filter that sets a state variable to a constant,
+    /// without creators. The checks may be nested and ordered in any way that evaluates to the
+    /// same, like after `optimize`.
+    fn set_statement(
+        &self,
+        event_processor: &EventProcessor,
+    ) -> Result<Option<SetStatement>, DecompileError> {
+        if !event_processor.creators.is_empty() {
+            return Ok(None);
+        }
+
+        let operands = and_operands(&event_processor.matcher);
+        let set = operands.len() - 1;
+        let checks = (
+            u16_checks(&operands[..set], "EventCodeExtractor").next(),
+            u16_checks(&operands[..set], "EventProducerAddressExtractor").next(),
+            self.set_state(operands[set]),
+        );
+
+        let ((event, event_code), (producer, from_address), (state_variable, value)) = match checks
+        {
+            (Some(event), Some(producer), Some(set)) => (event, producer, set),
+            _ => return Ok(None),
+        };
+
+        // `set` evaluates the event and producer checks first and sets the state last
+        let condition: Vec<usize> = (0..set).filter(|i| *i != event && *i != producer).collect();
+        let order: Vec<usize> = [event, producer]
+            .iter()
+            .copied()
+            .chain(condition.iter().copied())
+            .chain([set])
+            .collect();
+
+        if !keeps_outcome(&operands, &order) {
+            return Ok(None);
+        }
+
+        let condition = match self.condition(&operands, &condition) {
+            Err(DecompileError::UnsupportedNot) => return Ok(None),
+            condition => condition?,
+        };
+
+        Ok(Some(SetStatement {
+            state_variable: name(state_variable),
+            value: literal(value),
+            event_code: self.event_code(event_code),
+            from_address: self.address(from_address),
+            condition,
+            span: Span::default(),
+        }))
+    }
+
+    fn set_state(&self, matcher: &Matcher) -> Option<(&str, Literal)> {
+        if let Matcher::Single { extractor, filter } = matcher {
+            let extractor = decode_item(extractor).ok()?;
+            let filter = decode_item(filter).ok()?;
+
+            if extractor.name == "NoneExtractor" && filter.name == "SetStateToConstFilter" {
+                if let [Literal::U32(index), value] = filter.arguments.as_slice() {
+                    let state_variable = self.state_variables.get(index)?;
+
+                    return Some((state_variable, value.clone()));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Writes the `operands` at `indices` as the condition of a `send` or `set`, which is a
+    /// single `match`, or nothing if there are none.
+    fn condition(
+        &self,
+        operands: &[&Matcher],
+        indices: &[usize],
+    ) -> Result<Option<MatchExpr>, DecompileError> {
+        let operands: Vec<&Matcher> = indices.iter().map(|i| operands[*i]).collect();
+
+        match operands.as_slice() {
+            [] => return Ok(None),
+            [operand] => return Ok(Some(self.match_expr(operand)?)),
+            _ => {}
+        }
+
+        let operator_operands: Option<Vec<MatchExpr>> = operands
+            .iter()
+            .map(|operand| self.operator_match_expr(operand))
+            .collect();
+
+        if let Some(operator_operands) = operator_operands {
+            return Ok(operator_operands.into_iter().reduce(and_match_expr));
+        }
+
+        let children = operands
+            .into_iter()
+            .map(|operand| self.block_match(operand))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some(MatchExpr::Block {
+            block: BlockMatch::And {
+                children,
+                span: Span::default(),
+            },
+            span: Span::default(),
+        }))
+    }
+
+    /// Undoes the left fold `lower` builds out of the `match`es of a `do`. An `And` the fold
+    /// did not build, such as one `optimize` balanced, is only split into several `match`es if
+    /// it cannot be written in the operator syntax.
+    fn do_statement(
+        &self,
+        event_processor: &EventProcessor,
+    ) -> Result<DoStatement, DecompileError> {
+        let mut operands = vec![];
+        let mut matcher = &event_processor.matcher;
+
+        while let Matcher::And(left, right) = matcher {
+            operands.push(&**right);
+            matcher = left;
+        }
+
+        operands.push(matcher);
+
+        let mut matches = vec![];
+
+        for operand in operands.into_iter().rev() {
+            match self.operator_match_expr(operand) {
+                Some(match_expr) => matches.push(match_expr),
+                None => {
+                    for operand in and_operands(operand) {
+                        matches.push(self.match_expr(operand)?);
+                    }
+                }
+            }
+        }
+
+        let mut fires = vec![];
+
+        for creator in event_processor.creators.iter() {
+            let condition = match &creator.matcher {
+                Some(matcher) => Some(self.match_expr(matcher)?),
+                None => None,
+            };
+
+            fires.push(FireExpr {
+                extractor: self.optional_extractor(&creator.extractor)?,
+                producer: self.item_call(&creator.producer)?,
+                condition,
+                span: Span::default(),
+            });
+        }
+
+        Ok(DoStatement {
+            matches,
+            fires,
+            span: Span::default(),
+        })
+    }
+
+    /// Writes `matcher` in the operator syntax if it only checks the event code and the
+    /// producer address, and as a block otherwise.
+    fn match_expr(&self, matcher: &Matcher) -> Result<MatchExpr, DecompileError> {
+        if let Some(match_expr) = self.operator_match_expr(matcher) {
+            return Ok(match_expr);
+        }
+
+        Ok(MatchExpr::Block {
+            block: self.block_match(matcher)?,
+            span: Span::default(),
+        })
+    }
+
+    /// Writes `matcher` with `event`, `producer`, `tick`, `!`, `&&`, `||` and parentheses where
+    /// the precedence needs them. Returns nothing if it has any other check.
+    fn operator_match_expr(&self, matcher: &Matcher) -> Option<MatchExpr> {
+        let match_expr = match matcher {
+            Matcher::Single { .. } => return self.event_match_expr(matcher),
+            Matcher::Not(operand) => MatchExpr::Not {
+                operand: Box::new(parenthesized(
+                    self.operator_match_expr(operand)?,
+                    UNARY_PRECEDENCE,
+                )),
+                span: Span::default(),
+            },
+            Matcher::And(left, right) => and_match_expr(
+                self.operator_match_expr(left)?,
+                self.operator_match_expr(right)?,
+            ),
+            Matcher::Or(left, right) => MatchExpr::Or {
+                left: Box::new(parenthesized(
+                    self.operator_match_expr(left)?,
+                    OR_PRECEDENCE,
+                )),
+                right: Box::new(parenthesized(
+                    self.operator_match_expr(right)?,
+                    OR_PRECEDENCE + 1,
+                )),
+                span: Span::default(),
+            },
+        };
+
+        Some(match_expr)
+    }
+
+    fn event_match_expr(&self, matcher: &Matcher) -> Option<MatchExpr> {
+        if let Some(event_code) = value_equal_to_u16(matcher, "EventCodeExtractor") {
+            if event_code == INTERNAL_SYSTEM_TICK_EVENT_CODE {
+                return Some(MatchExpr::Tick {
+                    span: Span::default(),
+                });
+            }
+
+            return Some(MatchExpr::Event {
+                event_code: self.event_code(event_code),
+                span: Span::default(),
+            });
+        }
+
+        value_equal_to_u16(matcher, "EventProducerAddressExtractor").map(|address| {
+            MatchExpr::Producer {
+                address: self.address(address),
+                span: Span::default(),
+            }
+        })
+    }
+
+    fn block_match(&self, matcher: &Matcher) -> Result<BlockMatch, DecompileError> {
+        match matcher {
+            Matcher::Single { extractor, filter } => Ok(BlockMatch::Single {
+                extractor: self.optional_extractor(extractor)?,
+                filter: self.item_call(filter)?,
+                span: Span::default(),
+            }),
+            Matcher::Not(matcher) => match &**matcher {
+                Matcher::Single { extractor, filter } => Ok(BlockMatch::Not {
+                    extractor: self.optional_extractor(extractor)?,
+                    filter: self.item_call(filter)?,
+                    span: Span::default(),
+                }),
+                _ => Err(DecompileError::UnsupportedNot),
+            },
+            Matcher::Or(_, _) => Ok(BlockMatch::Or {
+                children: self.block_match_chain(matcher, |matcher| match matcher {
+                    Matcher::Or(matcher1, matcher2) => Some((matcher1, matcher2)),
+                    _ => None,
+                })?,
+                span: Span::default(),
+            }),
+            Matcher::And(_, _) => Ok(BlockMatch::And {
+                children: self.block_match_chain(matcher, |matcher| match matcher {
+                    Matcher::And(matcher1, matcher2) => Some((matcher1, matcher2)),
+                    _ => None,
+                })?,
+                span: Span::default(),
+            }),
+        }
+    }
+
+    /// Decompiles the operands of a chain of `Or` or `And` that nests to the left, which is how
+    /// `lower` folds the children of an `or` or `and` block.
+    fn block_match_chain<'m, F>(
+        &self,
+        matcher: &'m Matcher,
+        split: F,
+    ) -> Result<Vec<BlockMatch>, DecompileError>
+    where
+        F: Fn(&'m Matcher) -> Option<(&'m Matcher, &'m Matcher)>,
+    {
+        let mut operands = vec![];
+        let mut current = matcher;
+
+        while let Some((matcher1, matcher2)) = split(current) {
+            operands.push(matcher2);
+            current = matcher1;
+        }
+
+        operands.push(current);
+        operands
+            .into_iter()
+            .rev()
+            .map(|operand| self.block_match(operand))
+            .collect()
+    }
+
+    /// Leaves out a `NoneExtractor`, which is what `lower` uses when there is no extractor.
+    fn optional_extractor(
+        &self,
+        extractor: &dyn Debug,
+    ) -> Result<Option<ItemCall>, DecompileError> {
+        let item_call = self.item_call(extractor)?;
+
+        if item_call.name.value == "NoneExtractor" {
+            Ok(None)
+        } else {
+            Ok(Some(item_call))
+        }
+    }
+
+    fn item_call(&self, item: &dyn Debug) -> Result<ItemCall, DecompileError> {
+        let item = decode_item(item)?;
+        let signature = item_signature(&item.name).unwrap_or_default();
+        let arguments = signature
+            .iter()
+            .zip(item.arguments)
+            .map(|(argument_kind, value)| self.argument(*argument_kind, value))
+            .collect();
+
+        Ok(ItemCall {
+            name: name(&item.name),
+            arguments,
+            span: Span::default(),
+        })
+    }
+
+    fn argument(&self, argument_kind: ArgumentKind, value: Literal) -> Expression {
+        match (argument_kind, value) {
+            (ArgumentKind::Address, Literal::U16(address)) => self.address(address),
+            (ArgumentKind::StateIndex, Literal::U32(index)) => {
+                match self.state_variables.get(&index) {
+                    Some(state_variable) => Expression::Constant(name(state_variable)),
+                    None => literal(Literal::U32(index)),
+                }
+            }
+            (_, value) => literal(value),
+        }
+    }
+
+    fn address(&self, address: u16) -> Expression {
+        match self.devices.get(&address) {
+            Some(device) => Expression::Constant(name(device)),
+            None => literal(Literal::U16(address)),
+        }
+    }
+
+    fn event_code(&self, event_code: u16) -> Expression {
+        match self.event_codes.get(&event_code) {
+            Some(constant) => Expression::Constant(name(constant)),
+            None => literal(Literal::U16(event_code)),
+        }
+    }
+}
+
+const OR_PRECEDENCE: u8 = 1;
+const AND_PRECEDENCE: u8 = 2;
+const UNARY_PRECEDENCE: u8 = 3;
+
+/// Returns the operands of the `And`s at the root of `matcher` in the order they are evaluated.
+fn and_operands(matcher: &Matcher) -> Vec<&Matcher> {
+    match matcher {
+        Matcher::And(left, right) => {
+            let mut operands = and_operands(left);
+            operands.extend(and_operands(right));
+            operands
+        }
+        _ => vec![matcher],
+    }
+}
+
+/// Returns the operands that compare what `extractor_name` extracts with a `u16`, with their
+/// indices and the `u16`.
+fn u16_checks<'m>(
+    operands: &'m [&'m Matcher],
+    extractor_name: &'m str,
+) -> impl DoubleEndedIterator<Item = (usize, u16)> + 'm {
+    operands
+        .iter()
+        .enumerate()
+        .filter_map(move |(i, operand)| Some((i, value_equal_to_u16(operand, extractor_name)?)))
+}
+
+/// Whether evaluating `operands` in `order`, which holds their indices, has the same outcome as
+/// evaluating them as they are. Only checks that do not change the state may move, and never
+/// past one that does.
+fn keeps_outcome(operands: &[&Matcher], order: &[usize]) -> bool {
+    let impure_before = |position: usize| {
+        operands[..position]
+            .iter()
+            .filter(|operand| !is_pure(operand))
+            .count()
+    };
+
+    order.iter().enumerate().all(|(position, index)| {
+        if is_pure(operands[*index]) {
+            impure_before(position) == impure_before(*index)
+        } else {
+            position == *index
+        }
+    })
+}
+
+fn precedence(match_expr: &MatchExpr) -> u8 {
+    match match_expr {
+        MatchExpr::Or { .. } => OR_PRECEDENCE,
+        MatchExpr::And { .. } => AND_PRECEDENCE,
+        _ => UNARY_PRECEDENCE,
+    }
+}
+
+/// Wraps `match_expr` in parentheses if it binds less tightly than `min_precedence`.
+fn parenthesized(match_expr: MatchExpr, min_precedence: u8) -> MatchExpr {
+    if precedence(&match_expr) < min_precedence {
+        MatchExpr::Parenthesized {
+            match_expr: Box::new(match_expr),
+            span: Span::default(),
+        }
+    } else {
+        match_expr
+    }
+}
+
+/// Joins `left` and `right` with `&&`, which associates to the left.
+fn and_match_expr(left: MatchExpr, right: MatchExpr) -> MatchExpr {
+    MatchExpr::And {
+        left: Box::new(parenthesized(left, AND_PRECEDENCE)),
+        right: Box::new(parenthesized(right, AND_PRECEDENCE + 1)),
+        span: Span::default(),
+    }
+}
+
+pub(crate) fn value_to_literal(value: &Value) -> Literal {
+    match *value {
+        Value::U8(value) => Literal::U8(value),
+        Value::U16(value) => Literal::U16(value),
+        Value::U32(value) => Literal::U32(value),
+        Value::Bool(value) => Literal::Bool(value),
+        Value::Rgb(r, g, b) => Literal::Rgb(r, g, b),
+        Value::Rgbw(r, g, b, w) => Literal::Rgbw(r, g, b, w),
+    }
+}
+
+fn name(value: &str) -> Name {
+    Name {
+        value: value.to_string(),
+        span: Span::default(),
+    }
+}
+
+fn literal(value: Literal) -> Expression {
+    Expression::Literal {
+        value,
+        span: Span::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+
+    use ross_config::extractor::NoneExtractor;
+    use ross_config::filter::FlipStateFilter;
+
+    use crate::optimizer::optimize;
+    use crate::parser::Parser;
+
+    fn round_trip(text: &str) -> String {
+        let config = Parser::parse(text).unwrap();
+        let decompiled = decompile(&config).unwrap();
+
+        assert_eq!(
+            format!("{:?}", Parser::parse(&decompiled).unwrap()),
+            format!("{:?}", config)
+        );
+
+        decompiled
+    }
+
+    #[test]
+    fn let_and_peripheral_test() {
+        let text = "let a = 0x01~u8; let b = #010203;
+            pub(0x0001~u16, 0x0002~u16) peripheral 0x01~u32 relay double_exclusive(0x00~u8, 0x01~u8);
+            peripheral 0x00~u32 bcm single(0x02~u8);";
+
+        assert_eq!(
+            round_trip(text),
+            "let state_0 = 0x01~u8;\n\
+             let state_1 = #010203;\n\
+             \n\
+             peripheral 0x00000000~u32 bcm single(0x02~u8);\n\
+             pub(0x0001~u16, 0x0002~u16) peripheral 0x00000001~u32 relay double_exclusive(0x00~u8, 0x01~u8);\n"
+        );
+    }
+
+    #[test]
+    fn send_test() {
+        let text = "send BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 to 0x0003~u16;
+            send 0x0100~u16 from 0x0002~u16 to 0x0003~u16 if match tick;";
+
+        assert_eq!(
+            round_trip(text),
+            "send BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 to 0x0003~u16;\n\
+             send 0x0100~u16 from 0x0002~u16 to 0x0003~u16 if match tick;\n"
+        );
+    }
+
+    #[test]
+    fn set_test() {
+        let text = "let a = false;
+            set a to true on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16;
+            set a to false on BUTTON_RELEASED_EVENT_CODE from 0x0002~u16 if match {
+                ButtonIndexExtractor();
+                ValueEqualToConstFilter(0x01~u8);
+            }";
+
+        assert_eq!(
+            round_trip(text),
+            "let state_0 = false;\n\
+             \n\
+             set state_0 to true on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16;\n\
+             \n\
+             set state_0 to false on BUTTON_RELEASED_EVENT_CODE from 0x0002~u16 if match {\n    \
+                 ButtonIndexExtractor();\n    \
+                 ValueEqualToConstFilter(0x01~u8);\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn do_test() {
+        let text = "let active = false;
+            do {
+                match event BUTTON_PRESSED_EVENT_CODE;
+                match producer 0x0002~u16;
+                match { FlipStateFilter(active); }
+                fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, #ff00ff); } if match {
+                    StateEqualToConstFilter(active, true);
+                }
+                fire { PacketExtractor(); PacketProducer(0xffff~u16); }
+            }";
+
+        assert_eq!(
+            round_trip(text),
+            "let state_0 = false;\n\
+             \n\
+             do {\n    \
+                 match event BUTTON_PRESSED_EVENT_CODE;\n    \
+                 match producer 0x0002~u16;\n    \
+                 match {\n        \
+                     FlipStateFilter(state_0);\n    \
+                 }\n    \
+                 fire {\n        \
+                     BcmChangeBrightnessProducer(0x0003~u16, 0x00~u8, #ff00ff);\n    \
+                 } if match {\n        \
+                     StateEqualToConstFilter(state_0, true);\n    \
+                 }\n    \
+                 fire {\n        \
+                     PacketExtractor();\n        \
+                     PacketProducer(0xffff~u16);\n    \
+                 }\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn nested_block_match_test() {
+        round_trip(
+            "let a = 0~u8;
+            do {
+                match or {
+                    not { StateEqualToConstFilter(a, 0x01~u8); },
+                    and {
+                        { MessageCodeExtractor(); ValueEqualToConstFilter(0x0001~u16); },
+                        { TimeMatchesCronExpressionFilter(\"0 30 8 * * 1,2,3\"); }
+                    }
+                }
+                fire { MessageProducer(0x0001~u16, 0x0002~u16, true); }
+                fire { RelaySetValueProducer(0x0001~u16, 0x00~u8, \"second\"); }
+                fire { BcmAnimateBrightnessStateProducer(0x0001~u16, 0x00~u8, 500~u32, a); }
+            }",
+        );
+    }
+
+    #[test]
+    fn sugar_shaped_do_test() {
+        let text = "let a = false;
+            do {
+                match event 0x0100~u16;
+                match producer 0x0002~u16;
+                match { SetStateToConstFilter(a, true); }
+            }";
+
+        assert_eq!(
+            round_trip(text),
+            "let state_0 = false;\n\
+             \n\
+             set state_0 to true on 0x0100~u16 from 0x0002~u16;\n"
+        );
+    }
+
+    #[test]
+    fn operator_match_test() {
+        let text = "do {
+                match !(event BUTTON_PRESSED_EVENT_CODE || tick);
+                match (event BUTTON_RELEASED_EVENT_CODE || tick) && !producer 0x0002~u16;
+                match tick || event 0x0100~u16 && (producer 0x0002~u16 || producer 0x0003~u16);
+                match !!tick && (!event 0x0100~u16 || (tick || tick) && tick);
+                fire { NoneProducer(); }
+            }";
+
+        assert_eq!(
+            round_trip(text),
+            "do {\n    \
+                 match !(event BUTTON_PRESSED_EVENT_CODE || tick);\n    \
+                 match (event BUTTON_RELEASED_EVENT_CODE || tick) && !producer 0x0002~u16;\n    \
+                 match tick || event 0x0100~u16 && (producer 0x0002~u16 || producer 0x0003~u16);\n    \
+                 match !!tick && (!event 0x0100~u16 || (tick || tick) && tick);\n    \
+                 fire {\n        \
+                     NoneProducer();\n    \
+                 }\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn optimized_sugar_test() {
+        let text = "let active = false;
+            set active to true on BUTTON_RELEASED_EVENT_CODE from 0x0002~u16;
+            set active to false on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 if match tick;
+            send 0x0100~u16 from 0x0002~u16 to 0x0003~u16 if match !tick;
+            do {
+                match tick;
+                match producer 0x0002~u16;
+                match { FlipStateFilter(active); }
+                fire { NoneProducer(); }
+            }";
+        let config = optimize(Parser::parse(text).unwrap());
+        let decompiled = decompile(&config).unwrap();
+
+        assert_eq!(
+            format!("{:?}", optimize(Parser::parse(&decompiled).unwrap())),
+            format!("{:?}", config)
+        );
+        assert_eq!(
+            decompiled,
+            "let state_0 = false;\n\
+             \n\
+             set state_0 to true on BUTTON_RELEASED_EVENT_CODE from 0x0002~u16;\n\
+             set state_0 to false on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 if match tick;\n\
+             \n\
+             send 0x0100~u16 from 0x0002~u16 to 0x0003~u16 if match !tick;\n\
+             \n\
+             do {\n    \
+                 match tick;\n    \
+                 match producer 0x0002~u16;\n    \
+                 match {\n        \
+                     FlipStateFilter(state_0);\n    \
+                 }\n    \
+                 fire {\n        \
+                     NoneProducer();\n    \
+                 }\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn unsupported_not_test() {
+        let mut config = Parser::parse("do { match tick; }").unwrap();
+        let single = || Matcher::Single {
+            extractor: Box::new(NoneExtractor::new()),
+            filter: Box::new(FlipStateFilter::new(0)),
+        };
+
+        config.event_processors[0].matcher = Matcher::Not(Box::new(Matcher::And(
+            Box::new(single()),
+            Box::new(single()),
+        )));
+
+        assert_matches!(decompile(&config), Err(DecompileError::UnsupportedNot));
+    }
+
+    #[test]
+    fn non_contiguous_initial_state_test() {
+        let mut config = Parser::parse("let a = false;").unwrap();
+        config.initial_state.insert(2, Value::Bool(true));
+
+        assert_matches!(
+            decompile(&config),
+            Err(DecompileError::NonContiguousInitialState(2))
+        );
+    }
+
+    #[test]
+    fn devices_test() {
+        let text = "device kitchen_switch = 0x0002;
+            device kitchen_light = 0x0003;
+            pub(kitchen_switch) peripheral 0 bcm single(0);
+            send BUTTON_PRESSED_EVENT_CODE from kitchen_switch to kitchen_light;
+            do {
+                match producer kitchen_switch;
+                fire {
+                    BcmChangeBrightnessProducer(kitchen_light, 0, 0xff);
+                }
+            }";
+        let config = Parser::parse(text).unwrap();
+        let mut devices = BTreeMap::new();
+        devices.insert(0x0002, "kitchen_switch".to_string());
+        devices.insert(0x0003, "kitchen_light".to_string());
+
+        let decompiled = decompile_with_devices(&config, &devices).unwrap();
+
+        assert_eq!(
+            decompiled,
+            "device kitchen_switch = 0x0002~u16;\n\
+             device kitchen_light  = 0x0003~u16;\n\
+             \n\
+             pub(kitchen_switch) peripheral 0x00000000~u32 bcm single(0x00~u8);\n\
+             \n\
+             send BUTTON_PRESSED_EVENT_CODE from kitchen_switch to kitchen_light;\n\
+             \n\
+             do {\n    \
+                 match producer kitchen_switch;\n    \
+                 fire {\n        \
+                     BcmChangeBrightnessProducer(kitchen_light, 0x00~u8, 0xff~u8);\n    \
+                 }\n\
+             }\n"
+        );
+        assert_eq!(
+            format!("{:?}", Parser::parse(&decompiled).unwrap()),
+            format!("{:?}", config)
+        );
+    }
+
+    #[test]
+    fn block_three_children_test() {
+        let decompiled = round_trip(
+            "let a = 0~u32;
+            let b = false;
+            do {
+                match or {
+                    { StateEqualToConstFilter(a, 0x00000001~u32); },
+                    { StateEqualToConstFilter(a, 0x00000002~u32); },
+                    and {
+                        { FlipStateFilter(b); },
+                        { FlipStateFilter(b); },
+                        { FlipStateFilter(b); }
+                    }
+                }
+            }",
+        );
+
+        assert_eq!(decompiled.matches("or {").count(), 1);
+        assert_eq!(decompiled.matches("and {").count(), 1);
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt::{Display, Formatter};
+
+use crate::ast::Span;
+use crate::error::{ErrorKind, ParserError};
+use crate::location::Location;
+use crate::render::{collect_chain, render_snippet, RenderStyle};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// An error or warning. `span` is the byte range it is about in the file of `location`, which is
+/// empty when only its start is known. `code` is the name of the lint that reported it, if any.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub location: Location,
+    pub span: Span,
+    pub code: Option<String>,
+    pub message: String,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(location: Location, message: String) -> Self {
+        Self::new(Severity::Error, location, message)
+    }
+
+    pub fn warning(location: Location, message: String) -> Self {
+        Self::new(Severity::Warning, location, message)
+    }
+
+    pub fn new(severity: Severity, location: Location, message: String) -> Self {
+        Self {
+            severity,
+            span: Span {
+                start: location.offset,
+                end: location.offset,
+            },
+            location,
+            code: None,
+            message,
+            notes: vec![],
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    /// Renders the diagnostic against `source` the way rustc does, without colors.
+    pub fn render(&self, source: &str) -> String {
+        self.render_with_style(source, RenderStyle::Plain)
+    }
+
+    pub fn render_with_style(&self, source: &str, style: RenderStyle) -> String {
+        render_snippet(
+            source,
+            &self.heading(),
+            &self.message,
+            &self.location,
+            &self.notes,
+            style,
+        )
+    }
+}
+
+impl Diagnostic {
+    /// Returns the severity, followed by the code in brackets if there is one.
+    fn heading(&self) -> String {
+        match &self.code {
+            Some(code) => format!("{}[{}]", self.severity, code),
+            None => self.severity.to_string(),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}: {}", self.location, self.heading(), self.message)
+    }
+}
+
+impl From<&ParserError<Location>> for Diagnostic {
+    fn from(err: &ParserError<Location>) -> Self {
+        let mut chain = vec![];
+        let mut alt_notes = vec![];
+
+        collect_chain(err, &mut chain, &mut alt_notes);
+
+        // The deepest error is the most specific one, the rest of the chain only gives context
+        let primary_index = chain
+            .iter()
+            .rposition(|(_, kind)| !matches!(kind, ErrorKind::Nom(_)))
+            .unwrap_or(chain.len() - 1);
+        let (location, kind) = chain[primary_index];
+
+        let mut notes = vec![];
+
+        for (index, (location, kind)) in chain.iter().enumerate().rev() {
+            if index != primary_index && !matches!(kind, ErrorKind::Nom(_)) {
+                notes.push(format!("{} at {}", kind, location));
+            }
+        }
+
+        notes.append(&mut alt_notes);
+
+        Self {
+            notes,
+            ..Self::error(location.clone(), kind.to_string())
+        }
+    }
+}
+
+impl From<ParserError<Location>> for Diagnostic {
+    fn from(err: ParserError<Location>) -> Self {
+        Self::from(&err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::Expectation;
+
+    #[test]
+    fn from_parser_error_test() {
+        let source = "let a = false;\nconst b = 0x01;";
+        let err = ParserError::Base {
+            location: Location::new(None, source, 15),
+            kind: ErrorKind::Expected(Expectation::Something),
+            child: Some(Box::new(ParserError::Base {
+                location: Location::new(None, source, 25),
+                kind: ErrorKind::Expected(Expectation::Literal),
+                child: None,
+            })),
+        };
+
+        let diagnostic = Diagnostic::from(err);
+
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.location, Location::new(None, source, 25));
+        assert_eq!(diagnostic.message, "expected a literal");
+        assert_eq!(
+            diagnostic.notes,
+            vec!["expected something at 2:1".to_string()]
+        );
+    }
+
+    #[test]
+    fn display_test() {
+        let source = "let a = false;";
+        let diagnostic = Diagnostic::warning(
+            Location::new(Some("config.ross"), source, 4),
+            "unused variable".to_string(),
+        );
+
+        assert_eq!(
+            format!("{}", diagnostic),
+            "config.ross:1:5: warning: unused variable"
+        );
+    }
+
+    #[test]
+    fn render_test() {
+        let source = "let a = false;";
+        let diagnostic = Diagnostic::warning(
+            Location::new(Some("config.ross"), source, 4),
+            "unused variable".to_string(),
+        )
+        .with_note("remove it".to_string());
+
+        assert_eq!(
+            diagnostic.render(source),
+            "warning: unused variable\n \
+             --> config.ross:1:5\n  \
+             |\n\
+             1 | let a = false;\n  \
+             |     ^\n  \
+             = note: remove it"
+        );
+    }
+
+    #[test]
+    fn code_test() {
+        let source = "let a = false;";
+        let diagnostic = Diagnostic::warning(
+            Location::new(Some("config.ross"), source, 4),
+            "unused variable".to_string(),
+        )
+        .with_span(Span { start: 4, end: 5 })
+        .with_code("unused_let");
+
+        assert_eq!(
+            format!("{}", diagnostic),
+            "config.ross:1:5: warning[unused_let]: unused variable"
+        );
+        assert!(diagnostic
+            .render(source)
+            .starts_with("warning[unused_let]: unused variable\n"));
+    }
+}
@@ -3,6 +3,8 @@ use nom::error::{ErrorKind as NomErrorKind, FromExternalError, ParseError};
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter, Write};
 
+use crate::location::slice_offset;
+
 const MAX_LOCATION_LENGTH: usize = 50;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -15,6 +17,7 @@ pub enum Expectation {
     Literal,
     Value,
     Type,
+    Operator,
     Alpha,
     AlphaNumeric,
     Digit,
@@ -36,6 +39,7 @@ impl Display for Expectation {
             Expectation::Literal => write!(f, "a literal"),
             Expectation::Value => write!(f, "a value"),
             Expectation::Type => write!(f, "a type"),
+            Expectation::Operator => write!(f, "an operator"),
             Expectation::Alpha => write!(f, "an ascii letter"),
             Expectation::AlphaNumeric => write!(f, "an ascii alphanumeric character"),
             Expectation::Digit => write!(f, "an ascii digit"),
@@ -53,7 +57,27 @@ pub enum ErrorKind {
     UnknownExtractor,
     UnknownFilter,
     UnknownProducer,
+    UnknownConstant,
+    UnresolvedImport,
+    ImportCycle,
     CastFromToNotAllowed(&'static str, &'static str),
+    OperatorNotAllowed(&'static str, &'static str),
+    MismatchedTypes(&'static str, &'static str),
+    Overflow(&'static str),
+    IntegerOutOfRange(&'static str),
+    AmbiguousInteger,
+    DuplicateDeviceAddress(String),
+    DuplicateEventCode(String),
+    AlreadyDefined(String),
+    FirstDefinition(String),
+    BuiltInRedefined(String),
+    StateTypeNotAllowed(&'static str),
+    UndeclaredEventCode(&'static str),
+    ChainedComparison,
+    NeverMatches,
+    AlwaysMatches,
+    DivisionByZero,
+    ToggleCondition,
     External(Box<dyn Error + Send + Sync + 'static>),
 }
 
@@ -65,9 +89,54 @@ impl Display for ErrorKind {
             ErrorKind::UnknownExtractor => write!(f, "unknown extractor"),
             ErrorKind::UnknownFilter => write!(f, "unknown filter"),
             ErrorKind::UnknownProducer => write!(f, "unknown producer"),
+            ErrorKind::UnknownConstant => write!(f, "unknown constant"),
+            ErrorKind::UnresolvedImport => write!(f, "unresolved import"),
+            ErrorKind::ImportCycle => write!(f, "import cycle"),
             ErrorKind::CastFromToNotAllowed(from, to) => {
                 write!(f, "cast from {} to {} not allowed", from, to)
             }
+            ErrorKind::OperatorNotAllowed(operator, operand_type) => {
+                write!(f, "operator {} not allowed on {}", operator, operand_type)
+            }
+            ErrorKind::MismatchedTypes(left, right) => {
+                write!(f, "mismatched types {} and {}", left, right)
+            }
+            ErrorKind::Overflow(overflowed_type) => write!(f, "{} overflow", overflowed_type),
+            ErrorKind::DivisionByZero => write!(f, "division by zero"),
+            ErrorKind::IntegerOutOfRange(integer_type) => {
+                write!(f, "integer out of range for {}", integer_type)
+            }
+            ErrorKind::AmbiguousInteger => {
+                write!(
+                    f,
+                    "integer type cannot be inferred, add a ~u8, ~u16 or ~u32 suffix"
+                )
+            }
+            ErrorKind::DuplicateDeviceAddress(device) => {
+                write!(f, "address already used by device {}", device)
+            }
+            ErrorKind::DuplicateEventCode(event) => {
+                write!(f, "event code already used by {}", event)
+            }
+            ErrorKind::AlreadyDefined(name) => write!(f, "{} is already defined", name),
+            ErrorKind::FirstDefinition(name) => write!(f, "first definition of {}", name),
+            ErrorKind::BuiltInRedefined(name) => {
+                write!(f, "{} is a built-in constant and cannot be redefined", name)
+            }
+            ErrorKind::StateTypeNotAllowed(state_type) => {
+                write!(f, "state variable of type {} not allowed here", state_type)
+            }
+            ErrorKind::UndeclaredEventCode(value_type) => {
+                write!(
+                    f,
+                    "{} used as an event code, declare it with event",
+                    value_type
+                )
+            }
+            ErrorKind::ChainedComparison => write!(f, "comparison operators cannot be chained"),
+            ErrorKind::NeverMatches => write!(f, "condition can never be true"),
+            ErrorKind::AlwaysMatches => write!(f, "condition is always true"),
+            ErrorKind::ToggleCondition => write!(f, "toggle branches cannot have a condition"),
             ErrorKind::External(ref err) => {
                 writeln!(f, "external error:")?;
                 let mut f = IndentWriter::new("  ", f);
@@ -87,6 +156,66 @@ pub enum ParserError<I> {
     Alt(Vec<Self>),
 }
 
+impl<I> ParserError<I> {
+    pub fn location(&self) -> Option<&I> {
+        match self {
+            ParserError::Base { location, .. } => Some(location),
+            ParserError::Alt(_) => None,
+        }
+    }
+
+    pub fn map_location<J, F>(self, f: &mut F) -> ParserError<J>
+    where
+        F: FnMut(I) -> J,
+    {
+        match self {
+            ParserError::Base {
+                location,
+                kind,
+                child,
+            } => ParserError::Base {
+                location: f(location),
+                kind,
+                child: child.map(|child| Box::new(child.map_location(f))),
+            },
+            ParserError::Alt(siblings) => ParserError::Alt(
+                siblings
+                    .into_iter()
+                    .map(|sibling| sibling.map_location(f))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Moves the whole error to `location`. Used for errors that are created without knowing
+    /// where in the input they happened, like failed conversions of literals.
+    pub fn relocate<J: Clone>(self, location: J) -> ParserError<J> {
+        self.map_location(&mut |_| location.clone())
+    }
+}
+
+impl ParserError<&str> {
+    /// Returns how far into `input` the error got. Locations outside of `input` are ignored.
+    pub fn furthest_offset(&self, input: &str) -> Option<usize> {
+        match self {
+            ParserError::Base {
+                location, child, ..
+            } => {
+                let offset = slice_offset(input, location);
+                let child_offset = child
+                    .as_ref()
+                    .and_then(|child| child.furthest_offset(input));
+
+                offset.max(child_offset)
+            }
+            ParserError::Alt(siblings) => siblings
+                .iter()
+                .filter_map(|sibling| sibling.furthest_offset(input))
+                .max(),
+        }
+    }
+}
+
 impl<I: Display> Display for ParserError<I> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -107,7 +236,7 @@ impl<I: Display> Display for ParserError<I> {
                     location = location[0..MAX_LOCATION_LENGTH].to_string() + "...";
                 }
 
-                write!(f, "{} at '{}'", kind, location)?;
+                write!(f, "{} at {}", kind, location)?;
 
                 if let Some(child) = child {
                     if let ParserError::Alt(ref siblings) = **child {
@@ -178,7 +307,7 @@ impl<I> ParseError<I> for ParserError<I> {
 
     fn from_char(location: I, character: char) -> Self {
         ParserError::Base {
-            location: location,
+            location,
             kind: ErrorKind::Expected(Expectation::Symbol(character)),
             child: None,
         }
@@ -201,9 +330,10 @@ impl<I> ParseError<I> for ParserError<I> {
     }
 }
 
-impl<I> FromExternalError<I, ParserError<I>> for ParserError<I> {
-    fn from_external_error(_location: I, _kind: NomErrorKind, e: ParserError<I>) -> Self {
-        e
+impl<I: Clone> FromExternalError<I, ParserError<I>> for ParserError<I> {
+    fn from_external_error(location: I, _kind: NomErrorKind, e: ParserError<I>) -> Self {
+        // Errors passed through map_res come from literal conversions that have no location
+        e.relocate(location)
     }
 }
 
@@ -219,35 +349,6 @@ impl<I, E: Error + Send + Sync + 'static> FromExternalError<I, E> for ParserErro
 
 impl From<ParserError<&str>> for ParserError<String> {
     fn from(err: ParserError<&str>) -> ParserError<String> {
-        match err {
-            ParserError::Alt(siblings) => {
-                let mut new_siblings = vec![];
-                new_siblings.reserve(siblings.len());
-
-                for sibling in siblings {
-                    new_siblings.push(sibling.into());
-                }
-
-                ParserError::Alt(new_siblings)
-            }
-            ParserError::Base {
-                location,
-                kind,
-                child: Some(child),
-            } => ParserError::Base {
-                location: location.to_string(),
-                kind,
-                child: Some(Box::new((*child).into())),
-            },
-            ParserError::Base {
-                location,
-                kind,
-                child: None,
-            } => ParserError::Base {
-                location: location.to_string(),
-                kind,
-                child: None,
-            },
-        }
+        err.map_location(&mut |location: &str| location.to_string())
     }
 }
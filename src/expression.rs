@@ -0,0 +1,741 @@
+use nom::character::complete::multispace0;
+use nom::combinator::cut;
+use nom::sequence::{delimited, preceded, terminated};
+use nom::{Err as NomErr, IResult};
+use std::convert::TryFrom;
+
+use crate::ast::{BinaryOperator, Expression, Span, UnaryOperator};
+use crate::error::{ErrorKind, Expectation, ParserError};
+use crate::literal::Literal;
+use crate::parser::expression;
+use crate::symbol::{close_parenthesis, exclamation_mark, open_parenthesis};
+
+/// Longer symbols come first, so that `<<` is not taken for `<`.
+const BINARY_OPERATORS: [BinaryOperator; 18] = [
+    BinaryOperator::Shl,
+    BinaryOperator::Shr,
+    BinaryOperator::Le,
+    BinaryOperator::Ge,
+    BinaryOperator::Eq,
+    BinaryOperator::Ne,
+    BinaryOperator::And,
+    BinaryOperator::Or,
+    BinaryOperator::Mul,
+    BinaryOperator::Div,
+    BinaryOperator::Rem,
+    BinaryOperator::Add,
+    BinaryOperator::Sub,
+    BinaryOperator::BitAnd,
+    BinaryOperator::BitXor,
+    BinaryOperator::BitOr,
+    BinaryOperator::Lt,
+    BinaryOperator::Gt,
+];
+
+/// Parses an expression that is evaluated when lowering, like `base_address + 0x0003~u16`.
+/// Operands are literals, names of constants, parenthesized expressions and `!` applied to any
+/// of those.
+pub fn constant_expression<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Expression, ParserError<&'a str>> {
+    move |text| binary_expression(source, text, 0)
+}
+
+pub fn binary_operator(text: &str) -> IResult<&str, BinaryOperator, ParserError<&str>> {
+    for operator in BINARY_OPERATORS.iter() {
+        if let Some(input) = text.strip_prefix(operator.symbol()) {
+            return Ok((input, *operator));
+        }
+    }
+
+    Err(NomErr::Error(ParserError::Base {
+        location: text,
+        kind: ErrorKind::Expected(Expectation::Operator),
+        child: None,
+    }))
+}
+
+/// Parses operators of at least `min_precedence` with precedence climbing, so that operators
+/// of the same precedence associate to the left.
+fn binary_expression<'a>(
+    source: &'a str,
+    text: &'a str,
+    min_precedence: u8,
+) -> IResult<&'a str, Expression, ParserError<&'a str>> {
+    let (mut input, mut left) = unary_expression(source, text)?;
+
+    loop {
+        let (operand_text, operator) =
+            match delimited(multispace0, binary_operator, multispace0)(input) {
+                Ok(result) => result,
+                Err(NomErr::Error(_)) => return Ok((input, left)),
+                Err(err) => return Err(err),
+            };
+
+        if operator.precedence() < min_precedence {
+            return Ok((input, left));
+        }
+
+        // Like Rust, `a == b == c` is rejected rather than read as `(a == b) == c`
+        if let Expression::Binary {
+            operator: left_operator,
+            ..
+        } = &left
+        {
+            if is_comparison(*left_operator) && is_comparison(operator) {
+                return Err(NomErr::Failure(ParserError::Base {
+                    location: input.trim_start(),
+                    kind: ErrorKind::ChainedComparison,
+                    child: None,
+                }));
+            }
+        }
+
+        let (rest, right) =
+            cut(|text| binary_expression(source, text, operator.precedence() + 1))(operand_text)?;
+
+        left = Expression::Binary {
+            operator,
+            span: left.span().join(right.span()),
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+        input = rest;
+    }
+}
+
+fn is_comparison(operator: BinaryOperator) -> bool {
+    matches!(
+        operator,
+        BinaryOperator::Eq
+            | BinaryOperator::Ne
+            | BinaryOperator::Lt
+            | BinaryOperator::Le
+            | BinaryOperator::Gt
+            | BinaryOperator::Ge
+    )
+}
+
+fn unary_expression<'a>(
+    source: &'a str,
+    text: &'a str,
+) -> IResult<&'a str, Expression, ParserError<&'a str>> {
+    if let Ok((operand_text, _)) = terminated(exclamation_mark, multispace0)(text) {
+        let (input, operand) = cut(|text| unary_expression(source, text))(operand_text)?;
+
+        return Ok((
+            input,
+            Expression::Unary {
+                operator: UnaryOperator::Not,
+                operand: Box::new(operand),
+                span: Span::new(source, text, input),
+            },
+        ));
+    }
+
+    if let Ok((inner_text, _)) = terminated(open_parenthesis, multispace0)(text) {
+        let (input, expression) = cut(terminated(
+            |text| binary_expression(source, text, 0),
+            preceded(multispace0, close_parenthesis),
+        ))(inner_text)?;
+
+        return Ok((
+            input,
+            Expression::Parenthesized {
+                expression: Box::new(expression),
+                span: Span::new(source, text, input),
+            },
+        ));
+    }
+
+    expression(source)(text)
+}
+
+pub(crate) fn evaluate_unary(
+    operator: UnaryOperator,
+    operand: Literal,
+) -> Result<Literal, ErrorKind> {
+    match (operator, operand) {
+        (UnaryOperator::Not, Literal::U8(value)) => Ok(Literal::U8(!value)),
+        (UnaryOperator::Not, Literal::U16(value)) => Ok(Literal::U16(!value)),
+        (UnaryOperator::Not, Literal::U32(value)) => Ok(Literal::U32(!value)),
+        (UnaryOperator::Not, Literal::Bool(value)) => Ok(Literal::Bool(!value)),
+        (operator, operand) => Err(ErrorKind::OperatorNotAllowed(
+            operator.symbol(),
+            operand.type_name(),
+        )),
+    }
+}
+
+/// Applies `operator` with the typing rules of Rust: both operands have the same type, except
+/// for the amount of a shift, and integer results that do not fit their type are an overflow.
+/// Shifts drop the bits shifted out and only overflow when shifting by the width of the type or
+/// more.
+/// An integer without a suffix takes the type of the other operand.
+pub(crate) fn evaluate_binary(
+    operator: BinaryOperator,
+    left: Literal,
+    right: Literal,
+) -> Result<Literal, ErrorKind> {
+    let (left, right) = match operator {
+        BinaryOperator::Shl | BinaryOperator::Shr => (left, right),
+        _ => infer_integer_types(left, right)?,
+    };
+
+    match operator {
+        BinaryOperator::And | BinaryOperator::Or => {
+            let (left, right) = bool_operands(operator, &left, &right)?;
+
+            match operator {
+                BinaryOperator::And => Ok(Literal::Bool(left && right)),
+                _ => Ok(Literal::Bool(left || right)),
+            }
+        }
+        BinaryOperator::Eq | BinaryOperator::Ne => {
+            expect_same_type(&left, &right)?;
+
+            Ok(Literal::Bool(
+                (left == right) == (operator == BinaryOperator::Eq),
+            ))
+        }
+        BinaryOperator::Lt | BinaryOperator::Le | BinaryOperator::Gt | BinaryOperator::Ge => {
+            let (left, right) = integer_operands(operator, &left, &right)?;
+
+            match operator {
+                BinaryOperator::Lt => Ok(Literal::Bool(left < right)),
+                BinaryOperator::Le => Ok(Literal::Bool(left <= right)),
+                BinaryOperator::Gt => Ok(Literal::Bool(left > right)),
+                _ => Ok(Literal::Bool(left >= right)),
+            }
+        }
+        BinaryOperator::Shl | BinaryOperator::Shr => {
+            let value = integer(&left).ok_or_else(|| not_allowed(operator, &left))?;
+            let amount = integer(&right).ok_or_else(|| not_allowed(operator, &right))?;
+
+            if amount >= bits(&left) {
+                return Err(ErrorKind::Overflow(left.type_name()));
+            }
+
+            let mask = u64::MAX >> (64 - bits(&left));
+
+            match operator {
+                BinaryOperator::Shl => with_type_of(&left, (value << amount) & mask),
+                _ => with_type_of(&left, value >> amount),
+            }
+            .ok_or(ErrorKind::Overflow(left.type_name()))
+        }
+        BinaryOperator::BitAnd | BinaryOperator::BitXor | BinaryOperator::BitOr
+            if matches!(left, Literal::Bool(_)) =>
+        {
+            let (left, right) = bool_operands(operator, &left, &right)?;
+
+            match operator {
+                BinaryOperator::BitAnd => Ok(Literal::Bool(left & right)),
+                BinaryOperator::BitXor => Ok(Literal::Bool(left ^ right)),
+                _ => Ok(Literal::Bool(left | right)),
+            }
+        }
+        _ => {
+            let (left_value, right_value) = integer_operands(operator, &left, &right)?;
+
+            let value = match operator {
+                BinaryOperator::Mul => Some(left_value * right_value),
+                BinaryOperator::Div => left_value.checked_div(right_value),
+                BinaryOperator::Rem => left_value.checked_rem(right_value),
+                BinaryOperator::Add => Some(left_value + right_value),
+                BinaryOperator::Sub => left_value.checked_sub(right_value),
+                BinaryOperator::BitAnd => Some(left_value & right_value),
+                BinaryOperator::BitXor => Some(left_value ^ right_value),
+                _ => Some(left_value | right_value),
+            };
+
+            match value.map(|value| with_type_of(&left, value)) {
+                Some(Some(value)) => Ok(value),
+                None if right_value == 0 => Err(ErrorKind::DivisionByZero),
+                _ => Err(ErrorKind::Overflow(left.type_name())),
+            }
+        }
+    }
+}
+
+fn infer_integer_types(left: Literal, right: Literal) -> Result<(Literal, Literal), ErrorKind> {
+    let typed_integer =
+        |literal: &Literal| matches!(literal, Literal::U8(_) | Literal::U16(_) | Literal::U32(_));
+
+    match (&left, &right) {
+        (Literal::Integer(value), typed) if typed_integer(typed) => {
+            let left = with_type_of(typed, *value as u64)
+                .ok_or(ErrorKind::IntegerOutOfRange(typed.type_name()))?;
+
+            Ok((left, right))
+        }
+        (typed, Literal::Integer(value)) if typed_integer(typed) => {
+            let right = with_type_of(typed, *value as u64)
+                .ok_or(ErrorKind::IntegerOutOfRange(typed.type_name()))?;
+
+            Ok((left, right))
+        }
+        _ => Ok((left, right)),
+    }
+}
+
+fn expect_same_type(left: &Literal, right: &Literal) -> Result<(), ErrorKind> {
+    if left.type_name() == right.type_name() {
+        Ok(())
+    } else {
+        Err(ErrorKind::MismatchedTypes(
+            left.type_name(),
+            right.type_name(),
+        ))
+    }
+}
+
+fn bool_operands(
+    operator: BinaryOperator,
+    left: &Literal,
+    right: &Literal,
+) -> Result<(bool, bool), ErrorKind> {
+    expect_same_type(left, right)?;
+
+    match (left, right) {
+        (Literal::Bool(left), Literal::Bool(right)) => Ok((*left, *right)),
+        _ => Err(not_allowed(operator, left)),
+    }
+}
+
+/// Widens both operands to `u64`, in which no operation on two `u32` values can overflow.
+fn integer_operands(
+    operator: BinaryOperator,
+    left: &Literal,
+    right: &Literal,
+) -> Result<(u64, u64), ErrorKind> {
+    expect_same_type(left, right)?;
+
+    match (integer(left), integer(right)) {
+        (Some(left), Some(right)) => Ok((left, right)),
+        _ => Err(not_allowed(operator, left)),
+    }
+}
+
+fn integer(literal: &Literal) -> Option<u64> {
+    match literal {
+        Literal::U8(value) => Some(*value as u64),
+        Literal::U16(value) => Some(*value as u64),
+        Literal::U32(value) => Some(*value as u64),
+        Literal::Integer(value) => Some(*value as u64),
+        _ => None,
+    }
+}
+
+fn bits(literal: &Literal) -> u64 {
+    match literal {
+        Literal::U8(_) => 8,
+        Literal::U16(_) => 16,
+        _ => 32,
+    }
+}
+
+/// Returns `value` as a literal of the same integer type as `literal`, if it fits.
+fn with_type_of(literal: &Literal, value: u64) -> Option<Literal> {
+    match literal {
+        Literal::U8(_) => u8::try_from(value).ok().map(Literal::U8),
+        Literal::U16(_) => u16::try_from(value).ok().map(Literal::U16),
+        Literal::Integer(_) => u32::try_from(value).ok().map(Literal::Integer),
+        _ => u32::try_from(value).ok().map(Literal::U32),
+    }
+}
+
+fn not_allowed(operator: BinaryOperator, operand: &Literal) -> ErrorKind {
+    ErrorKind::OperatorNotAllowed(operator.symbol(), operand.type_name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+
+    use crate::ast::Name;
+
+    #[test]
+    fn precedence_test() {
+        let text = "a + b * c;input";
+
+        assert_matches!(
+            constant_expression(text)(text),
+            Ok((input, expression)) => {
+                assert_eq!(input, ";input");
+                assert_eq!(
+                    expression,
+                    Expression::Binary {
+                        operator: BinaryOperator::Add,
+                        left: Box::new(Expression::Constant(Name {
+                            value: "a".to_string(),
+                            span: Span { start: 0, end: 1 },
+                        })),
+                        right: Box::new(Expression::Binary {
+                            operator: BinaryOperator::Mul,
+                            left: Box::new(Expression::Constant(Name {
+                                value: "b".to_string(),
+                                span: Span { start: 4, end: 5 },
+                            })),
+                            right: Box::new(Expression::Constant(Name {
+                                value: "c".to_string(),
+                                span: Span { start: 8, end: 9 },
+                            })),
+                            span: Span { start: 4, end: 9 },
+                        }),
+                        span: Span { start: 0, end: 9 },
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn left_associativity_test() {
+        let text = "a-b-c";
+
+        assert_matches!(
+            constant_expression(text)(text),
+            Ok(("", Expression::Binary { operator: BinaryOperator::Sub, left, right, .. })) => {
+                assert_matches!(*left, Expression::Binary { operator: BinaryOperator::Sub, .. });
+                assert_matches!(*right, Expression::Constant(Name { value, .. }) => {
+                    assert_eq!(value, "c");
+                });
+            }
+        );
+    }
+
+    #[test]
+    fn parenthesized_test() {
+        let text = "!( a << 0x01~u8 ) == false;input";
+
+        assert_matches!(
+            constant_expression(text)(text),
+            Ok((";input", Expression::Binary { operator: BinaryOperator::Eq, left, .. })) => {
+                assert_matches!(*left, Expression::Unary { operand, span, .. } => {
+                    assert_eq!(span, Span { start: 0, end: 17 });
+                    assert_matches!(*operand, Expression::Parenthesized { expression, span } => {
+                        assert_eq!(span, Span { start: 1, end: 17 });
+                        assert_matches!(*expression, Expression::Binary {
+                            operator: BinaryOperator::Shl,
+                            ..
+                        });
+                    });
+                });
+            }
+        );
+    }
+
+    #[test]
+    fn single_operand_test() {
+        let text = "0x01~u8;input";
+
+        assert_matches!(
+            constant_expression(text)(text),
+            Ok((
+                ";input",
+                Expression::Literal {
+                    value: Literal::U8(0x01),
+                    ..
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn missing_operand_test() {
+        let text = "0x01~u8 + ;input";
+
+        assert_matches!(
+            constant_expression(text)(text),
+            Err(NomErr::Failure(ParserError::Base { location, .. })) => {
+                assert_eq!(location, ";input");
+            }
+        );
+    }
+
+    #[test]
+    fn unclosed_parenthesis_test() {
+        let text = "(0x01~u8 + 0x02~u8;input";
+
+        assert_matches!(
+            constant_expression(text)(text),
+            Err(NomErr::Failure(ParserError::Base { location, kind, .. })) => {
+                assert_eq!(location, ";input");
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Symbol(')')));
+            }
+        );
+    }
+
+    #[test]
+    fn chained_comparison_test() {
+        let text = "a == b == c;input";
+
+        assert_matches!(
+            constant_expression(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind: ErrorKind::ChainedComparison,
+                ..
+            })) => {
+                assert_eq!(location, "== c;input");
+            }
+        );
+    }
+
+    #[test]
+    fn parenthesized_comparison_test() {
+        let text = "(a < b) == c;input";
+
+        assert_matches!(
+            constant_expression(text)(text),
+            Ok((
+                ";input",
+                Expression::Binary {
+                    operator: BinaryOperator::Eq,
+                    ..
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn arithmetic_test() {
+        let evaluate = |operator, left, right| evaluate_binary(operator, left, right).unwrap();
+
+        assert_eq!(
+            evaluate(BinaryOperator::Add, Literal::U8(1), Literal::U8(2)),
+            Literal::U8(3)
+        );
+        assert_eq!(
+            evaluate(BinaryOperator::Sub, Literal::U16(5), Literal::U16(3)),
+            Literal::U16(2)
+        );
+        assert_eq!(
+            evaluate(BinaryOperator::Mul, Literal::U32(6), Literal::U32(7)),
+            Literal::U32(42)
+        );
+        assert_eq!(
+            evaluate(BinaryOperator::Div, Literal::U8(7), Literal::U8(2)),
+            Literal::U8(3)
+        );
+        assert_eq!(
+            evaluate(BinaryOperator::Rem, Literal::U8(7), Literal::U8(2)),
+            Literal::U8(1)
+        );
+        assert_eq!(
+            evaluate(BinaryOperator::Shl, Literal::U16(1), Literal::U8(15)),
+            Literal::U16(0x8000)
+        );
+        assert_eq!(
+            evaluate(BinaryOperator::Shr, Literal::U8(0x80), Literal::U32(7)),
+            Literal::U8(1)
+        );
+        assert_eq!(
+            evaluate(BinaryOperator::BitAnd, Literal::U8(0x0f), Literal::U8(0x3c)),
+            Literal::U8(0x0c)
+        );
+        assert_eq!(
+            evaluate(BinaryOperator::BitXor, Literal::U8(0x0f), Literal::U8(0x3c)),
+            Literal::U8(0x33)
+        );
+        assert_eq!(
+            evaluate(BinaryOperator::BitOr, Literal::U8(0x0f), Literal::U8(0x3c)),
+            Literal::U8(0x3f)
+        );
+    }
+
+    #[test]
+    fn overflow_test() {
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Add, Literal::U8(0xff), Literal::U8(1)),
+            Err(ErrorKind::Overflow("u8"))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Sub, Literal::U16(0), Literal::U16(1)),
+            Err(ErrorKind::Overflow("u16"))
+        );
+        assert_matches!(
+            evaluate_binary(
+                BinaryOperator::Mul,
+                Literal::U32(0x1_0000),
+                Literal::U32(0x1_0000)
+            ),
+            Err(ErrorKind::Overflow("u32"))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Shl, Literal::U8(0xff), Literal::U8(1)),
+            Ok(Literal::U8(0xfe))
+        );
+        assert_matches!(
+            evaluate_binary(
+                BinaryOperator::Shl,
+                Literal::U32(0xffff_ffff),
+                Literal::U8(31)
+            ),
+            Ok(Literal::U32(0x8000_0000))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Shl, Literal::U16(1), Literal::U8(16)),
+            Err(ErrorKind::Overflow("u16"))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Shr, Literal::U8(1), Literal::U8(8)),
+            Err(ErrorKind::Overflow("u8"))
+        );
+    }
+
+    #[test]
+    fn division_by_zero_test() {
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Div, Literal::U8(1), Literal::U8(0)),
+            Err(ErrorKind::DivisionByZero)
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Rem, Literal::U32(1), Literal::U32(0)),
+            Err(ErrorKind::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn comparison_test() {
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Lt, Literal::U8(1), Literal::U8(2)),
+            Ok(Literal::Bool(true))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Ge, Literal::U16(1), Literal::U16(2)),
+            Ok(Literal::Bool(false))
+        );
+        assert_matches!(
+            evaluate_binary(
+                BinaryOperator::Eq,
+                Literal::String("a".to_string()),
+                Literal::String("a".to_string())
+            ),
+            Ok(Literal::Bool(true))
+        );
+        assert_matches!(
+            evaluate_binary(
+                BinaryOperator::Ne,
+                Literal::Rgb(0, 0, 0),
+                Literal::Rgb(0, 0, 1)
+            ),
+            Ok(Literal::Bool(true))
+        );
+        assert_matches!(
+            evaluate_binary(
+                BinaryOperator::Lt,
+                Literal::Bool(false),
+                Literal::Bool(true)
+            ),
+            Err(ErrorKind::OperatorNotAllowed("<", "bool"))
+        );
+    }
+
+    #[test]
+    fn bool_test() {
+        assert_matches!(
+            evaluate_binary(
+                BinaryOperator::And,
+                Literal::Bool(true),
+                Literal::Bool(false)
+            ),
+            Ok(Literal::Bool(false))
+        );
+        assert_matches!(
+            evaluate_binary(
+                BinaryOperator::Or,
+                Literal::Bool(true),
+                Literal::Bool(false)
+            ),
+            Ok(Literal::Bool(true))
+        );
+        assert_matches!(
+            evaluate_binary(
+                BinaryOperator::BitXor,
+                Literal::Bool(true),
+                Literal::Bool(true)
+            ),
+            Ok(Literal::Bool(false))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::And, Literal::U8(1), Literal::U8(1)),
+            Err(ErrorKind::OperatorNotAllowed("&&", "u8"))
+        );
+        assert_matches!(
+            evaluate_unary(UnaryOperator::Not, Literal::Bool(true)),
+            Ok(Literal::Bool(false))
+        );
+        assert_matches!(
+            evaluate_unary(UnaryOperator::Not, Literal::U8(0x0f)),
+            Ok(Literal::U8(0xf0))
+        );
+        assert_matches!(
+            evaluate_unary(UnaryOperator::Not, Literal::String("a".to_string())),
+            Err(ErrorKind::OperatorNotAllowed("!", "string"))
+        );
+    }
+
+    #[test]
+    fn integer_inference_test() {
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Add, Literal::U8(1), Literal::Integer(2)),
+            Ok(Literal::U8(3))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Lt, Literal::Integer(1), Literal::U16(2)),
+            Ok(Literal::Bool(true))
+        );
+        assert_matches!(
+            evaluate_binary(
+                BinaryOperator::Mul,
+                Literal::Integer(6),
+                Literal::Integer(7)
+            ),
+            Ok(Literal::Integer(42))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Shl, Literal::Integer(1), Literal::U8(4)),
+            Ok(Literal::Integer(16))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Add, Literal::U8(1), Literal::Integer(0x100)),
+            Err(ErrorKind::IntegerOutOfRange("u8"))
+        );
+        assert_matches!(
+            evaluate_binary(
+                BinaryOperator::Add,
+                Literal::Bool(true),
+                Literal::Integer(1)
+            ),
+            Err(ErrorKind::MismatchedTypes("bool", "integer"))
+        );
+        assert_matches!(
+            evaluate_unary(UnaryOperator::Not, Literal::Integer(1)),
+            Err(ErrorKind::OperatorNotAllowed("!", "integer"))
+        );
+    }
+
+    #[test]
+    fn mismatched_types_test() {
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Add, Literal::U8(1), Literal::U16(1)),
+            Err(ErrorKind::MismatchedTypes("u8", "u16"))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Eq, Literal::Bool(true), Literal::U8(1)),
+            Err(ErrorKind::MismatchedTypes("bool", "u8"))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::BitOr, Literal::Bool(true), Literal::U8(1)),
+            Err(ErrorKind::MismatchedTypes("bool", "u8"))
+        );
+        assert_matches!(
+            evaluate_binary(BinaryOperator::Shl, Literal::Bool(true), Literal::U8(1)),
+            Err(ErrorKind::OperatorNotAllowed("<<", "bool"))
+        );
+    }
+}
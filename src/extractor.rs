@@ -19,22 +19,80 @@ pub fn extractor<'a>(
             semicolon,
         )(text)?;
 
-        impl_item_arg0!(input, name, arguments, NoneExtractor);
-        impl_item_arg0!(input, name, arguments, PacketExtractor);
-        impl_item_arg0!(input, name, arguments, EventCodeExtractor);
-        impl_item_arg0!(input, name, arguments, EventProducerAddressExtractor);
-        impl_item_arg0!(input, name, arguments, MessageCodeExtractor);
-        impl_item_arg0!(input, name, arguments, MessageValueExtractor);
-        impl_item_arg0!(input, name, arguments, ButtonIndexExtractor);
-
-        Err(Err::Error(ParserError::Base {
-            location: name,
-            kind: ErrorKind::UnknownExtractor,
-            child: None,
-        }))
+        let argument_locations = vec![name; arguments.len()];
+        let extractor =
+            create_extractor(name, &arguments, input, &argument_locations).map_err(Err::Error)?;
+
+        Ok((input, extractor))
     }
 }
 
+/// Creates the extractor called `name`. Argument count errors are reported at `location`. None of
+/// the extractors take arguments, so `_argument_locations` is only there to match `create_filter`
+/// and `create_producer`.
+pub fn create_extractor<L: Clone>(
+    name: &str,
+    arguments: &[Literal],
+    location: L,
+    _argument_locations: &[L],
+) -> Result<Box<dyn Extractor>, ParserError<L>> {
+    impl_item_arg0!(
+        name,
+        arguments,
+        location,
+        _argument_locations,
+        NoneExtractor
+    );
+    impl_item_arg0!(
+        name,
+        arguments,
+        location,
+        _argument_locations,
+        PacketExtractor
+    );
+    impl_item_arg0!(
+        name,
+        arguments,
+        location,
+        _argument_locations,
+        EventCodeExtractor
+    );
+    impl_item_arg0!(
+        name,
+        arguments,
+        location,
+        _argument_locations,
+        EventProducerAddressExtractor
+    );
+    impl_item_arg0!(
+        name,
+        arguments,
+        location,
+        _argument_locations,
+        MessageCodeExtractor
+    );
+    impl_item_arg0!(
+        name,
+        arguments,
+        location,
+        _argument_locations,
+        MessageValueExtractor
+    );
+    impl_item_arg0!(
+        name,
+        arguments,
+        location,
+        _argument_locations,
+        ButtonIndexExtractor
+    );
+
+    Err(ParserError::Base {
+        location,
+        kind: ErrorKind::UnknownExtractor,
+        child: None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -20,30 +20,135 @@ pub fn filter<'a>(
             semicolon,
         )(text)?;
 
-        impl_item_arg1!(input, name, arguments, ValueEqualToConstFilter);
-        impl_item_arg2!(input, name, arguments, StateEqualToConstFilter);
-        impl_item_arg1!(input, name, arguments, StateEqualToValueFilter);
-        impl_item_arg2!(input, name, arguments, IncrementStateByConstFilter);
-        impl_item_arg1!(input, name, arguments, IncrementStateByValueFilter);
-        impl_item_arg2!(input, name, arguments, DecrementStateByConstFilter);
-        impl_item_arg1!(input, name, arguments, DecrementStateByValueFilter);
-        impl_item_arg2!(input, name, arguments, SetStateToConstFilter);
-        impl_item_arg1!(input, name, arguments, SetStateToValueFilter);
-        impl_item_arg1!(input, name, arguments, FlipStateFilter);
-        impl_item_arg1!(input, name, arguments, TimeMatchesCronExpressionFilter);
-        impl_item_arg2!(input, name, arguments, StateMoreThanConstFilter);
-        impl_item_arg2!(input, name, arguments, StateLessThatConstFilter);
-        impl_item_arg2!(input, name, arguments, SetStateToStateFilter);
-        impl_item_arg2!(input, name, arguments, StateEqualToStateFilter);
+        let argument_locations = vec![name; arguments.len()];
+        let filter =
+            create_filter(name, &arguments, input, &argument_locations).map_err(Err::Error)?;
 
-        Err(Err::Error(ParserError::Base {
-            location: name,
-            kind: ErrorKind::UnknownFilter,
-            child: None,
-        }))
+        Ok((input, filter))
     }
 }
 
+/// Creates the filter called `name`. Argument count errors are reported at `location`, while
+/// errors of individual arguments are reported at their entry in `argument_locations`.
+pub fn create_filter<L: Clone>(
+    name: &str,
+    arguments: &[Literal],
+    location: L,
+    argument_locations: &[L],
+) -> Result<Box<dyn Filter>, ParserError<L>> {
+    impl_item_arg1!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        ValueEqualToConstFilter
+    );
+    impl_item_arg2!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        StateEqualToConstFilter
+    );
+    impl_item_arg1!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        StateEqualToValueFilter
+    );
+    impl_item_arg2!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        IncrementStateByConstFilter
+    );
+    impl_item_arg1!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        IncrementStateByValueFilter
+    );
+    impl_item_arg2!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        DecrementStateByConstFilter
+    );
+    impl_item_arg1!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        DecrementStateByValueFilter
+    );
+    impl_item_arg2!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        SetStateToConstFilter
+    );
+    impl_item_arg1!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        SetStateToValueFilter
+    );
+    impl_item_arg1!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        FlipStateFilter
+    );
+    impl_item_arg1!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        TimeMatchesCronExpressionFilter
+    );
+    impl_item_arg2!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        StateMoreThanConstFilter
+    );
+    impl_item_arg2!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        StateLessThatConstFilter
+    );
+    impl_item_arg2!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        SetStateToStateFilter
+    );
+    impl_item_arg2!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        StateEqualToStateFilter
+    );
+
+    Err(ParserError::Base {
+        location,
+        kind: ErrorKind::UnknownFilter,
+        child: None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,164 @@
+use crate::error::ParserError;
+use crate::location::Location;
+use crate::parser::Parser;
+use crate::printer::Printer;
+
+/// Re-emits `text` in the canonical layout of `Printer`, keeping its comments. Formatting the
+/// result again does not change it.
+pub fn format(text: &str) -> Result<String, ParserError<Location>> {
+    let program = Parser::parse_program(text)?;
+
+    Ok(Printer::print_with_comments(
+        &program,
+        text,
+        Parser::comments(text),
+    ))
+}
+
+/// Same as `format`, but error locations also carry `file_name`.
+pub fn format_named(file_name: &str, text: &str) -> Result<String, ParserError<Location>> {
+    let program = Parser::parse_named_program(file_name, text)?;
+
+    Ok(Printer::print_with_comments(
+        &program,
+        text,
+        Parser::comments(text),
+    ))
+}
+
+/// Returns whether `text` is already formatted.
+pub fn check(text: &str) -> Result<bool, ParserError<Location>> {
+    Ok(format(text)? == text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+
+    const FORMATTED: &str = "\
+// Devices
+const device_address   = 0x0002~u16; // this device
+const receiver_address = 0x0003~u16;
+
+const led_channel = 0x00~u8;
+
+let active = false;
+
+do {
+    // Flips the state on every press
+    match event BUTTON_PRESSED_EVENT_CODE;
+    match producer device_address;
+    match {
+        FlipStateFilter(active); // flip
+    }
+    fire {
+        BcmChangeBrightnessProducer(receiver_address, led_channel, 0xff~u8);
+    } if match {
+        StateEqualToConstFilter(active, true);
+    }
+    // TODO: turn off
+}
+// end
+";
+
+    #[test]
+    fn format_test() {
+        let text = "
+            // Devices
+            const device_address = 0x0002~u16; // this device
+            const receiver_address=0x0003~u16;
+
+
+            const led_channel = 0~u8;
+            let active = false;
+            do {
+                    // Flips the state on every press
+                match event BUTTON_PRESSED_EVENT_CODE;
+                match producer device_address;
+                match { FlipStateFilter(active); // flip
+                }
+                fire { BcmChangeBrightnessProducer(receiver_address, led_channel, 255~u8); } if match {
+                    StateEqualToConstFilter(active, true);
+                }
+                // TODO: turn off
+            }
+            // end";
+
+        assert_eq!(format(text).unwrap(), FORMATTED);
+    }
+
+    #[test]
+    fn idempotence_test() {
+        assert_eq!(format(FORMATTED).unwrap(), FORMATTED);
+        assert_matches!(check(FORMATTED), Ok(true));
+    }
+
+    #[test]
+    fn check_unformatted_test() {
+        assert_matches!(check("let a=false;"), Ok(false));
+    }
+
+    #[test]
+    fn empty_test() {
+        assert_eq!(format("  \n").unwrap(), "");
+        assert_eq!(
+            format("// only a comment\n").unwrap(),
+            "// only a comment\n"
+        );
+    }
+
+    #[test]
+    fn test_name_with_slashes_test() {
+        let text = "let active = false;
+            // initial state
+            test \"on // off\" { expect state active == false; }";
+
+        assert_eq!(
+            format(text).unwrap(),
+            "let active = false;\n\
+             \n\
+             // initial state\n\
+             test \"on // off\" {\n    \
+                 expect state active == false;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn integer_radix_test() {
+        let text = "event my_event = 0x0100;
+            device kitchen_light = 0x0003;
+            device kitchen_switch = 2;
+            do {
+                match event my_event;
+                fire { BcmChangeBrightnessProducer(kitchen_light, 0, 0xFF); }
+            }";
+
+        assert_eq!(
+            format(text).unwrap(),
+            "event my_event = 0x0100;\n\
+             \n\
+             device kitchen_light  = 0x0003;\n\
+             device kitchen_switch = 2;\n\
+             \n\
+             do {\n    \
+                 match event my_event;\n    \
+                 fire {\n        \
+                     BcmChangeBrightnessProducer(kitchen_light, 0, 0xFF);\n    \
+                 }\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn format_named_error_test() {
+        assert_matches!(
+            format_named("config.ross", "let a = ;"),
+            Err(ParserError::Base { location, .. }) => {
+                assert_eq!(location.file, Some("config.ross".to_string()));
+            }
+        );
+    }
+}
@@ -1,19 +1,138 @@
+use nom::branch::alt;
+use nom::character::complete::multispace0;
+use nom::combinator::map;
+use nom::sequence::{delimited, pair, terminated};
+use nom::IResult;
+
+use crate::ast::{ItemCall, Span};
+use crate::error::ParserError;
+use crate::parser::{argument_expression0, name};
+use crate::symbol::semicolon;
+
+/// Parses a call of an extractor, filter or producer without resolving it.
+pub fn item_call<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, ItemCall, ParserError<&'a str>> {
+    move |text| {
+        let (input, (name, arguments)) =
+            terminated(pair(name(source), argument_expression0(source)), semicolon)(text)?;
+
+        Ok((
+            input,
+            ItemCall {
+                name,
+                arguments,
+                span: Span::new(source, text, input),
+            },
+        ))
+    }
+}
+
+/// Parses the contents of a `match { ... }` or `fire { ... }` block, which is a filter or producer
+/// call, optionally preceded by an extractor call.
+pub fn extractor_and_item_call<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (Option<ItemCall>, ItemCall), ParserError<&'a str>> {
+    move |text| {
+        let item_call_parser = || delimited(multispace0, item_call(source), multispace0);
+
+        alt((
+            map(
+                pair(item_call_parser(), item_call_parser()),
+                |(extractor, item)| (Some(extractor), item),
+            ),
+            map(item_call_parser(), |item| (None, item)),
+        ))(text)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) enum ArgumentKind {
+    U8,
+    U16,
+    U32,
+    /// A `u16` that is the address of a device.
+    Address,
+    /// A `u32` that is the index of a state variable.
+    StateIndex,
+    Value,
+    BcmValue,
+    RelayValue,
+    CronExpression,
+}
+
+/// Argument kinds of every item `create_extractor`, `create_filter` and `create_producer` know,
+/// in the order they take them, which is also the order their `Debug` output lists the fields.
+pub(crate) fn item_signature(item_name: &str) -> Option<&'static [ArgumentKind]> {
+    use ArgumentKind::*;
+
+    let signature: &'static [ArgumentKind] = match item_name {
+        "NoneExtractor"
+        | "PacketExtractor"
+        | "EventCodeExtractor"
+        | "EventProducerAddressExtractor"
+        | "MessageCodeExtractor"
+        | "MessageValueExtractor"
+        | "ButtonIndexExtractor"
+        | "NoneProducer" => &[],
+        "ValueEqualToConstFilter" => &[Value],
+        "TimeMatchesCronExpressionFilter" => &[CronExpression],
+        "StateEqualToValueFilter"
+        | "IncrementStateByValueFilter"
+        | "DecrementStateByValueFilter"
+        | "SetStateToValueFilter"
+        | "FlipStateFilter" => &[StateIndex],
+        "StateEqualToConstFilter"
+        | "IncrementStateByConstFilter"
+        | "DecrementStateByConstFilter"
+        | "SetStateToConstFilter"
+        | "StateMoreThanConstFilter"
+        | "StateLessThatConstFilter" => &[StateIndex, Value],
+        "SetStateToStateFilter" | "StateEqualToStateFilter" => &[StateIndex, StateIndex],
+        "PacketProducer" => &[Address],
+        "MessageProducer" => &[Address, U16, Value],
+        "BcmChangeBrightnessProducer" => &[Address, U8, BcmValue],
+        "BcmChangeBrightnessStateProducer" => &[Address, U8, StateIndex],
+        "BcmAnimateBrightnessProducer" => &[Address, U8, U32, BcmValue],
+        "BcmAnimateBrightnessStateProducer" => &[Address, U8, U32, StateIndex],
+        "RelaySetValueProducer" => &[Address, U8, RelayValue],
+        _ => return None,
+    };
+
+    Some(signature)
+}
+
+/// Types of the state variables an item can use, for the items that cannot use every type.
+pub(crate) fn state_types(item_name: &str) -> Option<&'static [&'static str]> {
+    match item_name {
+        "IncrementStateByConstFilter"
+        | "IncrementStateByValueFilter"
+        | "DecrementStateByConstFilter"
+        | "DecrementStateByValueFilter"
+        | "StateMoreThanConstFilter"
+        | "StateLessThatConstFilter" => Some(&["u8", "u16", "u32"]),
+        "FlipStateFilter" => Some(&["bool"]),
+        "BcmChangeBrightnessStateProducer" | "BcmAnimateBrightnessStateProducer" => {
+            Some(&["u8", "rgb", "rgbw"])
+        }
+        _ => None,
+    }
+}
+
 #[macro_export]
 macro_rules! impl_item_arg0 {
-    ($input:expr, $name:expr, $arguments:expr, $item_type:ty) => {
+    ($name:expr, $arguments:expr, $location:expr, $argument_locations:expr, $item_type:ty) => {
         if $name == stringify!($item_type) {
-            use nom::Err as NomErr;
-
-            use crate::error::{ErrorKind, Expectation, ParserError};
+            use $crate::error::{ErrorKind, Expectation, ParserError};
 
             return if $arguments.len() == 0 {
-                Ok(($input, Box::new(<$item_type>::new())))
+                Ok(Box::new(<$item_type>::new()))
             } else {
-                Err(NomErr::Error(ParserError::Base {
-                    location: $input,
+                Err(ParserError::Base {
+                    location: $location.clone(),
                     kind: ErrorKind::Expected(Expectation::ArgumentCount(0, $arguments.len())),
                     child: None,
-                }))
+                })
             };
         }
     };
@@ -21,28 +140,25 @@ macro_rules! impl_item_arg0 {
 
 #[macro_export]
 macro_rules! impl_item_arg1 {
-    ($input:expr, $name:expr, $arguments:expr, $item_type:ty) => {
+    ($name:expr, $arguments:expr, $location:expr, $argument_locations:expr, $item_type:ty) => {
         if $name == stringify!($item_type) {
-            use nom::Err as NomErr;
-
-            use crate::error::{ErrorKind, Expectation, ParserError};
+            use $crate::error::{ErrorKind, Expectation, ParserError};
 
             return if $arguments.len() == 1 {
-                Ok((
-                    $input,
-                    Box::new(<$item_type>::new(
-                        $arguments[0]
-                            .clone()
-                            .try_into()
-                            .map_err(|err| Err::Error(err))?,
-                    )),
-                ))
+                Ok(Box::new(<$item_type>::new(
+                    $arguments[0]
+                        .clone()
+                        .try_into()
+                        .map_err(|err: ParserError<&str>| {
+                            err.relocate($argument_locations[0].clone())
+                        })?,
+                )))
             } else {
-                Err(NomErr::Error(ParserError::Base {
-                    location: $input,
+                Err(ParserError::Base {
+                    location: $location.clone(),
                     kind: ErrorKind::Expected(Expectation::ArgumentCount(1, $arguments.len())),
                     child: None,
-                }))
+                })
             };
         }
     };
@@ -50,32 +166,31 @@ macro_rules! impl_item_arg1 {
 
 #[macro_export]
 macro_rules! impl_item_arg2 {
-    ($input:expr, $name:expr, $arguments:expr, $item_type:ty) => {
+    ($name:expr, $arguments:expr, $location:expr, $argument_locations:expr, $item_type:ty) => {
         if $name == stringify!($item_type) {
-            use nom::Err as NomErr;
-
-            use crate::error::{ErrorKind, Expectation, ParserError};
+            use $crate::error::{ErrorKind, Expectation, ParserError};
 
             return if $arguments.len() == 2 {
-                Ok((
-                    $input,
-                    Box::new(<$item_type>::new(
-                        $arguments[0]
-                            .clone()
-                            .try_into()
-                            .map_err(|err| Err::Error(err))?,
-                        $arguments[1]
-                            .clone()
-                            .try_into()
-                            .map_err(|err| Err::Error(err))?,
-                    )),
-                ))
+                Ok(Box::new(<$item_type>::new(
+                    $arguments[0]
+                        .clone()
+                        .try_into()
+                        .map_err(|err: ParserError<&str>| {
+                            err.relocate($argument_locations[0].clone())
+                        })?,
+                    $arguments[1]
+                        .clone()
+                        .try_into()
+                        .map_err(|err: ParserError<&str>| {
+                            err.relocate($argument_locations[1].clone())
+                        })?,
+                )))
             } else {
-                Err(NomErr::Error(ParserError::Base {
-                    location: $input,
+                Err(ParserError::Base {
+                    location: $location.clone(),
                     kind: ErrorKind::Expected(Expectation::ArgumentCount(2, $arguments.len())),
                     child: None,
-                }))
+                })
             };
         }
     };
@@ -83,36 +198,37 @@ macro_rules! impl_item_arg2 {
 
 #[macro_export]
 macro_rules! impl_item_arg3 {
-    ($input:expr, $name:expr, $arguments:expr, $item_type:ty) => {
+    ($name:expr, $arguments:expr, $location:expr, $argument_locations:expr, $item_type:ty) => {
         if $name == stringify!($item_type) {
-            use nom::Err as NomErr;
-
-            use crate::error::{ErrorKind, Expectation, ParserError};
+            use $crate::error::{ErrorKind, Expectation, ParserError};
 
             return if $arguments.len() == 3 {
-                Ok((
-                    $input,
-                    Box::new(<$item_type>::new(
-                        $arguments[0]
-                            .clone()
-                            .try_into()
-                            .map_err(|err| Err::Error(err))?,
-                        $arguments[1]
-                            .clone()
-                            .try_into()
-                            .map_err(|err| Err::Error(err))?,
-                        $arguments[2]
-                            .clone()
-                            .try_into()
-                            .map_err(|err| Err::Error(err))?,
-                    )),
-                ))
+                Ok(Box::new(<$item_type>::new(
+                    $arguments[0]
+                        .clone()
+                        .try_into()
+                        .map_err(|err: ParserError<&str>| {
+                            err.relocate($argument_locations[0].clone())
+                        })?,
+                    $arguments[1]
+                        .clone()
+                        .try_into()
+                        .map_err(|err: ParserError<&str>| {
+                            err.relocate($argument_locations[1].clone())
+                        })?,
+                    $arguments[2]
+                        .clone()
+                        .try_into()
+                        .map_err(|err: ParserError<&str>| {
+                            err.relocate($argument_locations[2].clone())
+                        })?,
+                )))
             } else {
-                Err(NomErr::Error(ParserError::Base {
-                    location: $input,
+                Err(ParserError::Base {
+                    location: $location.clone(),
                     kind: ErrorKind::Expected(Expectation::ArgumentCount(3, $arguments.len())),
                     child: None,
-                }))
+                })
             };
         }
     };
@@ -120,40 +236,43 @@ macro_rules! impl_item_arg3 {
 
 #[macro_export]
 macro_rules! impl_item_arg4 {
-    ($input:expr, $name:expr, $arguments:expr, $item_type:ty) => {
+    ($name:expr, $arguments:expr, $location:expr, $argument_locations:expr, $item_type:ty) => {
         if $name == stringify!($item_type) {
-            use nom::Err as NomErr;
-
-            use crate::error::{ErrorKind, Expectation, ParserError};
+            use $crate::error::{ErrorKind, Expectation, ParserError};
 
             return if $arguments.len() == 4 {
-                Ok((
-                    $input,
-                    Box::new(<$item_type>::new(
-                        $arguments[0]
-                            .clone()
-                            .try_into()
-                            .map_err(|err| Err::Error(err))?,
-                        $arguments[1]
-                            .clone()
-                            .try_into()
-                            .map_err(|err| Err::Error(err))?,
-                        $arguments[2]
-                            .clone()
-                            .try_into()
-                            .map_err(|err| Err::Error(err))?,
-                        $arguments[3]
-                            .clone()
-                            .try_into()
-                            .map_err(|err| Err::Error(err))?,
-                    )),
-                ))
+                Ok(Box::new(<$item_type>::new(
+                    $arguments[0]
+                        .clone()
+                        .try_into()
+                        .map_err(|err: ParserError<&str>| {
+                            err.relocate($argument_locations[0].clone())
+                        })?,
+                    $arguments[1]
+                        .clone()
+                        .try_into()
+                        .map_err(|err: ParserError<&str>| {
+                            err.relocate($argument_locations[1].clone())
+                        })?,
+                    $arguments[2]
+                        .clone()
+                        .try_into()
+                        .map_err(|err: ParserError<&str>| {
+                            err.relocate($argument_locations[2].clone())
+                        })?,
+                    $arguments[3]
+                        .clone()
+                        .try_into()
+                        .map_err(|err: ParserError<&str>| {
+                            err.relocate($argument_locations[3].clone())
+                        })?,
+                )))
             } else {
-                Err(NomErr::Error(ParserError::Base {
-                    location: $input,
+                Err(ParserError::Base {
+                    location: $location.clone(),
                     kind: ErrorKind::Expected(Expectation::ArgumentCount(4, $arguments.len())),
                     child: None,
-                }))
+                })
             };
         }
     };
@@ -168,7 +287,7 @@ macro_rules! impl_tests_for_item_arg0 {
             use cool_asserts::assert_matches;
             use nom::Err as NomErr;
 
-            use crate::error::{ParserError, Expectation, ErrorKind};
+            use $crate::error::{ParserError, Expectation, ErrorKind};
 
             #[test]
             fn test() {
@@ -226,7 +345,7 @@ macro_rules! impl_tests_for_item_arg1 {
             use cool_asserts::assert_matches;
             use nom::Err as NomErr;
 
-            use crate::error::{ParserError, Expectation, ErrorKind};
+            use $crate::error::{ParserError, Expectation, ErrorKind};
 
             #[test]
             fn test() {
@@ -311,7 +430,7 @@ macro_rules! impl_tests_for_item_arg2 {
             use cool_asserts::assert_matches;
             use nom::Err as NomErr;
 
-            use crate::error::{ParserError, Expectation, ErrorKind};
+            use $crate::error::{ParserError, Expectation, ErrorKind};
 
             #[test]
             fn test() {
@@ -408,7 +527,7 @@ macro_rules! impl_tests_for_item_arg3 {
             use cool_asserts::assert_matches;
             use nom::Err as NomErr;
 
-            use crate::error::{ParserError, Expectation, ErrorKind};
+            use $crate::error::{ParserError, Expectation, ErrorKind};
 
             #[test]
             fn test() {
@@ -509,7 +628,7 @@ macro_rules! impl_tests_for_item_arg4 {
             use cool_asserts::assert_matches;
             use nom::Err as NomErr;
 
-            use crate::error::{ParserError, Expectation, ErrorKind};
+            use $crate::error::{ParserError, Expectation, ErrorKind};
 
             #[test]
             fn test() {
@@ -604,3 +723,90 @@ macro_rules! impl_tests_for_item_arg4 {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+    use nom::Err as NomErr;
+
+    use crate::ast::{Expression, Name};
+    use crate::error::{ErrorKind, Expectation};
+    use crate::literal::Literal;
+
+    #[test]
+    fn item_call_test() {
+        let text = "PacketProducer(0xabab~u16, receiver);input";
+
+        assert_matches!(
+            item_call(text)(text),
+            Ok((input, item_call)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    item_call,
+                    ItemCall {
+                        name: Name {
+                            value: "PacketProducer".to_string(),
+                            span: Span { start: 0, end: 14 },
+                        },
+                        arguments: vec![
+                            Expression::Literal {
+                                value: Literal::U16(0xabab),
+                                span: Span { start: 15, end: 25 },
+                            },
+                            Expression::Constant(Name {
+                                value: "receiver".to_string(),
+                                span: Span { start: 27, end: 35 },
+                            }),
+                        ],
+                        span: Span { start: 0, end: 37 },
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn extractor_and_item_call_test() {
+        let text = "EventCodeExtractor(); ValueEqualToConstFilter(0xabab~u16); }";
+
+        assert_matches!(
+            extractor_and_item_call(text)(text),
+            Ok(("}", (Some(extractor), item))) => {
+                assert_eq!(extractor.name.value, "EventCodeExtractor");
+                assert_eq!(item.name.value, "ValueEqualToConstFilter");
+            }
+        );
+    }
+
+    #[test]
+    fn extractor_and_item_call_no_extractor_test() {
+        let text = "ValueEqualToConstFilter(0xabab~u16); }";
+
+        assert_matches!(
+            extractor_and_item_call(text)(text),
+            Ok(("}", (None, item))) => {
+                assert_eq!(item.name.value, "ValueEqualToConstFilter");
+            }
+        );
+    }
+
+    #[test]
+    fn item_call_missing_semicolon_test() {
+        let text = "PacketProducer(0xabab~u16)input";
+
+        assert_matches!(
+            item_call(text)(text),
+            Err(NomErr::Error(ParserError::Base {
+                location,
+                kind,
+                child,
+            })) => {
+                assert_matches!(location, "input");
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Symbol(';')));
+                assert_matches!(child, None);
+            }
+        );
+    }
+}
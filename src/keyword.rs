@@ -118,3 +118,16 @@ implement_keyword_parser!(rgb_keyword, "rgb");
 implement_keyword_parser!(rgbw_keyword, "rgbw");
 implement_keyword_parser!(relay_keyword, "relay");
 implement_keyword_parser!(double_exclusive_keyword, "double_exclusive");
+implement_keyword_parser!(import_keyword, "import");
+implement_keyword_parser!(device_keyword, "device");
+implement_keyword_parser!(allow_keyword, "allow");
+implement_keyword_parser!(warn_keyword, "warn");
+implement_keyword_parser!(deny_keyword, "deny");
+implement_keyword_parser!(test_keyword, "test");
+implement_keyword_parser!(inject_keyword, "inject");
+implement_keyword_parser!(expect_keyword, "expect");
+implement_keyword_parser!(state_keyword, "state");
+implement_keyword_parser!(toggle_keyword, "toggle");
+implement_keyword_parser!(off_keyword, "off");
+implement_keyword_parser!(with_keyword, "with");
+implement_keyword_parser!(at_keyword, "at");
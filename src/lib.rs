@@ -1,12 +1,30 @@
+pub mod analysis;
+pub mod ast;
+pub mod coverage;
+pub mod decode;
+pub mod decompiler;
+pub mod diagnostic;
 pub mod error;
+pub mod expression;
 pub mod extractor;
 pub mod filter;
+pub mod formatter;
 pub mod item;
 pub mod keyword;
+pub mod lint;
 pub mod literal;
+pub mod location;
+pub mod lower;
+pub mod optimizer;
+pub mod printer;
 pub mod producer;
+pub mod render;
+pub mod resolver;
+pub mod simulator;
 pub mod statement;
 pub mod symbol;
+pub mod testing;
+pub mod trace;
 
 mod parser;
 pub use parser::*;
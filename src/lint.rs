@@ -0,0 +1,654 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::ast::*;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::{ErrorKind, ParserError};
+
+pub const UNUSED_CONST: &str = "unused_const";
+pub const UNUSED_LET: &str = "unused_let";
+pub const DO_WITHOUT_FIRE: &str = "do_without_fire";
+pub const UNDECLARED_EVENT_CODE: &str = "undeclared_event_code";
+pub const NEVER_MATCHES: &str = "never_matches";
+pub const ALWAYS_MATCHES: &str = "always_matches";
+pub const UNKNOWN_LINT: &str = "unknown_lint";
+
+/// What happens to what a lint finds: nothing, a warning or an error.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    /// Returns the level named `allow`, `warn` or `deny`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Something suspicious that a lint found in a file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Finding {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A check for code that is valid, but likely a mistake.
+pub trait Lint {
+    /// The name used for the lint in attributes, command line flags and diagnostic codes.
+    fn name(&self) -> &'static str;
+
+    fn default_level(&self) -> Level {
+        Level::Warn
+    }
+
+    fn check(&self, program: &Program) -> Vec<Finding>;
+}
+
+/// The lints to run and their levels. Besides the lints that check a `Program`, it knows the
+/// warnings of lowering, which can be allowed or denied the same way.
+pub struct LintRegistry {
+    lints: Vec<Box<dyn Lint>>,
+    levels: BTreeMap<String, Level>,
+}
+
+impl LintRegistry {
+    /// Creates a registry with every lint of this crate at its default level.
+    pub fn new() -> Self {
+        let mut registry = Self {
+            lints: vec![],
+            levels: BTreeMap::new(),
+        };
+
+        registry.register(Box::new(UnusedConst));
+        registry.register(Box::new(UnusedLet));
+        registry.register(Box::new(DoWithoutFire));
+
+        for name in [
+            UNDECLARED_EVENT_CODE,
+            NEVER_MATCHES,
+            ALWAYS_MATCHES,
+            UNKNOWN_LINT,
+        ] {
+            registry.levels.insert(name.to_string(), Level::Warn);
+        }
+
+        registry
+    }
+
+    /// Adds `lint` at its default level, replacing any lint with the same name.
+    pub fn register(&mut self, lint: Box<dyn Lint>) {
+        self.lints.retain(|other| other.name() != lint.name());
+        self.levels
+            .insert(lint.name().to_string(), lint.default_level());
+        self.lints.push(lint);
+    }
+
+    /// Sets the level of the lint named `name`. Returns `false` if there is no such lint.
+    pub fn set_level(&mut self, name: &str, level: Level) -> bool {
+        match self.levels.get_mut(name) {
+            Some(current) => {
+                *current = level;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn level(&self, name: &str) -> Option<Level> {
+        self.levels.get(name).copied()
+    }
+
+    /// Returns the diagnostics of a file: what the lints find in `program`, unless the file is
+    /// imported, and `warnings` of lowering it. Attributes in `program` override the levels of
+    /// the registry.
+    pub(crate) fn diagnostics(
+        &self,
+        file_name: Option<&str>,
+        source: &str,
+        program: &Program,
+        warnings: Vec<ParserError<Span>>,
+        imported: bool,
+    ) -> Vec<Diagnostic> {
+        let attributes = Attributes::new(program);
+        let mut found: Vec<(&str, Finding)> = vec![];
+
+        if !imported {
+            for lint in self.lints.iter() {
+                for finding in lint.check(program) {
+                    found.push((lint.name(), finding));
+                }
+            }
+        }
+
+        for warning in warnings {
+            if let ParserError::Base { location, kind, .. } = warning {
+                if let Some(name) = lowering_lint(&kind) {
+                    found.push((
+                        name,
+                        Finding {
+                            span: location,
+                            message: kind.to_string(),
+                        },
+                    ));
+                }
+            }
+        }
+
+        for attribute in attributes.all.iter() {
+            for lint in attribute.lints.iter() {
+                if !self.levels.contains_key(&lint.value) {
+                    found.push((
+                        UNKNOWN_LINT,
+                        Finding {
+                            span: lint.span,
+                            message: format!("unknown lint {}", lint.value),
+                        },
+                    ));
+                }
+            }
+        }
+
+        let mut diagnostics = vec![];
+
+        for (name, finding) in found {
+            let level = attributes.level(name, finding.span, self.level(name));
+
+            let severity = match level {
+                Some(Level::Warn) => Severity::Warning,
+                Some(Level::Deny) => Severity::Error,
+                Some(Level::Allow) | None => continue,
+            };
+
+            diagnostics.push(
+                Diagnostic::new(
+                    severity,
+                    finding.span.location(file_name, source),
+                    finding.message,
+                )
+                .with_span(finding.span)
+                .with_code(name),
+            );
+        }
+
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        diagnostics
+    }
+}
+
+impl Default for LintRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the name of the lint that a warning of lowering belongs to.
+fn lowering_lint(kind: &ErrorKind) -> Option<&'static str> {
+    match kind {
+        ErrorKind::UndeclaredEventCode(_) => Some(UNDECLARED_EVENT_CODE),
+        ErrorKind::NeverMatches => Some(NEVER_MATCHES),
+        ErrorKind::AlwaysMatches => Some(ALWAYS_MATCHES),
+        _ => None,
+    }
+}
+
+/// The attributes of a program, with the span each of them applies to.
+struct Attributes<'a> {
+    all: Vec<&'a AttributeStatement>,
+    /// Inner attributes, which apply everywhere, in order.
+    file: Vec<&'a AttributeStatement>,
+    /// Outer attributes with the span of the statement that follows them.
+    statements: Vec<(Span, &'a AttributeStatement)>,
+}
+
+impl<'a> Attributes<'a> {
+    fn new(program: &'a Program) -> Self {
+        let mut attributes = Self {
+            all: vec![],
+            file: vec![],
+            statements: vec![],
+        };
+        let mut pending = vec![];
+
+        for statement in program.statements.iter() {
+            match statement {
+                Statement::Attribute(attribute) => {
+                    attributes.all.push(attribute);
+
+                    if attribute.inner {
+                        attributes.file.push(attribute);
+                    } else {
+                        pending.push(attribute);
+                    }
+                }
+                statement => {
+                    for attribute in pending.drain(..) {
+                        attributes.statements.push((statement.span(), attribute));
+                    }
+                }
+            }
+        }
+
+        attributes
+    }
+
+    /// Returns the level of the lint `name` at `span`. Attributes of the statement override
+    /// those of the file, which override `level`.
+    fn level(&self, name: &str, span: Span, level: Option<Level>) -> Option<Level> {
+        let applies =
+            |attribute: &AttributeStatement| attribute.lints.iter().any(|lint| lint.value == name);
+
+        let file_levels = self.file.iter().filter(|attribute| applies(attribute));
+        let statement_levels = self
+            .statements
+            .iter()
+            .filter(|(statement_span, attribute)| {
+                statement_span.start <= span.start
+                    && span.start < statement_span.end
+                    && applies(attribute)
+            })
+            .map(|(_, attribute)| attribute);
+
+        file_levels
+            .chain(statement_levels)
+            .filter_map(|attribute| Level::from_name(&attribute.level.value))
+            .next_back()
+            .or(level)
+    }
+}
+
+/// `const` statements whose constant is never used.
+pub struct UnusedConst;
+
+impl Lint for UnusedConst {
+    fn name(&self) -> &'static str {
+        UNUSED_CONST
+    }
+
+    fn check(&self, program: &Program) -> Vec<Finding> {
+        let used = used_names(program);
+
+        program
+            .statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Const(statement) if !used.contains(&statement.name.value) => {
+                    Some(Finding {
+                        span: statement.name.span,
+                        message: format!("constant {} is never used", statement.name.value),
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// `let` statements whose state variable is never used.
+pub struct UnusedLet;
+
+impl Lint for UnusedLet {
+    fn name(&self) -> &'static str {
+        UNUSED_LET
+    }
+
+    fn check(&self, program: &Program) -> Vec<Finding> {
+        let used = used_names(program);
+
+        program
+            .statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Let(statement) if !used.contains(&statement.name.value) => {
+                    Some(Finding {
+                        span: statement.name.span,
+                        message: format!("state variable {} is never used", statement.name.value),
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// `do` statements without `fire`, which can change the state but never send anything.
+pub struct DoWithoutFire;
+
+impl Lint for DoWithoutFire {
+    fn name(&self) -> &'static str {
+        DO_WITHOUT_FIRE
+    }
+
+    fn check(&self, program: &Program) -> Vec<Finding> {
+        program
+            .statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Do(statement) if statement.fires.is_empty() => Some(Finding {
+                    span: statement.span,
+                    message: "do statement has no fire, so it never sends anything".to_string(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Returns the names of the constants and state variables that `program` refers to.
+fn used_names(program: &Program) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+
+    for statement in program.statements.iter() {
+        match statement {
+            Statement::Attribute(_) | Statement::Import(_) => {}
+            Statement::Let(statement) => expression_names(&statement.value, &mut names),
+            Statement::Const(statement) => expression_names(&statement.value, &mut names),
+            Statement::Device(statement) => expression_names(&statement.address, &mut names),
+            Statement::Event(statement) => expression_names(&statement.event_code, &mut names),
+            Statement::Peripheral(statement) => {
+                for expression in statement
+                    .gateway_addresses
+                    .iter()
+                    .flatten()
+                    .chain(Some(&statement.index))
+                    .chain(statement.arguments.iter())
+                {
+                    expression_names(expression, &mut names);
+                }
+            }
+            Statement::Send(statement) => {
+                expression_names(&statement.event_code, &mut names);
+                expression_names(&statement.from_address, &mut names);
+                expression_names(&statement.to_address, &mut names);
+
+                if let Some(condition) = &statement.condition {
+                    match_expr_names(condition, &mut names);
+                }
+            }
+            Statement::Set(statement) => {
+                names.insert(statement.state_variable.value.clone());
+                expression_names(&statement.value, &mut names);
+                expression_names(&statement.event_code, &mut names);
+                expression_names(&statement.from_address, &mut names);
+
+                if let Some(condition) = &statement.condition {
+                    match_expr_names(condition, &mut names);
+                }
+            }
+            Statement::Do(statement) => {
+                for match_expr in statement.matches.iter() {
+                    match_expr_names(match_expr, &mut names);
+                }
+
+                for fire_expr in statement.fires.iter() {
+                    for item_call in fire_expr.extractor.iter().chain(Some(&fire_expr.producer)) {
+                        item_call_names(item_call, &mut names);
+                    }
+
+                    if let Some(condition) = &fire_expr.condition {
+                        match_expr_names(condition, &mut names);
+                    }
+                }
+            }
+            Statement::Toggle(statement) => {
+                names.insert(statement.state_variable.value.clone());
+                expression_names(&statement.event_code, &mut names);
+                expression_names(&statement.from_address, &mut names);
+
+                for fire_expr in [&statement.on, &statement.off].iter() {
+                    for item_call in fire_expr.extractor.iter().chain(Some(&fire_expr.producer)) {
+                        item_call_names(item_call, &mut names);
+                    }
+                }
+            }
+            Statement::Test(statement) => {
+                for device_address in statement.device_address.iter() {
+                    expression_names(device_address, &mut names);
+                }
+
+                for step in statement.steps.iter() {
+                    match step {
+                        TestStep::Inject {
+                            event_code,
+                            from_address,
+                            data,
+                            ..
+                        } => {
+                            expression_names(event_code, &mut names);
+                            expression_names(from_address, &mut names);
+
+                            for value in data.iter() {
+                                expression_names(value, &mut names);
+                            }
+                        }
+                        TestStep::ExpectPacket { producer, .. } => {
+                            item_call_names(producer, &mut names)
+                        }
+                        TestStep::ExpectState {
+                            state_variable,
+                            value,
+                            ..
+                        } => {
+                            names.insert(state_variable.value.clone());
+                            expression_names(value, &mut names);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    names
+}
+
+fn match_expr_names(match_expr: &MatchExpr, names: &mut BTreeSet<String>) {
+    match match_expr {
+        MatchExpr::Event { event_code, .. } => expression_names(event_code, names),
+        MatchExpr::Producer { address, .. } => expression_names(address, names),
+        MatchExpr::Tick { .. } => {}
+        MatchExpr::Block { block, .. } => block_match_names(block, names),
+        MatchExpr::Not { operand, .. } => match_expr_names(operand, names),
+        MatchExpr::And { left, right, .. } | MatchExpr::Or { left, right, .. } => {
+            match_expr_names(left, names);
+            match_expr_names(right, names);
+        }
+        MatchExpr::Parenthesized { match_expr, .. } => match_expr_names(match_expr, names),
+    }
+}
+
+fn block_match_names(block: &BlockMatch, names: &mut BTreeSet<String>) {
+    match block {
+        BlockMatch::Single {
+            extractor, filter, ..
+        }
+        | BlockMatch::Not {
+            extractor, filter, ..
+        } => {
+            for item_call in extractor.iter().chain(Some(filter)) {
+                item_call_names(item_call, names);
+            }
+        }
+        BlockMatch::Or { children, .. } | BlockMatch::And { children, .. } => {
+            for child in children.iter() {
+                block_match_names(child, names);
+            }
+        }
+    }
+}
+
+fn item_call_names(item_call: &ItemCall, names: &mut BTreeSet<String>) {
+    for argument in item_call.arguments.iter() {
+        expression_names(argument, names);
+    }
+}
+
+fn expression_names(expression: &Expression, names: &mut BTreeSet<String>) {
+    match expression {
+        Expression::Literal { .. } => {}
+        Expression::Constant(name) => {
+            names.insert(name.value.clone());
+        }
+        Expression::Unary { operand, .. } => expression_names(operand, names),
+        Expression::Binary { left, right, .. } => {
+            expression_names(left, names);
+            expression_names(right, names);
+        }
+        Expression::Parenthesized { expression, .. } => expression_names(expression, names),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::Parser;
+
+    fn lint(text: &str, registry: &LintRegistry) -> Vec<Diagnostic> {
+        let program = Parser::parse_program(text).unwrap();
+
+        registry.diagnostics(None, text, &program, vec![], false)
+    }
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<(Severity, &str, usize)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.severity,
+                    diagnostic.code.as_deref().unwrap_or(""),
+                    diagnostic.span.start,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unused_test() {
+        let text = "const a = 0x01~u8;
+            const b = 0x02~u8;
+            let c = false;
+            let d = a;
+            set d to true on BUTTON_PRESSED_EVENT_CODE from 0x0001~u16;";
+
+        assert_eq!(
+            codes(&lint(text, &LintRegistry::new())),
+            vec![
+                (Severity::Warning, UNUSED_CONST, 37),
+                (Severity::Warning, UNUSED_LET, 66),
+            ]
+        );
+    }
+
+    #[test]
+    fn do_without_fire_test() {
+        let text = "do { match tick; }";
+
+        assert_eq!(
+            codes(&lint(text, &LintRegistry::new())),
+            vec![(Severity::Warning, DO_WITHOUT_FIRE, 0)]
+        );
+    }
+
+    #[test]
+    fn registry_level_test() {
+        let text = "const a = 0x01~u8;\ndo { match tick; }";
+        let mut registry = LintRegistry::new();
+
+        assert!(registry.set_level(UNUSED_CONST, Level::Allow));
+        assert!(registry.set_level(DO_WITHOUT_FIRE, Level::Deny));
+        assert!(!registry.set_level("unused_everything", Level::Deny));
+
+        assert_eq!(
+            codes(&lint(text, &registry)),
+            vec![(Severity::Error, DO_WITHOUT_FIRE, 19)]
+        );
+    }
+
+    #[test]
+    fn attribute_test() {
+        let text = "#![deny(unused_const)]
+            const a = 0x01~u8;
+            #[allow(unused_const)]
+            const b = 0x02~u8;
+            #[warn(unused_const, unused_stuff)]
+            const c = 0x03~u8;";
+
+        assert_eq!(
+            codes(&lint(text, &LintRegistry::new())),
+            vec![
+                (Severity::Error, UNUSED_CONST, 41),
+                (Severity::Warning, UNKNOWN_LINT, 153),
+                (Severity::Warning, UNUSED_CONST, 186),
+            ]
+        );
+    }
+
+    #[test]
+    fn lowering_warning_test() {
+        let text = "#[allow(never_matches)]
+            do { match event 0x0001~u16 && event 0x0002~u16; fire { PacketProducer(0x0001~u16); } }
+            do { match event 0x0001~u16 && event 0x0002~u16; fire { PacketProducer(0x0001~u16); } }";
+        let program = Parser::parse_program(text).unwrap();
+        let warnings = vec![
+            ParserError::Base {
+                location: program.statements[1].span(),
+                kind: ErrorKind::NeverMatches,
+                child: None,
+            },
+            ParserError::Base {
+                location: program.statements[2].span(),
+                kind: ErrorKind::NeverMatches,
+                child: None,
+            },
+        ];
+
+        assert_eq!(
+            codes(&LintRegistry::new().diagnostics(None, text, &program, warnings, false)),
+            vec![(Severity::Warning, NEVER_MATCHES, 136)]
+        );
+    }
+
+    #[test]
+    fn custom_lint_test() {
+        struct NoSend;
+
+        impl Lint for NoSend {
+            fn name(&self) -> &'static str {
+                "no_send"
+            }
+
+            fn default_level(&self) -> Level {
+                Level::Deny
+            }
+
+            fn check(&self, program: &Program) -> Vec<Finding> {
+                program
+                    .statements
+                    .iter()
+                    .filter(|statement| matches!(statement, Statement::Send(_)))
+                    .map(|statement| Finding {
+                        span: statement.span(),
+                        message: "send is not allowed".to_string(),
+                    })
+                    .collect()
+            }
+        }
+
+        let mut registry = LintRegistry::new();
+        registry.register(Box::new(NoSend));
+
+        assert_eq!(
+            codes(&lint(
+                "send 0x0001~u16 from 0x0002~u16 to 0x0003~u16;",
+                &registry
+            )),
+            vec![(Severity::Error, "no_send", 0)]
+        );
+    }
+}
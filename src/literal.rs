@@ -1,13 +1,14 @@
 use cron_parser::parse_field;
 use nom::branch::alt;
-use nom::bytes::complete::take_until;
-use nom::character::complete::alphanumeric1;
-use nom::combinator::success;
-use nom::sequence::{delimited, preceded, separated_pair, tuple};
+use nom::bytes::complete::{tag, take_until};
+use nom::character::complete::{alphanumeric1, digit1, hex_digit1, satisfy};
+use nom::combinator::{not, recognize, success};
+use nom::sequence::{delimited, preceded, separated_pair, terminated, tuple};
 use nom::{Err as NomErr, IResult};
 use parse_int::parse;
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
 
 use ross_config::cron::{CronExpression, CronField};
 use ross_config::Value;
@@ -25,12 +26,60 @@ pub enum Literal {
     U8(u8),
     U16(u16),
     U32(u32),
+    /// An integer without a `~type` suffix, which takes the type of where it is used.
+    Integer(u32),
+    /// The address of a device declared with `device`.
+    Device(u16),
+    /// An event code of `ross_protocol` or one declared with `event`.
+    Event(u16),
+    /// A state variable declared with `let`, by its index in the initial state. It can only be
+    /// passed where an item expects a state variable.
+    State(u32),
     Bool(bool),
     String(String),
     Rgb(u8, u8, u8),
     Rgbw(u8, u8, u8, u8),
 }
 
+impl Literal {
+    /// Returns the name of the literal's type, as used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Literal::U8(_) => "u8",
+            Literal::U16(_) => "u16",
+            Literal::U32(_) => "u32",
+            Literal::Integer(_) => "integer",
+            Literal::Device(_) => "device",
+            Literal::Event(_) => "event",
+            Literal::State(_) => "state",
+            Literal::Bool(_) => "bool",
+            Literal::String(_) => "string",
+            Literal::Rgb(_, _, _) => "rgb",
+            Literal::Rgbw(_, _, _, _) => "rgbw",
+        }
+    }
+}
+
+/// Writes the literal the way `literal` parses it, with typed integers in zero-padded
+/// hexadecimal.
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::U8(value) => write!(f, "0x{:02x}~u8", value),
+            Literal::U16(value) => write!(f, "0x{:04x}~u16", value),
+            Literal::U32(value) => write!(f, "0x{:08x}~u32", value),
+            Literal::Integer(value) => write!(f, "{}", value),
+            Literal::Device(address) => write!(f, "0x{:04x}~u16", address),
+            Literal::Event(event_code) => write!(f, "0x{:04x}~u16", event_code),
+            Literal::State(index) => write!(f, "0x{:08x}~u32", index),
+            Literal::Bool(value) => write!(f, "{}", value),
+            Literal::String(value) => write!(f, "\"{}\"", value),
+            Literal::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Literal::Rgbw(r, g, b, w) => write!(f, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, w),
+        }
+    }
+}
+
 pub fn state_variable<'a>(
     state_variables: &'a BTreeMap<&str, u32>,
 ) -> impl FnMut(&str) -> IResult<&str, u32, ParserError<&str>> + 'a {
@@ -72,6 +121,15 @@ pub fn literal(text: &str) -> IResult<&str, Literal, ParserError<&str>> {
         success("string"),
     ));
     let color_parser = tuple((preceded(hashtag, hex1), success("color")));
+    let integer_parser = tuple((
+        terminated(
+            recognize(alt((preceded(tag("0x"), hex_digit1), digit1))),
+            not(satisfy(|character: char| {
+                character.is_alphanumeric() || character == '_' || character == '~'
+            })),
+        ),
+        success("integer"),
+    ));
 
     match alt((
         boolean_parser,
@@ -79,6 +137,7 @@ pub fn literal(text: &str) -> IResult<&str, Literal, ParserError<&str>> {
         decimal_parser,
         string_parser,
         color_parser,
+        integer_parser,
     ))(text)
     {
         Ok((input, (value, "u8"))) => {
@@ -114,6 +173,17 @@ pub fn literal(text: &str) -> IResult<&str, Literal, ParserError<&str>> {
                 }))
             }
         }
+        Ok((input, (value, "integer"))) => {
+            if let Ok(value) = parse::<u32>(value) {
+                Ok((input, Literal::Integer(value)))
+            } else {
+                Err(NomErr::Error(ParserError::Base {
+                    location: value,
+                    kind: ErrorKind::Expected(Expectation::Value),
+                    child: None,
+                }))
+            }
+        }
         Ok((input, (value, "bool"))) => match value {
             "true" => Ok((input, Literal::Bool(true))),
             "false" => Ok((input, Literal::Bool(false))),
@@ -176,6 +246,60 @@ pub fn literal(text: &str) -> IResult<&str, Literal, ParserError<&str>> {
     }
 }
 
+/// The types a state variable can have, which are those of `Value`.
+pub(crate) const VALUE_TYPES: [&str; 6] = ["u8", "u16", "u32", "bool", "rgb", "rgbw"];
+
+/// Returns the name of the type of `value`, as listed in `VALUE_TYPES`.
+pub(crate) fn value_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::U8(_) => "u8",
+        Value::U16(_) => "u16",
+        Value::U32(_) => "u32",
+        Value::Bool(_) => "bool",
+        Value::Rgb(_, _, _) => "rgb",
+        Value::Rgbw(_, _, _, _) => "rgbw",
+    }
+}
+
+/// Converts `literal` to the type named `value_type`, one of `VALUE_TYPES`. An integer without a
+/// suffix takes that type.
+pub(crate) fn typed_literal(
+    literal: Literal,
+    value_type: &'static str,
+) -> Result<Literal, ParserError<&'static str>> {
+    match (value_type, literal) {
+        ("u8", literal) => Ok(Literal::U8(u8::try_from(literal)?)),
+        ("u16", literal) => Ok(Literal::U16(u16::try_from(literal)?)),
+        ("u32", literal) => Ok(Literal::U32(u32::try_from(literal)?)),
+        ("bool", literal) => Ok(Literal::Bool(bool::try_from(literal)?)),
+        ("rgb", literal @ Literal::Rgb(_, _, _))
+        | ("rgbw", literal @ Literal::Rgbw(_, _, _, _)) => Ok(literal),
+        (value_type, literal) => Err(ParserError::Base {
+            location: "",
+            kind: ErrorKind::CastFromToNotAllowed(literal.type_name(), value_type),
+            child: None,
+        }),
+    }
+}
+
+/// Converts `literal` to an address, which is a `u16` or a device. Devices are not allowed
+/// anywhere else.
+pub(crate) fn address(literal: Literal) -> Result<u16, ParserError<&'static str>> {
+    match literal {
+        Literal::Device(address) => Ok(address),
+        literal => u16::try_from(literal),
+    }
+}
+
+/// Converts `literal` to an event code, which is a `u16` or an event. Events are not allowed
+/// anywhere else.
+pub(crate) fn event_code(literal: Literal) -> Result<u16, ParserError<&'static str>> {
+    match literal {
+        Literal::Event(event_code) => Ok(event_code),
+        literal => u16::try_from(literal),
+    }
+}
+
 impl TryFrom<Literal> for u8 {
     type Error = ParserError<&'static str>;
 
@@ -192,6 +316,26 @@ impl TryFrom<Literal> for u8 {
                 kind: ErrorKind::CastFromToNotAllowed("u32", "u8"),
                 child: None,
             }),
+            Literal::Integer(value) => u8::try_from(value).map_err(|_| ParserError::Base {
+                location: "",
+                kind: ErrorKind::IntegerOutOfRange("u8"),
+                child: None,
+            }),
+            Literal::Device(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("device", "u8"),
+                child: None,
+            }),
+            Literal::Event(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("event", "u8"),
+                child: None,
+            }),
+            Literal::State(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("state", "u8"),
+                child: None,
+            }),
             Literal::Bool(_) => Err(ParserError::Base {
                 location: "",
                 kind: ErrorKind::CastFromToNotAllowed("bool", "u8"),
@@ -232,6 +376,26 @@ impl TryFrom<Literal> for u16 {
                 kind: ErrorKind::CastFromToNotAllowed("u32", "u16"),
                 child: None,
             }),
+            Literal::Integer(value) => u16::try_from(value).map_err(|_| ParserError::Base {
+                location: "",
+                kind: ErrorKind::IntegerOutOfRange("u16"),
+                child: None,
+            }),
+            Literal::Device(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("device", "u16"),
+                child: None,
+            }),
+            Literal::Event(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("event", "u16"),
+                child: None,
+            }),
+            Literal::State(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("state", "u16"),
+                child: None,
+            }),
             Literal::Bool(_) => Err(ParserError::Base {
                 location: "",
                 kind: ErrorKind::CastFromToNotAllowed("bool", "u16"),
@@ -271,7 +435,22 @@ impl TryFrom<Literal> for u32 {
                 kind: ErrorKind::CastFromToNotAllowed("u16", "u32"),
                 child: None,
             }),
-            Literal::U32(value) => Ok(value),
+            Literal::U32(value) | Literal::Integer(value) => Ok(value),
+            Literal::Device(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("device", "u32"),
+                child: None,
+            }),
+            Literal::Event(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("event", "u32"),
+                child: None,
+            }),
+            Literal::State(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("state", "u32"),
+                child: None,
+            }),
             Literal::Bool(_) => Err(ParserError::Base {
                 location: "",
                 kind: ErrorKind::CastFromToNotAllowed("bool", "u32"),
@@ -316,6 +495,26 @@ impl TryFrom<Literal> for bool {
                 kind: ErrorKind::CastFromToNotAllowed("u32", "bool"),
                 child: None,
             }),
+            Literal::Integer(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("integer", "bool"),
+                child: None,
+            }),
+            Literal::Device(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("device", "bool"),
+                child: None,
+            }),
+            Literal::Event(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("event", "bool"),
+                child: None,
+            }),
+            Literal::State(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("state", "bool"),
+                child: None,
+            }),
             Literal::Bool(value) => Ok(value),
             Literal::String(_) => Err(ParserError::Base {
                 location: "",
@@ -344,6 +543,26 @@ impl TryFrom<Literal> for Value {
             Literal::U8(value) => Ok(Value::U8(value)),
             Literal::U16(value) => Ok(Value::U16(value)),
             Literal::U32(value) => Ok(Value::U32(value)),
+            Literal::Device(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("device", "value"),
+                child: None,
+            }),
+            Literal::Event(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("event", "value"),
+                child: None,
+            }),
+            Literal::State(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("state", "value"),
+                child: None,
+            }),
+            Literal::Integer(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::AmbiguousInteger,
+                child: None,
+            }),
             Literal::Bool(value) => Ok(Value::Bool(value)),
             Literal::Rgb(r, g, b) => Ok(Value::Rgb(r, g, b)),
             Literal::Rgbw(r, g, b, w) => Ok(Value::Rgbw(r, g, b, w)),
@@ -364,6 +583,26 @@ impl TryFrom<Literal> for MessageValue {
             Literal::U8(value) => Ok(MessageValue::U8(value)),
             Literal::U16(value) => Ok(MessageValue::U16(value)),
             Literal::U32(value) => Ok(MessageValue::U32(value)),
+            Literal::Device(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("device", "message value"),
+                child: None,
+            }),
+            Literal::Event(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("event", "message value"),
+                child: None,
+            }),
+            Literal::State(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("state", "message value"),
+                child: None,
+            }),
+            Literal::Integer(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::AmbiguousInteger,
+                child: None,
+            }),
             Literal::Bool(value) => Ok(MessageValue::Bool(value)),
             Literal::String(_) => Err(ParserError::Base {
                 location: "",
@@ -390,6 +629,15 @@ impl TryFrom<Literal> for BcmValue {
     fn try_from(literal: Literal) -> Result<Self, Self::Error> {
         match literal {
             Literal::U8(value) => Ok(BcmValue::Single(value)),
+            Literal::Integer(value) => {
+                u8::try_from(value)
+                    .map(BcmValue::Single)
+                    .map_err(|_| ParserError::Base {
+                        location: "",
+                        kind: ErrorKind::IntegerOutOfRange("u8"),
+                        child: None,
+                    })
+            }
             Literal::Rgb(r, g, b) => Ok(BcmValue::Rgb(r, g, b)),
             Literal::Rgbw(r, g, b, w) => Ok(BcmValue::Rgbw(r, g, b, w)),
             Literal::U16(_) => Err(ParserError::Base {
@@ -402,6 +650,21 @@ impl TryFrom<Literal> for BcmValue {
                 kind: ErrorKind::CastFromToNotAllowed("u32", "bcm value"),
                 child: None,
             }),
+            Literal::Device(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("device", "bcm value"),
+                child: None,
+            }),
+            Literal::Event(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("event", "bcm value"),
+                child: None,
+            }),
+            Literal::State(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("state", "bcm value"),
+                child: None,
+            }),
             Literal::Bool(_) => Err(ParserError::Base {
                 location: "",
                 kind: ErrorKind::CastFromToNotAllowed("bool", "bcm value"),
@@ -436,6 +699,26 @@ impl TryFrom<Literal> for RelayValue {
                 kind: ErrorKind::CastFromToNotAllowed("u32", "relay value"),
                 child: None,
             }),
+            Literal::Integer(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("integer", "relay value"),
+                child: None,
+            }),
+            Literal::Device(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("device", "relay value"),
+                child: None,
+            }),
+            Literal::Event(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("event", "relay value"),
+                child: None,
+            }),
+            Literal::State(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("state", "relay value"),
+                child: None,
+            }),
             Literal::Rgb(_, _, _) => Err(ParserError::Base {
                 location: "",
                 kind: ErrorKind::CastFromToNotAllowed("rgb", "relay value"),
@@ -487,6 +770,26 @@ impl TryFrom<Literal> for CronExpression {
                 kind: ErrorKind::CastFromToNotAllowed("u32", "cron expression"),
                 child: None,
             }),
+            Literal::Integer(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("integer", "cron expression"),
+                child: None,
+            }),
+            Literal::Device(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("device", "cron expression"),
+                child: None,
+            }),
+            Literal::Event(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("event", "cron expression"),
+                child: None,
+            }),
+            Literal::State(_) => Err(ParserError::Base {
+                location: "",
+                kind: ErrorKind::CastFromToNotAllowed("state", "cron expression"),
+                child: None,
+            }),
             Literal::Bool(_) => Err(ParserError::Base {
                 location: "",
                 kind: ErrorKind::CastFromToNotAllowed("bool", "cron expression"),
@@ -780,6 +1083,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn unsuffixed_decimal_test() {
+        assert_matches!(literal("123;input"), Ok((";input", Literal::Integer(123))));
+    }
+
+    #[test]
+    fn unsuffixed_hex_test() {
+        assert_matches!(
+            literal("0xabab)input"),
+            Ok((")input", Literal::Integer(0xabab)))
+        );
+    }
+
+    #[test]
+    fn unsuffixed_too_large_test() {
+        assert_matches!(
+            literal("0x100000000;input"),
+            Err(NomErr::Error(ParserError::Base {
+                location,
+                kind,
+                child,
+            })) => {
+                assert_matches!(location, "0x100000000");
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Value));
+                assert_matches!(child, None);
+            }
+        );
+    }
+
+    #[test]
+    fn unsuffixed_inference_test() {
+        assert_matches!(u8::try_from(Literal::Integer(0xff)), Ok(0xff));
+        assert_matches!(u16::try_from(Literal::Integer(0xffff)), Ok(0xffff));
+        assert_matches!(
+            u32::try_from(Literal::Integer(0xffff_ffff)),
+            Ok(0xffff_ffff)
+        );
+        assert_matches!(
+            BcmValue::try_from(Literal::Integer(0x12)),
+            Ok(BcmValue::Single(0x12))
+        );
+        assert_matches!(
+            u8::try_from(Literal::Integer(0x100)),
+            Err(ParserError::Base {
+                kind: ErrorKind::IntegerOutOfRange("u8"),
+                ..
+            })
+        );
+        assert_matches!(
+            Value::try_from(Literal::Integer(0x01)),
+            Err(ParserError::Base {
+                kind: ErrorKind::AmbiguousInteger,
+                ..
+            })
+        );
+    }
+
     #[test]
     fn bool_true_test() {
         assert_matches!(literal("true;input"), Ok((";input", Literal::Bool(true))));
@@ -816,6 +1176,30 @@ mod tests {
         });
     }
 
+    #[test]
+    fn display_test() {
+        let literals = vec![
+            Literal::U8(0x0a),
+            Literal::U16(0xabab),
+            Literal::U32(0x01),
+            Literal::Bool(true),
+            Literal::String("first".to_string()),
+            Literal::Rgb(0x01, 0x23, 0x45),
+            Literal::Rgbw(0x01, 0x23, 0x45, 0x67),
+        ];
+
+        assert_eq!(literals[0].to_string(), "0x0a~u8");
+        assert_eq!(literals[2].to_string(), "0x00000001~u32");
+
+        for value in literals {
+            let text = value.to_string();
+
+            assert_matches!(literal(&text), Ok(("", parsed)) => {
+                assert_eq!(parsed, value);
+            });
+        }
+    }
+
     #[test]
     fn no_tilde_test() {
         assert_matches!(
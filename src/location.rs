@@ -0,0 +1,114 @@
+use nom::Offset;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: Option<String>,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// Creates a location for a byte offset into `source`, counting lines and columns from 1.
+    pub fn new(file: Option<&str>, source: &str, offset: usize) -> Self {
+        let offset = offset.min(source.len());
+        let preceding = &source[..offset];
+        let line_start = preceding.rfind('\n').map_or(0, |index| index + 1);
+
+        Self {
+            file: file.map(str::to_string),
+            offset,
+            line: preceding.matches('\n').count() + 1,
+            column: preceding[line_start..].chars().count() + 1,
+        }
+    }
+
+    /// Creates a location for the start of `slice`, which has to be a subslice of `source`.
+    pub fn from_slice(file: Option<&str>, source: &str, slice: &str) -> Self {
+        let offset = slice_offset(source, slice).unwrap_or(source.len());
+
+        Self::new(file, source, offset)
+    }
+}
+
+/// Returns the byte offset of `slice` in `source`, or `None` if `slice` is not a subslice of it.
+pub fn slice_offset(source: &str, slice: &str) -> Option<usize> {
+    let source_start = source.as_ptr() as usize;
+    let slice_start = slice.as_ptr() as usize;
+
+    if slice_start >= source_start && slice_start <= source_start + source.len() {
+        Some(source.offset(slice))
+    } else {
+        None
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_line_test() {
+        let location = Location::new(None, "let a = false;", 4);
+
+        assert_eq!(location.offset, 4);
+        assert_eq!(location.line, 1);
+        assert_eq!(location.column, 5);
+    }
+
+    #[test]
+    fn later_line_test() {
+        let location = Location::new(None, "let a = false;\n\n  const b", 18);
+
+        assert_eq!(location.line, 3);
+        assert_eq!(location.column, 3);
+    }
+
+    #[test]
+    fn multibyte_column_test() {
+        let location = Location::new(None, "ąčę x", 7);
+
+        assert_eq!(location.line, 1);
+        assert_eq!(location.column, 5);
+    }
+
+    #[test]
+    fn from_slice_test() {
+        let source = "let a = false;\nconst b = true;";
+        let location = Location::from_slice(Some("config.ross"), source, &source[21..]);
+
+        assert_eq!(location.offset, 21);
+        assert_eq!(location.line, 2);
+        assert_eq!(location.column, 7);
+    }
+
+    #[test]
+    fn from_foreign_slice_test() {
+        let source = "let a = false;";
+        let location = Location::from_slice(None, source, "");
+
+        assert_eq!(location.offset, source.len());
+    }
+
+    #[test]
+    fn display_test() {
+        let source = "let a = false;\nconst b = true;";
+
+        assert_eq!(
+            format!("{}", Location::new(Some("config.ross"), source, 21)),
+            "config.ross:2:7"
+        );
+        assert_eq!(format!("{}", Location::new(None, source, 21)), "2:7");
+    }
+}
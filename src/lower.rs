@@ -0,0 +1,2367 @@
+use std::collections::BTreeMap;
+use std::convert::{TryFrom, TryInto};
+
+use ross_config::config::Config;
+use ross_config::creator::Creator;
+use ross_config::event_processor::EventProcessor;
+use ross_config::extractor::{
+    EventCodeExtractor, EventProducerAddressExtractor, Extractor, NoneExtractor, PacketExtractor,
+};
+use ross_config::filter::{Filter, SetStateToConstFilter, ValueEqualToConstFilter};
+use ross_config::matcher::Matcher;
+use ross_config::peripheral::{BcmPeripheral, Peripheral, RelayPeripheral};
+use ross_config::producer::{PacketProducer, Producer};
+use ross_config::Value;
+use ross_protocol::event::event_code::*;
+
+use crate::analysis::{satisfiability, Satisfiability};
+use crate::ast::*;
+use crate::error::{ErrorKind, Expectation, ParserError};
+use crate::expression::{evaluate_binary, evaluate_unary};
+use crate::extractor::create_extractor;
+use crate::filter::create_filter;
+use crate::item::{item_signature, state_types, ArgumentKind};
+use crate::literal::{address, event_code, typed_literal, value_type_name, Literal, VALUE_TYPES};
+use crate::producer::create_producer;
+use crate::simulator::{Event, EventData};
+use crate::testing::{Action, Test, DEFAULT_DEVICE_ADDRESS};
+
+macro_rules! prepare_constant {
+    ($name:expr, $constants:expr, $constant_type:path) => {
+        $constants.insert(stringify!($name).to_string(), $constant_type($name));
+    };
+}
+
+/// Lowers every statement of `program` to a `Config`. Statements that fail to lower are
+/// skipped, so that the errors of all of them are returned.
+pub fn lower(program: &Program) -> Result<Config, Vec<ParserError<Span>>> {
+    let mut lowerer = Lowerer::new();
+    let mut errors = vec![];
+
+    for statement in program.statements.iter() {
+        if let Err(err) = lowerer.lower_statement(statement) {
+            errors.push(err);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(lowerer.finish())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Spans of the statements that the event processors of a config come from, by the index of the
+/// event processor, and of what their creators come from. The creator of a `send` comes from the
+/// whole statement and those of a `do` or `toggle` from its `fire` statements.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SourceMap {
+    pub event_processors: Vec<Span>,
+    pub creators: Vec<Vec<Span>>,
+}
+
+/// Resolves names and builds the `ross_config` types out of statements, in the order they are
+/// lowered.
+pub struct Lowerer {
+    constants: BTreeMap<String, Literal>,
+    state_variables: BTreeMap<String, u32>,
+    /// Types of the state variables, by index, as listed in `VALUE_TYPES`.
+    state_types: BTreeMap<u32, &'static str>,
+    devices: BTreeMap<u16, String>,
+    event_codes: BTreeMap<u16, String>,
+    /// Spans of the names declared so far, which does not include the built-in constants.
+    definitions: BTreeMap<String, Span>,
+    peripherals: BTreeMap<u32, Peripheral>,
+    initial_state: BTreeMap<u32, Value>,
+    event_processors: Vec<EventProcessor>,
+    source_map: SourceMap,
+    tests: Vec<Test>,
+    warnings: Vec<ParserError<Span>>,
+}
+
+impl Lowerer {
+    pub fn new() -> Self {
+        let mut lowerer = Self {
+            constants: BTreeMap::new(),
+            state_variables: BTreeMap::new(),
+            state_types: BTreeMap::new(),
+            devices: BTreeMap::new(),
+            event_codes: BTreeMap::new(),
+            definitions: BTreeMap::new(),
+            peripherals: BTreeMap::new(),
+            initial_state: BTreeMap::new(),
+            event_processors: vec![],
+            source_map: SourceMap::default(),
+            tests: vec![],
+            warnings: vec![],
+        };
+
+        prepare_constants(&mut lowerer.constants);
+
+        for (name, value) in lowerer.constants.iter() {
+            if let Literal::Event(event_code) = value {
+                lowerer.event_codes.insert(*event_code, name.clone());
+            }
+        }
+
+        lowerer
+    }
+
+    pub fn lower_statement(&mut self, statement: &Statement) -> Result<(), ParserError<Span>> {
+        match statement {
+            // Attributes only set the levels of lints
+            Statement::Attribute(_) => Ok(()),
+            // Only parsing a file through a resolver can resolve imports
+            Statement::Import(statement) => Err(ParserError::Base {
+                location: statement.span,
+                kind: ErrorKind::UnresolvedImport,
+                child: None,
+            }),
+            Statement::Let(statement) => self.lower_let_statement(statement),
+            Statement::Const(statement) => self.lower_const_statement(statement),
+            Statement::Device(statement) => self.lower_device_statement(statement),
+            Statement::Event(statement) => self.lower_event_statement(statement),
+            Statement::Peripheral(statement) => self.lower_peripheral_statement(statement),
+            Statement::Send(statement) => self.lower_send_statement(statement),
+            Statement::Set(statement) => self.lower_set_statement(statement),
+            Statement::Do(statement) => self.lower_do_statement(statement),
+            Statement::Toggle(statement) => {
+                self.lower_do_statement(&Self::toggle_do_statement(statement))
+            }
+            Statement::Test(statement) => self.lower_test_statement(statement),
+        }
+    }
+
+    /// Returns the names of the devices lowered so far, by address.
+    pub fn devices(&self) -> &BTreeMap<u16, String> {
+        &self.devices
+    }
+
+    /// Returns the names of the event codes of `ross_protocol` and those lowered so far, by code.
+    pub fn event_codes(&self) -> &BTreeMap<u16, String> {
+        &self.event_codes
+    }
+
+    /// Returns the warnings of the statements lowered so far. They do not stop a statement from
+    /// being lowered.
+    pub fn warnings(&self) -> &[ParserError<Span>] {
+        &self.warnings
+    }
+
+    /// Returns the warnings of the statements lowered so far and forgets them.
+    pub fn take_warnings(&mut self) -> Vec<ParserError<Span>> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Returns where the event processors lowered so far come from.
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Returns the tests lowered so far and forgets them. They are not part of the config.
+    pub fn take_tests(&mut self) -> Vec<Test> {
+        std::mem::take(&mut self.tests)
+    }
+
+    pub fn finish(self) -> Config {
+        Config {
+            peripherals: self.peripherals,
+            initial_state: self.initial_state,
+            event_processors: self.event_processors,
+        }
+    }
+
+    fn lower_let_statement(&mut self, statement: &LetStatement) -> Result<(), ParserError<Span>> {
+        self.define(&statement.name)?;
+
+        let value = match &statement.value_type {
+            Some(value_type) => {
+                let value_type = VALUE_TYPES
+                    .iter()
+                    .find(|name| **name == value_type.value)
+                    .ok_or(ParserError::Base {
+                        location: value_type.span,
+                        kind: ErrorKind::Expected(Expectation::Type),
+                        child: None,
+                    })?;
+
+                self.evaluate_as_type(&statement.value, value_type)?
+            }
+            None => self.evaluate(&statement.value)?,
+        };
+        let value = Value::try_from(value)
+            .map_err(|err: ParserError<&str>| err.relocate(statement.value.span()))?;
+        let initial_state_index = self.initial_state.len() as u32;
+        let name = statement.name.value.clone();
+
+        self.state_types
+            .insert(initial_state_index, value_type_name(&value));
+        self.initial_state.insert(initial_state_index, value);
+        self.state_variables
+            .insert(name.clone(), initial_state_index);
+        self.constants
+            .insert(name, Literal::State(initial_state_index));
+
+        Ok(())
+    }
+
+    fn lower_const_statement(
+        &mut self,
+        statement: &ConstStatement,
+    ) -> Result<(), ParserError<Span>> {
+        self.define(&statement.name)?;
+
+        let value = self.evaluate(&statement.value)?;
+
+        self.constants.insert(statement.name.value.clone(), value);
+
+        Ok(())
+    }
+
+    fn lower_device_statement(
+        &mut self,
+        statement: &DeviceStatement,
+    ) -> Result<(), ParserError<Span>> {
+        self.define(&statement.name)?;
+
+        let address = self.evaluate_address(&statement.address)?;
+
+        if let Some(device) = self.devices.get(&address) {
+            return Err(ParserError::Base {
+                location: statement.address.span(),
+                kind: ErrorKind::DuplicateDeviceAddress(device.clone()),
+                child: None,
+            });
+        }
+
+        self.devices.insert(address, statement.name.value.clone());
+        self.constants
+            .insert(statement.name.value.clone(), Literal::Device(address));
+
+        Ok(())
+    }
+
+    fn lower_event_statement(
+        &mut self,
+        statement: &EventStatement,
+    ) -> Result<(), ParserError<Span>> {
+        self.define(&statement.name)?;
+
+        let event_code = event_code(self.evaluate(&statement.event_code)?)
+            .map_err(|err: ParserError<&str>| err.relocate(statement.event_code.span()))?;
+
+        if let Some(event) = self.event_codes.get(&event_code) {
+            return Err(ParserError::Base {
+                location: statement.event_code.span(),
+                kind: ErrorKind::DuplicateEventCode(event.clone()),
+                child: None,
+            });
+        }
+
+        self.event_codes
+            .insert(event_code, statement.name.value.clone());
+        self.constants
+            .insert(statement.name.value.clone(), Literal::Event(event_code));
+
+        Ok(())
+    }
+
+    /// Declares `name`, unless it is a built-in constant or was declared before. The error for an
+    /// earlier declaration is located there, with the new one as its child.
+    fn define(&mut self, name: &Name) -> Result<(), ParserError<Span>> {
+        if let Some(first_span) = self.definitions.get(&name.value) {
+            return Err(ParserError::Base {
+                location: *first_span,
+                kind: ErrorKind::FirstDefinition(name.value.clone()),
+                child: Some(Box::new(ParserError::Base {
+                    location: name.span,
+                    kind: ErrorKind::AlreadyDefined(name.value.clone()),
+                    child: None,
+                })),
+            });
+        }
+
+        if self.constants.contains_key(&name.value) {
+            return Err(ParserError::Base {
+                location: name.span,
+                kind: ErrorKind::BuiltInRedefined(name.value.clone()),
+                child: None,
+            });
+        }
+
+        self.definitions.insert(name.value.clone(), name.span);
+
+        Ok(())
+    }
+
+    fn lower_peripheral_statement(
+        &mut self,
+        statement: &PeripheralStatement,
+    ) -> Result<(), ParserError<Span>> {
+        let index = self.evaluate_as::<u32>(&statement.index)?;
+        let arguments = &statement.arguments;
+
+        let expect_argument_count = |count: usize| {
+            if arguments.len() == count {
+                Ok(())
+            } else {
+                Err(ParserError::Base {
+                    location: statement.span,
+                    kind: ErrorKind::Expected(Expectation::ArgumentCount(count, arguments.len())),
+                    child: None,
+                })
+            }
+        };
+
+        let peripheral = match (
+            statement.family.value.as_str(),
+            statement.kind.value.as_str(),
+        ) {
+            ("bcm", "single") => {
+                expect_argument_count(1)?;
+
+                let channel = self.evaluate_as(&arguments[0])?;
+
+                Peripheral::Bcm(BcmPeripheral::Single(channel), vec![])
+            }
+            ("bcm", "rgb") => {
+                expect_argument_count(3)?;
+
+                let r = self.evaluate_as(&arguments[0])?;
+                let g = self.evaluate_as(&arguments[1])?;
+                let b = self.evaluate_as(&arguments[2])?;
+
+                Peripheral::Bcm(BcmPeripheral::Rgb(r, g, b), vec![])
+            }
+            ("bcm", "rgbw") => {
+                expect_argument_count(4)?;
+
+                let r = self.evaluate_as(&arguments[0])?;
+                let g = self.evaluate_as(&arguments[1])?;
+                let b = self.evaluate_as(&arguments[2])?;
+                let w = self.evaluate_as(&arguments[3])?;
+
+                Peripheral::Bcm(BcmPeripheral::Rgbw(r, g, b, w), vec![])
+            }
+            ("relay", "single") => {
+                expect_argument_count(1)?;
+
+                let channel = self.evaluate_as(&arguments[0])?;
+
+                Peripheral::Relay(RelayPeripheral::Single(channel), vec![])
+            }
+            ("relay", "double_exclusive") => {
+                expect_argument_count(2)?;
+
+                let channel1 = self.evaluate_as(&arguments[0])?;
+                let channel2 = self.evaluate_as(&arguments[1])?;
+
+                Peripheral::Relay(RelayPeripheral::DoubleExclusive(channel1, channel2), vec![])
+            }
+            _ => {
+                return Err(ParserError::Base {
+                    location: statement.kind.span,
+                    kind: ErrorKind::Expected(Expectation::Something),
+                    child: None,
+                })
+            }
+        };
+
+        let mut extra_gateway_addresses = vec![];
+
+        for gateway_address in statement.gateway_addresses.iter().flatten() {
+            extra_gateway_addresses.push(self.evaluate_address(gateway_address)?);
+        }
+
+        let peripheral = match peripheral {
+            Peripheral::Bcm(peripheral, mut gateway_addresses) => {
+                gateway_addresses.append(&mut extra_gateway_addresses);
+                Peripheral::Bcm(peripheral, gateway_addresses)
+            }
+            Peripheral::Relay(peripheral, mut gateway_addresses) => {
+                gateway_addresses.append(&mut extra_gateway_addresses);
+                Peripheral::Relay(peripheral, gateway_addresses)
+            }
+        };
+
+        self.peripherals.insert(index, peripheral);
+
+        Ok(())
+    }
+
+    fn lower_send_statement(&mut self, statement: &SendStatement) -> Result<(), ParserError<Span>> {
+        let event_code = self.evaluate_event_code(&statement.event_code)?;
+        let from_address = self.evaluate_address(&statement.from_address)?;
+        let to_address = self.evaluate_address(&statement.to_address)?;
+
+        let combined_matcher = Matcher::And(
+            Box::new(Self::event_matcher(event_code)),
+            Box::new(Self::producer_matcher(from_address)),
+        );
+
+        let matcher = match &statement.condition {
+            Some(condition) => Matcher::And(
+                Box::new(self.lower_match_expr(condition)?),
+                Box::new(combined_matcher),
+            ),
+            None => combined_matcher,
+        };
+
+        let packet_creator = Creator {
+            extractor: Box::new(PacketExtractor::new()),
+            producer: Box::new(PacketProducer::new(to_address)),
+            matcher: None,
+        };
+
+        self.check_condition(&matcher, None, statement.span);
+        self.source_map.event_processors.push(statement.span);
+        self.source_map.creators.push(vec![statement.span]);
+        self.event_processors.push(EventProcessor {
+            matcher,
+            creators: vec![packet_creator],
+        });
+
+        Ok(())
+    }
+
+    fn lower_set_statement(&mut self, statement: &SetStatement) -> Result<(), ParserError<Span>> {
+        let state_index = self.state_index(&statement.state_variable)?;
+        let target_value =
+            self.evaluate_as_type(&statement.value, self.state_types[&state_index])?;
+        let target_value = Value::try_from(target_value)
+            .map_err(|err: ParserError<&str>| err.relocate(statement.value.span()))?;
+        let event_code = self.evaluate_event_code(&statement.event_code)?;
+        let from_address = self.evaluate_address(&statement.from_address)?;
+
+        let combined_matcher = Matcher::And(
+            Box::new(Self::event_matcher(event_code)),
+            Box::new(Self::producer_matcher(from_address)),
+        );
+
+        let set_matcher = Matcher::Single {
+            extractor: Box::new(NoneExtractor::new()),
+            filter: Box::new(SetStateToConstFilter::new(state_index, target_value)),
+        };
+
+        let matcher = match &statement.condition {
+            Some(condition) => Matcher::And(
+                Box::new(combined_matcher),
+                Box::new(Matcher::And(
+                    Box::new(self.lower_match_expr(condition)?),
+                    Box::new(set_matcher),
+                )),
+            ),
+            None => Matcher::And(Box::new(combined_matcher), Box::new(set_matcher)),
+        };
+
+        self.check_condition(&matcher, None, statement.span);
+        self.source_map.event_processors.push(statement.span);
+        self.source_map.creators.push(vec![]);
+        self.event_processors.push(EventProcessor {
+            matcher,
+            creators: vec![],
+        });
+
+        Ok(())
+    }
+
+    fn lower_do_statement(&mut self, statement: &DoStatement) -> Result<(), ParserError<Span>> {
+        let mut matchers = vec![];
+
+        for match_expr in statement.matches.iter() {
+            matchers.push(self.lower_match_expr(match_expr)?);
+        }
+
+        let mut creators = vec![];
+
+        for fire_expr in statement.fires.iter() {
+            creators.push(self.lower_fire_expr(fire_expr)?);
+        }
+
+        let matcher = Self::fold_matchers(matchers, Matcher::And);
+        self.check_condition(&matcher, None, statement.span);
+
+        for (creator, fire_expr) in creators.iter().zip(statement.fires.iter()) {
+            if let (Some(condition), Some(condition_expr)) =
+                (&creator.matcher, &fire_expr.condition)
+            {
+                self.check_condition(condition, Some(&matcher), condition_expr.span());
+            }
+        }
+
+        self.source_map.event_processors.push(statement.span);
+        self.source_map.creators.push(
+            statement
+                .fires
+                .iter()
+                .map(|fire_expr| fire_expr.span)
+                .collect(),
+        );
+        self.event_processors
+            .push(EventProcessor { matcher, creators });
+
+        Ok(())
+    }
+
+    /// Returns the `do` statement that `statement` is short for, with the spans of the parts of
+    /// `statement` that its parts come from.
+    fn toggle_do_statement(statement: &ToggleStatement) -> DoStatement {
+        let state_variable = &statement.state_variable;
+        let state_filter = |name: &str, arguments: Vec<Expression>| MatchExpr::Block {
+            block: BlockMatch::Single {
+                extractor: None,
+                filter: ItemCall {
+                    name: Name {
+                        value: name.to_string(),
+                        span: state_variable.span,
+                    },
+                    arguments,
+                    span: state_variable.span,
+                },
+                span: state_variable.span,
+            },
+            span: state_variable.span,
+        };
+        let branch = |fire_expr: &FireExpr, value: bool| FireExpr {
+            condition: Some(state_filter(
+                "StateEqualToConstFilter",
+                vec![
+                    Expression::Constant(state_variable.clone()),
+                    Expression::Literal {
+                        value: Literal::Bool(value),
+                        span: fire_expr.span,
+                    },
+                ],
+            )),
+            ..fire_expr.clone()
+        };
+
+        DoStatement {
+            matches: vec![
+                MatchExpr::Event {
+                    event_code: statement.event_code.clone(),
+                    span: statement.event_code.span(),
+                },
+                MatchExpr::Producer {
+                    address: statement.from_address.clone(),
+                    span: statement.from_address.span(),
+                },
+                state_filter(
+                    "FlipStateFilter",
+                    vec![Expression::Constant(state_variable.clone())],
+                ),
+            ],
+            fires: vec![branch(&statement.on, true), branch(&statement.off, false)],
+            span: statement.span,
+        }
+    }
+
+    fn lower_test_statement(&mut self, statement: &TestStatement) -> Result<(), ParserError<Span>> {
+        let device_address = match &statement.device_address {
+            Some(device_address) => self.evaluate_address(device_address)?,
+            None => DEFAULT_DEVICE_ADDRESS,
+        };
+        let mut actions = vec![];
+
+        for step in statement.steps.iter() {
+            let action = match step {
+                TestStep::Inject {
+                    event_code,
+                    from_address,
+                    data,
+                    span,
+                } => {
+                    let event_code = self.evaluate_event_code(event_code)?;
+
+                    Action::Inject {
+                        event: Event {
+                            event_code,
+                            producer_address: self.evaluate_address(from_address)?,
+                            data: self.evaluate_event_data(event_code, data)?,
+                        },
+                        span: *span,
+                    }
+                }
+                TestStep::ExpectPacket { producer, span } => Action::ExpectPacket {
+                    producer: self.lower_producer(producer)?,
+                    span: *span,
+                },
+                TestStep::ExpectState {
+                    state_variable,
+                    value,
+                    span,
+                } => {
+                    let state_index = self.state_index(state_variable)?;
+                    let literal = self.evaluate_as_type(value, self.state_types[&state_index])?;
+                    let value = Value::try_from(literal)
+                        .map_err(|err: ParserError<&str>| err.relocate(value.span()))?;
+
+                    Action::ExpectState {
+                        state_index,
+                        value,
+                        span: *span,
+                    }
+                }
+            };
+
+            actions.push(action);
+        }
+
+        self.tests.push(Test {
+            name: statement.name.clone(),
+            span: statement.span,
+            device_address,
+            actions,
+        });
+
+        Ok(())
+    }
+
+    fn lower_match_expr(&mut self, match_expr: &MatchExpr) -> Result<Matcher, ParserError<Span>> {
+        match match_expr {
+            MatchExpr::Event { event_code, .. } => {
+                Ok(Self::event_matcher(self.evaluate_event_code(event_code)?))
+            }
+            MatchExpr::Producer { address, .. } => {
+                Ok(Self::producer_matcher(self.evaluate_address(address)?))
+            }
+            MatchExpr::Tick { .. } => Ok(Self::event_matcher(INTERNAL_SYSTEM_TICK_EVENT_CODE)),
+            MatchExpr::Block { block, .. } => self.lower_block_match(block),
+            MatchExpr::Not { operand, .. } => {
+                Ok(Matcher::Not(Box::new(self.lower_match_expr(operand)?)))
+            }
+            MatchExpr::And { left, right, .. } => Ok(Matcher::And(
+                Box::new(self.lower_match_expr(left)?),
+                Box::new(self.lower_match_expr(right)?),
+            )),
+            MatchExpr::Or { left, right, .. } => Ok(Matcher::Or(
+                Box::new(self.lower_match_expr(left)?),
+                Box::new(self.lower_match_expr(right)?),
+            )),
+            MatchExpr::Parenthesized { match_expr, .. } => self.lower_match_expr(match_expr),
+        }
+    }
+
+    fn lower_block_match(&self, block: &BlockMatch) -> Result<Matcher, ParserError<Span>> {
+        match block {
+            BlockMatch::Single {
+                extractor, filter, ..
+            } => Ok(Matcher::Single {
+                extractor: self.lower_optional_extractor(extractor)?,
+                filter: self.lower_filter(filter)?,
+            }),
+            BlockMatch::Not {
+                extractor, filter, ..
+            } => Ok(Matcher::Not(Box::new(Matcher::Single {
+                extractor: self.lower_optional_extractor(extractor)?,
+                filter: self.lower_filter(filter)?,
+            }))),
+            BlockMatch::Or { children, .. } => {
+                let matchers = self.lower_block_matches(children)?;
+                Ok(Self::fold_matchers(matchers, Matcher::Or))
+            }
+            BlockMatch::And { children, .. } => {
+                let matchers = self.lower_block_matches(children)?;
+                Ok(Self::fold_matchers(matchers, Matcher::And))
+            }
+        }
+    }
+
+    fn lower_block_matches(
+        &self,
+        blocks: &[BlockMatch],
+    ) -> Result<Vec<Matcher>, ParserError<Span>> {
+        blocks
+            .iter()
+            .map(|block| self.lower_block_match(block))
+            .collect()
+    }
+
+    fn lower_fire_expr(&mut self, fire_expr: &FireExpr) -> Result<Creator, ParserError<Span>> {
+        let matcher = match &fire_expr.condition {
+            Some(condition) => Some(self.lower_match_expr(condition)?),
+            None => None,
+        };
+
+        Ok(Creator {
+            extractor: self.lower_optional_extractor(&fire_expr.extractor)?,
+            producer: self.lower_producer(&fire_expr.producer)?,
+            matcher,
+        })
+    }
+
+    fn lower_optional_extractor(
+        &self,
+        extractor: &Option<ItemCall>,
+    ) -> Result<Box<dyn Extractor>, ParserError<Span>> {
+        match extractor {
+            Some(extractor) => {
+                let (arguments, argument_locations) = self.evaluate_arguments(extractor)?;
+                create_extractor(
+                    &extractor.name.value,
+                    &arguments,
+                    extractor.span,
+                    &argument_locations,
+                )
+            }
+            None => Ok(Box::new(NoneExtractor::new())),
+        }
+    }
+
+    fn lower_filter(&self, filter: &ItemCall) -> Result<Box<dyn Filter>, ParserError<Span>> {
+        let (arguments, argument_locations) = self.evaluate_arguments(filter)?;
+        create_filter(
+            &filter.name.value,
+            &arguments,
+            filter.span,
+            &argument_locations,
+        )
+    }
+
+    fn lower_producer(&self, producer: &ItemCall) -> Result<Box<dyn Producer>, ParserError<Span>> {
+        let (arguments, argument_locations) = self.evaluate_arguments(producer)?;
+        create_producer(
+            &producer.name.value,
+            &arguments,
+            producer.span,
+            &argument_locations,
+        )
+    }
+
+    /// Evaluates the arguments of `item_call`. State variables are only allowed where the item
+    /// expects one, and are passed on as their `u32` index. Values the item compares to or
+    /// stores in a state variable need to have the type of that variable.
+    fn evaluate_arguments(
+        &self,
+        item_call: &ItemCall,
+    ) -> Result<(Vec<Literal>, Vec<Span>), ParserError<Span>> {
+        let signature = item_signature(&item_call.name.value).unwrap_or(&[]);
+        let allowed_state_types = state_types(&item_call.name.value);
+        let mut state_type = None;
+        let mut arguments = vec![];
+        let mut argument_locations = vec![];
+
+        for (index, argument) in item_call.arguments.iter().enumerate() {
+            let value = match (signature.get(index), self.evaluate(argument)?) {
+                (Some(ArgumentKind::StateIndex), Literal::State(state_index)) => {
+                    let this_state_type = self.state_types[&state_index];
+
+                    if let Some(state_type) = state_type {
+                        if state_type != this_state_type {
+                            return Err(ParserError::Base {
+                                location: argument.span(),
+                                kind: ErrorKind::MismatchedTypes(state_type, this_state_type),
+                                child: None,
+                            });
+                        }
+                    }
+
+                    if let Some(allowed_state_types) = allowed_state_types {
+                        if !allowed_state_types.contains(&this_state_type) {
+                            return Err(ParserError::Base {
+                                location: argument.span(),
+                                kind: ErrorKind::StateTypeNotAllowed(this_state_type),
+                                child: None,
+                            });
+                        }
+                    }
+
+                    state_type = Some(this_state_type);
+                    Literal::U32(state_index)
+                }
+                (Some(ArgumentKind::Address), value) => Literal::U16(
+                    address(value)
+                        .map_err(|err: ParserError<&str>| err.relocate(argument.span()))?,
+                ),
+                (Some(ArgumentKind::Value), value) if state_type.is_some() => {
+                    typed_literal(value, state_type.unwrap())
+                        .map_err(|err: ParserError<&str>| err.relocate(argument.span()))?
+                }
+                (Some(ArgumentKind::StateIndex), _) => {
+                    return Err(ParserError::Base {
+                        location: argument.span(),
+                        kind: ErrorKind::Expected(Expectation::StateVariable),
+                        child: None,
+                    })
+                }
+                (_, value) => value,
+            };
+
+            arguments.push(value);
+            argument_locations.push(argument.span());
+        }
+
+        Ok((arguments, argument_locations))
+    }
+
+    fn state_index(&self, state_variable: &Name) -> Result<u32, ParserError<Span>> {
+        self.state_variables
+            .get(&state_variable.value)
+            .copied()
+            .ok_or(ParserError::Base {
+                location: state_variable.span,
+                kind: ErrorKind::Expected(Expectation::StateVariable),
+                child: None,
+            })
+    }
+
+    fn evaluate(&self, expression: &Expression) -> Result<Literal, ParserError<Span>> {
+        match expression {
+            Expression::Literal { value, .. } => Ok(value.clone()),
+            Expression::Constant(name) => {
+                self.constants
+                    .get(&name.value)
+                    .cloned()
+                    .ok_or(ParserError::Base {
+                        location: name.span,
+                        kind: ErrorKind::UnknownConstant,
+                        child: None,
+                    })
+            }
+            Expression::Unary {
+                operator,
+                operand,
+                span,
+            } => evaluate_unary(*operator, self.evaluate(operand)?).map_err(|kind| {
+                ParserError::Base {
+                    location: *span,
+                    kind,
+                    child: None,
+                }
+            }),
+            Expression::Binary {
+                operator,
+                left,
+                right,
+                span,
+            } => evaluate_binary(*operator, self.evaluate(left)?, self.evaluate(right)?).map_err(
+                |kind| ParserError::Base {
+                    location: *span,
+                    kind,
+                    child: None,
+                },
+            ),
+            Expression::Parenthesized { expression, .. } => self.evaluate(expression),
+        }
+    }
+
+    /// Evaluates `expression` as a literal of the type named `value_type`, one of `VALUE_TYPES`.
+    fn evaluate_as_type(
+        &self,
+        expression: &Expression,
+        value_type: &'static str,
+    ) -> Result<Literal, ParserError<Span>> {
+        typed_literal(self.evaluate(expression)?, value_type)
+            .map_err(|err: ParserError<&str>| err.relocate(expression.span()))
+    }
+
+    fn evaluate_as<T>(&self, expression: &Expression) -> Result<T, ParserError<Span>>
+    where
+        T: TryFrom<Literal, Error = ParserError<&'static str>>,
+    {
+        self.evaluate(expression)?
+            .try_into()
+            .map_err(|err: ParserError<&str>| err.relocate(expression.span()))
+    }
+
+    /// Evaluates the data an event with `event_code` carries, in the order of the fields of its
+    /// `EventData` variant. Events without data carry `EventData::None`.
+    fn evaluate_event_data(
+        &self,
+        event_code: u16,
+        data: &[Expression],
+    ) -> Result<EventData, ParserError<Span>> {
+        let event_data = match (event_code, data) {
+            (_, []) => EventData::None,
+            (BUTTON_PRESSED_EVENT_CODE, [index]) | (BUTTON_RELEASED_EVENT_CODE, [index]) => {
+                EventData::Button {
+                    index: self.evaluate_as(index)?,
+                }
+            }
+            (MESSAGE_EVENT_CODE, [code, value]) => EventData::Message {
+                code: self.evaluate_as(code)?,
+                value: self.evaluate_as(value)?,
+            },
+            (BCM_CHANGE_BRIGHTNESS_EVENT_CODE, [index, value]) => EventData::BcmChangeBrightness {
+                index: self.evaluate_as(index)?,
+                value: self.evaluate_as(value)?,
+            },
+            (BCM_ANIMATE_BRIGHTNESS_EVENT_CODE, [index, duration, value]) => {
+                EventData::BcmAnimateBrightness {
+                    index: self.evaluate_as(index)?,
+                    duration: self.evaluate_as(duration)?,
+                    value: self.evaluate_as(value)?,
+                }
+            }
+            (RELAY_SET_VALUE_EVENT_CODE, [index, value]) => EventData::RelaySetValue {
+                index: self.evaluate_as(index)?,
+                value: self.evaluate_as(value)?,
+            },
+            (_, [first, ..]) => {
+                let expected = match event_code {
+                    BUTTON_PRESSED_EVENT_CODE | BUTTON_RELEASED_EVENT_CODE => 1,
+                    MESSAGE_EVENT_CODE
+                    | BCM_CHANGE_BRIGHTNESS_EVENT_CODE
+                    | RELAY_SET_VALUE_EVENT_CODE => 2,
+                    BCM_ANIMATE_BRIGHTNESS_EVENT_CODE => 3,
+                    _ => 0,
+                };
+
+                return Err(ParserError::Base {
+                    location: first.span().join(data[data.len() - 1].span()),
+                    kind: ErrorKind::Expected(Expectation::ArgumentCount(expected, data.len())),
+                    child: None,
+                });
+            }
+        };
+
+        Ok(event_data)
+    }
+
+    fn evaluate_address(&self, expression: &Expression) -> Result<u16, ParserError<Span>> {
+        address(self.evaluate(expression)?)
+            .map_err(|err: ParserError<&str>| err.relocate(expression.span()))
+    }
+
+    /// Evaluates an event code, with a warning if it is not one of `ross_protocol` or declared
+    /// with `event`.
+    fn evaluate_event_code(&mut self, expression: &Expression) -> Result<u16, ParserError<Span>> {
+        let value = self.evaluate(expression)?;
+        let declared = matches!(value, Literal::Event(_));
+        let type_name = value.type_name();
+        let event_code =
+            event_code(value).map_err(|err: ParserError<&str>| err.relocate(expression.span()))?;
+
+        if !declared {
+            self.warnings.push(ParserError::Base {
+                location: expression.span(),
+                kind: ErrorKind::UndeclaredEventCode(type_name),
+                child: None,
+            });
+        }
+
+        Ok(event_code)
+    }
+
+    /// Warns about a condition that can never be true or is always true on the events that
+    /// `context` matches.
+    fn check_condition(&mut self, matcher: &Matcher, context: Option<&Matcher>, span: Span) {
+        let kind = match satisfiability(matcher, context) {
+            Satisfiability::Never => ErrorKind::NeverMatches,
+            Satisfiability::Always => ErrorKind::AlwaysMatches,
+            Satisfiability::Sometimes => return,
+        };
+
+        self.warnings.push(ParserError::Base {
+            location: span,
+            kind,
+            child: None,
+        });
+    }
+
+    fn event_matcher(event_code: u16) -> Matcher {
+        Matcher::Single {
+            extractor: Box::new(EventCodeExtractor::new()),
+            filter: Box::new(ValueEqualToConstFilter::new(Value::U16(event_code))),
+        }
+    }
+
+    fn producer_matcher(producer_address: u16) -> Matcher {
+        Matcher::Single {
+            extractor: Box::new(EventProducerAddressExtractor::new()),
+            filter: Box::new(ValueEqualToConstFilter::new(Value::U16(producer_address))),
+        }
+    }
+
+    /// Combines `matchers` from left to right, so `[a, b, c]` becomes `f(f(a, b), c)`.
+    fn fold_matchers<F>(matchers: Vec<Matcher>, f: F) -> Matcher
+    where
+        F: Fn(Box<Matcher>, Box<Matcher>) -> Matcher,
+    {
+        let mut matchers = matchers.into_iter();
+        let first = matchers
+            .next()
+            .expect("This should never be reached because the parsers use many1");
+
+        matchers.fold(first, |matcher1, matcher2| {
+            f(Box::new(matcher1), Box::new(matcher2))
+        })
+    }
+}
+
+impl Default for Lowerer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Adds the event codes of `ross_protocol` to `constants`, under the same names.
+pub(crate) fn prepare_constants(constants: &mut BTreeMap<String, Literal>) {
+    prepare_constant!(BOOTLOADER_HELLO_EVENT_CODE, constants, Literal::Event);
+    prepare_constant!(PROGRAMMER_HELLO_EVENT_CODE, constants, Literal::Event);
+    prepare_constant!(
+        PROGRAMMER_START_FIRMWARE_UPGRADE_EVENT_CODE,
+        constants,
+        Literal::Event
+    );
+    prepare_constant!(ACK_EVENT_CODE, constants, Literal::Event);
+    prepare_constant!(DATA_EVENT_CODE, constants, Literal::Event);
+    prepare_constant!(CONFIGURATOR_HELLO_EVENT_CODE, constants, Literal::Event);
+    prepare_constant!(BCM_CHANGE_BRIGHTNESS_EVENT_CODE, constants, Literal::Event);
+    prepare_constant!(BUTTON_PRESSED_EVENT_CODE, constants, Literal::Event);
+    prepare_constant!(BUTTON_RELEASED_EVENT_CODE, constants, Literal::Event);
+    prepare_constant!(INTERNAL_SYSTEM_TICK_EVENT_CODE, constants, Literal::Event);
+    prepare_constant!(
+        PROGRAMMER_START_CONFIG_UPGRADE_EVENT_CODE,
+        constants,
+        Literal::Event
+    );
+    prepare_constant!(
+        PROGRAMMER_SET_DEVICE_ADDRESS_EVENT_CODE,
+        constants,
+        Literal::Event
+    );
+    prepare_constant!(MESSAGE_EVENT_CODE, constants, Literal::Event);
+    prepare_constant!(RELAY_SET_VALUE_EVENT_CODE, constants, Literal::Event);
+    prepare_constant!(BCM_ANIMATE_BRIGHTNESS_EVENT_CODE, constants, Literal::Event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+    use ross_config::filter::{SetStateToStateFilter, StateEqualToConstFilter};
+
+    use ross_protocol::event::bcm::BcmValue;
+    use ross_protocol::event::relay::RelayValue;
+
+    use crate::parser::{ParseOptions, Parser};
+
+    fn lower_text(text: &str) -> Result<Config, Vec<ParserError<Span>>> {
+        lower(&Parser::parse_program(text).unwrap())
+    }
+
+    #[test]
+    fn send_test() {
+        let config = lower_text("send 0xabab~u16 from 0x0123~u16 to 0xffff~u16;").unwrap();
+        let event_processor = &config.event_processors[0];
+
+        assert_matches!(&event_processor.matcher, Matcher::And(matcher1, matcher2) => {
+            assert_matches!(&**matcher1, Matcher::Single {extractor, filter} => {
+                assert_eq!(
+                    format!("{:?}", extractor),
+                    format!("{:?}", EventCodeExtractor::new()),
+                );
+                assert_eq!(
+                    format!("{:?}", filter),
+                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab))),
+                );
+            });
+
+            assert_matches!(&**matcher2, Matcher::Single {extractor, filter} => {
+                assert_eq!(
+                    format!("{:?}", extractor),
+                    format!("{:?}", EventProducerAddressExtractor::new()),
+                );
+                assert_eq!(
+                    format!("{:?}", filter),
+                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0x0123))),
+                );
+            });
+        });
+
+        assert_eq!(event_processor.creators.len(), 1);
+        assert_eq!(
+            format!("{:?}", event_processor.creators[0].extractor),
+            format!("{:?}", PacketExtractor::new()),
+        );
+        assert_eq!(
+            format!("{:?}", event_processor.creators[0].producer),
+            format!("{:?}", PacketProducer::new(0xffff)),
+        );
+    }
+
+    #[test]
+    fn send_if_match_test() {
+        let config =
+            lower_text("send 0xabab~u16 from 0x0123~u16 to 0xffff~u16 if match event 0xbaba~u16;")
+                .unwrap();
+
+        assert_matches!(&config.event_processors[0].matcher, Matcher::And(matcher1, matcher2) => {
+            assert_matches!(&**matcher1, Matcher::Single {extractor, filter} => {
+                assert_eq!(
+                    format!("{:?}", extractor),
+                    format!("{:?}", EventCodeExtractor::new()),
+                );
+                assert_eq!(
+                    format!("{:?}", filter),
+                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xbaba))),
+                );
+            });
+
+            assert_matches!(&**matcher2, Matcher::And(_, _));
+        });
+    }
+
+    #[test]
+    fn send_constant_test() {
+        let config = lower_text(
+            "const receiver_address = 0xffff~u16;
+            send BUTTON_PRESSED_EVENT_CODE from 0x0123~u16 to receiver_address;",
+        )
+        .unwrap();
+
+        assert_eq!(
+            format!("{:?}", config.event_processors[0].creators[0].producer),
+            format!("{:?}", PacketProducer::new(0xffff)),
+        );
+    }
+
+    #[test]
+    fn send_invalid_literal_test() {
+        let text = "send 0xabababab~u32 from 0x0123~u16 to 0xffff~u16;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 5, end: 19 },
+                        kind: ErrorKind::CastFromToNotAllowed("u32", "u16"),
+                        child: None,
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_constant_test() {
+        let text = "send 0xabab~u16 from 0x0123~u16 to receiver_address;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 35, end: 51 },
+                        kind: ErrorKind::UnknownConstant,
+                        child: None,
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn set_test() {
+        let config = lower_text(
+            "let button_pressed = false;
+            set button_pressed to true on 0xabab~u16 from 0x0123~u16;",
+        )
+        .unwrap();
+
+        assert_eq!(config.initial_state.get(&0), Some(&Value::Bool(false)));
+        assert_matches!(&config.event_processors[0].matcher, Matcher::And(matcher1, matcher2) => {
+            assert_matches!(&**matcher1, Matcher::And(_, _));
+            assert_matches!(&**matcher2, Matcher::Single {extractor, filter} => {
+                assert_eq!(
+                    format!("{:?}", extractor),
+                    format!("{:?}", NoneExtractor::new()),
+                );
+                assert_eq!(
+                    format!("{:?}", filter),
+                    format!("{:?}", SetStateToConstFilter::new(0, Value::Bool(true))),
+                );
+            });
+        });
+        assert_eq!(config.event_processors[0].creators.len(), 0);
+    }
+
+    #[test]
+    fn set_unknown_state_variable_test() {
+        let text = "set button_pressed to true on 0xabab~u16 from 0x0123~u16;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 4, end: 18 },
+                        kind: ErrorKind::Expected(Expectation::StateVariable),
+                        child: None,
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn test_test() {
+        let text = "let a = false;
+            test \"a\" {
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0002~u16;
+                expect NoneProducer();
+                expect state a == true;
+            }";
+        let program = Parser::parse_program(text).unwrap();
+        let mut lowerer = Lowerer::new();
+
+        for statement in program.statements.iter() {
+            lowerer.lower_statement(statement).unwrap();
+        }
+
+        let tests = lowerer.take_tests();
+
+        assert!(lowerer.finish().event_processors.is_empty());
+        assert_matches!(tests.as_slice(), [test] => {
+            assert_eq!(test.name, "a");
+            assert_matches!(
+                test.actions.as_slice(),
+                [
+                    Action::Inject { event, .. },
+                    Action::ExpectPacket { .. },
+                    Action::ExpectState { state_index: 0, value: Value::Bool(true), .. },
+                ] => {
+                    assert_eq!(event.event_code, BUTTON_PRESSED_EVENT_CODE);
+                    assert_eq!(event.producer_address, 0x0002);
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn test_unknown_state_variable_test() {
+        let text = "test \"a\" { expect state a == true; }";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 24, end: 25 },
+                        kind: ErrorKind::Expected(Expectation::StateVariable),
+                        child: None,
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn do_test() {
+        let config = lower_text(
+            "do {
+                match event 0xabab~u16;
+                match producer 0x0123~u16;
+                match tick;
+                fire {
+                    PacketExtractor();
+                    PacketProducer(0xffff~u16);
+                }
+            }",
+        )
+        .unwrap();
+        let event_processor = &config.event_processors[0];
+
+        assert_matches!(&event_processor.matcher, Matcher::And(matcher1, matcher2) => {
+            assert_matches!(&**matcher1, Matcher::And(_, _));
+            assert_matches!(&**matcher2, Matcher::Single {extractor, filter} => {
+                assert_eq!(
+                    format!("{:?}", extractor),
+                    format!("{:?}", EventCodeExtractor::new()),
+                );
+                assert_eq!(
+                    format!("{:?}", filter),
+                    format!(
+                        "{:?}",
+                        ValueEqualToConstFilter::new(Value::U16(INTERNAL_SYSTEM_TICK_EVENT_CODE))
+                    ),
+                );
+            });
+        });
+
+        assert_eq!(event_processor.creators.len(), 1);
+        assert_eq!(
+            format!("{:?}", event_processor.creators[0].producer),
+            format!("{:?}", PacketProducer::new(0xffff)),
+        );
+    }
+
+    #[test]
+    fn toggle_test() {
+        let toggle = lower_text(
+            "let active = false;
+            toggle active on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 {
+                on: fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0xff~u8); };
+                off: fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0x00~u8); };
+            }",
+        )
+        .unwrap();
+        let do_statement = lower_text(
+            "let active = false;
+            do {
+                match event BUTTON_PRESSED_EVENT_CODE;
+                match producer 0x0002~u16;
+                match { FlipStateFilter(active); }
+                fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0xff~u8); } if match {
+                    StateEqualToConstFilter(active, true);
+                }
+                fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0x00~u8); } if match {
+                    StateEqualToConstFilter(active, false);
+                }
+            }",
+        )
+        .unwrap();
+
+        assert_eq!(format!("{:?}", toggle), format!("{:?}", do_statement));
+    }
+
+    #[test]
+    fn toggle_state_type_test() {
+        let text = "let level = 0x00~u8;
+            toggle level on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 {
+                on: fire { NoneProducer(); };
+                off: fire { NoneProducer(); };
+            }";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location,
+                        kind: ErrorKind::StateTypeNotAllowed("u8"),
+                        ..
+                    }] => {
+                        assert_eq!(location.start, text.find("level on").unwrap());
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn block_match_test() {
+        let config = lower_text(
+            "do {
+                match or {
+                    not {
+                        ValueEqualToConstFilter(0xabab~u16);
+                    },
+                    {
+                        EventCodeExtractor();
+                        ValueEqualToConstFilter(0x0123~u16);
+                    }
+                }
+            }",
+        )
+        .unwrap();
+
+        assert_matches!(&config.event_processors[0].matcher, Matcher::Or(matcher1, matcher2) => {
+            assert_matches!(&**matcher1, Matcher::Not(matcher) => {
+                assert_matches!(&**matcher, Matcher::Single {extractor, ..} => {
+                    assert_eq!(
+                        format!("{:?}", extractor),
+                        format!("{:?}", NoneExtractor::new()),
+                    );
+                });
+            });
+            assert_matches!(&**matcher2, Matcher::Single {extractor, filter} => {
+                assert_eq!(
+                    format!("{:?}", extractor),
+                    format!("{:?}", EventCodeExtractor::new()),
+                );
+                assert_eq!(
+                    format!("{:?}", filter),
+                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0x0123))),
+                );
+            });
+        });
+    }
+
+    #[test]
+    fn block_two_extractors_test() {
+        let text = "do {
+            match {
+                EventCodeExtractor();
+                NoneExtractor();
+            }
+        }";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        kind: ErrorKind::UnknownFilter,
+                        child: None,
+                        ..
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn fire_invalid_literal_test() {
+        let text = "do {
+            match tick;
+            fire {
+                PacketProducer(0xffffffff~u32);
+            }
+        }";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location,
+                        kind: ErrorKind::CastFromToNotAllowed("u32", "u16"),
+                        child: None,
+                    }] => {
+                        assert_eq!(&text[location.start..location.end], "0xffffffff~u32");
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn peripheral_test() {
+        let config = lower_text(
+            "peripheral 0x00~u32 bcm single(0x01~u8);
+            peripheral 0x01~u32 bcm rgb(0x01~u8, 0x23~u8, 0x45~u8);
+            peripheral 0x02~u32 bcm rgbw(0x01~u8, 0x23~u8, 0x45~u8, 0x67~u8);
+            peripheral 0x03~u32 relay single(0x01~u8);
+            pub(0x00~u16, 0x01~u16) peripheral 0x04~u32 relay double_exclusive(0x01~u8, 0x23~u8);",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.peripherals.get(&0x00),
+            Some(&Peripheral::Bcm(BcmPeripheral::Single(0x01), vec![]))
+        );
+        assert_eq!(
+            config.peripherals.get(&0x01),
+            Some(&Peripheral::Bcm(
+                BcmPeripheral::Rgb(0x01, 0x23, 0x45),
+                vec![]
+            ))
+        );
+        assert_eq!(
+            config.peripherals.get(&0x02),
+            Some(&Peripheral::Bcm(
+                BcmPeripheral::Rgbw(0x01, 0x23, 0x45, 0x67),
+                vec![]
+            ))
+        );
+        assert_eq!(
+            config.peripherals.get(&0x03),
+            Some(&Peripheral::Relay(RelayPeripheral::Single(0x01), vec![]))
+        );
+        assert_eq!(
+            config.peripherals.get(&0x04),
+            Some(&Peripheral::Relay(
+                RelayPeripheral::DoubleExclusive(0x01, 0x23),
+                vec![0x00, 0x01]
+            ))
+        );
+    }
+
+    #[test]
+    fn peripheral_argument_count_test() {
+        let text = "peripheral 0x00~u32 bcm rgb(0x01~u8);";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 0, end: 37 },
+                        kind: ErrorKind::Expected(Expectation::ArgumentCount(3, 1)),
+                        child: None,
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn later_statements_are_still_lowered_test() {
+        let text = "send 0xabababab~u32 from 0x0123~u16 to 0xffff~u16;
+            send receiver_address from 0x0123~u16 to 0xffff~u16;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_eq!(errors.len(), 2);
+            }
+        );
+    }
+
+    #[test]
+    fn constant_expression_test() {
+        let config = lower_text(
+            "const base_address = 0x0100~u16;
+            const receiver_address = (base_address | 0x0001~u16) << 0x01~u8;
+            let enabled = !(base_address + 0x0003~u16 == 0x0103~u16);
+            send BUTTON_PRESSED_EVENT_CODE from 0x0123~u16 to receiver_address;",
+        )
+        .unwrap();
+
+        assert_eq!(config.initial_state.get(&0), Some(&Value::Bool(false)));
+        assert_eq!(
+            format!("{:?}", config.event_processors[0].creators[0].producer),
+            format!("{:?}", PacketProducer::new(0x0202)),
+        );
+    }
+
+    #[test]
+    fn constant_expression_overflow_test() {
+        let text = "const a = 0xff~u8;\nconst b = a + 0x01~u8;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 29, end: 40 },
+                        kind: ErrorKind::Overflow("u8"),
+                        child: None,
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn constant_expression_mismatched_types_test() {
+        let text = "const a = 0x01~u8 * (0x0001~u16);";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 10, end: 32 },
+                        kind: ErrorKind::MismatchedTypes("u8", "u16"),
+                        child: None,
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn unsuffixed_integer_test() {
+        let config = lower_text(
+            "const receiver_address = 0xfff0 + 0x000f;
+            peripheral 2 bcm rgb(0x01, 0x23, 0x45);
+            send 0xabab from 0x0123 to receiver_address;
+            do {
+                match event BUTTON_PRESSED_EVENT_CODE;
+                fire {
+                    BcmChangeBrightnessProducer(receiver_address, 1, 255);
+                }
+            }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.peripherals.get(&0x02),
+            Some(&Peripheral::Bcm(
+                BcmPeripheral::Rgb(0x01, 0x23, 0x45),
+                vec![]
+            ))
+        );
+        assert_eq!(
+            format!("{:?}", config.event_processors[0].creators[0].producer),
+            format!("{:?}", PacketProducer::new(0xffff)),
+        );
+    }
+
+    #[test]
+    fn unsuffixed_integer_out_of_range_test() {
+        let text = "send 0xabab from 0x0123 to 0x10000;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 27, end: 34 },
+                        kind: ErrorKind::IntegerOutOfRange("u16"),
+                        child: None,
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn unsuffixed_integer_ambiguous_test() {
+        let text = "let a = 1;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 8, end: 9 },
+                        kind: ErrorKind::AmbiguousInteger,
+                        child: None,
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn device_test() {
+        let text = "device kitchen_switch = 0x0002;
+            device kitchen_light = 0x0003;
+            let on = false;
+            send BUTTON_PRESSED_EVENT_CODE from kitchen_switch to kitchen_light;
+            set on to true on BUTTON_PRESSED_EVENT_CODE from kitchen_switch;
+            do {
+                match producer kitchen_switch;
+                fire {
+                    PacketProducer(kitchen_light);
+                }
+            }";
+        let mut lowerer = Lowerer::new();
+
+        for statement in Parser::parse_program(text).unwrap().statements.iter() {
+            lowerer.lower_statement(statement).unwrap();
+        }
+
+        assert_eq!(
+            lowerer.devices().get(&0x0002),
+            Some(&"kitchen_switch".to_string())
+        );
+        assert_eq!(
+            lowerer.devices().get(&0x0003),
+            Some(&"kitchen_light".to_string())
+        );
+
+        let config = lowerer.finish();
+
+        assert_eq!(
+            format!("{:?}", config.event_processors[0].creators[0].producer),
+            format!("{:?}", PacketProducer::new(0x0003)),
+        );
+        assert_eq!(
+            format!("{:?}", config.event_processors[2].matcher),
+            format!("{:?}", Lowerer::producer_matcher(0x0002)),
+        );
+        assert_eq!(
+            format!("{:?}", config.event_processors[2].creators[0].producer),
+            format!("{:?}", PacketProducer::new(0x0003)),
+        );
+    }
+
+    #[test]
+    fn device_arithmetic_test() {
+        let text = "device kitchen_switch = 0x0002;
+            device kitchen_light = kitchen_switch + 1;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location,
+                        kind: ErrorKind::MismatchedTypes("device", "integer"),
+                        ..
+                    }] => {
+                        assert_eq!(location.start, text.find("kitchen_switch + 1").unwrap());
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn duplicate_device_address_test() {
+        let text = "device kitchen_switch = 0x0002;\ndevice kitchen_light = 0x0002;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 55, end: 61 },
+                        kind: ErrorKind::DuplicateDeviceAddress(device),
+                        child: None,
+                    }] => {
+                        assert_eq!(device, "kitchen_switch");
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn device_type_test() {
+        let text = "device kitchen_switch = 0x0002;
+            do {
+                match tick;
+                fire {
+                    BcmChangeBrightnessProducer(kitchen_switch, kitchen_switch, 0xff);
+                }
+            }";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        kind: ErrorKind::CastFromToNotAllowed("device", "u8"),
+                        ..
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn device_value_test() {
+        let text = "device kitchen_switch = 0x0002;
+            do {
+                match {
+                    EventProducerAddressExtractor();
+                    ValueEqualToConstFilter(kitchen_switch);
+                }
+            }
+            do {
+                match tick;
+                fire {
+                    MessageProducer(kitchen_switch, kitchen_switch, 0x01~u8);
+                }
+            }";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [
+                        ParserError::Base {
+                            location: value_location,
+                            kind: ErrorKind::CastFromToNotAllowed("device", "value"),
+                            ..
+                        },
+                        ParserError::Base {
+                            location: code_location,
+                            kind: ErrorKind::CastFromToNotAllowed("device", "u16"),
+                            ..
+                        },
+                    ] => {
+                        assert_eq!(value_location.start, text.find("kitchen_switch)").unwrap());
+                        assert_eq!(code_location.start, text.find("kitchen_switch, 0x01").unwrap());
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn event_test() {
+        let text = "event scene_changed = 0x1000;
+            send scene_changed from 0x0002 to 0x0003;
+            do {
+                match event scene_changed;
+                fire {
+                    MessageProducer(0x0003, 0x0001~u16, 0x0001~u16);
+                }
+            }";
+        let mut lowerer = Lowerer::new();
+
+        for statement in Parser::parse_program(text).unwrap().statements.iter() {
+            lowerer.lower_statement(statement).unwrap();
+        }
+
+        assert_eq!(
+            lowerer.event_codes().get(&0x1000),
+            Some(&"scene_changed".to_string())
+        );
+        assert!(lowerer.warnings().is_empty());
+
+        let config = lowerer.finish();
+
+        assert_eq!(
+            format!("{:?}", config.event_processors[1].matcher),
+            format!("{:?}", Lowerer::event_matcher(0x1000)),
+        );
+    }
+
+    #[test]
+    fn event_address_test() {
+        let text = "send BUTTON_PRESSED_EVENT_CODE from BUTTON_RELEASED_EVENT_CODE to 0x0003;
+            send BUTTON_PRESSED_EVENT_CODE from 0x0002 to MESSAGE_EVENT_CODE;
+            do {
+                match {
+                    EventCodeExtractor();
+                    ValueEqualToConstFilter(BUTTON_PRESSED_EVENT_CODE);
+                }
+            }
+            do {
+                match tick;
+                fire {
+                    MessageProducer(0x0003, BUTTON_RELEASED_EVENT_CODE, 0x01~u8);
+                }
+            }";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [
+                        ParserError::Base {
+                            location: from_location,
+                            kind: ErrorKind::CastFromToNotAllowed("event", "u16"),
+                            ..
+                        },
+                        ParserError::Base {
+                            location: to_location,
+                            kind: ErrorKind::CastFromToNotAllowed("event", "u16"),
+                            ..
+                        },
+                        ParserError::Base {
+                            location: value_location,
+                            kind: ErrorKind::CastFromToNotAllowed("event", "value"),
+                            ..
+                        },
+                        ParserError::Base {
+                            location: code_location,
+                            kind: ErrorKind::CastFromToNotAllowed("event", "u16"),
+                            ..
+                        },
+                    ] => {
+                        assert_eq!(from_location.start, text.find("BUTTON_RELEASED").unwrap());
+                        assert_eq!(to_location.start, text.find("MESSAGE_EVENT_CODE").unwrap());
+                        assert_eq!(
+                            value_location.start,
+                            text.find("BUTTON_PRESSED_EVENT_CODE)").unwrap()
+                        );
+                        assert_eq!(
+                            code_location.start,
+                            text.find("BUTTON_RELEASED_EVENT_CODE, 0x01").unwrap()
+                        );
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn device_event_code_test() {
+        let text = "device kitchen_switch = 0x0002;
+            send kitchen_switch from 0x0002 to 0x0003;
+            do {
+                match event kitchen_switch;
+            }";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [
+                        ParserError::Base {
+                            location: send_location,
+                            kind: ErrorKind::CastFromToNotAllowed("device", "u16"),
+                            ..
+                        },
+                        ParserError::Base {
+                            location: match_location,
+                            kind: ErrorKind::CastFromToNotAllowed("device", "u16"),
+                            ..
+                        },
+                    ] => {
+                        assert_eq!(send_location.start, text.find("kitchen_switch from").unwrap());
+                        assert_eq!(match_location.start, text.find("kitchen_switch;").unwrap());
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn inject_data_count_test() {
+        let text = "test \"a\" {
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0002 with 0x01~u8, 0x02~u8;
+            }";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location,
+                        kind: ErrorKind::Expected(Expectation::ArgumentCount(1, 2)),
+                        ..
+                    }] => {
+                        assert_eq!(&text[location.start..location.end], "0x01~u8, 0x02~u8");
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn duplicate_event_code_test() {
+        let text = "event pressed = BUTTON_PRESSED_EVENT_CODE;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 16, end: 41 },
+                        kind: ErrorKind::DuplicateEventCode(event),
+                        child: None,
+                    }] => {
+                        assert_eq!(event, "BUTTON_PRESSED_EVENT_CODE");
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn duplicate_event_code_value_test() {
+        for (value, event) in [
+            ("0x000d", "RELAY_SET_VALUE_EVENT_CODE"),
+            ("0x000e", "BCM_ANIMATE_BRIGHTNESS_EVENT_CODE"),
+        ] {
+            let text = format!("event mine = {};", value);
+
+            assert_matches!(
+                lower_text(&text),
+                Err(errors) => {
+                    assert_matches!(
+                        errors.as_slice(),
+                        [ParserError::Base {
+                            location: Span { start: 13, end: 19 },
+                            kind: ErrorKind::DuplicateEventCode(duplicate),
+                            child: None,
+                        }] => {
+                            assert_eq!(duplicate, event);
+                        }
+                    );
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn inject_event_data_test() {
+        let text = "test \"peripherals\" {
+                inject RELAY_SET_VALUE_EVENT_CODE from 0x0002 with 0x00~u8, true;
+                inject BCM_ANIMATE_BRIGHTNESS_EVENT_CODE from 0x0002 with 0x01~u8, 500~u32, 0xff~u8;
+            }";
+        let parsed = Parser::parse_with(ParseOptions::text(text).with_tests()).unwrap();
+
+        assert_matches!(
+            parsed.tests.unwrap()[0].actions.as_slice(),
+            [
+                Action::Inject { event: relay, .. },
+                Action::Inject { event: bcm, .. },
+            ] => {
+                assert_eq!(relay.event_code, RELAY_SET_VALUE_EVENT_CODE);
+                assert_eq!(
+                    relay.data,
+                    EventData::RelaySetValue {
+                        index: 0x00,
+                        value: RelayValue::Single(true),
+                    }
+                );
+                assert_eq!(bcm.event_code, BCM_ANIMATE_BRIGHTNESS_EVENT_CODE);
+                assert_eq!(
+                    bcm.data,
+                    EventData::BcmAnimateBrightness {
+                        index: 0x01,
+                        duration: 500,
+                        value: BcmValue::Single(0xff),
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn already_defined_test() {
+        let text = "const a = 0x01~u8;\nlet a = false;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 6, end: 7 },
+                        kind: ErrorKind::FirstDefinition(first_name),
+                        child: Some(child),
+                    }] => {
+                        assert_eq!(first_name, "a");
+                        assert_matches!(
+                            child.as_ref(),
+                            ParserError::Base {
+                                location: Span { start: 23, end: 24 },
+                                kind: ErrorKind::AlreadyDefined(name),
+                                child: None,
+                            } => {
+                                assert_eq!(name, "a");
+                            }
+                        );
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn built_in_redefined_test() {
+        let text = "let BUTTON_PRESSED_EVENT_CODE = false;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 4, end: 29 },
+                        kind: ErrorKind::BuiltInRedefined(name),
+                        child: None,
+                    }] => {
+                        assert_eq!(name, "BUTTON_PRESSED_EVENT_CODE");
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn undeclared_event_code_test() {
+        let text = "const scene_changed = 0x1000~u16;
+            send scene_changed from 0x0002 to 0x0003;
+            send BUTTON_PRESSED_EVENT_CODE from 0x0002 to 0x0003;";
+        let mut lowerer = Lowerer::new();
+
+        for statement in Parser::parse_program(text).unwrap().statements.iter() {
+            lowerer.lower_statement(statement).unwrap();
+        }
+
+        assert_matches!(
+            lowerer.warnings(),
+            [ParserError::Base {
+                location: Span { start: 51, end: 64 },
+                kind: ErrorKind::UndeclaredEventCode("u16"),
+                child: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn state_argument_test() {
+        let config = lower_text(
+            "let a = 0x00~u8;
+            let b = 0x00~u8;
+            do { match { SetStateToStateFilter(a, b); } }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            format!("{:?}", config.event_processors[0].matcher),
+            format!(
+                "{:?}",
+                Matcher::Single {
+                    extractor: Box::new(NoneExtractor::new()),
+                    filter: Box::new(SetStateToStateFilter::new(0, 1)),
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn state_expected_test() {
+        let text = "do { match { FlipStateFilter(0~u32); } }";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [ParserError::Base {
+                        location: Span { start: 29, end: 34 },
+                        kind: ErrorKind::Expected(Expectation::StateVariable),
+                        child: None,
+                    }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn state_used_as_value_test() {
+        let text = "let a = 0x00~u8;
+            do { match tick; fire { BcmAnimateBrightnessStateProducer(0x0001~u16, 0~u8, a, a); } }
+            const b = a + 0x01~u32;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [
+                        ParserError::Base {
+                            location: Span { start: 105, end: 106 },
+                            kind: ErrorKind::CastFromToNotAllowed("state", "u32"),
+                            ..
+                        },
+                        ParserError::Base {
+                            kind: ErrorKind::MismatchedTypes("state", "u32"),
+                            ..
+                        },
+                    ]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn typed_let_test() {
+        let config = lower_text(
+            "let a: u16 = 0;
+            let b: bool = false;
+            let c = 0x01~u8;
+            do { match { StateEqualToConstFilter(a, 1); } }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            format!("{:?}", config.initial_state),
+            format!(
+                "{:?}",
+                BTreeMap::from([
+                    (0, Value::U16(0)),
+                    (1, Value::Bool(false)),
+                    (2, Value::U8(1))
+                ])
+            )
+        );
+        assert_eq!(
+            format!("{:?}", config.event_processors[0].matcher),
+            format!(
+                "{:?}",
+                Matcher::Single {
+                    extractor: Box::new(NoneExtractor::new()),
+                    filter: Box::new(StateEqualToConstFilter::new(0, Value::U16(1))),
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn typed_let_error_test() {
+        let text = "let a: u9 = 0;\nlet b: bool = 0x01~u8;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [
+                        ParserError::Base {
+                            location: Span { start: 7, end: 9 },
+                            kind: ErrorKind::Expected(Expectation::Type),
+                            child: None,
+                        },
+                        ParserError::Base {
+                            location: Span { start: 29, end: 36 },
+                            kind: ErrorKind::CastFromToNotAllowed("u8", "bool"),
+                            ..
+                        },
+                    ]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn state_type_mismatch_test() {
+        let text = "let active: bool = false;
+            let level = 0x00~u8;
+            do { match { SetStateToConstFilter(active, 0xff~u8); } }
+            do { match { SetStateToStateFilter(active, level); } }
+            do { match { FlipStateFilter(level); } }
+            set level to true on BUTTON_PRESSED_EVENT_CODE from 0x0001~u16;";
+
+        assert_matches!(
+            lower_text(text),
+            Err(errors) => {
+                assert_matches!(
+                    errors.as_slice(),
+                    [
+                        ParserError::Base {
+                            kind: ErrorKind::CastFromToNotAllowed("u8", "bool"),
+                            ..
+                        },
+                        ParserError::Base {
+                            kind: ErrorKind::MismatchedTypes("bool", "u8"),
+                            ..
+                        },
+                        ParserError::Base {
+                            kind: ErrorKind::StateTypeNotAllowed("u8"),
+                            ..
+                        },
+                        ParserError::Base {
+                            kind: ErrorKind::CastFromToNotAllowed("bool", "u8"),
+                            ..
+                        },
+                    ]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn block_three_children_test() {
+        let config = lower_text(
+            "let a = false;
+            let b = false;
+            let c = false;
+            do {
+                match and {
+                    { FlipStateFilter(a); },
+                    { FlipStateFilter(b); },
+                    { FlipStateFilter(c); }
+                }
+            }",
+        )
+        .unwrap();
+
+        assert_matches!(&config.event_processors[0].matcher, Matcher::And(matcher1, matcher2) => {
+            assert_matches!(&**matcher1, Matcher::And(_, _));
+            assert_matches!(&**matcher2, Matcher::Single { .. });
+        });
+    }
+
+    #[test]
+    fn operator_test() {
+        let config = lower_text(
+            "do {
+                match (event 0x0001~u16 && producer 0x0002~u16) || !tick;
+            }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            format!("{:?}", config.event_processors[0].matcher),
+            format!(
+                "{:?}",
+                Matcher::Or(
+                    Box::new(Matcher::And(
+                        Box::new(Lowerer::event_matcher(0x0001)),
+                        Box::new(Lowerer::producer_matcher(0x0002)),
+                    )),
+                    Box::new(Matcher::Not(Box::new(Lowerer::event_matcher(
+                        INTERNAL_SYSTEM_TICK_EVENT_CODE
+                    )))),
+                )
+            ),
+        );
+    }
+
+    #[test]
+    fn never_matches_test() {
+        let text = "do {
+                match event BUTTON_PRESSED_EVENT_CODE;
+                match event BUTTON_RELEASED_EVENT_CODE;
+            }
+            send BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 to 0x0003~u16 if match !tick;
+            send BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 to 0x0003~u16 if match producer 0x0001~u16;";
+        let mut lowerer = Lowerer::new();
+
+        for statement in Parser::parse_program(text).unwrap().statements.iter() {
+            lowerer.lower_statement(statement).unwrap();
+        }
+
+        assert_matches!(
+            lowerer.warnings(),
+            [
+                ParserError::Base {
+                    location: Span { start: 0, end: 129 },
+                    kind: ErrorKind::NeverMatches,
+                    child: None,
+                },
+                ParserError::Base {
+                    location: Span {
+                        start: 231,
+                        end: 321
+                    },
+                    kind: ErrorKind::NeverMatches,
+                    child: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn always_matches_test() {
+        let text = "do {
+                match !event BUTTON_PRESSED_EVENT_CODE || !event BUTTON_RELEASED_EVENT_CODE;
+                fire { PacketProducer(0x0001~u16); } if match !tick;
+            }
+            do {
+                match event BUTTON_PRESSED_EVENT_CODE;
+                fire { PacketProducer(0x0001~u16); } if match event BUTTON_PRESSED_EVENT_CODE || tick;
+            }";
+        let mut lowerer = Lowerer::new();
+
+        for statement in Parser::parse_program(text).unwrap().statements.iter() {
+            lowerer.lower_statement(statement).unwrap();
+        }
+
+        assert_matches!(
+            lowerer.warnings(),
+            [
+                ParserError::Base {
+                    location: Span { start: 0, end: 180 },
+                    kind: ErrorKind::AlwaysMatches,
+                    child: None,
+                },
+                ParserError::Base {
+                    location: Span {
+                        start: 309,
+                        end: 355
+                    },
+                    kind: ErrorKind::AlwaysMatches,
+                    child: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,682 @@
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+use ross_config::serializer::ConfigSerializer;
+
+use ross_dsl::coverage::Coverage;
+use ross_dsl::diagnostic::Diagnostic;
+use ross_dsl::formatter::format_named;
+use ross_dsl::lint::{Level, LintRegistry};
+use ross_dsl::literal::literal;
+use ross_dsl::location::Location;
+use ross_dsl::optimizer::optimize;
+use ross_dsl::resolver::{FileResolver, FileSystemResolver};
+use ross_dsl::testing::run_tests;
+use ross_dsl::trace::{self, parse_trace};
+use ross_dsl::{ParseOptions, Parsed, Parser};
+
+const USAGE: &str = "usage:
+    ross-dsl check [<lint flags>] <file>
+    ross-dsl build [<lint flags>] <file> [-o <output>]
+    ross-dsl dump [<lint flags>] [--pretty] [--unoptimized] <file>
+    ross-dsl fmt [--check] <file>...
+    ross-dsl test [<lint flags>] [<coverage flags>] <file>
+    ross-dsl replay [<lint flags>] [<coverage flags>] [--address <address>] <file> <trace>
+
+lint flags:
+    -A <lint>    allow the lint
+    -W <lint>    warn about the lint
+    -D <lint>    deny the lint
+
+coverage flags:
+    --coverage        print how often each statement matched
+    --lcov <output>   write the coverage to output in the lcov format
+
+dump flags:
+    --pretty        pretty-print the config
+    --unoptimized   print the config as written instead of as build writes it
+
+replay flags:
+    --address <address>   replay as the device at address, 0x0000 by default";
+
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+fn main() {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+
+    let exit_code = match arguments.split_first() {
+        Some((command, arguments)) => match command.as_str() {
+            "check" => check(arguments),
+            "build" => build(arguments),
+            "dump" => dump(arguments),
+            "fmt" => fmt(arguments),
+            "test" => test(arguments),
+            "replay" => replay(arguments),
+            _ => usage(),
+        },
+        None => usage(),
+    };
+
+    process::exit(exit_code);
+}
+
+fn usage() -> i32 {
+    eprintln!("{}", USAGE);
+    EXIT_USAGE
+}
+
+/// Reports every invalid statement of the file.
+fn check(arguments: &[String]) -> i32 {
+    let (arguments, lints) = match lint_flags(arguments) {
+        Ok(flags) => flags,
+        Err(exit_code) => return exit_code,
+    };
+
+    let file = match arguments.as_slice() {
+        [file] => file,
+        _ => return usage(),
+    };
+
+    match parse(file, &lints) {
+        Some(_) => 0,
+        None => EXIT_FAILURE,
+    }
+}
+
+/// Writes the optimized config in the binary form devices are flashed with, to `output` or to
+/// stdout.
+fn build(arguments: &[String]) -> i32 {
+    let (arguments, lints) = match lint_flags(arguments) {
+        Ok(flags) => flags,
+        Err(exit_code) => return exit_code,
+    };
+
+    let (file, output) = match arguments.as_slice() {
+        [file] => (file, None),
+        [file, flag, output] if flag == "-o" => (file, Some(output)),
+        [flag, output, file] if flag == "-o" => (file, Some(output)),
+        _ => return usage(),
+    };
+
+    let config = match parse(file, &lints) {
+        Some(parsed) => optimize(parsed.config),
+        None => return EXIT_FAILURE,
+    };
+
+    let data = match ConfigSerializer::serialize(&config) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("error: {}: failed to serialize config: {:?}", file, err);
+            return EXIT_FAILURE;
+        }
+    };
+
+    let result = match output {
+        Some(output) => fs::write(output, data),
+        None => io::stdout().write_all(&data),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Prints the `Debug` form of the optimized config that `build` writes, pretty-printed with
+/// `--pretty`. With `--unoptimized`, prints the config as it is written instead.
+fn dump(arguments: &[String]) -> i32 {
+    let (arguments, lints) = match lint_flags(arguments) {
+        Ok(flags) => flags,
+        Err(exit_code) => return exit_code,
+    };
+
+    let DumpArguments {
+        file,
+        pretty,
+        optimized,
+    } = match dump_arguments(&arguments) {
+        Ok(arguments) => arguments,
+        Err(exit_code) => return exit_code,
+    };
+
+    let config = match parse(file, &lints) {
+        Some(parsed) if optimized => optimize(parsed.config),
+        Some(parsed) => parsed.config,
+        None => return EXIT_FAILURE,
+    };
+
+    if pretty {
+        println!("{:#?}", config);
+    } else {
+        println!("{:?}", config);
+    }
+
+    0
+}
+
+/// Formats every file in place. With `--check`, lists the files that are not formatted instead
+/// and fails if there are any.
+fn fmt(arguments: &[String]) -> i32 {
+    let check = arguments.iter().any(|argument| argument == "--check");
+    let files: Vec<&String> = arguments
+        .iter()
+        .filter(|argument| *argument != "--check")
+        .collect();
+
+    if files.is_empty() {
+        return usage();
+    }
+
+    let mut exit_code = 0;
+
+    for file in files {
+        let text = match read(file) {
+            Some(text) => text,
+            None => {
+                exit_code = EXIT_FAILURE;
+                continue;
+            }
+        };
+
+        match format_named(file, &text) {
+            Ok(formatted) if formatted == text => {}
+            Ok(_) if check => {
+                println!("{} is not formatted", file);
+                exit_code = EXIT_FAILURE;
+            }
+            Ok(formatted) => {
+                if let Err(err) = fs::write(file, formatted) {
+                    eprintln!("error: {}: {}", file, err);
+                    exit_code = EXIT_FAILURE;
+                }
+            }
+            Err(err) => {
+                eprintln!("{}", err.render(&text));
+                exit_code = EXIT_FAILURE;
+            }
+        }
+    }
+
+    exit_code
+}
+
+/// Runs the `test` statements of the file, reporting the `expect` statements that failed. Fails
+/// if any test does.
+fn test(arguments: &[String]) -> i32 {
+    let (arguments, lints) = match lint_flags(arguments) {
+        Ok(flags) => flags,
+        Err(exit_code) => return exit_code,
+    };
+    let (arguments, coverage_output) = match coverage_flags(&arguments) {
+        Ok(flags) => flags,
+        Err(exit_code) => return exit_code,
+    };
+
+    let file = match arguments.as_slice() {
+        [file] => file,
+        _ => return usage(),
+    };
+
+    let (config, source_map, tests) = match parse(file, &lints) {
+        Some(Parsed {
+            config,
+            source_map,
+            tests,
+            ..
+        }) => (
+            config,
+            source_map.unwrap_or_default(),
+            tests.unwrap_or_default(),
+        ),
+        None => return EXIT_FAILURE,
+    };
+
+    let source = FileSystemResolver::new().read(file).unwrap_or_default();
+    let results = run_tests(&config, &tests);
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    let mut coverage = Coverage::new(&source_map);
+
+    for result in results {
+        coverage.record_matches(&result.matches);
+
+        if result.passed() {
+            println!("test {} ... ok", result.name);
+            continue;
+        }
+
+        println!("test {} ... FAILED", result.name);
+
+        for failure in result.failures {
+            eprintln!("{}", failure.render(Some(file), &source));
+        }
+    }
+
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        if failed == 0 { "ok" } else { "FAILED" },
+        tests.len() - failed,
+        failed
+    );
+
+    if !report_coverage(&coverage, &coverage_output, file, &source) || failed != 0 {
+        EXIT_FAILURE
+    } else {
+        0
+    }
+}
+
+/// Replays the events of the trace through the config of the file, as if it ran on the device at
+/// `--address`, which is 0x0000 by default. Prints the statements that each event matched and
+/// the packets they would have sent.
+fn replay(arguments: &[String]) -> i32 {
+    let (arguments, lints) = match lint_flags(arguments) {
+        Ok(flags) => flags,
+        Err(exit_code) => return exit_code,
+    };
+    let (arguments, coverage_output) = match coverage_flags(&arguments) {
+        Ok(flags) => flags,
+        Err(exit_code) => return exit_code,
+    };
+
+    let ReplayArguments {
+        device_address,
+        file,
+        trace_file,
+    } = match replay_arguments(&arguments) {
+        Ok(arguments) => arguments,
+        Err(exit_code) => return exit_code,
+    };
+
+    let (config, source_map) = match parse(file, &lints) {
+        Some(Parsed {
+            config, source_map, ..
+        }) => (config, source_map.unwrap_or_default()),
+        None => return EXIT_FAILURE,
+    };
+
+    let trace_text = match read(trace_file) {
+        Some(text) => text,
+        None => return EXIT_FAILURE,
+    };
+
+    let trace = match parse_trace(&trace_text) {
+        Ok(trace) => trace,
+        Err(err) => {
+            let err = err.map_location(&mut |location: Location| Location {
+                file: Some(trace_file.to_string()),
+                ..location
+            });
+
+            eprintln!("{}", Diagnostic::from(err).render(&trace_text));
+            return EXIT_FAILURE;
+        }
+    };
+
+    let replayed = match trace::replay(&config, &source_map, device_address, &trace) {
+        Ok(replayed) => replayed,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return EXIT_FAILURE;
+        }
+    };
+
+    let source = FileSystemResolver::new().read(file).unwrap_or_default();
+    let mut coverage = Coverage::new(&source_map);
+
+    for entry in replayed {
+        println!(
+            "{} event 0x{:04x} from 0x{:04x}",
+            entry.timestamp, entry.event.event_code, entry.event.producer_address
+        );
+
+        for matched in entry.matches {
+            coverage.record(matched.event_processor, &matched.creators);
+
+            let location = matched.span.location(Some(file), &source);
+
+            println!("    matched {}:{}:{}", file, location.line, location.column);
+
+            for packet in matched.packets {
+                println!(
+                    "        sends to 0x{:04x}: {:?}",
+                    packet.receiver_address, packet.event
+                );
+            }
+        }
+    }
+
+    if report_coverage(&coverage, &coverage_output, file, &source) {
+        0
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+/// The arguments of `dump` that are not lint flags.
+#[derive(Debug, PartialEq)]
+struct DumpArguments<'a> {
+    file: &'a str,
+    pretty: bool,
+    optimized: bool,
+}
+
+/// Reads the `--pretty` and `--unoptimized` flags and the file argument of `dump`.
+fn dump_arguments(arguments: &[String]) -> Result<DumpArguments<'_>, i32> {
+    let mut pretty = false;
+    let mut optimized = true;
+    let mut remaining = vec![];
+
+    for argument in arguments {
+        match argument.as_str() {
+            "--pretty" => pretty = true,
+            "--unoptimized" => optimized = false,
+            _ => remaining.push(argument.as_str()),
+        }
+    }
+
+    match remaining.as_slice() {
+        [file] => Ok(DumpArguments {
+            file,
+            pretty,
+            optimized,
+        }),
+        _ => Err(usage()),
+    }
+}
+
+/// The arguments of `replay` that are not lint or coverage flags.
+#[derive(Debug, PartialEq)]
+struct ReplayArguments<'a> {
+    device_address: u16,
+    file: &'a str,
+    trace_file: &'a str,
+}
+
+/// Reads the `--address <address>` flag, which defaults to 0x0000, and the file and trace
+/// arguments of `replay`.
+fn replay_arguments(arguments: &[String]) -> Result<ReplayArguments<'_>, i32> {
+    let mut device_address = 0x0000;
+    let mut remaining = vec![];
+    let mut arguments = arguments.iter();
+
+    while let Some(argument) = arguments.next() {
+        if argument != "--address" {
+            remaining.push(argument.as_str());
+            continue;
+        }
+
+        device_address = match arguments.next() {
+            Some(address) => match parse_address(address) {
+                Some(address) => address,
+                None => {
+                    eprintln!("error: invalid address {}", address);
+                    return Err(EXIT_USAGE);
+                }
+            },
+            None => return Err(usage()),
+        };
+    }
+
+    match remaining.as_slice() {
+        [file, trace_file] => Ok(ReplayArguments {
+            device_address,
+            file,
+            trace_file,
+        }),
+        _ => Err(usage()),
+    }
+}
+
+/// Takes the `-A`, `-W` and `-D` flags out of `arguments`, returning the other arguments and
+/// the lints with the levels the flags set.
+fn lint_flags(arguments: &[String]) -> Result<(Vec<String>, LintRegistry), i32> {
+    let mut lints = LintRegistry::new();
+    let mut remaining = vec![];
+    let mut arguments = arguments.iter();
+
+    while let Some(argument) = arguments.next() {
+        let level = match argument.as_str() {
+            "-A" => Level::Allow,
+            "-W" => Level::Warn,
+            "-D" => Level::Deny,
+            _ => {
+                remaining.push(argument.clone());
+                continue;
+            }
+        };
+
+        match arguments.next() {
+            Some(name) if lints.set_level(name, level) => {}
+            Some(name) => {
+                eprintln!("error: unknown lint {}", name);
+                return Err(EXIT_USAGE);
+            }
+            None => return Err(usage()),
+        }
+    }
+
+    Ok((remaining, lints))
+}
+
+/// How the coverage flags ask for the coverage to be reported.
+#[derive(Default)]
+struct CoverageOutput {
+    text: bool,
+    lcov: Option<String>,
+}
+
+/// Takes the `--coverage` and `--lcov <output>` flags out of `arguments`, returning the other
+/// arguments and how to report the coverage.
+fn coverage_flags(arguments: &[String]) -> Result<(Vec<String>, CoverageOutput), i32> {
+    let mut output = CoverageOutput::default();
+    let mut remaining = vec![];
+    let mut arguments = arguments.iter();
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--coverage" => output.text = true,
+            "--lcov" => match arguments.next() {
+                Some(lcov) => output.lcov = Some(lcov.clone()),
+                None => return Err(usage()),
+            },
+            _ => remaining.push(argument.clone()),
+        }
+    }
+
+    Ok((remaining, output))
+}
+
+/// Prints the coverage and writes it in the lcov format, as `output` asks. Returns whether
+/// writing it succeeded.
+fn report_coverage(coverage: &Coverage, output: &CoverageOutput, file: &str, source: &str) -> bool {
+    if output.text {
+        print!("\n{}", coverage.text_report(file, source));
+    }
+
+    match &output.lcov {
+        Some(lcov) => match fs::write(lcov, coverage.lcov_report(file, source)) {
+            Ok(()) => true,
+            Err(err) => {
+                eprintln!("error: {}: {}", lcov, err);
+                false
+            }
+        },
+        None => true,
+    }
+}
+
+/// Parses the file and the files it imports, along with where the event processors come from
+/// and the tests, printing every diagnostic to stderr. Returns nothing if they are invalid.
+fn parse(file: &str, lints: &LintRegistry) -> Option<Parsed> {
+    let resolver = FileSystemResolver::new();
+    let options = ParseOptions::file(file, &resolver)
+        .recovering()
+        .with_lints(lints)
+        .with_source_map()
+        .with_tests();
+
+    match Parser::parse_with(options) {
+        Ok(parsed) => {
+            print_diagnostics(&resolver, &parsed.diagnostics);
+            Some(parsed)
+        }
+        Err(failure) => {
+            print_diagnostics(&resolver, &failure.into_diagnostics());
+            None
+        }
+    }
+}
+
+fn print_diagnostics(resolver: &FileSystemResolver, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics.iter() {
+        let source = diagnostic
+            .location
+            .file
+            .as_ref()
+            .and_then(|file| resolver.read(file).ok())
+            .unwrap_or_default();
+
+        eprintln!("{}", diagnostic.render(&source));
+    }
+}
+
+/// Parses a `u16` literal, with or without the `~u16` suffix.
+fn parse_address(text: &str) -> Option<u16> {
+    match literal(text) {
+        Ok(("", address)) => u16::try_from(address).ok(),
+        _ => None,
+    }
+}
+
+fn read(file: &str) -> Option<String> {
+    match fs::read_to_string(file) {
+        Ok(text) => Some(text),
+        Err(err) => {
+            eprintln!("error: {}: {}", file, err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(arguments: &[&str]) -> Vec<String> {
+        arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn lint_flags_test() {
+        let (remaining, lints) = lint_flags(&strings(&[
+            "-A",
+            "unused_const",
+            "a.ross",
+            "-D",
+            "never_matches",
+        ]))
+        .unwrap();
+
+        assert_eq!(remaining, strings(&["a.ross"]));
+        assert_eq!(lints.level("unused_const"), Some(Level::Allow));
+        assert_eq!(lints.level("never_matches"), Some(Level::Deny));
+    }
+
+    #[test]
+    fn lint_flags_error_test() {
+        assert_eq!(
+            lint_flags(&strings(&["-W", "unknown", "a.ross"])).err(),
+            Some(EXIT_USAGE)
+        );
+        assert_eq!(
+            lint_flags(&strings(&["a.ross", "-W"])).err(),
+            Some(EXIT_USAGE)
+        );
+    }
+
+    #[test]
+    fn coverage_flags_test() {
+        let (remaining, output) =
+            coverage_flags(&strings(&["--coverage", "a.ross", "--lcov", "lcov.info"])).unwrap();
+
+        assert_eq!(remaining, strings(&["a.ross"]));
+        assert!(output.text);
+        assert_eq!(output.lcov, Some("lcov.info".to_string()));
+        assert_eq!(
+            coverage_flags(&strings(&["a.ross", "--lcov"])).err(),
+            Some(EXIT_USAGE)
+        );
+    }
+
+    #[test]
+    fn dump_arguments_test() {
+        assert_eq!(
+            dump_arguments(&strings(&["a.ross"])),
+            Ok(DumpArguments {
+                file: "a.ross",
+                pretty: false,
+                optimized: true,
+            })
+        );
+        assert_eq!(
+            dump_arguments(&strings(&["--unoptimized", "a.ross", "--pretty"])),
+            Ok(DumpArguments {
+                file: "a.ross",
+                pretty: true,
+                optimized: false,
+            })
+        );
+        assert_eq!(dump_arguments(&strings(&["--pretty"])), Err(EXIT_USAGE));
+    }
+
+    #[test]
+    fn replay_arguments_test() {
+        assert_eq!(
+            replay_arguments(&strings(&["a.ross", "a.trace"])),
+            Ok(ReplayArguments {
+                device_address: 0x0000,
+                file: "a.ross",
+                trace_file: "a.trace",
+            })
+        );
+        assert_eq!(
+            replay_arguments(&strings(&["a.ross", "--address", "0x0005~u16", "a.trace"])),
+            Ok(ReplayArguments {
+                device_address: 0x0005,
+                file: "a.ross",
+                trace_file: "a.trace",
+            })
+        );
+    }
+
+    #[test]
+    fn replay_arguments_error_test() {
+        assert_eq!(
+            replay_arguments(&strings(&["--address", "0x10000", "a.ross", "a.trace"])),
+            Err(EXIT_USAGE)
+        );
+        assert_eq!(
+            replay_arguments(&strings(&["a.ross", "a.trace", "--address"])),
+            Err(EXIT_USAGE)
+        );
+        assert_eq!(replay_arguments(&strings(&["a.ross"])), Err(EXIT_USAGE));
+    }
+
+    #[test]
+    fn parse_address_test() {
+        assert_eq!(parse_address("0x0005"), Some(0x0005));
+        assert_eq!(parse_address("5~u16"), Some(0x0005));
+        assert_eq!(parse_address("true"), None);
+        assert_eq!(parse_address("0x0005 a"), None);
+    }
+}
@@ -0,0 +1,407 @@
+use std::fmt::Debug;
+
+use ross_config::config::Config;
+use ross_config::matcher::Matcher;
+
+use crate::decode::{decode_item, is_pure, DecodedItem};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Chain {
+    And,
+    Or,
+}
+
+/// A matcher with its items decoded, which compares by what it checks.
+#[derive(Debug, PartialEq)]
+enum MatcherKey {
+    Single(DecodedItem, DecodedItem),
+    Not(Box<MatcherKey>),
+    And(Box<MatcherKey>, Box<MatcherKey>),
+    Or(Box<MatcherKey>, Box<MatcherKey>),
+}
+
+/// Optimizes the matchers of every event processor and creator in `config`.
+pub fn optimize(config: Config) -> Config {
+    let event_processors = config
+        .event_processors
+        .into_iter()
+        .map(|mut event_processor| {
+            event_processor.matcher = optimize_matcher(event_processor.matcher);
+
+            for creator in event_processor.creators.iter_mut() {
+                creator.matcher = creator.matcher.take().map(optimize_matcher);
+            }
+
+            event_processor
+        })
+        .collect();
+
+    Config {
+        event_processors,
+        ..config
+    }
+}
+
+/// Returns a matcher that matches the same events and changes the state in the same way, but
+/// that is usually smaller and cheaper to evaluate:
+/// * `Not(Not(matcher))` becomes `matcher`.
+/// * Chains of `And` or `Or` are flattened and rebuilt as balanced trees.
+/// * Checks that appear twice in a chain are kept once.
+/// * Checks of the event code and producer address come before the other checks in a chain.
+///
+/// Checks are never removed or moved across a filter that may change the state, as that could
+/// change what the filter sees or whether it is evaluated at all.
+pub fn optimize_matcher(matcher: Matcher) -> Matcher {
+    match matcher {
+        Matcher::Not(matcher) => match optimize_matcher(*matcher) {
+            Matcher::Not(matcher) => *matcher,
+            matcher => Matcher::Not(Box::new(matcher)),
+        },
+        Matcher::And(_, _) => optimize_chain(matcher, Chain::And),
+        Matcher::Or(_, _) => optimize_chain(matcher, Chain::Or),
+        matcher => matcher,
+    }
+}
+
+fn optimize_chain(matcher: Matcher, chain: Chain) -> Matcher {
+    let mut operands = vec![];
+    flatten(matcher, chain, &mut operands);
+
+    let mut optimized = vec![];
+    let mut pure_run: Vec<Matcher> = vec![];
+    let mut pure_keys = vec![];
+
+    for operand in operands {
+        if is_pure(&operand) {
+            match matcher_key(&operand) {
+                Some(key) if pure_keys.contains(&key) => {}
+                key => {
+                    pure_keys.extend(key);
+                    pure_run.push(operand);
+                }
+            }
+        } else {
+            optimized.append(&mut sort_by_cost(pure_run));
+            pure_run = vec![];
+            pure_keys.clear();
+            optimized.push(operand);
+        }
+    }
+
+    optimized.append(&mut sort_by_cost(pure_run));
+
+    balance(optimized, chain)
+}
+
+/// Collects the operands of nested `chain`s in evaluation order, optimizing each of them.
+fn flatten(matcher: Matcher, chain: Chain, operands: &mut Vec<Matcher>) {
+    match (matcher, chain) {
+        (Matcher::And(matcher1, matcher2), Chain::And)
+        | (Matcher::Or(matcher1, matcher2), Chain::Or) => {
+            flatten(*matcher1, chain, operands);
+            flatten(*matcher2, chain, operands);
+        }
+        (matcher, _) => match optimize_matcher(matcher) {
+            // Removing a double `Not` can uncover another link of the chain
+            matcher @ Matcher::And(_, _) if chain == Chain::And => {
+                flatten(matcher, chain, operands)
+            }
+            matcher @ Matcher::Or(_, _) if chain == Chain::Or => flatten(matcher, chain, operands),
+            matcher => operands.push(matcher),
+        },
+    }
+}
+
+/// Combines `operands` into a tree of `chain` whose depth grows with the logarithm of their
+/// count. Both `And` and `Or` are associative, so this keeps the evaluation order.
+fn balance(mut operands: Vec<Matcher>, chain: Chain) -> Matcher {
+    if operands.len() == 1 {
+        return operands.remove(0);
+    }
+
+    let right = operands.split_off(operands.len() / 2);
+    let left = balance(operands, chain);
+    let right = balance(right, chain);
+
+    match chain {
+        Chain::And => Matcher::And(Box::new(left), Box::new(right)),
+        Chain::Or => Matcher::Or(Box::new(left), Box::new(right)),
+    }
+}
+
+/// Sorts pure operands so that the cheapest are evaluated first. The sort is stable, so
+/// operands of the same cost keep their order.
+fn sort_by_cost(mut operands: Vec<Matcher>) -> Vec<Matcher> {
+    operands.sort_by_key(cost);
+    operands
+}
+
+/// Estimates how expensive `matcher` is to evaluate. Comparing a field of the event is cheaper
+/// than reading the state, which is cheaper than evaluating a cron expression.
+fn cost(matcher: &Matcher) -> u32 {
+    match matcher {
+        Matcher::Single { extractor, filter } => {
+            match (
+                item_name(extractor).as_deref(),
+                item_name(filter).as_deref(),
+            ) {
+                (_, Some("TimeMatchesCronExpressionFilter")) => 4,
+                (Some("EventCodeExtractor"), Some("ValueEqualToConstFilter"))
+                | (Some("EventProducerAddressExtractor"), Some("ValueEqualToConstFilter")) => 1,
+                (_, Some("ValueEqualToConstFilter")) => 2,
+                _ => 3,
+            }
+        }
+        Matcher::Not(matcher) => cost(matcher),
+        Matcher::And(matcher1, matcher2) | Matcher::Or(matcher1, matcher2) => {
+            cost(matcher1) + cost(matcher2)
+        }
+    }
+}
+
+/// Returns the name of an extractor or filter, or nothing if it does not exist in the DSL.
+fn item_name(item: &dyn Debug) -> Option<String> {
+    decode_item(item).ok().map(|item| item.name)
+}
+
+/// Returns what `matcher` checks, made of its decoded items, so that matchers that check the
+/// same thing have equal keys. Returns nothing if an item cannot be decoded, so that such a
+/// matcher is never taken for a duplicate.
+fn matcher_key(matcher: &Matcher) -> Option<MatcherKey> {
+    let key = match matcher {
+        Matcher::Single { extractor, filter } => {
+            MatcherKey::Single(decode_item(extractor).ok()?, decode_item(filter).ok()?)
+        }
+        Matcher::Not(matcher) => MatcherKey::Not(Box::new(matcher_key(matcher)?)),
+        Matcher::And(matcher1, matcher2) => MatcherKey::And(
+            Box::new(matcher_key(matcher1)?),
+            Box::new(matcher_key(matcher2)?),
+        ),
+        Matcher::Or(matcher1, matcher2) => MatcherKey::Or(
+            Box::new(matcher_key(matcher1)?),
+            Box::new(matcher_key(matcher2)?),
+        ),
+    };
+
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::hash_map::DefaultHasher;
+    use std::convert::TryFrom;
+    use std::hash::{Hash, Hasher};
+
+    use cool_asserts::assert_matches;
+    use ross_config::cron::CronExpression;
+    use ross_config::extractor::{EventCodeExtractor, NoneExtractor};
+    use ross_config::filter::{
+        FlipStateFilter, StateEqualToConstFilter, TimeMatchesCronExpressionFilter,
+        ValueEqualToConstFilter,
+    };
+    use ross_config::Value;
+
+    use crate::literal::Literal;
+    use crate::parser::Parser;
+
+    fn event(event_code: u16) -> Matcher {
+        Matcher::Single {
+            extractor: Box::new(EventCodeExtractor::new()),
+            filter: Box::new(ValueEqualToConstFilter::new(Value::U16(event_code))),
+        }
+    }
+
+    fn state(value: bool) -> Matcher {
+        Matcher::Single {
+            extractor: Box::new(NoneExtractor::new()),
+            filter: Box::new(StateEqualToConstFilter::new(0, Value::Bool(value))),
+        }
+    }
+
+    fn flip() -> Matcher {
+        Matcher::Single {
+            extractor: Box::new(NoneExtractor::new()),
+            filter: Box::new(FlipStateFilter::new(0)),
+        }
+    }
+
+    fn and(matcher1: Matcher, matcher2: Matcher) -> Matcher {
+        Matcher::And(Box::new(matcher1), Box::new(matcher2))
+    }
+
+    fn or(matcher1: Matcher, matcher2: Matcher) -> Matcher {
+        Matcher::Or(Box::new(matcher1), Box::new(matcher2))
+    }
+
+    fn not(matcher: Matcher) -> Matcher {
+        Matcher::Not(Box::new(matcher))
+    }
+
+    fn assert_optimized(matcher: Matcher, expected: Matcher) {
+        assert_eq!(
+            format!("{:?}", optimize_matcher(matcher)),
+            format!("{:?}", expected)
+        );
+    }
+
+    /// Evaluates `matcher` the way a device would, short-circuiting `And` and `Or`. Every
+    /// check gets an arbitrary result that depends on `seed`, on the check and on how many
+    /// filters that may change the state ran before it. Those filters are recorded in `trace`.
+    fn evaluate(matcher: &Matcher, seed: u64, trace: &mut Vec<String>) -> bool {
+        match matcher {
+            Matcher::Single { .. } => {
+                let key = format!("{:?}", matcher);
+                let mut hasher = DefaultHasher::new();
+                (seed, &key, trace.len()).hash(&mut hasher);
+
+                if !is_pure(matcher) {
+                    trace.push(key);
+                }
+
+                hasher.finish() & 1 == 0
+            }
+            Matcher::Not(matcher) => !evaluate(matcher, seed, trace),
+            Matcher::And(matcher1, matcher2) => {
+                evaluate(matcher1, seed, trace) && evaluate(matcher2, seed, trace)
+            }
+            Matcher::Or(matcher1, matcher2) => {
+                evaluate(matcher1, seed, trace) || evaluate(matcher2, seed, trace)
+            }
+        }
+    }
+
+    fn assert_equivalent(text: &str) {
+        let original = Parser::parse(text).unwrap();
+        let optimized = optimize(Parser::parse(text).unwrap());
+
+        for (original, optimized) in original
+            .event_processors
+            .iter()
+            .zip(optimized.event_processors.iter())
+        {
+            for seed in 0..256 {
+                let mut original_trace = vec![];
+                let mut optimized_trace = vec![];
+
+                assert_eq!(
+                    evaluate(&original.matcher, seed, &mut original_trace),
+                    evaluate(&optimized.matcher, seed, &mut optimized_trace),
+                    "{:?} and {:?} differ",
+                    original.matcher,
+                    optimized.matcher
+                );
+                assert_eq!(original_trace, optimized_trace);
+            }
+        }
+    }
+
+    #[test]
+    fn double_not_test() {
+        assert_optimized(not(not(event(0x0001))), event(0x0001));
+        assert_optimized(not(not(not(event(0x0001)))), not(event(0x0001)));
+    }
+
+    #[test]
+    fn balance_test() {
+        assert_optimized(
+            and(
+                and(and(event(0x0001), event(0x0002)), event(0x0003)),
+                event(0x0004),
+            ),
+            and(
+                and(event(0x0001), event(0x0002)),
+                and(event(0x0003), event(0x0004)),
+            ),
+        );
+    }
+
+    #[test]
+    fn flatten_through_double_not_test() {
+        assert_optimized(
+            or(event(0x0001), not(not(or(event(0x0002), event(0x0003))))),
+            or(event(0x0001), or(event(0x0002), event(0x0003))),
+        );
+    }
+
+    #[test]
+    fn duplicate_test() {
+        assert_optimized(
+            and(event(0x0001), and(state(true), event(0x0001))),
+            and(event(0x0001), state(true)),
+        );
+    }
+
+    #[test]
+    fn matcher_key_test() {
+        assert_eq!(
+            matcher_key(&and(event(0x0001), not(state(true)))),
+            matcher_key(&and(event(0x0001), not(state(true))))
+        );
+        assert_ne!(matcher_key(&event(0x0001)), matcher_key(&event(0x0002)));
+        assert_ne!(matcher_key(&state(true)), matcher_key(&state(false)));
+        assert_ne!(matcher_key(&state(true)), matcher_key(&not(state(true))));
+    }
+
+    #[test]
+    fn cheap_first_test() {
+        let cron = Matcher::Single {
+            extractor: Box::new(NoneExtractor::new()),
+            filter: Box::new(TimeMatchesCronExpressionFilter::new(
+                CronExpression::try_from(Literal::String("0 0 * * * *".to_string())).unwrap(),
+            )),
+        };
+
+        assert_matches!(
+            optimize_matcher(or(cron, or(state(true), event(0x0001)))),
+            Matcher::Or(matcher1, matcher2) => {
+                assert_eq!(format!("{:?}", matcher1), format!("{:?}", event(0x0001)));
+                assert_matches!(*matcher2, Matcher::Or(matcher1, _) => {
+                    assert_eq!(format!("{:?}", matcher1), format!("{:?}", state(true)));
+                });
+            }
+        );
+    }
+
+    #[test]
+    fn state_changes_keep_order_test() {
+        let matcher = and(state(true), and(flip(), and(event(0x0001), state(true))));
+
+        assert_optimized(
+            matcher,
+            and(and(state(true), flip()), and(event(0x0001), state(true))),
+        );
+    }
+
+    #[test]
+    fn equivalence_test() {
+        assert_equivalent(
+            "let a = false;
+            send BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 to 0x0003~u16 if match not {
+                StateEqualToConstFilter(a, true);
+            }
+            set a to true on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 if match {
+                StateEqualToConstFilter(a, false);
+            }
+            do {
+                match event BUTTON_RELEASED_EVENT_CODE;
+                match { FlipStateFilter(a); }
+                match !!(tick || producer 0x0001~u16) && (event 0x0001~u16 || tick);
+                match or {
+                    { StateEqualToConstFilter(a, true); },
+                    { FlipStateFilter(a); },
+                    { StateEqualToConstFilter(a, true); },
+                    and { { FlipStateFilter(a); }, { StateEqualToConstFilter(a, false); } },
+                    { StateEqualToConstFilter(a, true); }
+                }
+                match and {
+                    { StateEqualToConstFilter(a, true); },
+                    { EventCodeExtractor(); ValueEqualToConstFilter(0x0001~u16); },
+                    { StateEqualToConstFilter(a, true); },
+                    { TimeMatchesCronExpressionFilter(\"0 30 8 * * 1,2,3\"); }
+                }
+            }",
+        );
+    }
+}
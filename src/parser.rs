@@ -1,191 +1,752 @@
 use nom::character::complete::{multispace0, multispace1};
+use nom::combinator::map;
 use nom::error::ErrorKind as NomErrorKind;
 use nom::multi::separated_list0;
 use nom::sequence::{delimited, preceded, terminated};
 use nom::Err as NomErr;
 use nom::InputTakeAtPosition;
 use nom::{AsChar, IResult};
-use std::collections::BTreeMap;
-use std::convert::TryInto;
+use std::collections::{BTreeMap, BTreeSet};
 
 use ross_config::config::Config;
-use ross_protocol::event::event_code::*;
 
+use crate::ast::{Comment, Expression, ImportStatement, Name, Program, Span, Statement};
+use crate::diagnostic::{Diagnostic, Severity};
 use crate::error::{ErrorKind, Expectation, ParserError};
+use crate::lint::LintRegistry;
 use crate::literal::{literal, literal_or_constant, Literal};
+use crate::location::Location;
+use crate::lower::{Lowerer, SourceMap};
+use crate::resolver::FileResolver;
+use crate::statement::attribute_statement::attribute_statement;
 use crate::statement::const_statement::const_statement;
+use crate::statement::device_statement::device_statement;
 use crate::statement::do_statement::do_statement;
+use crate::statement::event_statement::event_statement;
+use crate::statement::import_statement::import_statement;
 use crate::statement::let_statement::let_statement;
 use crate::statement::peripheral_statement::peripheral_statement;
 use crate::statement::send_statement::send_statement;
 use crate::statement::set_statement::set_statement;
+use crate::statement::test_statement::test_statement;
+use crate::statement::toggle_statement::toggle_statement;
 use crate::symbol::{close_parenthesis, comma, open_parenthesis};
+use crate::testing::Test;
 
-macro_rules! prepare_constant {
-    ($name:expr, $constants:expr, $constant_type:path) => {
-        $constants.insert(stringify!($name), $constant_type($name));
-    };
+/// The lowered file and the warnings, or its errors and the warnings.
+type ParseResult =
+    Result<(Lowerer, Vec<Diagnostic>), (Vec<ParserError<Location>>, Vec<Diagnostic>)>;
+
+/// What to parse and what to return along with the config. Built with `text` or `file`, then
+/// passed to `Parser::parse_with`.
+#[derive(Clone, Copy)]
+pub struct ParseOptions<'a> {
+    input: Input<'a>,
+    file_name: Option<&'a str>,
+    recover: bool,
+    lints: Option<&'a LintRegistry>,
+    source_map: bool,
+    tests: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Input<'a> {
+    Text(&'a str),
+    File(&'a dyn FileResolver),
+}
+
+impl<'a> ParseOptions<'a> {
+    /// Parses `text`, which cannot import other files.
+    pub fn text(text: &'a str) -> Self {
+        Self::new(Input::Text(text), None)
+    }
+
+    /// Parses the file named `file_name` along with the files it imports, reading all of them
+    /// through `resolver`. Only the `const`, `device`, `event` and `peripheral` statements of
+    /// imported files are used. Locations carry the name of the file they are in.
+    pub fn file(file_name: &'a str, resolver: &'a dyn FileResolver) -> Self {
+        Self::new(Input::File(resolver), Some(file_name))
+    }
+
+    fn new(input: Input<'a>, file_name: Option<&'a str>) -> Self {
+        Self {
+            input,
+            file_name,
+            recover: false,
+            lints: None,
+            source_map: false,
+            tests: false,
+        }
+    }
+
+    /// Makes the locations of `text` carry `file_name`.
+    pub fn with_file_name(mut self, file_name: &'a str) -> Self {
+        self.file_name = Some(file_name);
+        self
+    }
+
+    /// Instead of stopping at the first invalid statement, skips it and keeps going, so that all
+    /// of the invalid statements are reported.
+    pub fn recovering(mut self) -> Self {
+        self.recover = true;
+        self
+    }
+
+    /// Runs `lints`, whose warnings are returned along with the config. Lints that are denied
+    /// make the parse fail. Lints only check the file being parsed, while the warnings of
+    /// lowering come from every file.
+    pub fn with_lints(mut self, lints: &'a LintRegistry) -> Self {
+        self.lints = Some(lints);
+        self
+    }
+
+    /// Also returns where the event processors of the config come from, which is always the
+    /// file being parsed.
+    pub fn with_source_map(mut self) -> Self {
+        self.source_map = true;
+        self
+    }
+
+    /// Also returns the `test` statements, which are left out of the config. Those of imported
+    /// files are ignored.
+    pub fn with_tests(mut self) -> Self {
+        self.tests = true;
+        self
+    }
+}
+
+/// The config and what else `ParseOptions` asked for.
+#[derive(Debug)]
+pub struct Parsed {
+    pub config: Config,
+    pub source_map: Option<SourceMap>,
+    pub tests: Option<Vec<Test>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// The errors, which are empty if only denied lints failed the parse, and what the lints found.
+#[derive(Debug)]
+pub struct ParseFailure {
+    pub errors: Vec<ParserError<Location>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseFailure {
+    /// Returns the errors followed by what the lints found.
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.errors
+            .into_iter()
+            .map(Diagnostic::from)
+            .chain(self.diagnostics)
+            .collect()
+    }
 }
 
 pub struct Parser {}
 
 impl Parser {
-    pub fn parse<'a, 'b>(text: &'a str) -> Result<Config, ParserError<String>> {
-        let mut peripherals = BTreeMap::new();
-        let mut initial_state = BTreeMap::new();
-        let mut state_variables = BTreeMap::new();
-        let mut constants = BTreeMap::new();
-        let mut event_processors = vec![];
+    /// Parses `text` and returns the config, or the first error.
+    pub fn parse(text: &str) -> Result<Config, ParserError<Location>> {
+        Self::parse_with(ParseOptions::text(text))
+            .map(|parsed| parsed.config)
+            .map_err(|mut failure| failure.errors.remove(0))
+    }
 
-        Self::prepare_constants(&mut constants);
+    /// Parses what `options` describe. On failure, the errors are sorted by where they are.
+    pub fn parse_with(options: ParseOptions) -> Result<Parsed, ParseFailure> {
+        let result = match options.input {
+            Input::Text(text) => {
+                Self::parse_source(options.file_name, text, options.recover, options.lints)
+            }
+            Input::File(resolver) => Self::parse_files(
+                options.file_name.unwrap_or_default(),
+                resolver,
+                options.recover,
+                options.lints,
+            ),
+        };
 
-        let commentless_text_string = Self::remove_comments(text.to_string());
-        let mut commentless_text = commentless_text_string.as_str();
+        match result {
+            Ok((mut lowerer, diagnostics)) => {
+                let source_map = options.source_map.then(|| lowerer.source_map().clone());
+                let tests = options.tests.then(|| lowerer.take_tests());
 
-        while commentless_text.len() != 0 {
-            let mut errors = vec![];
+                Ok(Parsed {
+                    config: lowerer.finish(),
+                    source_map,
+                    tests,
+                    diagnostics,
+                })
+            }
+            Err((errors, diagnostics)) => Err(ParseFailure {
+                errors,
+                diagnostics,
+            }),
+        }
+    }
 
-            match preceded(multispace0, peripheral_statement(&constants))(commentless_text) {
-                Ok((input, (index, peripheral))) => {
-                    peripherals.insert(index, peripheral);
-                    commentless_text = input;
-                    continue;
-                }
-                Err(NomErr::Error(err)) => errors.push(err),
-                Err(NomErr::Failure(err)) => return Err(err.into()),
-                _ => {}
+    /// Parses `text` into its syntax tree without resolving any names. Spans in the tree are
+    /// byte offsets into `text`.
+    pub fn parse_program(text: &str) -> Result<Program, ParserError<Location>> {
+        Self::parse_program_source(None, text)
+    }
+
+    /// Same as `parse_program`, but error locations also carry `file_name`.
+    pub fn parse_named_program(
+        file_name: &str,
+        text: &str,
+    ) -> Result<Program, ParserError<Location>> {
+        Self::parse_program_source(Some(file_name), text)
+    }
+
+    /// Returns the `//` comments of `text`, which every other method ignores.
+    pub fn comments(text: &str) -> Vec<Comment> {
+        let mut comments = vec![];
+        let mut line_start = 0;
+        let mut in_string = false;
+
+        for line in text.split_inclusive('\n') {
+            let content = line.trim_end_matches(&['\r', '\n'][..]);
+
+            if let Some(comment_start) = Self::comment_start(content, &mut in_string) {
+                let comment_text = content[comment_start..].trim_end();
+
+                comments.push(Comment {
+                    text: comment_text.to_string(),
+                    span: Span {
+                        start: line_start + comment_start,
+                        end: line_start + comment_start + comment_text.len(),
+                    },
+                    own_line: content[..comment_start].trim().is_empty(),
+                });
             }
 
-            match preceded(multispace0, let_statement)(commentless_text) {
-                Ok((input, (name, value))) => {
-                    let initial_state_index = initial_state.len() as u32;
-                    initial_state.insert(initial_state_index, value.try_into()?);
-                    state_variables.insert(name, initial_state_index);
-                    constants.insert(name, Literal::U32(initial_state_index));
-                    commentless_text = input;
+            line_start += line.len();
+        }
 
-                    continue;
-                }
-                Err(NomErr::Error(err)) => errors.push(err),
-                Err(NomErr::Failure(err)) => return Err(err.into()),
-                _ => {}
+        comments
+    }
+
+    fn parse_program_source(
+        file_name: Option<&str>,
+        text: &str,
+    ) -> Result<Program, ParserError<Location>> {
+        let commentless_text_string = Self::remove_comments(text);
+        let source = commentless_text_string.as_str();
+
+        match Self::parse_statements(source, false) {
+            (program, errors) if errors.is_empty() => Ok(program),
+            (_, mut errors) => Err(errors.remove(0).map_location(&mut |location: &str| {
+                Location::from_slice(file_name, source, location)
+            })),
+        }
+    }
+
+    /// Returns the config and what `lints` found, or the errors and what `lints` found. Denied
+    /// lints make it fail even without errors.
+    fn parse_source(
+        file_name: Option<&str>,
+        text: &str,
+        recover: bool,
+        lints: Option<&LintRegistry>,
+    ) -> ParseResult {
+        let commentless_text_string = Self::remove_comments(text);
+        let source = commentless_text_string.as_str();
+
+        let (program, errors) = Self::parse_statements(source, recover);
+
+        let mut errors = errors
+            .into_iter()
+            .map(|err| {
+                err.map_location(&mut |location: &str| {
+                    Location::from_slice(file_name, source, location)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Statements before the first syntax error still get lowered, so that an earlier
+        // lowering error is reported first
+        let mut lowerer = Lowerer::new();
+        let mut lowering_errors = vec![];
+
+        for statement in program.statements.iter() {
+            if let Err(err) = lowerer.lower_statement(statement) {
+                lowering_errors
+                    .push(err.map_location(&mut |span: Span| span.location(file_name, source)));
             }
+        }
 
-            match preceded(multispace0, const_statement)(commentless_text) {
-                Ok((input, (name, value))) => {
-                    constants.insert(name, value);
-                    commentless_text = input;
+        let diagnostics = match lints {
+            Some(lints) => {
+                lints.diagnostics(file_name, source, &program, lowerer.take_warnings(), false)
+            }
+            None => vec![],
+        };
+
+        if !lowering_errors.is_empty() {
+            errors.append(&mut lowering_errors);
+            errors.sort_by_key(Self::error_offset);
+        }
+
+        Self::finish(errors, diagnostics, lowerer)
+    }
+
+    fn finish(
+        errors: Vec<ParserError<Location>>,
+        diagnostics: Vec<Diagnostic>,
+        lowerer: Lowerer,
+    ) -> ParseResult {
+        let denied = diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error);
 
-                    continue;
+        if errors.is_empty() && !denied {
+            Ok((lowerer, diagnostics))
+        } else {
+            Err((errors, diagnostics))
+        }
+    }
+
+    fn parse_files(
+        file_name: &str,
+        resolver: &dyn FileResolver,
+        recover: bool,
+        lints: Option<&LintRegistry>,
+    ) -> ParseResult {
+        let mut importer = Importer {
+            resolver,
+            lowerer: Lowerer::new(),
+            stack: vec![],
+            imported: BTreeSet::new(),
+            definitions: BTreeMap::new(),
+            recover,
+            lints,
+            diagnostics: vec![],
+        };
+
+        let errors = match resolver.read(file_name) {
+            Ok(text) => importer.lower_file(file_name, &text, false),
+            Err(err) => vec![ParserError::Base {
+                location: Location::new(Some(file_name), "", 0),
+                kind: ErrorKind::External(Box::new(err)),
+                child: None,
+            }],
+        };
+
+        Self::finish(errors, importer.diagnostics, importer.lowerer)
+    }
+
+    fn parse_statements(text: &str, recover: bool) -> (Program, Vec<ParserError<&str>>) {
+        let mut program = Program::default();
+        let mut errors = vec![];
+
+        let mut commentless_text = text;
+
+        while !commentless_text.is_empty() {
+            match Self::parse_statement(text, commentless_text) {
+                Ok((input, statement)) => {
+                    program.statements.extend(statement);
+                    commentless_text = input;
+                }
+                Err(err) if recover => {
+                    errors.push(err);
+                    commentless_text = Self::skip_statement(commentless_text);
+                }
+                Err(err) => {
+                    errors.push(err);
+                    break;
                 }
-                Err(NomErr::Error(err)) => errors.push(err),
-                Err(NomErr::Failure(err)) => return Err(err.into()),
-                _ => {}
             }
+        }
 
-            match preceded(multispace0, send_statement(&constants))(commentless_text) {
-                Ok((input, event_processor)) => {
-                    event_processors.push(event_processor);
-                    commentless_text = input;
+        (program, errors)
+    }
+
+    /// Parses a single statement at the start of `text`, returning the remaining input. There is
+    /// no statement if `text` starts with whitespace only.
+    fn parse_statement<'a>(
+        source: &'a str,
+        text: &'a str,
+    ) -> Result<(&'a str, Option<Statement>), ParserError<&'a str>> {
+        let mut errors = vec![];
+
+        match preceded(
+            multispace0,
+            map(attribute_statement(source), Statement::Attribute),
+        )(text)
+        {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
+
+        match preceded(
+            multispace0,
+            map(import_statement(source), Statement::Import),
+        )(text)
+        {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
 
-                    continue;
+        match preceded(
+            multispace0,
+            map(peripheral_statement(source), Statement::Peripheral),
+        )(text)
+        {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
+
+        match preceded(multispace0, map(let_statement(source), Statement::Let))(text) {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
+
+        match preceded(multispace0, map(const_statement(source), Statement::Const))(text) {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
+
+        match preceded(
+            multispace0,
+            map(device_statement(source), Statement::Device),
+        )(text)
+        {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
+
+        match preceded(multispace0, map(event_statement(source), Statement::Event))(text) {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
+
+        match preceded(multispace0, map(send_statement(source), Statement::Send))(text) {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
+
+        match preceded(multispace0, map(do_statement(source), Statement::Do))(text) {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
+
+        match preceded(multispace0, map(set_statement(source), Statement::Set))(text) {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
+
+        match preceded(
+            multispace0,
+            map(toggle_statement(source), |statement| {
+                Statement::Toggle(Box::new(statement))
+            }),
+        )(text)
+        {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
+
+        match preceded(multispace0, map(test_statement(source), Statement::Test))(text) {
+            Ok((input, statement)) => return Ok((input, Some(statement))),
+            Err(NomErr::Error(err)) => errors.push(err),
+            Err(NomErr::Failure(err)) => return Err(err),
+            _ => {}
+        }
+
+        if let Ok((input, _)) = multispace1::<_, ParserError<&str>>(text) {
+            return Ok((input, None));
+        }
+
+        Err(Self::select_furthest_errors(text, errors))
+    }
+
+    fn error_offset(err: &ParserError<Location>) -> usize {
+        match err {
+            ParserError::Base { location, .. } => location.offset,
+            ParserError::Alt(siblings) => siblings.first().map_or(0, Self::error_offset),
+        }
+    }
+
+    /// Skips to the next top level synchronisation point, which is either a `;`, the `]` of an
+    /// attribute or a balanced `}`.
+    fn skip_statement(text: &str) -> &str {
+        let mut depth = 0;
+        let mut in_string = false;
+
+        for (index, character) in text.char_indices() {
+            match character {
+                '"' => in_string = !in_string,
+                _ if in_string => {}
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+
+                    if depth <= 0 {
+                        return &text[index + 1..];
+                    }
                 }
-                Err(NomErr::Error(err)) => errors.push(err),
-                Err(NomErr::Failure(err)) => return Err(err.into()),
+                ';' | ']' if depth == 0 => return &text[index + 1..],
                 _ => {}
             }
+        }
 
-            match preceded(multispace0, do_statement(&constants))(commentless_text) {
-                Ok((input, event_processor)) => {
-                    event_processors.push(event_processor);
-                    commentless_text = input;
+        &text[text.len()..]
+    }
 
-                    continue;
-                }
-                Err(NomErr::Error(err)) => errors.push(err),
-                Err(NomErr::Failure(err)) => return Err(err.into()),
-                _ => {}
+    /// Picks the errors of the statements that got the furthest into `text`. If none of them got
+    /// past the first token, there is nothing better to report than a generic error.
+    fn select_furthest_errors<'a>(
+        text: &'a str,
+        errors: Vec<ParserError<&'a str>>,
+    ) -> ParserError<&'a str> {
+        let statement_start = text.len() - text.trim_start().len();
+        let furthest_offset = errors
+            .iter()
+            .filter_map(|err| err.furthest_offset(text))
+            .max()
+            .unwrap_or(0);
+
+        if furthest_offset <= statement_start {
+            return ParserError::Base {
+                location: &text[statement_start..],
+                kind: ErrorKind::Expected(Expectation::Something),
+                child: None,
+            };
+        }
+
+        let mut furthest_errors = errors
+            .into_iter()
+            .filter(|err| err.furthest_offset(text) == Some(furthest_offset))
+            .collect::<Vec<_>>();
+
+        if furthest_errors.len() == 1 {
+            furthest_errors.pop().unwrap()
+        } else {
+            ParserError::Alt(furthest_errors)
+        }
+    }
+
+    /// Blanks out comments with spaces, so that offsets, lines and columns in the returned text
+    /// still match the original one.
+    fn remove_comments(text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut in_string = false;
+
+        for line in text.split_inclusive('\n') {
+            let content = line.trim_end_matches(&['\r', '\n'][..]);
+
+            if let Some(comment_start) = Self::comment_start(content, &mut in_string) {
+                result += &content[..comment_start];
+                result += &" ".repeat(content.len() - comment_start);
+                result += &line[content.len()..];
+            } else {
+                result += line;
             }
+        }
 
-            match preceded(multispace0, set_statement(&constants, &state_variables))(
-                commentless_text,
-            ) {
-                Ok((input, event_processor)) => {
-                    event_processors.push(event_processor);
-                    commentless_text = input;
+        result
+    }
 
-                    continue;
+    /// Returns where the comment on `line` starts, if it has one. `//` in a string literal does
+    /// not start a comment. `in_string` tells whether a string is still open from earlier lines
+    /// and is updated for the next one.
+    fn comment_start(line: &str, in_string: &mut bool) -> Option<usize> {
+        let mut characters = line.char_indices().peekable();
+
+        while let Some((index, character)) = characters.next() {
+            match character {
+                '"' => *in_string = !*in_string,
+                '/' if !*in_string && matches!(characters.peek(), Some((_, '/'))) => {
+                    return Some(index)
                 }
-                Err(NomErr::Error(err)) => errors.push(err),
-                Err(NomErr::Failure(err)) => return Err(err.into()),
                 _ => {}
             }
+        }
+
+        None
+    }
+}
+
+/// Lowers a file and, recursively, the files it imports into a single `Lowerer`.
+struct Importer<'a> {
+    resolver: &'a dyn FileResolver,
+    lowerer: Lowerer,
+    /// Names of the files being imported, innermost last.
+    stack: Vec<String>,
+    imported: BTreeSet<String>,
+    /// Locations of the names declared so far, in any file.
+    definitions: BTreeMap<String, Location>,
+    recover: bool,
+    lints: Option<&'a LintRegistry>,
+    /// What the lints found in the files lowered so far.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Importer<'a> {
+    /// Returns the errors of the file and of the files it imports. Those of an imported file are
+    /// placed where its `import` statement is.
+    fn lower_file(&mut self, name: &str, text: &str, imported: bool) -> Vec<ParserError<Location>> {
+        let commentless_text_string = Parser::remove_comments(text);
+        let source = commentless_text_string.as_str();
+
+        let (program, syntax_errors) = Parser::parse_statements(source, self.recover);
+
+        let mut errors = syntax_errors
+            .into_iter()
+            .map(|err| {
+                let err = err.map_location(&mut |location: &str| {
+                    Location::from_slice(Some(name), source, location)
+                });
+
+                (Parser::error_offset(&err), err)
+            })
+            .collect::<Vec<_>>();
+
+        self.stack.push(name.to_string());
+
+        let mut warnings = vec![];
 
-            if let Ok((input, _)) = multispace1::<_, ParserError<&str>>(commentless_text) {
-                commentless_text = input;
-                continue;
+        for statement in program.statements.iter() {
+            let result = match statement {
+                Statement::Import(import) => match self.import(name, import) {
+                    Ok(import_errors) => {
+                        let offset = import.span.start;
+                        errors.extend(import_errors.into_iter().map(|err| (offset, err)));
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                },
+                Statement::Let(_) if imported => Ok(()),
+                Statement::Const(_)
+                | Statement::Device(_)
+                | Statement::Event(_)
+                | Statement::Peripheral(_)
+                | Statement::Let(_) => match self.define(name, source, statement) {
+                    Err(err) => {
+                        errors.push((Parser::error_offset(&err), err));
+                        Ok(())
+                    }
+                    Ok(()) => self.lowerer.lower_statement(statement),
+                },
+                _ if imported => Ok(()),
+                _ => self.lowerer.lower_statement(statement),
+            };
+
+            if let Err(err) = result {
+                let err = err.map_location(&mut |span: Span| span.location(Some(name), source));
+
+                errors.push((Parser::error_offset(&err), err));
             }
 
-            return Err(ParserError::Base {
-                location: commentless_text.to_string(),
-                kind: ErrorKind::Expected(Expectation::Something),
-                child: None,
-            });
+            warnings.extend(self.lowerer.take_warnings());
         }
 
-        Ok(Config {
-            peripherals,
-            initial_state,
-            event_processors,
-        })
+        self.stack.pop();
+
+        if let Some(lints) = self.lints {
+            let diagnostics = lints.diagnostics(Some(name), source, &program, warnings, imported);
+            self.diagnostics.extend(diagnostics);
+        }
+
+        errors.sort_by_key(|(offset, _)| *offset);
+        errors.into_iter().map(|(_, err)| err).collect()
     }
 
-    fn remove_comments(text: String) -> String {
-        let mut result = "".to_string();
+    /// Records the name that `statement` declares. Names declared twice in the same file are left
+    /// to the lowerer, which knows their spans, while this reports those declared in another file.
+    fn define(
+        &mut self,
+        file_name: &str,
+        source: &str,
+        statement: &Statement,
+    ) -> Result<(), ParserError<Location>> {
+        let declared_name = match statement.declared_name() {
+            Some(declared_name) => declared_name,
+            None => return Ok(()),
+        };
+        let location = declared_name.span.location(Some(file_name), source);
 
-        for line in text.lines() {
-            if let Some(code_portion) = line.split("//").nth(0) {
-                result += code_portion;
+        match self.definitions.get(&declared_name.value) {
+            Some(first_location) if first_location.file != location.file => {
+                Err(ParserError::Base {
+                    location: first_location.clone(),
+                    kind: ErrorKind::FirstDefinition(declared_name.value.clone()),
+                    child: Some(Box::new(ParserError::Base {
+                        location,
+                        kind: ErrorKind::AlreadyDefined(declared_name.value.clone()),
+                        child: None,
+                    })),
+                })
+            }
+            Some(_) => Ok(()),
+            None => {
+                self.definitions
+                    .insert(declared_name.value.clone(), location);
+                Ok(())
             }
+        }
+    }
+
+    fn import(
+        &mut self,
+        importer: &str,
+        import: &ImportStatement,
+    ) -> Result<Vec<ParserError<Location>>, ParserError<Span>> {
+        let name = self.resolver.resolve(importer, &import.path);
 
-            result += "\n";
+        if self.stack.contains(&name) {
+            return Err(ParserError::Base {
+                location: import.path_span,
+                kind: ErrorKind::ImportCycle,
+                child: None,
+            });
         }
 
-        result
-    }
+        // Every file is only lowered once, even if several files import it
+        if !self.imported.insert(name.clone()) {
+            return Ok(vec![]);
+        }
 
-    fn prepare_constants(constants: &mut BTreeMap<&str, Literal>) {
-        prepare_constant!(BOOTLOADER_HELLO_EVENT_CODE, constants, Literal::U16);
-        prepare_constant!(PROGRAMMER_HELLO_EVENT_CODE, constants, Literal::U16);
-        prepare_constant!(
-            PROGRAMMER_START_FIRMWARE_UPGRADE_EVENT_CODE,
-            constants,
-            Literal::U16
-        );
-        prepare_constant!(ACK_EVENT_CODE, constants, Literal::U16);
-        prepare_constant!(DATA_EVENT_CODE, constants, Literal::U16);
-        prepare_constant!(CONFIGURATOR_HELLO_EVENT_CODE, constants, Literal::U16);
-        prepare_constant!(BCM_CHANGE_BRIGHTNESS_EVENT_CODE, constants, Literal::U16);
-        prepare_constant!(BUTTON_PRESSED_EVENT_CODE, constants, Literal::U16);
-        prepare_constant!(BUTTON_RELEASED_EVENT_CODE, constants, Literal::U16);
-        prepare_constant!(INTERNAL_SYSTEM_TICK_EVENT_CODE, constants, Literal::U16);
-        prepare_constant!(
-            PROGRAMMER_START_CONFIG_UPGRADE_EVENT_CODE,
-            constants,
-            Literal::U16
-        );
-        prepare_constant!(
-            PROGRAMMER_SET_DEVICE_ADDRESS_EVENT_CODE,
-            constants,
-            Literal::U16
-        );
-        prepare_constant!(MESSAGE_EVENT_CODE, constants, Literal::U16);
+        let text = self.resolver.read(&name).map_err(|err| ParserError::Base {
+            location: import.path_span,
+            kind: ErrorKind::UnresolvedImport,
+            child: Some(Box::new(ParserError::Base {
+                location: import.path_span,
+                kind: ErrorKind::External(Box::new(err)),
+                child: None,
+            })),
+        })?;
+
+        Ok(self.lower_file(&name, &text, true))
     }
 }
 
 pub fn name_parser(text: &str) -> IResult<&str, &str, ParserError<&str>> {
-    if let Some(character) = text.chars().nth(0) {
-        if character.is_digit(10) || character == '_' {
+    if let Some(character) = text.chars().next() {
+        if character.is_ascii_digit() || character == '_' {
             return Err(NomErr::Error(ParserError::Base {
                 location: text,
                 kind: ErrorKind::Expected(Expectation::Name),
@@ -199,7 +760,7 @@ pub fn name_parser(text: &str) -> IResult<&str, &str, ParserError<&str>> {
     }
 
     match text.split_at_position1_complete(
-        |item| !item.is_alpha() && !item.is_digit(10) && item != '_',
+        |item| !item.is_alpha() && !item.is_ascii_digit() && item != '_',
         NomErrorKind::Alpha,
     ) {
         Ok((input, name)) => Ok((input, name)),
@@ -222,14 +783,14 @@ pub fn name_parser(text: &str) -> IResult<&str, &str, ParserError<&str>> {
 
 pub fn hex1(text: &str) -> IResult<&str, &str, ParserError<&str>> {
     text.split_at_position1_complete(
-        |item| !item.is_digit(16) && item != 'x',
+        |item| !item.is_ascii_hexdigit() && item != 'x',
         NomErrorKind::HexDigit,
     )
 }
 
 pub fn dec1(text: &str) -> IResult<&str, &str, ParserError<&str>> {
     text.split_at_position1_complete(
-        |item| !item.is_digit(10) && item != '-',
+        |item| !item.is_ascii_digit() && item != '-',
         NomErrorKind::Digit,
     )
 }
@@ -249,6 +810,82 @@ pub fn argument_or_constant0<'a>(
     }
 }
 
+pub fn name<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Name, ParserError<&'a str>> {
+    move |text| {
+        let (input, value) = name_parser(text)?;
+
+        Ok((
+            input,
+            Name {
+                value: value.to_string(),
+                span: Span::new(source, text, input),
+            },
+        ))
+    }
+}
+
+pub fn literal_expression<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Expression, ParserError<&'a str>> {
+    move |text| {
+        let (input, value) = literal(text)?;
+
+        Ok((
+            input,
+            Expression::Literal {
+                value,
+                span: Span::new(source, text, input),
+            },
+        ))
+    }
+}
+
+/// Parses either a literal or the name of a constant. Constants are only resolved when lowering.
+pub fn expression<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Expression, ParserError<&'a str>> {
+    move |text| match literal_expression(source)(text) {
+        Err(NomErr::Error(err)) => match name(source)(text) {
+            Ok((input, name)) => Ok((input, Expression::Constant(name))),
+            Err(NomErr::Error(_)) => Err(NomErr::Error(err)),
+            Err(err) => Err(err),
+        },
+        result => result,
+    }
+}
+
+pub fn argument_expression0<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<Expression>, ParserError<&'a str>> {
+    move |text| {
+        delimited(
+            terminated(open_parenthesis, multispace0),
+            separated_list0(
+                comma,
+                delimited(multispace0, expression(source), multispace0),
+            ),
+            close_parenthesis,
+        )(text)
+    }
+}
+
+pub fn literal_argument_expression0<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<Expression>, ParserError<&'a str>> {
+    move |text| {
+        delimited(
+            terminated(open_parenthesis, multispace0),
+            separated_list0(
+                comma,
+                delimited(multispace0, literal_expression(source), multispace0),
+            ),
+            close_parenthesis,
+        )(text)
+    }
+}
+
 pub fn argument0(text: &str) -> IResult<&str, Vec<Literal>, ParserError<&str>> {
     delimited(
         terminated(open_parenthesis, multispace0),
@@ -264,6 +901,501 @@ mod tests {
     use cool_asserts::assert_matches;
 
     use crate::error::{ErrorKind, Expectation, ParserError};
+    use crate::resolver::MemoryResolver;
+
+    fn first_error(options: ParseOptions) -> Result<Config, ParserError<Location>> {
+        Parser::parse_with(options)
+            .map(|parsed| parsed.config)
+            .map_err(|mut failure| failure.errors.remove(0))
+    }
+
+    #[test]
+    fn parse_error_location_test() {
+        let text = "let a = false;\nconst b = 0x01~u8;\nsend 0x01~u16 from b to 0x0002~u16;";
+
+        assert_matches!(
+            Parser::parse(text),
+            Err(ParserError::Base {
+                location,
+                kind,
+                child,
+            }) => {
+                assert_eq!(location, Location::new(None, text, 53));
+                assert_eq!(location.line, 3);
+                assert_eq!(location.column, 20);
+                assert_matches!(kind, ErrorKind::CastFromToNotAllowed("u8", "u16"));
+                assert_matches!(child, None);
+            }
+        );
+    }
+
+    #[test]
+    fn parse_error_location_after_comment_test() {
+        let text = "// let a = 0x01;\nlet b = 0x01; // comment";
+
+        assert_matches!(
+            first_error(ParseOptions::text(text).with_file_name("config.ross")),
+            Err(ParserError::Base {
+                location,
+                kind,
+                child: _,
+            }) => {
+                assert_eq!(format!("{}", location), "config.ross:2:9");
+                assert_matches!(kind, ErrorKind::AmbiguousInteger);
+            }
+        );
+    }
+
+    #[test]
+    fn parse_furthest_error_test() {
+        let text = "do {\n    match event 0x01~u16;\n    match evnt 0x02~u16;\n}";
+
+        assert_matches!(
+            Parser::parse(text),
+            Err(ParserError::Base {
+                location,
+                kind,
+                child: _,
+            }) => {
+                assert_eq!(location.line, 3);
+                assert_eq!(location.column, 5);
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Symbol('}')));
+            }
+        );
+    }
+
+    #[test]
+    fn parse_unknown_statement_test() {
+        let text = "let a = false;\n  foo bar;";
+
+        assert_matches!(
+            Parser::parse(text),
+            Err(ParserError::Base {
+                location,
+                kind,
+                child,
+            }) => {
+                assert_eq!(location.line, 2);
+                assert_eq!(location.column, 3);
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Something));
+                assert_matches!(child, None);
+            }
+        );
+    }
+
+    #[test]
+    fn parse_recovering_test() {
+        let text = "const a = 0x01~u8;
+send a from 0x0001~u16 to 0x0002~u16;
+let b = 0x01;
+const c = 0x02~u8;
+do {
+    match evnt 0x01~u16;
+}
+send BUTTON_PRESSED_EVENT_CODE from 0x0001~u16 to 0x0002~u16;";
+
+        assert_matches!(
+            Parser::parse_with(ParseOptions::text(text).recovering())
+                .map_err(ParseFailure::into_diagnostics),
+            Err(diagnostics) => {
+                let lines = diagnostics
+                    .iter()
+                    .map(|diagnostic| diagnostic.location.line)
+                    .collect::<Vec<_>>();
+
+                assert_eq!(lines, vec![2, 3, 6]);
+                assert_eq!(diagnostics[0].message, "cast from u8 to u16 not allowed");
+            }
+        );
+    }
+
+    #[test]
+    fn parse_recovering_valid_test() {
+        let text = "let a = false;
+send BUTTON_PRESSED_EVENT_CODE from 0x0001~u16 to 0x0002~u16;";
+
+        assert_matches!(
+            Parser::parse_with(ParseOptions::text(text).recovering())
+                .map_err(ParseFailure::into_diagnostics),
+            Ok(Parsed { config, .. }) => {
+                assert_eq!(config.initial_state.len(), 1);
+                assert_eq!(config.event_processors.len(), 1);
+            }
+        );
+    }
+
+    #[test]
+    fn parse_file_test() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert(
+            "common.ross",
+            "const device_address = 0x0002~u16;
+            peripheral 0~u32 bcm single(0~u8);
+            let unused = false;
+            send 0x0001~u16 from 0x0002~u16 to 0x0003~u16;",
+        );
+        resolver.insert(
+            "main.ross",
+            "import \"common.ross\";
+            let button = false;
+            send BUTTON_PRESSED_EVENT_CODE from device_address to 0xffff~u16;",
+        );
+
+        let config = Parser::parse_with(ParseOptions::file("main.ross", &resolver))
+            .unwrap()
+            .config;
+
+        assert_eq!(config.peripherals.len(), 1);
+        assert_eq!(config.initial_state.len(), 1);
+        assert_eq!(config.event_processors.len(), 1);
+    }
+
+    #[test]
+    fn parse_file_diamond_test() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("common.ross", "const a = 0x01~u16;");
+        resolver.insert("left.ross", "import \"common.ross\";");
+        resolver.insert("right.ross", "import \"common.ross\";");
+        resolver.insert(
+            "main.ross",
+            "import \"left.ross\"; import \"right.ross\"; send a from a to a;",
+        );
+
+        assert_matches!(
+            Parser::parse_with(ParseOptions::file("main.ross", &resolver)),
+            Ok(_)
+        );
+    }
+
+    #[test]
+    fn parse_file_cycle_test() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("a.ross", "import \"b.ross\";");
+        resolver.insert("b.ross", "const b = 0x01~u8;\nimport \"a.ross\";");
+
+        assert_matches!(
+            first_error(ParseOptions::file("a.ross", &resolver)),
+            Err(ParserError::Base {
+                location,
+                kind,
+                child,
+            }) => {
+                assert_eq!(location.file, Some("b.ross".to_string()));
+                assert_eq!(location.line, 2);
+                assert_eq!(location.column, 8);
+                assert_matches!(kind, ErrorKind::ImportCycle);
+                assert_matches!(child, None);
+            }
+        );
+    }
+
+    #[test]
+    fn parse_file_error_location_test() {
+        let common = "const a = 0x01~u8;\nperipheral 0~u32 bcm single(0x0001~u16);";
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("common.ross", common);
+        resolver.insert("main.ross", "\nimport \"common.ross\";");
+
+        assert_matches!(
+            first_error(ParseOptions::file("main.ross", &resolver)),
+            Err(ParserError::Base { location, kind, .. }) => {
+                assert_eq!(location, Location::new(Some("common.ross"), common, 47));
+                assert_matches!(kind, ErrorKind::CastFromToNotAllowed("u16", "u8"));
+            }
+        );
+    }
+
+    #[test]
+    fn parse_file_missing_import_test() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("main.ross", "import \"common.ross\";");
+
+        assert_matches!(
+            first_error(ParseOptions::file("main.ross", &resolver)),
+            Err(ParserError::Base {
+                location,
+                kind,
+                child,
+            }) => {
+                assert_eq!(location.offset, 7);
+                assert_matches!(kind, ErrorKind::UnresolvedImport);
+                assert_matches!(child, Some(_));
+            }
+        );
+    }
+
+    #[test]
+    fn parse_file_recovering_test() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("common.ross", "const a = ;");
+        resolver.insert(
+            "main.ross",
+            "const b = ;\nimport \"common.ross\";\nconst c = ;",
+        );
+
+        assert_matches!(
+            Parser::parse_with(ParseOptions::file("main.ross", &resolver).recovering())
+                .map_err(ParseFailure::into_diagnostics),
+            Err(diagnostics) => {
+                let files: Vec<_> = diagnostics
+                    .iter()
+                    .map(|diagnostic| (diagnostic.location.file.clone().unwrap(), diagnostic.location.line))
+                    .collect();
+
+                assert_eq!(
+                    files,
+                    vec![
+                        ("main.ross".to_string(), 1),
+                        ("common.ross".to_string(), 1),
+                        ("main.ross".to_string(), 3),
+                    ]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn parse_linted_test() {
+        let text = "const a = 0x01~u8;
+let b = false;
+send BUTTON_PRESSED_EVENT_CODE from 0x0001~u16 to 0x0002~u16;";
+
+        assert_matches!(
+            Parser::parse_with(
+                ParseOptions::text(text)
+                    .with_file_name("config.ross")
+                    .recovering()
+                    .with_lints(&LintRegistry::new())
+            ),
+            Ok(Parsed { config, diagnostics: warnings, .. }) => {
+                assert_eq!(config.event_processors.len(), 1);
+
+                let warnings: Vec<_> = warnings
+                    .iter()
+                    .map(|warning| warning.to_string())
+                    .collect();
+
+                assert_eq!(
+                    warnings,
+                    vec![
+                        "config.ross:1:7: warning[unused_const]: constant a is never used",
+                        "config.ross:2:5: warning[unused_let]: state variable b is never used",
+                    ]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn parse_linted_denied_test() {
+        let text = "#![deny(unused_let)]
+let a = 0x01~u16;
+let b = false;";
+
+        assert_matches!(
+            Parser::parse_with(ParseOptions::text(text).recovering().with_lints(&LintRegistry::new()))
+                .map_err(ParseFailure::into_diagnostics),
+            Err(diagnostics) => {
+                let lines: Vec<_> = diagnostics
+                    .iter()
+                    .map(|diagnostic| (diagnostic.severity, diagnostic.location.line))
+                    .collect();
+
+                assert_eq!(lines, vec![(Severity::Error, 2), (Severity::Error, 3)]);
+            }
+        );
+    }
+
+    #[test]
+    fn parse_linted_error_test() {
+        let text = "let a = false;\nconst b = ;";
+
+        assert_matches!(
+            Parser::parse_with(ParseOptions::text(text).recovering().with_lints(&LintRegistry::new()))
+                .map_err(ParseFailure::into_diagnostics),
+            Err(diagnostics) => {
+                let severities: Vec<_> = diagnostics
+                    .iter()
+                    .map(|diagnostic| (diagnostic.severity, diagnostic.location.line))
+                    .collect();
+
+                assert_eq!(severities, vec![(Severity::Error, 2), (Severity::Warning, 1)]);
+            }
+        );
+    }
+
+    #[test]
+    fn parse_file_linted_test() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("common.ross", "const a = 0x01~u16;\nconst b = 0x02~u16;");
+        resolver.insert(
+            "main.ross",
+            "import \"common.ross\";\nsend a from a to a;\ndo { match tick; }",
+        );
+
+        assert_matches!(
+            Parser::parse_with(
+                ParseOptions::file("main.ross", &resolver)
+                    .recovering()
+                    .with_lints(&LintRegistry::new())
+            ),
+            Ok(Parsed { diagnostics: warnings, .. }) => {
+                let codes: Vec<_> = warnings
+                    .iter()
+                    .map(|warning| (warning.location.file.clone().unwrap(), warning.code.clone().unwrap()))
+                    .collect();
+
+                assert_eq!(
+                    codes,
+                    vec![
+                        ("main.ross".to_string(), "undeclared_event_code".to_string()),
+                        ("main.ross".to_string(), "do_without_fire".to_string()),
+                    ]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn parse_file_already_defined_test() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("common.ross", "device light = 0x0003;");
+        resolver.insert(
+            "main.ross",
+            "import \"common.ross\";\nconst light = 0x0004~u16;",
+        );
+
+        assert_matches!(
+            Parser::parse_with(ParseOptions::file("main.ross", &resolver).recovering())
+                .map_err(ParseFailure::into_diagnostics),
+            Err(diagnostics) => {
+                let diagnostics: Vec<_> = diagnostics
+                    .iter()
+                    .map(|diagnostic| (diagnostic.to_string(), diagnostic.notes.clone()))
+                    .collect();
+
+                assert_eq!(
+                    diagnostics,
+                    vec![(
+                        "main.ross:2:7: error: light is already defined".to_string(),
+                        vec!["first definition of light at common.ross:1:8".to_string()],
+                    )]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn parse_with_outputs_test() {
+        let text = "send BUTTON_PRESSED_EVENT_CODE from 0x0001~u16 to 0x0002~u16;
+test \"send\" {
+    inject BUTTON_PRESSED_EVENT_CODE from 0x0001;
+}";
+
+        assert_matches!(
+            Parser::parse_with(ParseOptions::text(text)),
+            Ok(Parsed { config, source_map: None, tests: None, .. }) => {
+                assert_eq!(config.event_processors.len(), 1);
+            }
+        );
+        assert_matches!(
+            Parser::parse_with(ParseOptions::text(text).with_source_map().with_tests()),
+            Ok(Parsed { source_map: Some(source_map), tests: Some(tests), .. }) => {
+                assert_eq!(source_map.event_processors.len(), 1);
+                assert_eq!(tests.len(), 1);
+                assert_eq!(tests[0].name, "send");
+            }
+        );
+    }
+
+    #[test]
+    fn parse_import_without_resolver_test() {
+        assert_matches!(
+            Parser::parse("import \"common.ross\";"),
+            Err(ParserError::Base { kind, .. }) => {
+                assert_matches!(kind, ErrorKind::UnresolvedImport);
+            }
+        );
+    }
+
+    #[test]
+    fn skip_statement_test() {
+        assert_eq!(Parser::skip_statement("send a;input"), "input");
+        assert_eq!(
+            Parser::skip_statement("do { match { X(\"}\"); }; fire { Y(); } }input"),
+            "input"
+        );
+        assert_eq!(Parser::skip_statement("#[allow(a b)]input"), "input");
+        assert_eq!(Parser::skip_statement("send a"), "");
+    }
+
+    #[test]
+    fn comments_test() {
+        let text = "let a = false; // comment  \r\n  // own line\nconst b = true;";
+
+        assert_eq!(
+            Parser::comments(text),
+            vec![
+                Comment {
+                    text: "// comment".to_string(),
+                    span: Span { start: 15, end: 25 },
+                    own_line: false,
+                },
+                Comment {
+                    text: "// own line".to_string(),
+                    span: Span { start: 31, end: 42 },
+                    own_line: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_comments_preserves_positions_test() {
+        let text = "let a = false; // comment\r\n// comment\nconst b = true;";
+        let commentless_text = Parser::remove_comments(text);
+
+        assert_eq!(commentless_text.len(), text.len());
+        assert_eq!(
+            commentless_text,
+            "let a = false;           \r\n          \nconst b = true;"
+        );
+    }
+
+    #[test]
+    fn comments_in_string_test() {
+        let text = "import \"lib//common.ross\"; // comment";
+
+        assert_eq!(
+            Parser::comments(text),
+            vec![Comment {
+                text: "// comment".to_string(),
+                span: Span { start: 27, end: 37 },
+                own_line: false,
+            }]
+        );
+        assert_eq!(
+            Parser::remove_comments(text),
+            "import \"lib//common.ross\";           "
+        );
+    }
+
+    #[test]
+    fn parse_file_import_with_slashes_test() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("lib//common.ross", "const device_address = 0x0002~u16;");
+        resolver.insert(
+            "main.ross",
+            "import \"lib//common.ross\"; // shared constants
+            send BUTTON_PRESSED_EVENT_CODE from device_address to 0xffff~u16;",
+        );
+
+        let config = Parser::parse_with(ParseOptions::file("main.ross", &resolver))
+            .unwrap()
+            .config;
+
+        assert_eq!(config.event_processors.len(), 1);
+    }
 
     #[test]
     fn name_parser_test() {
@@ -0,0 +1,828 @@
+use std::collections::VecDeque;
+use std::mem::discriminant;
+
+use crate::ast::*;
+use crate::literal::{literal, Literal};
+
+const INDENTATION: &str = "    ";
+
+/// Prints a `Program` as canonical source: one statement per line, blocks indented by four spaces
+/// and a blank line around every statement that spans several lines or starts a different kind
+/// of statement. The names of consecutive `const`, `device` or `event` statements are padded so
+/// that their values line up.
+///
+/// When printing with comments, each comment is placed before the first node that follows it in
+/// the source, or at the end of the line of the node it trails. Blank lines between statements
+/// in the source are kept, but collapsed to one.
+pub struct Printer<'a> {
+    output: String,
+    indentation: usize,
+    line_start: bool,
+    source: Option<&'a str>,
+    comments: VecDeque<Comment>,
+    /// Source offset up to which top-level statements and comments have been printed.
+    cursor: usize,
+    name_width: usize,
+}
+
+impl<'a> Printer<'a> {
+    pub fn print(program: &Program) -> String {
+        Printer::new(None, vec![]).program(program)
+    }
+
+    /// Same as `print`, but also prints `comments`. `source` is the text `program` was parsed
+    /// from, which the spans of both refer to.
+    pub fn print_with_comments(
+        program: &Program,
+        source: &'a str,
+        comments: Vec<Comment>,
+    ) -> String {
+        Printer::new(Some(source), comments).program(program)
+    }
+
+    /// Prints a single statement without a trailing newline.
+    pub fn print_statement(statement: &Statement) -> String {
+        let mut printer = Printer::new(None, vec![]);
+
+        printer.statement(statement);
+        printer.output.truncate(printer.output.trim_end().len());
+        printer.output
+    }
+
+    fn new(source: Option<&'a str>, comments: Vec<Comment>) -> Self {
+        Self {
+            output: String::new(),
+            indentation: 0,
+            line_start: true,
+            source,
+            comments: comments.into(),
+            cursor: 0,
+            name_width: 0,
+        }
+    }
+
+    fn program(mut self, program: &Program) -> String {
+        let statements = &program.statements;
+
+        let separated: Vec<bool> = statements
+            .iter()
+            .enumerate()
+            .map(|(i, statement)| {
+                i > 0
+                    && (Self::separated_kinds(&statements[i - 1], statement)
+                        || self.blank_line_in(statements[i - 1].span().end, statement.span().start))
+            })
+            .collect();
+
+        let mut name_widths = vec![0; statements.len()];
+        let mut group_start = 0;
+
+        // Groups never mix kinds, because a blank line separates different kinds
+        for i in 0..=statements.len() {
+            let in_group = i < statements.len()
+                && Self::declared_name(&statements[i]).is_some()
+                && (i == group_start || !separated[i]);
+
+            if !in_group {
+                let width = statements[group_start..i]
+                    .iter()
+                    .filter_map(|statement| Self::declared_name(statement))
+                    .map(|name| name.value.len())
+                    .max()
+                    .unwrap_or(0);
+
+                for name_width in name_widths[group_start..i].iter_mut() {
+                    *name_width = width;
+                }
+
+                group_start =
+                    if i < statements.len() && Self::declared_name(&statements[i]).is_some() {
+                        i
+                    } else {
+                        i + 1
+                    };
+            }
+        }
+
+        for (i, statement) in statements.iter().enumerate() {
+            if i > 0 && Self::separated_kinds(&statements[i - 1], statement) {
+                self.blank_line();
+            }
+
+            self.leading_comments(statement.span().start);
+
+            if self.blank_line_in(self.cursor, statement.span().start) {
+                self.blank_line();
+            }
+
+            self.name_width = name_widths[i];
+            self.statement(statement);
+            self.cursor = statement.span().end;
+            self.trailing_comment(statement.span().end);
+            self.newline();
+        }
+
+        self.leading_comments(usize::MAX);
+        self.output
+    }
+
+    /// Returns the name of a statement whose name is padded to line up with its neighbours.
+    fn declared_name(statement: &Statement) -> Option<&Name> {
+        match statement {
+            Statement::Const(statement) => Some(&statement.name),
+            Statement::Device(statement) => Some(&statement.name),
+            Statement::Event(statement) => Some(&statement.name),
+            _ => None,
+        }
+    }
+
+    /// Whether a blank line goes between two statements regardless of the source. An outer
+    /// attribute stays right above the statement it applies to.
+    fn separated_kinds(previous: &Statement, statement: &Statement) -> bool {
+        if let Statement::Attribute(AttributeStatement { inner: false, .. }) = previous {
+            return false;
+        }
+
+        discriminant(previous) != discriminant(statement)
+            || Self::print_statement(previous).contains('\n')
+            || Self::print_statement(statement).contains('\n')
+    }
+
+    /// Whether the source has a line with only whitespace on it between `start` and `end`.
+    fn blank_line_in(&self, start: usize, end: usize) -> bool {
+        match self.source {
+            Some(source) if start < end && end <= source.len() => {
+                let lines: Vec<&str> = source[start..end].split('\n').collect();
+
+                lines.len() > 2
+                    && lines[1..lines.len() - 1]
+                        .iter()
+                        .any(|line| line.trim().is_empty())
+            }
+            _ => false,
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Attribute(statement) => {
+                let lints: Vec<&str> = statement
+                    .lints
+                    .iter()
+                    .map(|lint| lint.value.as_str())
+                    .collect();
+
+                self.write(&format!(
+                    "#{}[{}({})]",
+                    if statement.inner { "!" } else { "" },
+                    statement.level.value,
+                    lints.join(", ")
+                ));
+            }
+            Statement::Import(statement) => {
+                self.write(&format!("import \"{}\";", statement.path));
+            }
+            Statement::Let(statement) => {
+                let value_type = match &statement.value_type {
+                    Some(value_type) => format!(": {}", value_type.value),
+                    None => String::new(),
+                };
+
+                self.write(&format!(
+                    "let {}{} = {};",
+                    statement.name.value,
+                    value_type,
+                    self.expression(&statement.value)
+                ));
+            }
+            Statement::Const(statement) => {
+                self.write(&format!(
+                    "const {:width$} = {};",
+                    statement.name.value,
+                    self.expression(&statement.value),
+                    width = self.name_width
+                ));
+            }
+            Statement::Device(statement) => {
+                self.write(&format!(
+                    "device {:width$} = {};",
+                    statement.name.value,
+                    self.expression(&statement.address),
+                    width = self.name_width
+                ));
+            }
+            Statement::Event(statement) => {
+                self.write(&format!(
+                    "event {:width$} = {};",
+                    statement.name.value,
+                    self.expression(&statement.event_code),
+                    width = self.name_width
+                ));
+            }
+            Statement::Peripheral(statement) => {
+                if let Some(gateway_addresses) = &statement.gateway_addresses {
+                    self.write(&format!("pub{} ", self.arguments(gateway_addresses)));
+                }
+
+                self.write(&format!(
+                    "peripheral {} {} {}{};",
+                    self.expression(&statement.index),
+                    statement.family.value,
+                    statement.kind.value,
+                    self.arguments(&statement.arguments)
+                ));
+            }
+            Statement::Send(statement) => {
+                self.write(&format!(
+                    "send {} from {} to {}",
+                    self.expression(&statement.event_code),
+                    self.expression(&statement.from_address),
+                    self.expression(&statement.to_address)
+                ));
+                self.condition_or_semicolon(&statement.condition);
+            }
+            Statement::Set(statement) => {
+                self.write(&format!(
+                    "set {} to {} on {} from {}",
+                    statement.state_variable.value,
+                    self.expression(&statement.value),
+                    self.expression(&statement.event_code),
+                    self.expression(&statement.from_address)
+                ));
+                self.condition_or_semicolon(&statement.condition);
+            }
+            Statement::Do(statement) => {
+                self.write("do {");
+                self.indent();
+
+                for match_expr in statement.matches.iter() {
+                    self.leading_comments(match_expr.span().start);
+                    self.match_expr(match_expr);
+                    self.trailing_comment(match_expr.span().end);
+                    self.newline();
+                }
+
+                for fire_expr in statement.fires.iter() {
+                    self.leading_comments(fire_expr.span.start);
+                    self.fire_expr(fire_expr);
+                    self.trailing_comment(fire_expr.span.end);
+                    self.newline();
+                }
+
+                self.leading_comments(statement.span.end);
+                self.dedent();
+                self.write("}");
+            }
+            Statement::Toggle(statement) => {
+                self.write(&format!(
+                    "toggle {} on {} from {} {{",
+                    statement.state_variable.value,
+                    self.expression(&statement.event_code),
+                    self.expression(&statement.from_address)
+                ));
+                self.indent();
+
+                for (keyword, fire_expr) in [("on", &statement.on), ("off", &statement.off)].iter()
+                {
+                    self.leading_comments(fire_expr.span.start);
+                    self.write(&format!("{}: ", keyword));
+                    self.fire_expr(fire_expr);
+                    self.write(";");
+                    self.trailing_comment(fire_expr.span.end);
+                    self.newline();
+                }
+
+                self.leading_comments(statement.span.end);
+                self.dedent();
+                self.write("}");
+            }
+            Statement::Test(statement) => {
+                self.write(&format!("test \"{}\"", statement.name));
+
+                if let Some(device_address) = &statement.device_address {
+                    self.write(&format!(" at {}", self.expression(device_address)));
+                }
+
+                self.write(" {");
+                self.indent();
+
+                for step in statement.steps.iter() {
+                    self.leading_comments(step.span().start);
+                    self.test_step(step);
+                    self.trailing_comment(step.span().end);
+                    self.newline();
+                }
+
+                self.leading_comments(statement.span.end);
+                self.dedent();
+                self.write("}");
+            }
+        }
+    }
+
+    fn test_step(&mut self, step: &TestStep) {
+        match step {
+            TestStep::Inject {
+                event_code,
+                from_address,
+                data,
+                ..
+            } => {
+                self.write(&format!(
+                    "inject {} from {}",
+                    self.expression(event_code),
+                    self.expression(from_address)
+                ));
+
+                if !data.is_empty() {
+                    let data: Vec<String> =
+                        data.iter().map(|value| self.expression(value)).collect();
+                    self.write(&format!(" with {}", data.join(", ")));
+                }
+
+                self.write(";");
+            }
+            TestStep::ExpectPacket { producer, .. } => {
+                self.write(&format!("expect {}", self.item_call(producer)))
+            }
+            TestStep::ExpectState {
+                state_variable,
+                value,
+                ..
+            } => self.write(&format!(
+                "expect state {} == {};",
+                state_variable.value,
+                self.expression(value)
+            )),
+        }
+    }
+
+    fn condition_or_semicolon(&mut self, condition: &Option<MatchExpr>) {
+        match condition {
+            Some(condition) => {
+                self.write(" if ");
+                self.match_expr(condition);
+            }
+            None => self.write(";"),
+        }
+    }
+
+    fn match_expr(&mut self, match_expr: &MatchExpr) {
+        self.write("match ");
+
+        match match_expr {
+            MatchExpr::Block { block, .. } => self.block_match(block),
+            _ => {
+                self.match_operand(match_expr);
+                self.write(";");
+            }
+        }
+    }
+
+    /// Prints a condition of the operator syntax, without `match` and `;`.
+    fn match_operand(&mut self, match_expr: &MatchExpr) {
+        match match_expr {
+            MatchExpr::Event { event_code, .. } => {
+                self.write(&format!("event {}", self.expression(event_code)))
+            }
+            MatchExpr::Producer { address, .. } => {
+                self.write(&format!("producer {}", self.expression(address)))
+            }
+            MatchExpr::Tick { .. } => self.write("tick"),
+            MatchExpr::Block { block, .. } => self.block_match(block),
+            MatchExpr::Not { operand, .. } => {
+                self.write("!");
+                self.match_operand(operand);
+            }
+            MatchExpr::And { left, right, .. } => {
+                self.match_operand(left);
+                self.write(" && ");
+                self.match_operand(right);
+            }
+            MatchExpr::Or { left, right, .. } => {
+                self.match_operand(left);
+                self.write(" || ");
+                self.match_operand(right);
+            }
+            MatchExpr::Parenthesized { match_expr, .. } => {
+                self.write("(");
+                self.match_operand(match_expr);
+                self.write(")");
+            }
+        }
+    }
+
+    fn block_match(&mut self, block: &BlockMatch) {
+        match block {
+            BlockMatch::Single {
+                extractor,
+                filter,
+                span,
+            } => self.item_block(extractor, filter, span.end),
+            BlockMatch::Not {
+                extractor,
+                filter,
+                span,
+            } => {
+                self.write("not ");
+                self.item_block(extractor, filter, span.end);
+            }
+            BlockMatch::Or { children, span } => {
+                self.write("or ");
+                self.block_match_list(children, span.end);
+            }
+            BlockMatch::And { children, span } => {
+                self.write("and ");
+                self.block_match_list(children, span.end);
+            }
+        }
+    }
+
+    fn block_match_list(&mut self, children: &[BlockMatch], end: usize) {
+        self.write("{");
+        self.indent();
+
+        for (i, child) in children.iter().enumerate() {
+            if i != 0 {
+                self.write(",");
+                self.newline();
+            }
+
+            self.leading_comments(child.span().start);
+            self.block_match(child);
+        }
+
+        self.newline();
+        self.leading_comments(end);
+        self.dedent();
+        self.write("}");
+    }
+
+    fn fire_expr(&mut self, fire_expr: &FireExpr) {
+        let end = match &fire_expr.condition {
+            Some(condition) => condition.span().start,
+            None => fire_expr.span.end,
+        };
+
+        self.write("fire ");
+        self.item_block(&fire_expr.extractor, &fire_expr.producer, end);
+
+        if let Some(condition) = &fire_expr.condition {
+            self.write(" if ");
+            self.match_expr(condition);
+        }
+    }
+
+    /// Prints `{`, the calls and `}`, with the comments in the block that end before `end`.
+    fn item_block(&mut self, extractor: &Option<ItemCall>, item: &ItemCall, end: usize) {
+        self.write("{");
+        self.indent();
+
+        for item in extractor.iter().chain(Some(item)) {
+            self.leading_comments(item.span.start);
+            self.write(&self.item_call(item));
+            self.trailing_comment(item.span.end);
+            self.newline();
+        }
+
+        self.leading_comments(end);
+        self.dedent();
+        self.write("}");
+    }
+
+    /// Prints the comments that start before `start` on lines of their own. At the top level,
+    /// blank lines before them in the source are kept.
+    fn leading_comments(&mut self, start: usize) {
+        while matches!(self.comments.front(), Some(comment) if comment.span.start < start) {
+            let comment = self.comments.pop_front().unwrap();
+
+            if !self.line_start {
+                self.newline();
+            }
+
+            if self.indentation == 0 {
+                if self.blank_line_in(self.cursor, comment.span.start) {
+                    self.blank_line();
+                }
+
+                self.cursor = comment.span.end;
+            }
+
+            self.write(&comment.text);
+            self.newline();
+        }
+    }
+
+    /// Prints the next comment at the end of the current line if it follows `end` on the same
+    /// line in the source.
+    fn trailing_comment(&mut self, end: usize) {
+        if let (Some(comment), Some(source)) = (self.comments.front(), self.source) {
+            let same_line =
+                comment.span.start >= end && !source[end..comment.span.start].contains('\n');
+
+            if !comment.own_line && same_line {
+                let comment = self.comments.pop_front().unwrap();
+
+                self.write(" ");
+                self.write(&comment.text);
+
+                if self.indentation == 0 {
+                    self.cursor = comment.span.end;
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        if self.line_start {
+            for _ in 0..self.indentation {
+                self.output.push_str(INDENTATION);
+            }
+
+            self.line_start = false;
+        }
+
+        self.output.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        self.output.push('\n');
+        self.line_start = true;
+    }
+
+    /// Starts a new line after an empty one, unless the output is empty or already ends with one.
+    fn blank_line(&mut self) {
+        if !self.line_start {
+            self.newline();
+        }
+
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.newline();
+        }
+    }
+
+    fn indent(&mut self) {
+        self.newline();
+        self.indentation += 1;
+    }
+
+    fn dedent(&mut self) {
+        self.indentation -= 1;
+    }
+
+    fn expression(&self, value: &Expression) -> String {
+        match value {
+            Expression::Literal {
+                value: Literal::Integer(integer),
+                span,
+            } => self.integer(*integer, *span),
+            Expression::Literal { value, .. } => value.to_string(),
+            Expression::Constant(name) => name.value.clone(),
+            Expression::Unary {
+                operator, operand, ..
+            } => format!("{}{}", operator.symbol(), self.expression(operand)),
+            Expression::Binary {
+                operator,
+                left,
+                right,
+                ..
+            } => format!(
+                "{} {} {}",
+                self.expression(left),
+                operator.symbol(),
+                self.expression(right)
+            ),
+            Expression::Parenthesized {
+                expression: inner, ..
+            } => format!("({})", self.expression(inner)),
+        }
+    }
+
+    /// Prints an integer without a type the way the source writes it, so that event codes and
+    /// addresses stay in hex, or in decimal if there is no source.
+    fn integer(&self, integer: u32, span: Span) -> String {
+        let written = self
+            .source
+            .and_then(|source| source.get(span.start..span.end));
+
+        match written.map(|written| (written, literal(written))) {
+            Some((written, Ok(("", Literal::Integer(value))))) if value == integer => {
+                written.to_string()
+            }
+            _ => integer.to_string(),
+        }
+    }
+
+    fn arguments(&self, arguments: &[Expression]) -> String {
+        let arguments: Vec<String> = arguments
+            .iter()
+            .map(|argument| self.expression(argument))
+            .collect();
+
+        format!("({})", arguments.join(", "))
+    }
+
+    fn item_call(&self, item_call: &ItemCall) -> String {
+        format!(
+            "{}{};",
+            item_call.name.value,
+            self.arguments(&item_call.arguments)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::Parser;
+
+    fn reprint(text: &str) -> String {
+        Printer::print(&Parser::parse_program(text).unwrap())
+    }
+
+    #[test]
+    fn simple_statements_test() {
+        let text = "const   a=0x01~u8;let b = true;let c = #010203;
+            pub( 0x0001~u16 ,a ) peripheral 0~u32 bcm rgb(0~u8,1~u8,2~u8);
+            send BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 to 0x0003~u16;";
+
+        assert_eq!(
+            reprint(text),
+            "const a = 0x01~u8;\n\
+             \n\
+             let b = true;\n\
+             let c = #010203;\n\
+             \n\
+             pub(0x0001~u16, a) peripheral 0x00000000~u32 bcm rgb(0x00~u8, 0x01~u8, 0x02~u8);\n\
+             \n\
+             send BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 to 0x0003~u16;\n"
+        );
+    }
+
+    #[test]
+    fn set_if_match_test() {
+        let text = "let a = false; set a to true on 0x0001~u16 from 0x0002~u16 if match {
+            ButtonIndexExtractor(); ValueEqualToConstFilter(0x00~u8); }";
+
+        assert_eq!(
+            reprint(text),
+            "let a = false;\n\
+             \n\
+             set a to true on 0x0001~u16 from 0x0002~u16 if match {\n    \
+                 ButtonIndexExtractor();\n    \
+                 ValueEqualToConstFilter(0x00~u8);\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn do_statement_test() {
+        let text = "do { match tick; match or { { FlipStateFilter(a); }, not { StateEqualToConstFilter(a, true); } }
+            fire { PacketProducer(0x0001~u16); } if match event 0x0002~u16; }";
+
+        assert_eq!(
+            reprint(text),
+            "do {\n    \
+                 match tick;\n    \
+                 match or {\n        \
+                     {\n            \
+                         FlipStateFilter(a);\n        \
+                     },\n        \
+                     not {\n            \
+                         StateEqualToConstFilter(a, true);\n        \
+                     }\n    \
+                 }\n    \
+                 fire {\n        \
+                     PacketProducer(0x0001~u16);\n    \
+                 } if match event 0x0002~u16;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn reparse_test() {
+        let text = "let a = false;
+            do {
+                match event BUTTON_PRESSED_EVENT_CODE;
+                match and { { FlipStateFilter(a); }, { EventCodeExtractor(); ValueEqualToConstFilter(0x0001~u16); } }
+                fire { PacketExtractor(); PacketProducer(0xffff~u16); }
+            }";
+        let printed = reprint(text);
+
+        assert_eq!(reprint(&printed), printed);
+        assert_eq!(
+            format!("{:?}", Parser::parse(&printed).unwrap()),
+            format!("{:?}", Parser::parse(text).unwrap())
+        );
+    }
+
+    #[test]
+    fn constant_expression_test() {
+        let text = "const a=0x01~u8;const b=!(a<<0x02~u8|a)*a==0x05~u8&&true;";
+
+        assert_eq!(
+            reprint(text),
+            "const a = 0x01~u8;\n\
+             const b = !(a << 0x02~u8 | a) * a == 0x05~u8 && true;\n"
+        );
+    }
+
+    #[test]
+    fn device_test() {
+        let text = "device kitchen_switch=0x0002;device light = 0x0003;const a = 0x01~u8;";
+
+        assert_eq!(
+            reprint(text),
+            "device kitchen_switch = 2;\n\
+             device light          = 3;\n\
+             \n\
+             const a = 0x01~u8;\n"
+        );
+    }
+
+    #[test]
+    fn event_test() {
+        let text = "event scene_changed=0x1000;event scene_reset = 0x1001;";
+
+        assert_eq!(
+            reprint(text),
+            "event scene_changed = 4096;\n\
+             event scene_reset   = 4097;\n"
+        );
+    }
+
+    #[test]
+    fn operator_test() {
+        let text = "do { match ( event a&&producer b )||! tick; }";
+
+        assert_eq!(
+            reprint(text),
+            "do {\n    \
+                 match (event a && producer b) || !tick;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn typed_let_test() {
+        let text = "let a :u8 = 0;let b=false;";
+
+        assert_eq!(reprint(text), "let a: u8 = 0;\nlet b = false;\n");
+    }
+
+    #[test]
+    fn test_test() {
+        let text = "let a = false;test \"flip\"{inject  BUTTON_PRESSED_EVENT_CODE from 0x0002~u16;
+            expect NoneProducer( );expect state a==true;}";
+
+        assert_eq!(
+            reprint(text),
+            "let a = false;\n\
+             \n\
+             test \"flip\" {\n    \
+                 inject BUTTON_PRESSED_EVENT_CODE from 0x0002~u16;\n    \
+                 expect NoneProducer();\n    \
+                 expect state a == true;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn toggle_test() {
+        let text = "let a = false;toggle a on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16{
+            on :fire {NoneProducer( );} ;off: fire { NoneProducer(); };}";
+
+        assert_eq!(
+            reprint(text),
+            "let a = false;\n\
+             \n\
+             toggle a on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 {\n    \
+                 on: fire {\n        \
+                     NoneProducer();\n    \
+                 };\n    \
+                 off: fire {\n        \
+                     NoneProducer();\n    \
+                 };\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn attribute_test() {
+        let text = "#![ allow( unused_let ,unused_const ) ]let a = false;
+            #[deny(do_without_fire)]do { match tick; }";
+
+        assert_eq!(
+            reprint(text),
+            "#![allow(unused_let, unused_const)]\n\
+             \n\
+             let a = false;\n\
+             \n\
+             #[deny(do_without_fire)]\n\
+             do {\n    \
+                 match tick;\n\
+             }\n"
+        );
+    }
+}
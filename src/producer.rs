@@ -20,23 +20,80 @@ pub fn producer<'a>(
             semicolon,
         )(text)?;
 
-        impl_item_arg0!(input, name, arguments, NoneProducer);
-        impl_item_arg1!(input, name, arguments, PacketProducer);
-        impl_item_arg3!(input, name, arguments, MessageProducer);
-        impl_item_arg3!(input, name, arguments, BcmChangeBrightnessProducer);
-        impl_item_arg3!(input, name, arguments, BcmChangeBrightnessStateProducer);
-        impl_item_arg4!(input, name, arguments, BcmAnimateBrightnessProducer);
-        impl_item_arg4!(input, name, arguments, BcmAnimateBrightnessStateProducer);
-        impl_item_arg3!(input, name, arguments, RelaySetValueProducer);
+        let argument_locations = vec![name; arguments.len()];
+        let producer =
+            create_producer(name, &arguments, input, &argument_locations).map_err(Err::Error)?;
 
-        Err(Err::Error(ParserError::Base {
-            location: name,
-            kind: ErrorKind::UnknownProducer,
-            child: None,
-        }))
+        Ok((input, producer))
     }
 }
 
+/// Creates the producer called `name`. Argument count errors are reported at `location`, while
+/// errors of individual arguments are reported at their entry in `argument_locations`.
+pub fn create_producer<L: Clone>(
+    name: &str,
+    arguments: &[Literal],
+    location: L,
+    argument_locations: &[L],
+) -> Result<Box<dyn Producer>, ParserError<L>> {
+    impl_item_arg0!(name, arguments, location, argument_locations, NoneProducer);
+    impl_item_arg1!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        PacketProducer
+    );
+    impl_item_arg3!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        MessageProducer
+    );
+    impl_item_arg3!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        BcmChangeBrightnessProducer
+    );
+    impl_item_arg3!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        BcmChangeBrightnessStateProducer
+    );
+    impl_item_arg4!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        BcmAnimateBrightnessProducer
+    );
+    impl_item_arg4!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        BcmAnimateBrightnessStateProducer
+    );
+    impl_item_arg3!(
+        name,
+        arguments,
+        location,
+        argument_locations,
+        RelaySetValueProducer
+    );
+
+    Err(ParserError::Base {
+        location,
+        kind: ErrorKind::UnknownProducer,
+        child: None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,325 @@
+use std::fmt::Write;
+
+use crate::diagnostic::Diagnostic;
+use crate::error::{ErrorKind, ParserError};
+use crate::location::Location;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD_BLUE: &str = "\x1b[1;34m";
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum RenderStyle {
+    Plain,
+    Colored,
+}
+
+impl RenderStyle {
+    fn paint(&self, color: &str, text: &str) -> String {
+        match self {
+            RenderStyle::Plain => text.to_string(),
+            RenderStyle::Colored => format!("{}{}{}", color, text, RESET),
+        }
+    }
+}
+
+impl ParserError<Location> {
+    /// Renders the error against `source` the way rustc does, without colors.
+    pub fn render(&self, source: &str) -> String {
+        self.render_with_style(source, RenderStyle::Plain)
+    }
+
+    pub fn render_with_style(&self, source: &str, style: RenderStyle) -> String {
+        Diagnostic::from(self).render_with_style(source, style)
+    }
+
+    fn furthest_offset(&self) -> usize {
+        match self {
+            ParserError::Base {
+                location, child, ..
+            } => child.as_ref().map_or(location.offset, |child| {
+                child.furthest_offset().max(location.offset)
+            }),
+            ParserError::Alt(siblings) => siblings
+                .iter()
+                .map(|sibling| sibling.furthest_offset())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    fn deepest(&self) -> Option<(&Location, &ErrorKind)> {
+        let mut chain = vec![];
+        collect_chain(self, &mut chain, &mut vec![]);
+        chain.pop()
+    }
+}
+
+/// Walks down the error, following the alternatives that got the furthest into the input.
+/// Alternatives that failed earlier are collapsed into a single note.
+pub(crate) fn collect_chain<'a>(
+    err: &'a ParserError<Location>,
+    chain: &mut Vec<(&'a Location, &'a ErrorKind)>,
+    notes: &mut Vec<String>,
+) {
+    match err {
+        ParserError::Base {
+            location,
+            kind,
+            child,
+        } => {
+            chain.push((location, kind));
+
+            if let Some(child) = child {
+                collect_chain(child, chain, notes);
+            }
+        }
+        ParserError::Alt(siblings) => {
+            let furthest_offset = err.furthest_offset();
+            let mut furthest_siblings = siblings
+                .iter()
+                .filter(|sibling| sibling.furthest_offset() == furthest_offset);
+
+            let collapsed_count = siblings.len() - furthest_siblings.clone().count();
+
+            if let Some(sibling) = furthest_siblings.next() {
+                collect_chain(sibling, chain, notes);
+            }
+
+            for sibling in furthest_siblings {
+                if let Some((location, kind)) = sibling.deepest() {
+                    notes.push(format!("alternatively {} at {}", kind, location));
+                }
+            }
+
+            if collapsed_count == 1 {
+                notes.push("1 other alternative failed earlier".to_string());
+            } else if collapsed_count > 1 {
+                notes.push(format!(
+                    "{} other alternatives failed earlier",
+                    collapsed_count
+                ));
+            }
+        }
+    }
+}
+
+/// Renders `message` with the source line `location` points to and an underline below the
+/// token found there.
+pub fn render_snippet(
+    source: &str,
+    severity: &str,
+    message: &str,
+    location: &Location,
+    notes: &[String],
+    style: RenderStyle,
+) -> String {
+    let line_number = location.line.to_string();
+    let gutter = " ".repeat(line_number.len());
+    let line = source.lines().nth(location.line - 1).unwrap_or("");
+
+    let padding = line
+        .chars()
+        .take(location.column - 1)
+        .map(|character| if character == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+    let underline = "^".repeat(token_length(source, location.offset));
+
+    let mut result = String::new();
+
+    let _ = writeln!(
+        result,
+        "{}{}",
+        style.paint(BOLD_RED, severity),
+        style.paint(BOLD, &format!(": {}", message)),
+    );
+    let _ = writeln!(
+        result,
+        "{}{} {}",
+        gutter,
+        style.paint(BOLD_BLUE, "-->"),
+        location
+    );
+    let _ = writeln!(result, "{} {}", gutter, style.paint(BOLD_BLUE, "|"));
+    let _ = writeln!(
+        result,
+        "{} {}",
+        style.paint(BOLD_BLUE, &format!("{} |", line_number)),
+        line
+    );
+    let _ = write!(
+        result,
+        "{} {} {}{}",
+        gutter,
+        style.paint(BOLD_BLUE, "|"),
+        padding,
+        style.paint(BOLD_RED, &underline),
+    );
+
+    for note in notes {
+        let _ = write!(
+            result,
+            "\n{} {} {}",
+            gutter,
+            style.paint(BOLD_BLUE, "="),
+            style.paint(BOLD, &format!("note: {}", note)),
+        );
+    }
+
+    result
+}
+
+fn token_length(source: &str, offset: usize) -> usize {
+    let is_symbol = |character: char| "~;:,(){}[]=\"#".contains(character);
+
+    let mut characters = source[offset.min(source.len())..].chars();
+
+    match characters.next() {
+        Some(character) if !character.is_whitespace() && !is_symbol(character) => {
+            1 + characters
+                .take_while(|character| !character.is_whitespace() && !is_symbol(*character))
+                .count()
+        }
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nom::error::ErrorKind as NomErrorKind;
+
+    use crate::error::Expectation;
+
+    fn base(
+        source: &str,
+        offset: usize,
+        kind: ErrorKind,
+        child: Option<ParserError<Location>>,
+    ) -> ParserError<Location> {
+        ParserError::Base {
+            location: Location::new(Some("config.ross"), source, offset),
+            kind,
+            child: child.map(Box::new),
+        }
+    }
+
+    #[test]
+    fn single_error_test() {
+        let source = "let a = false;\nconst b = 0x01;";
+        let err = base(source, 25, ErrorKind::Expected(Expectation::Literal), None);
+
+        assert_eq!(
+            err.render(source),
+            "error: expected a literal\n \
+             --> config.ross:2:11\n  \
+             |\n\
+             2 | const b = 0x01;\n  \
+             |           ^^^^"
+        );
+    }
+
+    #[test]
+    fn child_error_test() {
+        let source = "send 0x01~u16 frm a to b;";
+        let err = base(
+            source,
+            0,
+            ErrorKind::Nom(NomErrorKind::Alt),
+            Some(base(
+                source,
+                14,
+                ErrorKind::Expected(Expectation::Keyword("from")),
+                None,
+            )),
+        );
+
+        assert_eq!(
+            err.render(source),
+            "error: expected \"from\"\n \
+             --> config.ross:1:15\n  \
+             |\n\
+             1 | send 0x01~u16 frm a to b;\n  \
+             |               ^^^"
+        );
+    }
+
+    #[test]
+    fn alt_collapse_test() {
+        let source = "do { match evnt 0x01~u16; }";
+        let err = ParserError::Alt(vec![
+            base(
+                source,
+                0,
+                ErrorKind::Expected(Expectation::Keyword("let")),
+                None,
+            ),
+            base(
+                source,
+                0,
+                ErrorKind::Expected(Expectation::Keyword("send")),
+                None,
+            ),
+            base(
+                source,
+                0,
+                ErrorKind::Expected(Expectation::Something),
+                Some(base(
+                    source,
+                    11,
+                    ErrorKind::Expected(Expectation::Keyword("event")),
+                    None,
+                )),
+            ),
+        ]);
+
+        assert_eq!(
+            err.render(source),
+            "error: expected \"event\"\n \
+             --> config.ross:1:12\n  \
+             |\n\
+             1 | do { match evnt 0x01~u16; }\n  \
+             |            ^^^^\n  \
+             = note: expected something at config.ross:1:1\n  \
+             = note: 2 other alternatives failed earlier"
+        );
+    }
+
+    #[test]
+    fn end_of_input_test() {
+        let source = "let a = false";
+        let err = base(
+            source,
+            13,
+            ErrorKind::Expected(Expectation::Symbol(';')),
+            None,
+        );
+
+        assert_eq!(
+            err.render(source),
+            "error: expected ';'\n \
+             --> config.ross:1:14\n  \
+             |\n\
+             1 | let a = false\n  \
+             |              ^"
+        );
+    }
+
+    #[test]
+    fn colored_test() {
+        let source = "let a = false";
+        let err = base(
+            source,
+            13,
+            ErrorKind::Expected(Expectation::Symbol(';')),
+            None,
+        );
+
+        let rendered = err.render_with_style(source, RenderStyle::Colored);
+
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: expected ';'\x1b[0m\n"));
+        assert!(rendered.ends_with("\x1b[1;31m^\x1b[0m"));
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::Path;
+
+/// Finds and reads the files `import` statements refer to.
+pub trait FileResolver {
+    /// Returns the name of the file `path` refers to when it is imported from the file named
+    /// `importer`. Files are told apart by their names, which errors also carry.
+    fn resolve(&self, importer: &str, path: &str) -> String;
+
+    fn read(&self, name: &str) -> Result<String, IoError>;
+}
+
+/// Resolves paths relative to the directory of the importing file.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileSystemResolver {}
+
+impl FileSystemResolver {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl FileResolver for FileSystemResolver {
+    fn resolve(&self, importer: &str, path: &str) -> String {
+        let directory = Path::new(importer)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+
+        directory.join(path).to_string_lossy().into_owned()
+    }
+
+    fn read(&self, name: &str) -> Result<String, IoError> {
+        fs::read_to_string(name)
+    }
+}
+
+/// Keeps files in memory under their names. Paths are names as they are.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryResolver {
+    files: BTreeMap<String, String>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: &str, text: &str) {
+        self.files.insert(name.to_string(), text.to_string());
+    }
+}
+
+impl FileResolver for MemoryResolver {
+    fn resolve(&self, _importer: &str, path: &str) -> String {
+        path.to_string()
+    }
+
+    fn read(&self, name: &str) -> Result<String, IoError> {
+        self.files
+            .get(name)
+            .cloned()
+            .ok_or_else(|| IoError::new(IoErrorKind::NotFound, format!("no file named {:?}", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+
+    #[test]
+    fn file_system_resolve_test() {
+        let resolver = FileSystemResolver::new();
+
+        assert_eq!(
+            resolver.resolve("configs/kitchen.ross", "common.ross"),
+            Path::new("configs").join("common.ross").to_string_lossy()
+        );
+        assert_eq!(
+            resolver.resolve("kitchen.ross", "common.ross"),
+            "common.ross"
+        );
+    }
+
+    #[test]
+    fn memory_test() {
+        let mut resolver = MemoryResolver::new();
+        resolver.insert("common.ross", "const a = 0x01~u8;");
+
+        assert_eq!(resolver.resolve("main.ross", "common.ross"), "common.ross");
+        assert_matches!(resolver.read("common.ross"), Ok(text) => {
+            assert_eq!(text, "const a = 0x01~u8;");
+        });
+        assert_matches!(resolver.read("other.ross"), Err(err) => {
+            assert_eq!(err.kind(), IoErrorKind::NotFound);
+        });
+    }
+}
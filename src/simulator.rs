@@ -0,0 +1,1332 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use ross_config::config::Config;
+use ross_config::matcher::Matcher;
+use ross_config::Value;
+use ross_protocol::event::bcm::BcmValue;
+use ross_protocol::event::event_code::*;
+use ross_protocol::event::message::MessageValue;
+use ross_protocol::event::relay::RelayValue;
+
+use crate::decode::{decode_item, DecodeError, DecodedItem};
+use crate::literal::Literal;
+
+/// An event as the event processors of a device see it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Event {
+    pub event_code: u16,
+    pub producer_address: u16,
+    pub data: EventData,
+}
+
+/// What an event carries besides its code and producer.
+#[derive(Debug, PartialEq, Clone)]
+pub enum EventData {
+    None,
+    Button {
+        index: u8,
+    },
+    Message {
+        code: u16,
+        value: MessageValue,
+    },
+    BcmChangeBrightness {
+        index: u8,
+        value: BcmValue,
+    },
+    BcmAnimateBrightness {
+        index: u8,
+        duration: u32,
+        value: BcmValue,
+    },
+    RelaySetValue {
+        index: u8,
+        value: RelayValue,
+    },
+}
+
+impl Event {
+    pub fn button_pressed(producer_address: u16, index: u8) -> Self {
+        Self {
+            event_code: BUTTON_PRESSED_EVENT_CODE,
+            producer_address,
+            data: EventData::Button { index },
+        }
+    }
+
+    pub fn button_released(producer_address: u16, index: u8) -> Self {
+        Self {
+            event_code: BUTTON_RELEASED_EVENT_CODE,
+            producer_address,
+            data: EventData::Button { index },
+        }
+    }
+
+    /// The tick a device sends itself, so `device_address` is the address of the simulated
+    /// device.
+    pub fn tick(device_address: u16) -> Self {
+        Self {
+            event_code: INTERNAL_SYSTEM_TICK_EVENT_CODE,
+            producer_address: device_address,
+            data: EventData::None,
+        }
+    }
+
+    pub fn message(producer_address: u16, code: u16, value: MessageValue) -> Self {
+        Self {
+            event_code: MESSAGE_EVENT_CODE,
+            producer_address,
+            data: EventData::Message { code, value },
+        }
+    }
+}
+
+/// An event sent by the simulated device.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Packet {
+    pub receiver_address: u16,
+    pub event: Event,
+}
+
+/// The outcome of feeding a single event to the simulated device.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Step {
+    pub packets: Vec<Packet>,
+    /// The state after the event, by index.
+    pub state: BTreeMap<u32, Value>,
+}
+
+/// An event processor that matched an event.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Match {
+    /// The index of the event processor in the config.
+    pub event_processor: usize,
+    /// The indices of the creators of the event processor that ran, whether they sent a packet
+    /// or not.
+    pub creators: Vec<usize>,
+    pub packets: Vec<Packet>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SimulationError {
+    /// The extractor, filter or producer does not exist in the DSL.
+    UnknownItem(String),
+    /// The item exists, but its arguments do not have the types it takes.
+    UnexpectedArguments(String),
+    /// The item exists, but its outcome cannot be known on the host.
+    UnsupportedItem(String),
+}
+
+impl Display for SimulationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationError::UnknownItem(name) => write!(f, "unknown item {}", name),
+            SimulationError::UnexpectedArguments(name) => {
+                write!(f, "unexpected arguments of {}", name)
+            }
+            SimulationError::UnsupportedItem(name) => write!(f, "{} cannot be simulated", name),
+        }
+    }
+}
+
+impl Error for SimulationError {}
+
+/// Feeds `events` in order to a device at `device_address` that runs `config`, returning what
+/// happens at each of them.
+pub fn simulate(
+    config: &Config,
+    device_address: u16,
+    events: &[Event],
+) -> Result<Vec<Step>, SimulationError> {
+    Simulator::new(config, device_address).run(events)
+}
+
+/// Runs the event processors of a `Config` on the host, the way a device at `device_address`
+/// would. Filters and producers run in the order the device runs them, so that those changing
+/// the state see the changes of earlier ones.
+pub struct Simulator<'a> {
+    config: &'a Config,
+    device_address: u16,
+    state: BTreeMap<u32, Value>,
+}
+
+/// What an extractor gets out of an event.
+#[derive(Debug, PartialEq)]
+enum Extracted {
+    None,
+    Packet,
+    Value(Value),
+}
+
+impl<'a> Simulator<'a> {
+    pub fn new(config: &'a Config, device_address: u16) -> Self {
+        Self {
+            config,
+            device_address,
+            state: config.initial_state.clone(),
+        }
+    }
+
+    pub fn state(&self) -> &BTreeMap<u32, Value> {
+        &self.state
+    }
+
+    pub fn run(&mut self, events: &[Event]) -> Result<Vec<Step>, SimulationError> {
+        events
+            .iter()
+            .map(|event| {
+                Ok(Step {
+                    packets: self.step(event)?,
+                    state: self.state.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Runs every event processor on `event`, returning the packets they send.
+    pub fn step(&mut self, event: &Event) -> Result<Vec<Packet>, SimulationError> {
+        Ok(self
+            .step_matches(event)?
+            .into_iter()
+            .flat_map(|matched| matched.packets)
+            .collect())
+    }
+
+    /// Same as `step`, but returns the event processors that matched `event`, each with the
+    /// packets it sends.
+    pub fn step_matches(&mut self, event: &Event) -> Result<Vec<Match>, SimulationError> {
+        let config = self.config;
+        let mut matches = vec![];
+
+        for (index, event_processor) in config.event_processors.iter().enumerate() {
+            if !self.matches(&event_processor.matcher, event)? {
+                continue;
+            }
+
+            let mut creators = vec![];
+            let mut packets = vec![];
+
+            for (creator_index, creator) in event_processor.creators.iter().enumerate() {
+                if let Some(matcher) = &creator.matcher {
+                    if !self.matches(matcher, event)? {
+                        continue;
+                    }
+                }
+
+                if let Some(extracted) = self.extract(&creator.extractor, event)? {
+                    creators.push(creator_index);
+                    packets.extend(self.produce(&creator.producer, &extracted, event)?);
+                }
+            }
+
+            matches.push(Match {
+                event_processor: index,
+                creators,
+                packets,
+            });
+        }
+
+        Ok(matches)
+    }
+
+    /// Returns what `producer` sends in response to `event` with the current state, which is
+    /// nothing for a `NoneProducer`.
+    pub(crate) fn expected_packet(
+        &self,
+        producer: &dyn Debug,
+        event: &Event,
+    ) -> Result<Option<Packet>, SimulationError> {
+        self.produce(producer, &Extracted::Packet, event)
+    }
+
+    fn matches(&mut self, matcher: &Matcher, event: &Event) -> Result<bool, SimulationError> {
+        match matcher {
+            Matcher::Single { extractor, filter } => match self.extract(extractor, event)? {
+                Some(extracted) => self.filter(filter, &extracted),
+                None => Ok(false),
+            },
+            Matcher::Not(matcher) => Ok(!self.matches(matcher, event)?),
+            Matcher::Or(left, right) => {
+                Ok(self.matches(left, event)? || self.matches(right, event)?)
+            }
+            Matcher::And(left, right) => {
+                Ok(self.matches(left, event)? && self.matches(right, event)?)
+            }
+        }
+    }
+
+    /// Returns nothing if the extractor does not apply to `event`, like a `ButtonIndexExtractor`
+    /// to a tick.
+    fn extract(
+        &self,
+        extractor: &dyn Debug,
+        event: &Event,
+    ) -> Result<Option<Extracted>, SimulationError> {
+        let name = item(extractor)?.name;
+
+        let extracted = match (name.as_str(), &event.data) {
+            ("NoneExtractor", _) => Extracted::None,
+            ("PacketExtractor", _) => Extracted::Packet,
+            ("EventCodeExtractor", _) => Extracted::Value(Value::U16(event.event_code)),
+            ("EventProducerAddressExtractor", _) => {
+                Extracted::Value(Value::U16(event.producer_address))
+            }
+            ("MessageCodeExtractor", EventData::Message { code, .. }) => {
+                Extracted::Value(Value::U16(*code))
+            }
+            ("MessageValueExtractor", EventData::Message { value, .. }) => {
+                Extracted::Value(match *value {
+                    MessageValue::U8(value) => Value::U8(value),
+                    MessageValue::U16(value) => Value::U16(value),
+                    MessageValue::U32(value) => Value::U32(value),
+                    MessageValue::Bool(value) => Value::Bool(value),
+                })
+            }
+            ("ButtonIndexExtractor", EventData::Button { index }) => {
+                Extracted::Value(Value::U8(*index))
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(extracted))
+    }
+
+    fn filter(
+        &mut self,
+        filter: &dyn Debug,
+        extracted: &Extracted,
+    ) -> Result<bool, SimulationError> {
+        let DecodedItem { name, arguments } = item(filter)?;
+        let unknown = || SimulationError::UnknownItem(name.clone());
+        let unexpected = || SimulationError::UnexpectedArguments(name.clone());
+        let extracted = match extracted {
+            Extracted::Value(value) => Some(*value),
+            _ => None,
+        };
+
+        let result = match (name.as_str(), arguments.as_slice()) {
+            ("ValueEqualToConstFilter", [value]) => {
+                extracted == Some(Value::try_from(value.clone()).map_err(|_| unexpected())?)
+            }
+            ("StateEqualToConstFilter", [index, value]) => {
+                let value = Value::try_from(value.clone()).map_err(|_| unexpected())?;
+
+                self.state_value(index) == Some(value)
+            }
+            ("StateEqualToValueFilter", [index]) => {
+                extracted.is_some() && self.state_value(index) == extracted
+            }
+            ("StateEqualToStateFilter", [index, other]) => {
+                let value = self.state_value(index);
+
+                value.is_some() && value == self.state_value(other)
+            }
+            ("StateMoreThanConstFilter", [index, value])
+            | ("StateLessThatConstFilter", [index, value]) => {
+                let value = Value::try_from(value.clone()).map_err(|_| unexpected())?;
+                let expected = match name.as_str() {
+                    "StateMoreThanConstFilter" => Ordering::Greater,
+                    _ => Ordering::Less,
+                };
+
+                self.state_value(index)
+                    .and_then(|state_value| compare(state_value, value))
+                    == Some(expected)
+            }
+            ("SetStateToConstFilter", [index, value]) => {
+                let value = Value::try_from(value.clone()).map_err(|_| unexpected())?;
+
+                self.set_state_value(index, Some(value))
+            }
+            ("SetStateToValueFilter", [index]) => self.set_state_value(index, extracted),
+            ("SetStateToStateFilter", [index, other]) => {
+                let value = self.state_value(other);
+
+                self.set_state_value(index, value)
+            }
+            ("FlipStateFilter", [index]) => {
+                let value = match self.state_value(index) {
+                    Some(Value::Bool(value)) => Some(Value::Bool(!value)),
+                    _ => None,
+                };
+
+                self.set_state_value(index, value)
+            }
+            ("IncrementStateByConstFilter", [index, amount])
+            | ("DecrementStateByConstFilter", [index, amount]) => {
+                let amount = Value::try_from(amount.clone()).map_err(|_| unexpected())?;
+                let value = self
+                    .state_value(index)
+                    .and_then(|value| add(value, amount, name.starts_with("Decrement")));
+
+                self.set_state_value(index, value)
+            }
+            ("IncrementStateByValueFilter", [index]) | ("DecrementStateByValueFilter", [index]) => {
+                let value = self
+                    .state_value(index)
+                    .zip(extracted)
+                    .and_then(|(value, amount)| add(value, amount, name.starts_with("Decrement")));
+
+                self.set_state_value(index, value)
+            }
+            ("TimeMatchesCronExpressionFilter", _) => {
+                return Err(SimulationError::UnsupportedItem(name))
+            }
+            _ => return Err(unknown()),
+        };
+
+        Ok(result)
+    }
+
+    fn produce(
+        &self,
+        producer: &dyn Debug,
+        extracted: &Extracted,
+        event: &Event,
+    ) -> Result<Option<Packet>, SimulationError> {
+        let DecodedItem { name, arguments } = item(producer)?;
+        let unknown = || SimulationError::UnknownItem(name.clone());
+        let unexpected = || SimulationError::UnexpectedArguments(name.clone());
+        let literal = |literal: &Literal| literal.clone();
+
+        let (receiver_address, event_code, data) = match (name.as_str(), arguments.as_slice()) {
+            ("NoneProducer", []) => return Ok(None),
+            ("PacketProducer", [receiver_address]) => {
+                return match extracted {
+                    Extracted::Packet => Ok(Some(Packet {
+                        receiver_address: u16::try_from(literal(receiver_address))
+                            .map_err(|_| unexpected())?,
+                        event: event.clone(),
+                    })),
+                    _ => Ok(None),
+                };
+            }
+            ("MessageProducer", [receiver_address, code, value]) => (
+                receiver_address,
+                MESSAGE_EVENT_CODE,
+                EventData::Message {
+                    code: u16::try_from(literal(code)).map_err(|_| unexpected())?,
+                    value: MessageValue::try_from(literal(value)).map_err(|_| unexpected())?,
+                },
+            ),
+            ("BcmChangeBrightnessProducer", [receiver_address, index, value]) => (
+                receiver_address,
+                BCM_CHANGE_BRIGHTNESS_EVENT_CODE,
+                EventData::BcmChangeBrightness {
+                    index: u8::try_from(literal(index)).map_err(|_| unexpected())?,
+                    value: BcmValue::try_from(literal(value)).map_err(|_| unexpected())?,
+                },
+            ),
+            ("BcmChangeBrightnessStateProducer", [receiver_address, index, state_index]) => {
+                let value = match self.state_value(state_index).and_then(bcm_value) {
+                    Some(value) => value,
+                    None => return Ok(None),
+                };
+
+                (
+                    receiver_address,
+                    BCM_CHANGE_BRIGHTNESS_EVENT_CODE,
+                    EventData::BcmChangeBrightness {
+                        index: u8::try_from(literal(index)).map_err(|_| unexpected())?,
+                        value,
+                    },
+                )
+            }
+            ("BcmAnimateBrightnessProducer", [receiver_address, index, duration, value]) => (
+                receiver_address,
+                BCM_ANIMATE_BRIGHTNESS_EVENT_CODE,
+                EventData::BcmAnimateBrightness {
+                    index: u8::try_from(literal(index)).map_err(|_| unexpected())?,
+                    duration: u32::try_from(literal(duration)).map_err(|_| unexpected())?,
+                    value: BcmValue::try_from(literal(value)).map_err(|_| unexpected())?,
+                },
+            ),
+            (
+                "BcmAnimateBrightnessStateProducer",
+                [receiver_address, index, duration, state_index],
+            ) => {
+                let value = match self.state_value(state_index).and_then(bcm_value) {
+                    Some(value) => value,
+                    None => return Ok(None),
+                };
+
+                (
+                    receiver_address,
+                    BCM_ANIMATE_BRIGHTNESS_EVENT_CODE,
+                    EventData::BcmAnimateBrightness {
+                        index: u8::try_from(literal(index)).map_err(|_| unexpected())?,
+                        duration: u32::try_from(literal(duration)).map_err(|_| unexpected())?,
+                        value,
+                    },
+                )
+            }
+            ("RelaySetValueProducer", [receiver_address, index, value]) => (
+                receiver_address,
+                RELAY_SET_VALUE_EVENT_CODE,
+                EventData::RelaySetValue {
+                    index: u8::try_from(literal(index)).map_err(|_| unexpected())?,
+                    value: RelayValue::try_from(literal(value)).map_err(|_| unexpected())?,
+                },
+            ),
+            _ => return Err(unknown()),
+        };
+
+        Ok(Some(Packet {
+            receiver_address: u16::try_from(literal(receiver_address)).map_err(|_| unexpected())?,
+            event: Event {
+                event_code,
+                producer_address: self.device_address,
+                data,
+            },
+        }))
+    }
+
+    fn state_value(&self, index: &Literal) -> Option<Value> {
+        let index = u32::try_from(index.clone()).ok()?;
+
+        self.state.get(&index).copied()
+    }
+
+    /// Sets the state at `index` to `value`, returning whether there was a value to set.
+    fn set_state_value(&mut self, index: &Literal, value: Option<Value>) -> bool {
+        match (u32::try_from(index.clone()), value) {
+            (Ok(index), Some(value)) => {
+                self.state.insert(index, value);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn item(item: &dyn Debug) -> Result<DecodedItem, SimulationError> {
+    decode_item(item).map_err(|err| match err {
+        DecodeError::UnknownItem(name) => SimulationError::UnknownItem(name),
+        DecodeError::UnexpectedArguments(name) => SimulationError::UnexpectedArguments(name),
+    })
+}
+
+/// Adds or subtracts values of the same integer type, wrapping around like the device does.
+fn add(value: Value, amount: Value, subtract: bool) -> Option<Value> {
+    match (value, amount) {
+        (Value::U8(value), Value::U8(amount)) if subtract => {
+            Some(Value::U8(value.wrapping_sub(amount)))
+        }
+        (Value::U8(value), Value::U8(amount)) => Some(Value::U8(value.wrapping_add(amount))),
+        (Value::U16(value), Value::U16(amount)) if subtract => {
+            Some(Value::U16(value.wrapping_sub(amount)))
+        }
+        (Value::U16(value), Value::U16(amount)) => Some(Value::U16(value.wrapping_add(amount))),
+        (Value::U32(value), Value::U32(amount)) if subtract => {
+            Some(Value::U32(value.wrapping_sub(amount)))
+        }
+        (Value::U32(value), Value::U32(amount)) => Some(Value::U32(value.wrapping_add(amount))),
+        _ => None,
+    }
+}
+
+fn compare(left: Value, right: Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::U8(left), Value::U8(right)) => Some(left.cmp(&right)),
+        (Value::U16(left), Value::U16(right)) => Some(left.cmp(&right)),
+        (Value::U32(left), Value::U32(right)) => Some(left.cmp(&right)),
+        _ => None,
+    }
+}
+
+fn bcm_value(value: Value) -> Option<BcmValue> {
+    match value {
+        Value::U8(value) => Some(BcmValue::Single(value)),
+        Value::Rgb(r, g, b) => Some(BcmValue::Rgb(r, g, b)),
+        Value::Rgbw(r, g, b, w) => Some(BcmValue::Rgbw(r, g, b, w)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+    use ross_protocol::event::relay::RelayDoubleExclusiveValue;
+
+    use crate::extractor::create_extractor;
+    use crate::filter::create_filter;
+    use crate::parser::Parser;
+    use crate::producer::create_producer;
+
+    const DEVICE_ADDRESS: u16 = 0x0001;
+
+    fn run(text: &str, events: &[Event]) -> Vec<Step> {
+        simulate(&Parser::parse(text).unwrap(), DEVICE_ADDRESS, events).unwrap()
+    }
+
+    fn bcm_packet(value: BcmValue) -> Packet {
+        Packet {
+            receiver_address: 0x0003,
+            event: Event {
+                event_code: BCM_CHANGE_BRIGHTNESS_EVENT_CODE,
+                producer_address: DEVICE_ADDRESS,
+                data: EventData::BcmChangeBrightness { index: 0, value },
+            },
+        }
+    }
+
+    fn config(state: &[Value]) -> Config {
+        let mut config = Parser::parse("").unwrap();
+        config.initial_state = (0..).zip(state.iter().copied()).collect();
+
+        config
+    }
+
+    fn extract(extractor: &str, event: &Event) -> Option<Extracted> {
+        let config = config(&[]);
+        let extractor = create_extractor(extractor, &[], (), &[]).unwrap();
+
+        Simulator::new(&config, DEVICE_ADDRESS)
+            .extract(&extractor, event)
+            .unwrap()
+    }
+
+    /// Runs `filter` on a device with `state`, on what an extractor got out of an event, and
+    /// returns its result with the state after it.
+    fn filter(
+        state: &[Value],
+        filter: &str,
+        arguments: &[Literal],
+        extracted: Extracted,
+    ) -> (bool, Vec<Value>) {
+        let config = config(state);
+        let filter = create_filter(filter, arguments, (), &vec![(); arguments.len()]).unwrap();
+        let mut simulator = Simulator::new(&config, DEVICE_ADDRESS);
+        let result = simulator.filter(&filter, &extracted).unwrap();
+
+        (result, simulator.state().values().copied().collect())
+    }
+
+    /// Runs `producer` on a device with `state`, on what a `PacketExtractor` got out of a button
+    /// press.
+    fn produce(state: &[Value], producer: &str, arguments: &[Literal]) -> Option<Packet> {
+        let config = config(state);
+        let producer =
+            create_producer(producer, arguments, (), &vec![(); arguments.len()]).unwrap();
+
+        Simulator::new(&config, DEVICE_ADDRESS)
+            .produce(
+                &producer,
+                &Extracted::Packet,
+                &Event::button_pressed(0x0002, 0),
+            )
+            .unwrap()
+    }
+
+    fn packet(event_code: u16, data: EventData) -> Option<Packet> {
+        Some(Packet {
+            receiver_address: 0x0003,
+            event: Event {
+                event_code,
+                producer_address: DEVICE_ADDRESS,
+                data,
+            },
+        })
+    }
+
+    #[test]
+    fn fire_if_match_test() {
+        let text = "let active = false;
+            do {
+                match event BUTTON_PRESSED_EVENT_CODE;
+                match producer 0x0002~u16;
+                match { FlipStateFilter(active); }
+                fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0xff~u8); } if match {
+                    StateEqualToConstFilter(active, true);
+                }
+                fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0x00~u8); } if match {
+                    StateEqualToConstFilter(active, false);
+                }
+            }";
+        let steps = run(
+            text,
+            &[
+                Event::button_pressed(0x0002, 0),
+                Event::button_released(0x0002, 0),
+                Event::button_pressed(0x0004, 0),
+                Event::button_pressed(0x0002, 0),
+            ],
+        );
+
+        assert_eq!(
+            steps,
+            vec![
+                Step {
+                    packets: vec![bcm_packet(BcmValue::Single(0xff))],
+                    state: BTreeMap::from([(0, Value::Bool(true))]),
+                },
+                Step {
+                    packets: vec![],
+                    state: BTreeMap::from([(0, Value::Bool(true))]),
+                },
+                Step {
+                    packets: vec![],
+                    state: BTreeMap::from([(0, Value::Bool(true))]),
+                },
+                Step {
+                    packets: vec![bcm_packet(BcmValue::Single(0x00))],
+                    state: BTreeMap::from([(0, Value::Bool(false))]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn send_test() {
+        let text = "send BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 to 0x0003~u16;";
+        let event = Event::button_pressed(0x0002, 1);
+
+        assert_eq!(
+            run(text, &[event.clone(), Event::tick(DEVICE_ADDRESS)])
+                .into_iter()
+                .map(|step| step.packets)
+                .collect::<Vec<_>>(),
+            vec![
+                vec![Packet {
+                    receiver_address: 0x0003,
+                    event,
+                }],
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn state_test() {
+        let text = "let count = 0xfe~u8;
+            let level = 0x00~u8;
+            do {
+                match tick;
+                match { IncrementStateByConstFilter(count, 0x01~u8); }
+                fire { BcmChangeBrightnessStateProducer(0x0003~u16, 0~u8, count); }
+            }
+            do {
+                match event MESSAGE_EVENT_CODE;
+                match { MessageValueExtractor(); SetStateToValueFilter(level); }
+                match { StateLessThatConstFilter(level, 0x10~u8); }
+                fire { MessageProducer(0x0003~u16, 0x0001~u16, 0x01~u8); }
+            }";
+        let steps = run(
+            text,
+            &[
+                Event::tick(DEVICE_ADDRESS),
+                Event::tick(DEVICE_ADDRESS),
+                Event::message(0x0002, 0x0000, MessageValue::U8(0x20)),
+                Event::message(0x0002, 0x0000, MessageValue::U8(0x05)),
+            ],
+        );
+
+        assert_eq!(steps[0].packets, vec![bcm_packet(BcmValue::Single(0xff))]);
+        assert_eq!(steps[1].packets, vec![bcm_packet(BcmValue::Single(0x00))]);
+        assert_eq!(steps[2].packets, vec![]);
+        assert_eq!(steps[2].state[&1], Value::U8(0x20));
+        assert_eq!(
+            steps[3].packets,
+            vec![Packet {
+                receiver_address: 0x0003,
+                event: Event {
+                    event_code: MESSAGE_EVENT_CODE,
+                    producer_address: DEVICE_ADDRESS,
+                    data: EventData::Message {
+                        code: 0x0001,
+                        value: MessageValue::U8(0x01),
+                    },
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn short_circuit_test() {
+        let text = "let a = false;
+            let b = false;
+            do { match or { { FlipStateFilter(a); }, { FlipStateFilter(b); } } }";
+        let steps = run(text, &[Event::tick(DEVICE_ADDRESS)]);
+
+        assert_eq!(
+            steps[0].state,
+            BTreeMap::from([(0, Value::Bool(true)), (1, Value::Bool(false))])
+        );
+    }
+
+    #[test]
+    fn unsupported_item_test() {
+        let config =
+            Parser::parse("do { match { TimeMatchesCronExpressionFilter(\"0 0 8 * * *\"); } }")
+                .unwrap();
+
+        assert_matches!(
+            simulate(&config, DEVICE_ADDRESS, &[Event::tick(DEVICE_ADDRESS)]),
+            Err(SimulationError::UnsupportedItem(name)) => {
+                assert_eq!(name, "TimeMatchesCronExpressionFilter");
+            }
+        );
+    }
+
+    #[test]
+    fn none_extractor_test() {
+        assert_eq!(
+            extract("NoneExtractor", &Event::tick(DEVICE_ADDRESS)),
+            Some(Extracted::None)
+        );
+    }
+
+    #[test]
+    fn packet_extractor_test() {
+        assert_eq!(
+            extract("PacketExtractor", &Event::tick(DEVICE_ADDRESS)),
+            Some(Extracted::Packet)
+        );
+    }
+
+    #[test]
+    fn event_code_extractor_test() {
+        assert_eq!(
+            extract("EventCodeExtractor", &Event::button_pressed(0x0002, 0)),
+            Some(Extracted::Value(Value::U16(BUTTON_PRESSED_EVENT_CODE)))
+        );
+    }
+
+    #[test]
+    fn event_producer_address_extractor_test() {
+        assert_eq!(
+            extract(
+                "EventProducerAddressExtractor",
+                &Event::button_pressed(0x0002, 0)
+            ),
+            Some(Extracted::Value(Value::U16(0x0002)))
+        );
+    }
+
+    #[test]
+    fn message_code_extractor_test() {
+        assert_eq!(
+            extract(
+                "MessageCodeExtractor",
+                &Event::message(0x0002, 0x0100, MessageValue::U8(0x01))
+            ),
+            Some(Extracted::Value(Value::U16(0x0100)))
+        );
+        assert_eq!(
+            extract("MessageCodeExtractor", &Event::button_pressed(0x0002, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn message_value_extractor_test() {
+        let values = [
+            (MessageValue::U8(0x01), Value::U8(0x01)),
+            (MessageValue::U16(0x0100), Value::U16(0x0100)),
+            (MessageValue::U32(0x0001_0000), Value::U32(0x0001_0000)),
+            (MessageValue::Bool(true), Value::Bool(true)),
+        ];
+
+        for (message_value, value) in values {
+            assert_eq!(
+                extract(
+                    "MessageValueExtractor",
+                    &Event::message(0x0002, 0x0000, message_value)
+                ),
+                Some(Extracted::Value(value))
+            );
+        }
+
+        assert_eq!(
+            extract("MessageValueExtractor", &Event::tick(DEVICE_ADDRESS)),
+            None
+        );
+    }
+
+    #[test]
+    fn button_index_extractor_test() {
+        assert_eq!(
+            extract("ButtonIndexExtractor", &Event::button_released(0x0002, 3)),
+            Some(Extracted::Value(Value::U8(3)))
+        );
+        assert_eq!(
+            extract(
+                "ButtonIndexExtractor",
+                &Event::message(0x0002, 0x0000, MessageValue::U8(3))
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn value_equal_to_const_filter_test() {
+        let run = |extracted| filter(&[], "ValueEqualToConstFilter", &[Literal::U8(1)], extracted);
+
+        assert!(run(Extracted::Value(Value::U8(1))).0);
+        assert!(!run(Extracted::Value(Value::U8(2))).0);
+        assert!(!run(Extracted::Value(Value::U16(1))).0);
+        assert!(!run(Extracted::None).0);
+    }
+
+    #[test]
+    fn state_equal_to_const_filter_test() {
+        let run = |index, value| {
+            filter(
+                &[Value::U8(1)],
+                "StateEqualToConstFilter",
+                &[Literal::U32(index), value],
+                Extracted::None,
+            )
+            .0
+        };
+
+        assert!(run(0, Literal::U8(1)));
+        assert!(!run(0, Literal::U8(2)));
+        assert!(!run(0, Literal::U16(1)));
+        assert!(!run(1, Literal::U8(1)));
+    }
+
+    #[test]
+    fn state_equal_to_value_filter_test() {
+        let run = |extracted| {
+            filter(
+                &[Value::U8(1)],
+                "StateEqualToValueFilter",
+                &[Literal::U32(0)],
+                extracted,
+            )
+            .0
+        };
+
+        assert!(run(Extracted::Value(Value::U8(1))));
+        assert!(!run(Extracted::Value(Value::U8(2))));
+        assert!(!run(Extracted::Value(Value::U16(1))));
+        assert!(!run(Extracted::None));
+    }
+
+    #[test]
+    fn state_equal_to_state_filter_test() {
+        let run = |other| {
+            filter(
+                &[Value::U8(1), Value::U8(1), Value::U16(1)],
+                "StateEqualToStateFilter",
+                &[Literal::U32(0), Literal::U32(other)],
+                Extracted::None,
+            )
+            .0
+        };
+
+        assert!(run(1));
+        assert!(!run(2));
+        assert!(!run(3));
+    }
+
+    #[test]
+    fn state_more_than_const_filter_test() {
+        let run = |state, value| {
+            filter(
+                &[state],
+                "StateMoreThanConstFilter",
+                &[Literal::U32(0), value],
+                Extracted::None,
+            )
+            .0
+        };
+
+        assert!(run(Value::U8(5), Literal::U8(4)));
+        assert!(!run(Value::U8(5), Literal::U8(5)));
+        assert!(!run(Value::U8(5), Literal::U16(4)));
+        assert!(!run(Value::Bool(true), Literal::Bool(false)));
+    }
+
+    #[test]
+    fn state_less_that_const_filter_test() {
+        let run = |state, value| {
+            filter(
+                &[state],
+                "StateLessThatConstFilter",
+                &[Literal::U32(0), value],
+                Extracted::None,
+            )
+            .0
+        };
+
+        assert!(run(Value::U32(4), Literal::U32(5)));
+        assert!(!run(Value::U32(5), Literal::U32(5)));
+        assert!(!run(Value::U32(4), Literal::U8(5)));
+        assert!(!run(Value::Rgb(0, 0, 0), Literal::Rgb(1, 1, 1)));
+    }
+
+    #[test]
+    fn set_state_to_const_filter_test() {
+        assert_eq!(
+            filter(
+                &[Value::U8(0)],
+                "SetStateToConstFilter",
+                &[Literal::U32(0), Literal::U8(7)],
+                Extracted::None,
+            ),
+            (true, vec![Value::U8(7)])
+        );
+    }
+
+    #[test]
+    fn set_state_to_value_filter_test() {
+        let run = |extracted| {
+            filter(
+                &[Value::U16(0)],
+                "SetStateToValueFilter",
+                &[Literal::U32(0)],
+                extracted,
+            )
+        };
+
+        assert_eq!(
+            run(Extracted::Value(Value::U16(3))),
+            (true, vec![Value::U16(3)])
+        );
+        assert_eq!(run(Extracted::None), (false, vec![Value::U16(0)]));
+    }
+
+    #[test]
+    fn set_state_to_state_filter_test() {
+        let run = |other| {
+            filter(
+                &[Value::U8(0), Value::U8(9)],
+                "SetStateToStateFilter",
+                &[Literal::U32(0), Literal::U32(other)],
+                Extracted::None,
+            )
+        };
+
+        assert_eq!(run(1), (true, vec![Value::U8(9), Value::U8(9)]));
+        assert_eq!(run(2), (false, vec![Value::U8(0), Value::U8(9)]));
+    }
+
+    #[test]
+    fn flip_state_filter_test() {
+        let run = |state| {
+            filter(
+                &[state],
+                "FlipStateFilter",
+                &[Literal::U32(0)],
+                Extracted::None,
+            )
+        };
+
+        assert_eq!(run(Value::Bool(false)), (true, vec![Value::Bool(true)]));
+        assert_eq!(run(Value::U8(0)), (false, vec![Value::U8(0)]));
+    }
+
+    #[test]
+    fn increment_state_by_const_filter_test() {
+        let run = |state, amount| {
+            filter(
+                &[state],
+                "IncrementStateByConstFilter",
+                &[Literal::U32(0), amount],
+                Extracted::None,
+            )
+        };
+
+        assert_eq!(
+            run(Value::U16(1), Literal::U16(2)),
+            (true, vec![Value::U16(3)])
+        );
+        assert_eq!(
+            run(Value::U8(0xff), Literal::U8(1)),
+            (true, vec![Value::U8(0x00)])
+        );
+        assert_eq!(
+            run(Value::U8(1), Literal::U16(1)),
+            (false, vec![Value::U8(1)])
+        );
+    }
+
+    #[test]
+    fn increment_state_by_value_filter_test() {
+        let run = |extracted| {
+            filter(
+                &[Value::U32(u32::MAX)],
+                "IncrementStateByValueFilter",
+                &[Literal::U32(0)],
+                extracted,
+            )
+        };
+
+        assert_eq!(
+            run(Extracted::Value(Value::U32(2))),
+            (true, vec![Value::U32(1)])
+        );
+        assert_eq!(
+            run(Extracted::Value(Value::U8(2))),
+            (false, vec![Value::U32(u32::MAX)])
+        );
+        assert_eq!(run(Extracted::None), (false, vec![Value::U32(u32::MAX)]));
+    }
+
+    #[test]
+    fn decrement_state_by_const_filter_test() {
+        let run = |state, amount| {
+            filter(
+                &[state],
+                "DecrementStateByConstFilter",
+                &[Literal::U32(0), amount],
+                Extracted::None,
+            )
+        };
+
+        assert_eq!(
+            run(Value::U32(5), Literal::U32(2)),
+            (true, vec![Value::U32(3)])
+        );
+        assert_eq!(
+            run(Value::U8(0x00), Literal::U8(1)),
+            (true, vec![Value::U8(0xff)])
+        );
+        assert_eq!(
+            run(Value::Bool(true), Literal::Bool(true)),
+            (false, vec![Value::Bool(true)])
+        );
+    }
+
+    #[test]
+    fn decrement_state_by_value_filter_test() {
+        let run = |extracted| {
+            filter(
+                &[Value::U16(0)],
+                "DecrementStateByValueFilter",
+                &[Literal::U32(0)],
+                extracted,
+            )
+        };
+
+        assert_eq!(
+            run(Extracted::Value(Value::U16(1))),
+            (true, vec![Value::U16(0xffff)])
+        );
+        assert_eq!(
+            run(Extracted::Value(Value::U32(1))),
+            (false, vec![Value::U16(0)])
+        );
+        assert_eq!(run(Extracted::None), (false, vec![Value::U16(0)]));
+    }
+
+    #[test]
+    fn none_producer_test() {
+        assert_eq!(produce(&[], "NoneProducer", &[]), None);
+    }
+
+    #[test]
+    fn packet_producer_test() {
+        let config = config(&[]);
+        let producer =
+            create_producer("PacketProducer", &[Literal::U16(0x0003)], (), &[()]).unwrap();
+        let simulator = Simulator::new(&config, DEVICE_ADDRESS);
+        let event = Event::button_pressed(0x0002, 0);
+
+        assert_eq!(
+            simulator.produce(&producer, &Extracted::Packet, &event),
+            Ok(Some(Packet {
+                receiver_address: 0x0003,
+                event: event.clone(),
+            }))
+        );
+        assert_eq!(
+            simulator.produce(&producer, &Extracted::None, &event),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn message_producer_test() {
+        assert_eq!(
+            produce(
+                &[],
+                "MessageProducer",
+                &[
+                    Literal::U16(0x0003),
+                    Literal::U16(0x0100),
+                    Literal::Bool(true)
+                ]
+            ),
+            packet(
+                MESSAGE_EVENT_CODE,
+                EventData::Message {
+                    code: 0x0100,
+                    value: MessageValue::Bool(true),
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn bcm_change_brightness_producer_test() {
+        assert_eq!(
+            produce(
+                &[],
+                "BcmChangeBrightnessProducer",
+                &[Literal::U16(0x0003), Literal::U8(1), Literal::Rgb(1, 2, 3)]
+            ),
+            packet(
+                BCM_CHANGE_BRIGHTNESS_EVENT_CODE,
+                EventData::BcmChangeBrightness {
+                    index: 1,
+                    value: BcmValue::Rgb(1, 2, 3),
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn bcm_change_brightness_state_producer_test() {
+        let run = |state| {
+            produce(
+                &[state],
+                "BcmChangeBrightnessStateProducer",
+                &[Literal::U16(0x0003), Literal::U8(1), Literal::U32(0)],
+            )
+        };
+
+        assert_eq!(
+            run(Value::Rgbw(1, 2, 3, 4)),
+            packet(
+                BCM_CHANGE_BRIGHTNESS_EVENT_CODE,
+                EventData::BcmChangeBrightness {
+                    index: 1,
+                    value: BcmValue::Rgbw(1, 2, 3, 4),
+                }
+            )
+        );
+        assert_eq!(run(Value::Bool(true)), None);
+    }
+
+    #[test]
+    fn bcm_animate_brightness_producer_test() {
+        assert_eq!(
+            produce(
+                &[],
+                "BcmAnimateBrightnessProducer",
+                &[
+                    Literal::U16(0x0003),
+                    Literal::U8(0),
+                    Literal::U32(500),
+                    Literal::U8(0x80)
+                ]
+            ),
+            packet(
+                BCM_ANIMATE_BRIGHTNESS_EVENT_CODE,
+                EventData::BcmAnimateBrightness {
+                    index: 0,
+                    duration: 500,
+                    value: BcmValue::Single(0x80),
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn bcm_animate_brightness_state_producer_test() {
+        let run = |state| {
+            produce(
+                &[state],
+                "BcmAnimateBrightnessStateProducer",
+                &[
+                    Literal::U16(0x0003),
+                    Literal::U8(0),
+                    Literal::U32(500),
+                    Literal::U32(0),
+                ],
+            )
+        };
+
+        assert_eq!(
+            run(Value::U8(0x80)),
+            packet(
+                BCM_ANIMATE_BRIGHTNESS_EVENT_CODE,
+                EventData::BcmAnimateBrightness {
+                    index: 0,
+                    duration: 500,
+                    value: BcmValue::Single(0x80),
+                }
+            )
+        );
+        assert_eq!(run(Value::U16(0x0080)), None);
+    }
+
+    #[test]
+    fn relay_set_value_producer_test() {
+        let run = |value| {
+            produce(
+                &[],
+                "RelaySetValueProducer",
+                &[Literal::U16(0x0003), Literal::U8(1), value],
+            )
+        };
+
+        assert_eq!(
+            run(Literal::Bool(true)),
+            packet(
+                RELAY_SET_VALUE_EVENT_CODE,
+                EventData::RelaySetValue {
+                    index: 1,
+                    value: RelayValue::Single(true),
+                }
+            )
+        );
+        assert_eq!(
+            run(Literal::String("first".to_string())),
+            packet(
+                RELAY_SET_VALUE_EVENT_CODE,
+                EventData::RelaySetValue {
+                    index: 1,
+                    value: RelayValue::DoubleExclusive(RelayDoubleExclusiveValue::FirstChannelOn),
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn unexpected_arguments_test() {
+        // Only read through its `Debug` output
+        #[allow(dead_code)]
+        #[derive(Debug)]
+        struct MessageProducer {
+            receiver_address: u32,
+            code: u16,
+            value: Value,
+        }
+
+        let config = config(&[]);
+        let simulator = Simulator::new(&config, DEVICE_ADDRESS);
+        let event = Event::tick(DEVICE_ADDRESS);
+        let produce =
+            |producer: MessageProducer| simulator.produce(&producer, &Extracted::Packet, &event);
+        let expected = Err(SimulationError::UnexpectedArguments(
+            "MessageProducer".to_string(),
+        ));
+
+        assert_eq!(
+            produce(MessageProducer {
+                receiver_address: 0x0003,
+                code: 0x0001,
+                value: Value::Rgb(1, 2, 3),
+            }),
+            expected
+        );
+        assert_eq!(
+            produce(MessageProducer {
+                receiver_address: 0x0001_0000,
+                code: 0x0001,
+                value: Value::U8(1),
+            }),
+            expected
+        );
+    }
+}
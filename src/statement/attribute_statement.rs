@@ -0,0 +1,148 @@
+use nom::branch::alt;
+use nom::character::complete::multispace0;
+use nom::combinator::{consumed, cut, map, opt};
+use nom::multi::separated_list1;
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use nom::IResult;
+
+use crate::ast::{AttributeStatement, Name, Span};
+use crate::error::ParserError;
+use crate::keyword::{allow_keyword, deny_keyword, warn_keyword};
+use crate::parser::name;
+use crate::symbol::{
+    close_bracket, close_parenthesis, comma, exclamation_mark, hashtag, open_bracket,
+    open_parenthesis,
+};
+
+pub fn attribute_statement<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, AttributeStatement, ParserError<&'a str>> {
+    move |text| {
+        let level_parser = map(
+            consumed(alt((allow_keyword, warn_keyword, deny_keyword))),
+            |(consumed, level)| Name {
+                value: level.to_string(),
+                span: Span::of(source, consumed),
+            },
+        );
+        let lints_parser = delimited(
+            preceded(multispace0, open_parenthesis),
+            separated_list1(
+                preceded(multispace0, comma),
+                preceded(multispace0, name(source)),
+            ),
+            preceded(multispace0, close_parenthesis),
+        );
+        let content_parser = delimited(
+            preceded(multispace0, open_bracket),
+            pair(preceded(multispace0, level_parser), lints_parser),
+            preceded(multispace0, close_bracket),
+        );
+        let mut attribute_parser = preceded(
+            hashtag,
+            cut(tuple((
+                map(opt(terminated(exclamation_mark, multispace0)), |mark| {
+                    mark.is_some()
+                }),
+                content_parser,
+            ))),
+        );
+
+        let (input, (inner, (level, lints))) = attribute_parser(text)?;
+
+        Ok((
+            input,
+            AttributeStatement {
+                inner,
+                level,
+                lints,
+                span: Span::new(source, text, input),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+    use nom::Err as NomErr;
+
+    use crate::error::{ErrorKind, Expectation};
+
+    #[test]
+    fn inner_test() {
+        let text = "#![allow(unused_const, unused_let)]input";
+
+        assert_matches!(
+            attribute_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert!(statement.inner);
+                assert_eq!(
+                    statement.level,
+                    Name {
+                        value: "allow".to_string(),
+                        span: Span { start: 3, end: 8 },
+                    }
+                );
+                assert_eq!(
+                    statement
+                        .lints
+                        .iter()
+                        .map(|lint| lint.value.as_str())
+                        .collect::<Vec<_>>(),
+                    vec!["unused_const", "unused_let"]
+                );
+                assert_eq!(statement.span, Span { start: 0, end: 35 });
+            }
+        );
+    }
+
+    #[test]
+    fn outer_test() {
+        let text = "#[ deny( never_matches ) ]input";
+
+        assert_matches!(
+            attribute_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert!(!statement.inner);
+                assert_eq!(statement.level.value, "deny");
+                assert_eq!(statement.lints[0].value, "never_matches");
+                assert_eq!(statement.lints[0].span, Span { start: 9, end: 22 });
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_level_test() {
+        let text = "#[forbid(never_matches)]input";
+
+        assert_matches!(
+            attribute_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                kind: ErrorKind::Nom(_),
+                child: Some(_),
+                ..
+            }))
+        );
+    }
+
+    #[test]
+    fn missing_lint_test() {
+        let text = "#[allow()]input";
+
+        assert_matches!(
+            attribute_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind: ErrorKind::Expected(Expectation::Name),
+                ..
+            })) => {
+                assert_eq!(location, ")]input");
+            }
+        );
+    }
+}
@@ -3,20 +3,35 @@ use nom::combinator::cut;
 use nom::sequence::{delimited, preceded, separated_pair, terminated};
 use nom::IResult;
 
+use crate::ast::{ConstStatement, Span};
 use crate::error::ParserError;
+use crate::expression::constant_expression;
 use crate::keyword::const_keyword;
-use crate::literal::{literal, Literal};
-use crate::parser::name_parser;
+use crate::parser::name;
 use crate::symbol::{equal_sign, semicolon};
 
-pub fn const_statement(text: &str) -> IResult<&str, (&str, Literal), ParserError<&str>> {
-    let name_parser = delimited(multispace1, name_parser, multispace0);
-    let equal_sign_parser = terminated(equal_sign, multispace0);
-    let name_value_pair_parser = separated_pair(name_parser, equal_sign_parser, literal);
-    let keyword_parser = preceded(const_keyword, cut(name_value_pair_parser));
-    let mut semicolon_parser = terminated(keyword_parser, semicolon);
+pub fn const_statement<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, ConstStatement, ParserError<&'a str>> {
+    move |text| {
+        let name_parser = delimited(multispace1, name(source), multispace0);
+        let equal_sign_parser = terminated(equal_sign, multispace0);
+        let name_value_pair_parser =
+            separated_pair(name_parser, equal_sign_parser, constant_expression(source));
+        let keyword_parser = preceded(const_keyword, cut(name_value_pair_parser));
+        let mut semicolon_parser = terminated(keyword_parser, semicolon);
 
-    semicolon_parser(text)
+        let (input, (name, value)) = semicolon_parser(text)?;
+
+        Ok((
+            input,
+            ConstStatement {
+                name,
+                value,
+                span: Span::new(source, text, input),
+            },
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -26,44 +41,139 @@ mod tests {
     use cool_asserts::assert_matches;
     use nom::Err as NomErr;
 
+    use crate::ast::{Expression, Name};
     use crate::error::{ErrorKind, Expectation};
+    use crate::literal::Literal;
 
     #[test]
     fn hex_u32_test() {
+        let text = "const state = 0xabababab~u32;input";
+
         assert_matches!(
-            const_statement("const state = 0xabababab~u32;input"),
-            Ok(("input", ("state", Literal::U32(0xabab_abab)))),
+            const_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    ConstStatement {
+                        name: Name {
+                            value: "state".to_string(),
+                            span: Span { start: 6, end: 11 },
+                        },
+                        value: Expression::Literal {
+                            value: Literal::U32(0xabab_abab),
+                            span: Span { start: 14, end: 28 },
+                        },
+                        span: Span { start: 0, end: 29 },
+                    }
+                );
+            }
         );
     }
 
     #[test]
     fn bool_test() {
+        let text = "const state = false;input";
+
+        assert_matches!(
+            const_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    ConstStatement {
+                        name: Name {
+                            value: "state".to_string(),
+                            span: Span { start: 6, end: 11 },
+                        },
+                        value: Expression::Literal {
+                            value: Literal::Bool(false),
+                            span: Span { start: 14, end: 19 },
+                        },
+                        span: Span { start: 0, end: 20 },
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn expression_test() {
+        let text = "const state = base+0x01~u8;input";
+
         assert_matches!(
-            const_statement("const state = false;input"),
-            Ok(("input", ("state", Literal::Bool(false)))),
+            const_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_matches!(statement.value, Expression::Binary { left, right, span, .. } => {
+                    assert_matches!(*left, Expression::Constant(Name { value, .. }) => {
+                        assert_eq!(value, "base");
+                    });
+                    assert_matches!(*right, Expression::Literal { value: Literal::U8(0x01), .. });
+                    assert_eq!(span, Span { start: 14, end: 26 });
+                });
+            }
         );
     }
 
     #[test]
     fn weird_spacing1_test() {
+        let text = "const state=0xabababab~u32;input";
+
         assert_matches!(
-            const_statement("const state=0xabababab~u32;input"),
-            Ok(("input", ("state", Literal::U32(0xabab_abab)))),
+            const_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    ConstStatement {
+                        name: Name {
+                            value: "state".to_string(),
+                            span: Span { start: 6, end: 11 },
+                        },
+                        value: Expression::Literal {
+                            value: Literal::U32(0xabab_abab),
+                            span: Span { start: 12, end: 26 },
+                        },
+                        span: Span { start: 0, end: 27 },
+                    }
+                );
+            }
         );
     }
 
     #[test]
     fn weird_spacing2_test() {
+        let text = "const  state  =  0xabababab~u32;input";
+
         assert_matches!(
-            const_statement("const  state  =  0xabababab~u32;input"),
-            Ok(("input", ("state", Literal::U32(0xabab_abab)))),
+            const_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    ConstStatement {
+                        name: Name {
+                            value: "state".to_string(),
+                            span: Span { start: 7, end: 12 },
+                        },
+                        value: Expression::Literal {
+                            value: Literal::U32(0xabab_abab),
+                            span: Span { start: 17, end: 31 },
+                        },
+                        span: Span { start: 0, end: 32 },
+                    }
+                );
+            }
         );
     }
 
     #[test]
     fn only_keyword_test() {
+        let text = "const;input";
+
         assert_matches!(
-            const_statement("const;input"),
+            const_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location,
                 kind,
@@ -78,8 +188,10 @@ mod tests {
 
     #[test]
     fn invalid_name_test() {
+        let text = "const 1state = 0xabababab~u32;input";
+
         assert_matches!(
-            const_statement("const 1state = 0xabababab~u32;input"),
+            const_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location,
                 kind,
@@ -94,8 +206,10 @@ mod tests {
 
     #[test]
     fn wrong_keyword_test() {
+        let text = "let state = 0xabababab~u32;input";
+
         assert_matches!(
-            const_statement("let state = 0xabababab~u32;input"),
+            const_statement(text)(text),
             Err(NomErr::Error(ParserError::Base {
                 location,
                 kind,
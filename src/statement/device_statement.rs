@@ -0,0 +1,125 @@
+use nom::character::complete::{multispace0, multispace1};
+use nom::combinator::cut;
+use nom::sequence::{delimited, preceded, separated_pair, terminated};
+use nom::IResult;
+
+use crate::ast::{DeviceStatement, Span};
+use crate::error::ParserError;
+use crate::expression::constant_expression;
+use crate::keyword::device_keyword;
+use crate::parser::name;
+use crate::symbol::{equal_sign, semicolon};
+
+pub fn device_statement<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, DeviceStatement, ParserError<&'a str>> {
+    move |text| {
+        let name_parser = delimited(multispace1, name(source), multispace0);
+        let equal_sign_parser = terminated(equal_sign, multispace0);
+        let name_address_pair_parser =
+            separated_pair(name_parser, equal_sign_parser, constant_expression(source));
+        let keyword_parser = preceded(device_keyword, cut(name_address_pair_parser));
+        let mut semicolon_parser = terminated(keyword_parser, semicolon);
+
+        let (input, (name, address)) = semicolon_parser(text)?;
+
+        Ok((
+            input,
+            DeviceStatement {
+                name,
+                address,
+                span: Span::new(source, text, input),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+    use nom::Err as NomErr;
+
+    use crate::ast::{Expression, Name};
+    use crate::error::{ErrorKind, Expectation};
+    use crate::literal::Literal;
+
+    #[test]
+    fn integer_address_test() {
+        let text = "device kitchen_switch = 0x0002;input";
+
+        assert_matches!(
+            device_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    DeviceStatement {
+                        name: Name {
+                            value: "kitchen_switch".to_string(),
+                            span: Span { start: 7, end: 21 },
+                        },
+                        address: Expression::Literal {
+                            value: Literal::Integer(0x0002),
+                            span: Span { start: 24, end: 30 },
+                        },
+                        span: Span { start: 0, end: 31 },
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn expression_test() {
+        let text = "device kitchen_light = base_address + 1;input";
+
+        assert_matches!(
+            device_statement(text)(text),
+            Ok((
+                "input",
+                DeviceStatement {
+                    address: Expression::Binary { .. },
+                    ..
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn missing_address_test() {
+        let text = "device kitchen_switch;input";
+
+        assert_matches!(
+            device_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind,
+                child,
+            })) => {
+                assert_matches!(location, ";input");
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Symbol('=')));
+                assert_matches!(child, None);
+            },
+        );
+    }
+
+    #[test]
+    fn wrong_keyword_test() {
+        let text = "const kitchen_switch = 0x0002~u16;input";
+
+        assert_matches!(
+            device_statement(text)(text),
+            Err(NomErr::Error(ParserError::Base {
+                location,
+                kind,
+                child,
+            })) => {
+                assert_matches!(location, "const");
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Keyword("device")));
+                assert_matches!(child, None);
+            },
+        );
+    }
+}
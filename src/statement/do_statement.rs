@@ -1,56 +1,40 @@
 use nom::character::complete::multispace0;
-use nom::combinator::{cut, map};
+use nom::combinator::cut;
 use nom::multi::{many0, many1};
 use nom::sequence::{pair, preceded, terminated};
 use nom::IResult;
-use std::collections::BTreeMap;
-
-use ross_config::event_processor::EventProcessor;
-use ross_config::matcher::Matcher;
 
+use crate::ast::{DoStatement, Span};
 use crate::error::ParserError;
 use crate::keyword::do_keyword;
-use crate::literal::Literal;
 use crate::statement::fire_statement::fire_statement;
 use crate::statement::match_statement::match_statement;
 use crate::symbol::{close_brace, open_brace};
 
 pub fn do_statement<'a>(
-    constants: &'a BTreeMap<&str, Literal>,
-) -> impl FnMut(&str) -> IResult<&str, EventProcessor, ParserError<&str>> + 'a {
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, DoStatement, ParserError<&'a str>> {
     move |text| {
         let content_parser = preceded(
             open_brace,
             pair(
-                map(
-                    many1(preceded(multispace0, match_statement(constants))),
-                    map_matchers_to_matcher,
-                ),
-                many0(preceded(multispace0, fire_statement(constants))),
+                many1(preceded(multispace0, match_statement(source))),
+                many0(preceded(multispace0, fire_statement(source))),
             ),
         );
         let keyword_parser = preceded(do_keyword, cut(preceded(multispace0, content_parser)));
         let mut close_brace_parser = terminated(keyword_parser, preceded(multispace0, close_brace));
 
-        let (input, (matcher, creators)) = close_brace_parser(text)?;
+        let (input, (matches, fires)) = close_brace_parser(text)?;
 
-        Ok((input, EventProcessor { matcher, creators }))
-    }
-}
-
-fn map_matchers_to_matcher(mut matchers: Vec<Matcher>) -> Matcher {
-    if matchers.len() < 2 {
-        panic!("This should never be reached because we're using many1");
-    } else if matchers.len() == 2 {
-        let matcher2 = matchers.pop().unwrap();
-        let matcher1 = matchers.pop().unwrap();
-        Matcher::And(Box::new(matcher1), Box::new(matcher2))
-    } else {
-        let matcher2 = matchers.pop().unwrap();
-        Matcher::And(
-            Box::new(map_matchers_to_matcher(matchers)),
-            Box::new(matcher2),
-        )
+        Ok((
+            input,
+            DoStatement {
+                matches,
+                fires,
+                span: Span::new(source, text, input),
+            },
+        ))
     }
 }
 
@@ -61,87 +45,47 @@ mod tests {
     use cool_asserts::assert_matches;
     use nom::Err as NomErr;
 
-    use ross_config::extractor::{
-        EventCodeExtractor, EventProducerAddressExtractor, PacketExtractor,
-    };
-    use ross_config::filter::ValueEqualToConstFilter;
-    use ross_config::producer::PacketProducer;
-    use ross_config::Value;
-
+    use crate::ast::{Expression, MatchExpr};
     use crate::error::{ErrorKind, Expectation};
+    use crate::literal::Literal;
 
     #[test]
     fn provided_extractor_test() {
-        let constants = BTreeMap::new();
-        let (input, event_producer) = do_statement(&constants)(
-            "do {
+        let text = "do {
                 match event 0xabab~u16;
                 match producer 0x0123~u16;
                 fire {
                     PacketExtractor();
                     PacketProducer(0xffff~u16);
                 }
-            }input",
-        )
-        .unwrap();
-
-        assert_eq!(input, "input");
+            }input";
 
         assert_matches!(
-            event_producer.matcher,
-            Matcher::And(matcher1, matcher2) => {
-                assert_matches!(*matcher1, Matcher::Single{extractor, filter} => {
-                        assert_eq!(
-                            format!("{:?}", extractor),
-                            format!("{:?}", EventCodeExtractor::new())
-                        );
-                        assert_eq!(
-                            format!("{:?}", filter),
-                            format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab)))
-                        );
-                    },
-                );
-
-                assert_matches!(*matcher2, Matcher::Single{extractor, filter} => {
-                        assert_eq!(
-                            format!("{:?}", extractor),
-                            format!("{:?}", EventProducerAddressExtractor::new())
-                        );
-                        assert_eq!(
-                            format!("{:?}", filter),
-                            format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0x0123)))
-                        );
-                    },
+            do_statement(text)(text),
+            Ok(("input", statement)) => {
+                assert_matches!(
+                    statement.matches.as_slice(),
+                    [MatchExpr::Event { .. }, MatchExpr::Producer { .. }]
                 );
-            },
-        );
-
-        let creators = event_producer.creators;
-
-        assert_eq!(creators.len(), 1);
-        assert_eq!(
-            format!("{:?}", creators[0].extractor),
-            format!("{:?}", PacketExtractor::new()),
-        );
-        assert_eq!(
-            format!("{:?}", creators[0].producer),
-            format!("{:?}", PacketProducer::new(0xffff)),
+                assert_eq!(statement.fires.len(), 1);
+                assert_eq!(statement.fires[0].producer.name.value, "PacketProducer");
+                assert_eq!(statement.span, Span::new(text, text, &text[text.len() - 5..]));
+            }
         );
     }
 
     #[test]
     fn missing_close_brace_test() {
-        let constants = BTreeMap::new();
-        assert_matches!(
-            do_statement(&constants)(
-                "do {
+        let text = "do {
                     match event 0xabab~u16;
                     match producer 0x0123~u16;
                     fire {
                         PacketExtractor();
                         PacketProducer(0xffff~u16);
-                    }",
-            ),
+                    }";
+
+        assert_matches!(
+            do_statement(text)(text),
             Err(NomErr::Error(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Expected(Expectation::Symbol('}')),
@@ -152,23 +96,52 @@ mod tests {
 
     #[test]
     fn invalid_literal_test() {
-        let constants = BTreeMap::new();
-        assert_matches!(
-            do_statement(&constants)(
-                "do {
+        let text = "do {
                     match event 0xabab~u16;
                     match producer 0x0123~u16;
                     fire {
                         PacketExtractor();
                         PacketProducer(0xffffffff~u32);
                     }
-                }",
-            ),
+                }input";
+
+        // The lowering rejects the u32, the parser keeps it as written
+        assert_matches!(
+            do_statement(text)(text),
+            Ok(("input", statement)) => {
+                assert_matches!(
+                    statement.fires[0].producer.arguments.as_slice(),
+                    [Expression::Literal { value: Literal::U32(0xffffffff), .. }]
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn out_of_range_literal_test() {
+        let text = "do {
+                    match event 0xabab~u16;
+                    fire {
+                        PacketProducer(0xfffff~u16);
+                    }
+                }";
+
+        assert_matches!(
+            do_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
-                location: _,
-                kind: ErrorKind::CastFromToNotAllowed("u32", "u16"),
-                child: None,
-            }))
+                child: Some(child),
+                ..
+            })) => {
+                assert_matches!(*child, ParserError::Alt(siblings) => {
+                    assert_matches!(&siblings[0], ParserError::Base {
+                        location,
+                        kind: ErrorKind::Expected(Expectation::Symbol(')')),
+                        ..
+                    } => {
+                        assert!(location.starts_with("0xfffff~u16"));
+                    });
+                });
+            }
         );
     }
 }
@@ -0,0 +1,109 @@
+use nom::character::complete::{multispace0, multispace1};
+use nom::combinator::cut;
+use nom::sequence::{delimited, preceded, separated_pair, terminated};
+use nom::IResult;
+
+use crate::ast::{EventStatement, Span};
+use crate::error::ParserError;
+use crate::expression::constant_expression;
+use crate::keyword::event_keyword;
+use crate::parser::name;
+use crate::symbol::{equal_sign, semicolon};
+
+pub fn event_statement<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, EventStatement, ParserError<&'a str>> {
+    move |text| {
+        let name_parser = delimited(multispace1, name(source), multispace0);
+        let equal_sign_parser = terminated(equal_sign, multispace0);
+        let name_event_code_pair_parser =
+            separated_pair(name_parser, equal_sign_parser, constant_expression(source));
+        let keyword_parser = preceded(event_keyword, cut(name_event_code_pair_parser));
+        let mut semicolon_parser = terminated(keyword_parser, semicolon);
+
+        let (input, (name, event_code)) = semicolon_parser(text)?;
+
+        Ok((
+            input,
+            EventStatement {
+                name,
+                event_code,
+                span: Span::new(source, text, input),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+    use nom::Err as NomErr;
+
+    use crate::ast::{Expression, Name};
+    use crate::error::{ErrorKind, Expectation};
+    use crate::literal::Literal;
+
+    #[test]
+    fn integer_event_code_test() {
+        let text = "event scene_changed = 0x1000;input";
+
+        assert_matches!(
+            event_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    EventStatement {
+                        name: Name {
+                            value: "scene_changed".to_string(),
+                            span: Span { start: 6, end: 19 },
+                        },
+                        event_code: Expression::Literal {
+                            value: Literal::Integer(0x1000),
+                            span: Span { start: 22, end: 28 },
+                        },
+                        span: Span { start: 0, end: 29 },
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn missing_event_code_test() {
+        let text = "event scene_changed = ;input";
+
+        assert_matches!(
+            event_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind,
+                child,
+            })) => {
+                assert_matches!(location, ";input");
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Literal));
+                assert_matches!(child, Some(_));
+            },
+        );
+    }
+
+    #[test]
+    fn wrong_keyword_test() {
+        let text = "device scene_changed = 0x1000;input";
+
+        assert_matches!(
+            event_statement(text)(text),
+            Err(NomErr::Error(ParserError::Base {
+                location,
+                kind,
+                child,
+            })) => {
+                assert_matches!(location, "device");
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Keyword("event")));
+                assert_matches!(child, None);
+            },
+        );
+    }
+}
@@ -1,67 +1,62 @@
 use nom::branch::alt;
 use nom::character::complete::{multispace0, multispace1};
-use nom::combinator::{cut, map};
-use nom::sequence::{delimited, pair, preceded, separated_pair, terminated};
+use nom::combinator::{consumed, cut, map};
+use nom::sequence::{preceded, separated_pair, terminated};
 use nom::IResult;
-use std::collections::BTreeMap;
-
-use ross_config::creator::Creator;
-use ross_config::extractor::{Extractor, NoneExtractor};
-use ross_config::producer::Producer;
 
+use crate::ast::{FireExpr, ItemCall, Span};
 use crate::error::ParserError;
-use crate::extractor::extractor;
+use crate::item::extractor_and_item_call;
 use crate::keyword::{fire_keyword, if_keyword};
-use crate::literal::Literal;
-use crate::producer::producer;
 use crate::statement::match_statement::match_statement;
 use crate::symbol::{close_brace, open_brace};
 
 pub fn fire_statement<'a>(
-    constants: &'a BTreeMap<&str, Literal>,
-) -> impl FnMut(&str) -> IResult<&str, Creator, ParserError<&str>> + 'a {
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, FireExpr, ParserError<&'a str>> {
     move |text| {
         let if_match_parser = {
-            let additional_matcher_parser = cut(preceded(multispace1, match_statement(constants)));
+            let additional_matcher_parser = cut(preceded(multispace1, match_statement(source)));
             let pair_parser = separated_pair(
-                terminated(base_syntax_parser(constants), multispace0),
+                terminated(base_syntax_parser(source), multispace0),
                 if_keyword,
                 additional_matcher_parser,
             );
 
-            map(pair_parser, |((extractor, producer), matcher)| Creator {
-                extractor,
-                producer,
-                matcher: Some(matcher),
-            })
+            map(
+                consumed(pair_parser),
+                |(consumed, ((extractor, producer), condition))| FireExpr {
+                    extractor,
+                    producer,
+                    condition: Some(condition),
+                    span: Span::of(source, consumed),
+                },
+            )
         };
 
         let normal_syntax_parser = {
-            map(base_syntax_parser(constants), |(extractor, producer)| {
-                Creator {
+            map(
+                consumed(base_syntax_parser(source)),
+                |(consumed, (extractor, producer))| FireExpr {
                     extractor,
                     producer,
-                    matcher: None,
-                }
-            })
+                    condition: None,
+                    span: Span::of(source, consumed),
+                },
+            )
         };
 
         alt((if_match_parser, normal_syntax_parser))(text)
     }
 }
 
+type BaseSyntax = (Option<ItemCall>, ItemCall);
+
 fn base_syntax_parser<'a>(
-    constants: &'a BTreeMap<&str, Literal>,
-) -> impl FnMut(&str) -> IResult<&str, (Box<dyn Extractor>, Box<dyn Producer>), ParserError<&str>> + 'a
-{
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, BaseSyntax, ParserError<&'a str>> {
     move |text| {
-        let extractor_parser = alt((
-            delimited(multispace0, extractor(constants), multispace0),
-            |input| Ok((input, Box::new(NoneExtractor::new()) as Box<dyn Extractor>)),
-        ));
-
-        let producer_parser = delimited(multispace0, producer(constants), multispace0);
-        let content_parser = preceded(open_brace, pair(extractor_parser, producer_parser));
+        let content_parser = preceded(open_brace, extractor_and_item_call(source));
         let keyword_parser = preceded(fire_keyword, cut(preceded(multispace1, content_parser)));
         let mut close_brace_parser = terminated(keyword_parser, preceded(multispace0, close_brace));
         close_brace_parser(text)
@@ -76,46 +71,42 @@ mod tests {
     use nom::error::ErrorKind as NomErrorKind;
     use nom::Err as NomErr;
 
-    use ross_config::extractor::{EventCodeExtractor, PacketExtractor};
-    use ross_config::filter::ValueEqualToConstFilter;
-    use ross_config::matcher::Matcher;
-    use ross_config::producer::PacketProducer;
-    use ross_config::Value;
-
+    use crate::ast::{BlockMatch, Expression, MatchExpr};
     use crate::error::ErrorKind;
+    use crate::literal::Literal;
 
     #[test]
     fn provided_extractor_test() {
-        let constants = BTreeMap::new();
-        let (input, creator) = fire_statement(&constants)(
-            "fire {
+        let text = "fire {
                 PacketExtractor();
                 PacketProducer(0xabab~u16);
-            }input",
-        )
-        .unwrap();
-
-        assert_eq!(input, "input");
-        assert_eq!(
-            format!("{:?}", creator.extractor),
-            format!("{:?}", PacketExtractor::new())
-        );
-        assert_eq!(
-            format!("{:?}", creator.producer),
-            format!("{:?}", PacketProducer::new(0xabab))
+            }input";
+
+        assert_matches!(
+            fire_statement(text)(text),
+            Ok(("input", fire)) => {
+                assert_matches!(fire.extractor, Some(extractor) => {
+                    assert_eq!(extractor.name.value, "PacketExtractor");
+                });
+                assert_eq!(fire.producer.name.value, "PacketProducer");
+                assert_matches!(
+                    fire.producer.arguments.as_slice(),
+                    [Expression::Literal { value: Literal::U16(0xabab), .. }]
+                );
+                assert_matches!(fire.condition, None);
+                assert_eq!(fire.span, Span { start: 0, end: 99 });
+            }
         );
-        assert_matches!(creator.matcher, None,);
     }
 
     #[test]
     fn missing_close_brace_test() {
-        let constants = BTreeMap::new();
-        assert_matches!(
-            fire_statement(&constants)(
-                "fire {
+        let text = "fire {
                     PacketExtractor();
-                    PacketProducer(0xabab~u16);",
-            ),
+                    PacketProducer(0xabab~u16);";
+
+        assert_matches!(
+            fire_statement(text)(text),
             Err(NomErr::Error(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Nom(NomErrorKind::Alt),
@@ -126,57 +117,73 @@ mod tests {
 
     #[test]
     fn invalid_literal_test() {
-        let constants = BTreeMap::new();
-        assert_matches!(
-            fire_statement(&constants)(
-                "fire {
+        let text = "fire {
                     PacketExtractor();
                     PacketProducer(0xabababab~u32);
-                }",
-            ),
-            Err(NomErr::Failure(ParserError::Base {
-                location: _,
-                kind: ErrorKind::CastFromToNotAllowed("u32", "u16"),
-                child: None,
-            }))
+                }input";
+
+        // The lowering rejects the u32, the parser keeps it as written
+        assert_matches!(
+            fire_statement(text)(text),
+            Ok(("input", fire)) => {
+                assert_matches!(
+                    fire.producer.arguments.as_slice(),
+                    [Expression::Literal { value: Literal::U32(0xabababab), span }] => {
+                        assert_eq!(&text[span.start..span.end], "0xabababab~u32");
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn out_of_range_literal_test() {
+        let text = "fire {
+                    PacketExtractor();
+                    PacketProducer(0x1abab~u16);
+                }";
+
+        assert_matches!(
+            fire_statement(text)(text),
+            Err(NomErr::Error(ParserError::Base {
+                kind: ErrorKind::Nom(NomErrorKind::Alt),
+                child: Some(child),
+                ..
+            })) => {
+                assert_matches!(*child, ParserError::Alt(siblings) => {
+                    assert_matches!(siblings[0], ParserError::Base { location, .. } => {
+                        assert!(location.starts_with("PacketProducer(0x1abab~u16);"));
+                    });
+                });
+            }
         );
     }
 
     #[test]
     fn if_match_test() {
-        let constants = BTreeMap::new();
-        let (input, creator) = fire_statement(&constants)(
-            "fire {
+        let text = "fire {
                 PacketExtractor();
                 PacketProducer(0xabab~u16);
             } if match {
                 EventCodeExtractor();
                 ValueEqualToConstFilter(0x0123~u16);
-            }input",
-        )
-        .unwrap();
-
-        assert_eq!(input, "input");
-        assert_eq!(
-            format!("{:?}", creator.extractor),
-            format!("{:?}", PacketExtractor::new())
-        );
-        assert_eq!(
-            format!("{:?}", creator.producer),
-            format!("{:?}", PacketProducer::new(0xabab))
-        );
+            }input";
+
         assert_matches!(
-            creator.matcher,
-            Some(Matcher::Single{extractor, filter}) => {
-                assert_eq!(
-                    format!("{:?}", extractor),
-                    format!("{:?}", EventCodeExtractor::new())
-                );
-                assert_eq!(
-                    format!("{:?}", filter),
-                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0x0123)))
+            fire_statement(text)(text),
+            Ok(("input", fire)) => {
+                assert_eq!(fire.producer.name.value, "PacketProducer");
+                assert_matches!(
+                    fire.condition,
+                    Some(MatchExpr::Block {
+                        block: BlockMatch::Single { extractor: Some(extractor), filter, .. },
+                        ..
+                    }) => {
+                        assert_eq!(extractor.name.value, "EventCodeExtractor");
+                        assert_eq!(filter.name.value, "ValueEqualToConstFilter");
+                    }
                 );
-            },
+            }
         );
     }
 }
@@ -0,0 +1,120 @@
+use nom::bytes::complete::take_until;
+use nom::character::complete::{multispace0, multispace1};
+use nom::combinator::cut;
+use nom::sequence::{delimited, preceded, terminated};
+use nom::IResult;
+
+use crate::ast::{ImportStatement, Span};
+use crate::error::ParserError;
+use crate::keyword::import_keyword;
+use crate::symbol::{double_quote, semicolon};
+
+pub fn import_statement<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, ImportStatement, ParserError<&'a str>> {
+    move |text| {
+        let path_parser = delimited(double_quote, take_until("\""), double_quote);
+        let keyword_parser = preceded(import_keyword, cut(preceded(multispace1, path_parser)));
+        let mut semicolon_parser =
+            terminated(keyword_parser, cut(preceded(multispace0, semicolon)));
+
+        let (input, path) = semicolon_parser(text)?;
+        let path_start = Span::of(source, path).start - 1;
+
+        Ok((
+            input,
+            ImportStatement {
+                path: path.to_string(),
+                path_span: Span {
+                    start: path_start,
+                    end: path_start + path.len() + 2,
+                },
+                span: Span::new(source, text, input),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+    use nom::Err as NomErr;
+
+    use crate::error::{ErrorKind, Expectation};
+
+    #[test]
+    fn relative_path_test() {
+        let text = "import \"common.ross\";input";
+
+        assert_matches!(
+            import_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    ImportStatement {
+                        path: "common.ross".to_string(),
+                        path_span: Span { start: 7, end: 20 },
+                        span: Span { start: 0, end: 21 },
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn missing_quotes_test() {
+        let text = "import common.ross;input";
+
+        assert_matches!(
+            import_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind,
+                child,
+            })) => {
+                assert_matches!(location, "common.ross;input");
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Symbol('"')));
+                assert_matches!(child, None);
+            },
+        );
+    }
+
+    #[test]
+    fn missing_semicolon_test() {
+        let text = "import \"common.ross\"input";
+
+        assert_matches!(
+            import_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind,
+                child,
+            })) => {
+                assert_matches!(location, "input");
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Symbol(';')));
+                assert_matches!(child, None);
+            },
+        );
+    }
+
+    #[test]
+    fn wrong_keyword_test() {
+        let text = "const state = 0xabababab~u32;input";
+
+        assert_matches!(
+            import_statement(text)(text),
+            Err(NomErr::Error(ParserError::Base {
+                location,
+                kind,
+                child,
+            })) => {
+                assert_matches!(location, "const");
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Keyword("import")));
+                assert_matches!(child, None);
+            },
+        );
+    }
+}
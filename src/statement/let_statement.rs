@@ -1,22 +1,45 @@
 use nom::character::complete::{multispace0, multispace1};
-use nom::combinator::cut;
-use nom::sequence::{delimited, preceded, separated_pair, terminated};
+use nom::combinator::{cut, opt};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated};
 use nom::IResult;
 
+use crate::ast::{LetStatement, Span};
 use crate::error::ParserError;
+use crate::expression::constant_expression;
 use crate::keyword::let_keyword;
-use crate::literal::{literal, Literal};
-use crate::parser::name_parser;
-use crate::symbol::{equal_sign, semicolon};
-
-pub fn let_statement(text: &str) -> IResult<&str, (&str, Literal), ParserError<&str>> {
-    let name_parser = delimited(multispace1, name_parser, multispace0);
-    let equal_sign_parser = terminated(equal_sign, multispace0);
-    let name_value_pair_parser = separated_pair(name_parser, equal_sign_parser, literal);
-    let keyword_parser = preceded(let_keyword, cut(name_value_pair_parser));
-    let mut semicolon_parser = terminated(keyword_parser, semicolon);
-
-    semicolon_parser(text)
+use crate::parser::name;
+use crate::symbol::{colon, equal_sign, semicolon};
+
+pub fn let_statement<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, LetStatement, ParserError<&'a str>> {
+    move |text| {
+        let name_parser = delimited(multispace1, name(source), multispace0);
+        let type_parser = opt(preceded(
+            terminated(colon, multispace0),
+            cut(terminated(name(source), multispace0)),
+        ));
+        let equal_sign_parser = terminated(equal_sign, multispace0);
+        let name_value_pair_parser = separated_pair(
+            pair(name_parser, type_parser),
+            equal_sign_parser,
+            constant_expression(source),
+        );
+        let keyword_parser = preceded(let_keyword, cut(name_value_pair_parser));
+        let mut semicolon_parser = terminated(keyword_parser, semicolon);
+
+        let (input, ((name, value_type), value)) = semicolon_parser(text)?;
+
+        Ok((
+            input,
+            LetStatement {
+                name,
+                value_type,
+                value,
+                span: Span::new(source, text, input),
+            },
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -26,44 +49,171 @@ mod tests {
     use cool_asserts::assert_matches;
     use nom::Err as NomErr;
 
+    use crate::ast::{Expression, Name};
     use crate::error::{ErrorKind, Expectation};
+    use crate::literal::Literal;
 
     #[test]
     fn hex_u32_test() {
+        let text = "let state = 0xabababab~u32;input";
+
         assert_matches!(
-            let_statement("let state = 0xabababab~u32;input"),
-            Ok(("input", ("state", Literal::U32(0xabab_abab)))),
+            let_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    LetStatement {
+                        name: Name {
+                            value: "state".to_string(),
+                            span: Span { start: 4, end: 9 },
+                        },
+                        value_type: None,
+                        value: Expression::Literal {
+                            value: Literal::U32(0xabab_abab),
+                            span: Span { start: 12, end: 26 },
+                        },
+                        span: Span { start: 0, end: 27 },
+                    }
+                );
+            }
         );
     }
 
     #[test]
     fn bool_test() {
+        let text = "let state = false;input";
+
         assert_matches!(
-            let_statement("let state = false;input"),
-            Ok(("input", ("state", Literal::Bool(false)))),
+            let_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    LetStatement {
+                        name: Name {
+                            value: "state".to_string(),
+                            span: Span { start: 4, end: 9 },
+                        },
+                        value_type: None,
+                        value: Expression::Literal {
+                            value: Literal::Bool(false),
+                            span: Span { start: 12, end: 17 },
+                        },
+                        span: Span { start: 0, end: 18 },
+                    }
+                );
+            }
         );
     }
 
     #[test]
     fn weird_spacing1_test() {
+        let text = "let state=0xabababab~u32;input";
+
         assert_matches!(
-            let_statement("let state=0xabababab~u32;input"),
-            Ok(("input", ("state", Literal::U32(0xabab_abab)))),
+            let_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    LetStatement {
+                        name: Name {
+                            value: "state".to_string(),
+                            span: Span { start: 4, end: 9 },
+                        },
+                        value_type: None,
+                        value: Expression::Literal {
+                            value: Literal::U32(0xabab_abab),
+                            span: Span { start: 10, end: 24 },
+                        },
+                        span: Span { start: 0, end: 25 },
+                    }
+                );
+            }
         );
     }
 
     #[test]
     fn weird_spacing2_test() {
+        let text = "let  state  =  0xabababab~u32;input";
+
+        assert_matches!(
+            let_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    LetStatement {
+                        name: Name {
+                            value: "state".to_string(),
+                            span: Span { start: 5, end: 10 },
+                        },
+                        value_type: None,
+                        value: Expression::Literal {
+                            value: Literal::U32(0xabab_abab),
+                            span: Span { start: 15, end: 29 },
+                        },
+                        span: Span { start: 0, end: 30 },
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn value_type_test() {
+        let text = "let state : u8 = 0;input";
+
         assert_matches!(
-            let_statement("let  state  =  0xabababab~u32;input"),
-            Ok(("input", ("state", Literal::U32(0xabab_abab)))),
+            let_statement(text)(text),
+            Ok((input, statement)) => {
+                assert_eq!(input, "input");
+                assert_eq!(
+                    statement,
+                    LetStatement {
+                        name: Name {
+                            value: "state".to_string(),
+                            span: Span { start: 4, end: 9 },
+                        },
+                        value_type: Some(Name {
+                            value: "u8".to_string(),
+                            span: Span { start: 12, end: 14 },
+                        }),
+                        value: Expression::Literal {
+                            value: Literal::Integer(0),
+                            span: Span { start: 17, end: 18 },
+                        },
+                        span: Span { start: 0, end: 19 },
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn missing_value_type_test() {
+        let text = "let state: = false;input";
+
+        assert_matches!(
+            let_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind,
+                ..
+            })) => {
+                assert_matches!(location, "= false;input");
+                assert_matches!(kind, ErrorKind::Expected(Expectation::Name));
+            },
         );
     }
 
     #[test]
     fn only_keyword_test() {
+        let text = "let;input";
+
         assert_matches!(
-            let_statement("let;input"),
+            let_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location,
                 kind,
@@ -78,8 +228,10 @@ mod tests {
 
     #[test]
     fn invalid_name_test() {
+        let text = "let 1state = 0xabababab~u32;input";
+
         assert_matches!(
-            let_statement("let 1state = 0xabababab~u32;input"),
+            let_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location,
                 kind,
@@ -94,8 +246,10 @@ mod tests {
 
     #[test]
     fn wrong_keyword_test() {
+        let text = "const state = 0xabababab~u32;input";
+
         assert_matches!(
-            let_statement("const state = 0xabababab~u32;input"),
+            let_statement(text)(text),
             Err(NomErr::Error(ParserError::Base {
                 location,
                 kind,
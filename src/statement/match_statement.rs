@@ -1,177 +1,258 @@
 use nom::branch::alt;
 use nom::character::complete::{multispace0, multispace1};
-use nom::combinator::{cut, map, map_res};
-use nom::sequence::{delimited, pair, preceded, separated_pair, terminated};
+use nom::combinator::{consumed, cut, map};
+use nom::multi::separated_list1;
+use nom::sequence::{delimited, preceded, terminated};
+use nom::Err as NomErr;
 use nom::IResult;
-use std::collections::BTreeMap;
-use std::convert::TryInto;
-
-use ross_config::extractor::Extractor;
-use ross_config::extractor::{EventCodeExtractor, EventProducerAddressExtractor, NoneExtractor};
-use ross_config::filter::Filter;
-use ross_config::filter::ValueEqualToConstFilter;
-use ross_config::matcher::Matcher;
-use ross_config::Value;
-use ross_protocol::event::event_code::INTERNAL_SYSTEM_TICK_EVENT_CODE;
-
-use crate::error::ParserError;
-use crate::extractor::extractor;
-use crate::filter::filter;
+
+use crate::ast::{BinaryOperator, BlockMatch, MatchExpr, Span};
+use crate::error::{ErrorKind, Expectation, ParserError};
+use crate::expression::binary_operator;
+use crate::item::extractor_and_item_call;
 use crate::keyword::{
     and_keyword, event_keyword, match_keyword, not_keyword, or_keyword, producer_keyword,
     tick_keyword,
 };
-use crate::literal::{literal_or_constant, Literal};
-use crate::symbol::{close_brace, comma, open_brace, semicolon};
+use crate::parser::expression;
+use crate::symbol::{
+    close_brace, close_parenthesis, comma, exclamation_mark, open_brace, open_parenthesis,
+    semicolon,
+};
 
 pub fn match_statement<'a>(
-    constants: &'a BTreeMap<&str, Literal>,
-) -> impl FnMut(&str) -> IResult<&str, Matcher, ParserError<&str>> + 'a {
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MatchExpr, ParserError<&'a str>> {
     move |text| {
-        let event_match_parser = {
-            let content_parser = map_res::<_, _, _, _, ParserError<&str>, _, _>(
-                literal_or_constant(constants),
-                |event_code| {
-                    let event_code = event_code.try_into()?;
-
-                    let extractor = Box::new(EventCodeExtractor::new()) as Box<dyn Extractor>;
-                    let filter = Box::new(ValueEqualToConstFilter::new(Value::U16(event_code)))
-                        as Box<dyn Filter>;
-
-                    Ok(Matcher::Single { extractor, filter })
-                },
-            );
-
-            let event_keyword_parser =
-                preceded(event_keyword, cut(preceded(multispace1, content_parser)));
-
-            let match_keyword_parser =
-                preceded(match_keyword, preceded(multispace1, event_keyword_parser));
-
-            terminated(match_keyword_parser, semicolon)
-        };
-
-        let producer_match_parser = {
-            let content_parser = map_res::<_, _, _, _, ParserError<&str>, _, _>(
-                literal_or_constant(constants),
-                |producer_address| {
-                    let producer_address = producer_address.try_into()?;
-
-                    let extractor =
-                        Box::new(EventProducerAddressExtractor::new()) as Box<dyn Extractor>;
-                    let filter =
-                        Box::new(ValueEqualToConstFilter::new(Value::U16(producer_address)))
-                            as Box<dyn Filter>;
-
-                    Ok(Matcher::Single { extractor, filter })
-                },
-            );
-
-            let producer_keyword_parser =
-                preceded(producer_keyword, cut(preceded(multispace1, content_parser)));
+        let operator_syntax_parser = map(
+            consumed(terminated(
+                preceded(
+                    match_keyword,
+                    preceded(multispace1, |text| {
+                        binary_match_expr(source, text, BinaryOperator::Or)
+                    }),
+                ),
+                preceded(multispace0, semicolon),
+            )),
+            |(consumed, match_expr)| match_expr.with_span(Span::of(source, consumed)),
+        );
 
-            let match_keyword_parser = preceded(
+        let normal_syntax_parser = map(
+            consumed(preceded(
                 match_keyword,
-                preceded(multispace1, producer_keyword_parser),
-            );
-
-            terminated(match_keyword_parser, semicolon)
-        };
+                preceded(multispace1, block_match_parser(source)),
+            )),
+            |(consumed, block)| MatchExpr::Block {
+                block,
+                span: Span::of(source, consumed),
+            },
+        );
 
-        let tick_match_parser = {
-            let content_parser = |text| {
-                let extractor = Box::new(EventCodeExtractor::new()) as Box<dyn Extractor>;
-                let filter = Box::new(ValueEqualToConstFilter::new(Value::U16(
-                    INTERNAL_SYSTEM_TICK_EVENT_CODE,
-                ))) as Box<dyn Filter>;
+        alt((operator_syntax_parser, normal_syntax_parser))(text)
+    }
+}
 
-                Ok((text, Matcher::Single { extractor, filter }))
+/// Parses operands joined by `operator`, which is `||` or `&&`. The operands of `||` are `&&`
+/// expressions, so that `&&` binds tighter, and both associate to the left.
+fn binary_match_expr<'a>(
+    source: &'a str,
+    text: &'a str,
+    operator: BinaryOperator,
+) -> IResult<&'a str, MatchExpr, ParserError<&'a str>> {
+    let operand = |text| match operator {
+        BinaryOperator::Or => binary_match_expr(source, text, BinaryOperator::And),
+        _ => unary_match_expr(source, text),
+    };
+
+    let (mut input, mut left) = operand(text)?;
+
+    loop {
+        let operand_text =
+            match delimited(multispace0, logical_operator(operator), multispace0)(input) {
+                Ok((operand_text, _)) => operand_text,
+                Err(NomErr::Error(_)) => return Ok((input, left)),
+                Err(err) => return Err(err),
             };
 
-            let tick_keyword_parser = preceded(tick_keyword, content_parser);
-
-            let match_keyword_parser =
-                preceded(match_keyword, preceded(multispace1, tick_keyword_parser));
+        let (rest, right) = cut(operand)(operand_text)?;
+        let span = Span {
+            start: left.span().start,
+            end: right.span().end,
+        };
 
-            terminated(match_keyword_parser, semicolon)
+        left = match operator {
+            BinaryOperator::Or => MatchExpr::Or {
+                left: Box::new(left),
+                right: Box::new(right),
+                span,
+            },
+            _ => MatchExpr::And {
+                left: Box::new(left),
+                right: Box::new(right),
+                span,
+            },
         };
+        input = rest;
+    }
+}
 
-        let normal_syntax_parser = preceded(
-            match_keyword,
-            preceded(multispace1, block_match_parser(constants)),
-        );
+fn unary_match_expr<'a>(
+    source: &'a str,
+    text: &'a str,
+) -> IResult<&'a str, MatchExpr, ParserError<&'a str>> {
+    let not_parser = map(
+        consumed(preceded(
+            exclamation_mark,
+            cut(preceded(multispace0, |text| unary_match_expr(source, text))),
+        )),
+        |(consumed, operand)| MatchExpr::Not {
+            operand: Box::new(operand),
+            span: Span::of(source, consumed),
+        },
+    );
+
+    let parenthesized_parser = map(
+        consumed(preceded(
+            open_parenthesis,
+            cut(terminated(
+                delimited(
+                    multispace0,
+                    |text| binary_match_expr(source, text, BinaryOperator::Or),
+                    multispace0,
+                ),
+                close_parenthesis,
+            )),
+        )),
+        |(consumed, match_expr)| MatchExpr::Parenthesized {
+            match_expr: Box::new(match_expr),
+            span: Span::of(source, consumed),
+        },
+    );
+
+    let event_parser = map(
+        consumed(preceded(
+            event_keyword,
+            cut(preceded(multispace1, expression(source))),
+        )),
+        |(consumed, event_code)| MatchExpr::Event {
+            event_code,
+            span: Span::of(source, consumed),
+        },
+    );
+
+    let producer_parser = map(
+        consumed(preceded(
+            producer_keyword,
+            cut(preceded(multispace1, expression(source))),
+        )),
+        |(consumed, address)| MatchExpr::Producer {
+            address,
+            span: Span::of(source, consumed),
+        },
+    );
+
+    let tick_parser = map(consumed(tick_keyword), |(consumed, _)| MatchExpr::Tick {
+        span: Span::of(source, consumed),
+    });
+
+    alt((
+        not_parser,
+        parenthesized_parser,
+        event_parser,
+        producer_parser,
+        tick_parser,
+    ))(text)
+}
 
-        alt((
-            event_match_parser,
-            producer_match_parser,
-            tick_match_parser,
-            normal_syntax_parser,
-        ))(text)
+fn logical_operator(
+    operator: BinaryOperator,
+) -> impl FnMut(&str) -> IResult<&str, BinaryOperator, ParserError<&str>> {
+    move |text| match binary_operator(text) {
+        Ok((input, parsed)) if parsed == operator => Ok((input, parsed)),
+        Ok(_) => Err(NomErr::Error(ParserError::Base {
+            location: text,
+            kind: ErrorKind::Expected(Expectation::Operator),
+            child: None,
+        })),
+        Err(err) => Err(err),
     }
 }
 
 fn block_match_parser<'a>(
-    constants: &'a BTreeMap<&str, Literal>,
-) -> impl FnMut(&str) -> IResult<&str, Matcher, ParserError<&str>> + 'a {
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, BlockMatch, ParserError<&'a str>> {
     move |text| {
         let not_parser = {
-            let extractor_parser = alt((
-                delimited(multispace0, extractor(constants), multispace0),
-                |input| Ok((input, Box::new(NoneExtractor::new()) as Box<dyn Extractor>)),
-            ));
-            let filter_parser = delimited(multispace0, filter(constants), multispace0);
-            let content_parser = preceded(open_brace, cut(pair(extractor_parser, filter_parser)));
-            let map_parser = map(content_parser, |(extractor, filter)| {
-                Matcher::Not(Box::new(Matcher::Single { extractor, filter }))
-            });
-            let keyword_parser = preceded(not_keyword, cut(preceded(multispace0, map_parser)));
-            terminated(keyword_parser, preceded(multispace0, close_brace))
+            let content_parser = preceded(open_brace, cut(extractor_and_item_call(source)));
+            let keyword_parser = preceded(not_keyword, cut(preceded(multispace0, content_parser)));
+            map(
+                consumed(terminated(
+                    keyword_parser,
+                    preceded(multispace0, close_brace),
+                )),
+                |(consumed, (extractor, filter))| BlockMatch::Not {
+                    extractor,
+                    filter,
+                    span: Span::of(source, consumed),
+                },
+            )
         };
 
         let or_parser = {
             let content_parser = preceded(
                 open_brace,
-                separated_pair(
-                    preceded(multispace0, block_match_parser(constants)),
+                separated_list1(
                     preceded(multispace0, comma),
-                    preceded(multispace0, block_match_parser(constants)),
+                    preceded(multispace0, block_match_parser(source)),
                 ),
             );
-            let map_parser = map(content_parser, |(matcher1, matcher2)| {
-                Matcher::Or(Box::new(matcher1), Box::new(matcher2))
-            });
-            let keyword_parser = preceded(or_keyword, cut(preceded(multispace0, map_parser)));
-            terminated(keyword_parser, preceded(multispace0, close_brace))
+            let keyword_parser = preceded(or_keyword, cut(preceded(multispace0, content_parser)));
+            map(
+                consumed(terminated(
+                    keyword_parser,
+                    preceded(multispace0, close_brace),
+                )),
+                |(consumed, children)| BlockMatch::Or {
+                    children,
+                    span: Span::of(source, consumed),
+                },
+            )
         };
 
         let and_parser = {
             let content_parser = preceded(
                 open_brace,
-                separated_pair(
-                    preceded(multispace0, block_match_parser(constants)),
+                separated_list1(
                     preceded(multispace0, comma),
-                    preceded(multispace0, block_match_parser(constants)),
+                    preceded(multispace0, block_match_parser(source)),
                 ),
             );
-            let map_parser = map(content_parser, |(matcher1, matcher2)| {
-                Matcher::And(Box::new(matcher1), Box::new(matcher2))
-            });
-            let keyword_parser = preceded(and_keyword, cut(preceded(multispace0, map_parser)));
-            terminated(keyword_parser, preceded(multispace0, close_brace))
+            let keyword_parser = preceded(and_keyword, cut(preceded(multispace0, content_parser)));
+            map(
+                consumed(terminated(
+                    keyword_parser,
+                    preceded(multispace0, close_brace),
+                )),
+                |(consumed, children)| BlockMatch::And {
+                    children,
+                    span: Span::of(source, consumed),
+                },
+            )
         };
 
         let base_syntax_parser = {
-            let extractor_parser = alt((
-                delimited(multispace0, extractor(constants), multispace0),
-                |input| Ok((input, Box::new(NoneExtractor::new()) as Box<dyn Extractor>)),
-            ));
-            let filter_parser = delimited(multispace0, filter(constants), multispace0);
-            let content_parser = preceded(open_brace, cut(pair(extractor_parser, filter_parser)));
-            let map_parser = map(content_parser, |(extractor, filter)| Matcher::Single {
-                extractor,
-                filter,
-            });
-            terminated(map_parser, preceded(multispace0, close_brace))
+            let content_parser = preceded(open_brace, cut(extractor_and_item_call(source)));
+            map(
+                consumed(terminated(
+                    content_parser,
+                    preceded(multispace0, close_brace),
+                )),
+                |(consumed, (extractor, filter))| BlockMatch::Single {
+                    extractor,
+                    filter,
+                    span: Span::of(source, consumed),
+                },
+            )
         };
 
         alt((not_parser, or_parser, and_parser, base_syntax_parser))(text)
@@ -186,104 +267,81 @@ mod tests {
     use nom::error::ErrorKind as NomErrorKind;
     use nom::Err as NomErr;
 
+    use crate::ast::Expression;
     use crate::error::ErrorKind;
+    use crate::literal::Literal;
 
     #[test]
     fn block_extractor_test() {
-        let constants = BTreeMap::new();
-        let (input, matcher) = match_statement(&constants)(
-            "match {
+        let text = "match {
                 EventCodeExtractor();
                 ValueEqualToConstFilter(0xabab~u16);
-            }input",
-        )
-        .unwrap();
-
-        assert_eq!(input, "input");
-        assert_matches!(matcher, Matcher::Single {extractor, filter} => {
-            assert_eq!(
-                format!("{:?}", extractor),
-                format!("{:?}", EventCodeExtractor::new())
-            );
-            assert_eq!(
-                format!("{:?}", filter),
-                format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab)))
-            );
-        });
+            }input";
+
+        assert_matches!(
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Block { block, span })) => {
+                assert_eq!(span, Span { start: 0, end: 112 });
+                assert_matches!(block, BlockMatch::Single { extractor: Some(extractor), filter, .. } => {
+                    assert_eq!(extractor.name.value, "EventCodeExtractor");
+                    assert_eq!(filter.name.value, "ValueEqualToConstFilter");
+                    assert_matches!(
+                        filter.arguments.as_slice(),
+                        [Expression::Literal { value: Literal::U16(0xabab), .. }]
+                    );
+                });
+            }
+        );
     }
 
     #[test]
     fn block_no_extractor_test() {
-        let constants = BTreeMap::new();
-        let (input, matcher) = match_statement(&constants)(
-            "match {
+        let text = "match {
                 ValueEqualToConstFilter(0xabab~u16);
-            }input",
-        )
-        .unwrap();
-
-        assert_eq!(input, "input");
-        assert_matches!(matcher, Matcher::Single {extractor, filter} => {
-            assert_eq!(
-                format!("{:?}", extractor),
-                format!("{:?}", NoneExtractor::new())
-            );
-            assert_eq!(
-                format!("{:?}", filter),
-                format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab)))
-            );
-        });
-    }
+            }input";
 
-    #[test]
-    fn block_two_extractors_test() {
-        let constants = BTreeMap::new();
         assert_matches!(
-            match_statement(&constants)(
-                "match {
-                    EventCodeExtractor();
-                    NoneExtractor();
-                }input",
-            ),
-            Err(NomErr::Failure(ParserError::Base {
-                location: _,
-                kind: ErrorKind::UnknownFilter,
-                child: None,
-            }))
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Block { block, .. })) => {
+                assert_matches!(block, BlockMatch::Single { extractor: None, filter, .. } => {
+                    assert_eq!(filter.name.value, "ValueEqualToConstFilter");
+                });
+            }
         );
     }
 
+    #[test]
+    fn block_three_items_test() {
+        let text = "match {
+                EventCodeExtractor();
+                NoneExtractor();
+                ValueEqualToConstFilter(0xabab~u16);
+            }input";
+
+        assert_matches!(match_statement(text)(text), Err(NomErr::Error(_)));
+    }
+
     #[test]
     fn block_not_test() {
-        let constants = BTreeMap::new();
-        let (input, matcher) = match_statement(&constants)(
-            "match not {
+        let text = "match not {
                 EventCodeExtractor();
                 ValueEqualToConstFilter(0xabab~u16);
-            }input",
-        )
-        .unwrap();
+            }input";
 
-        assert_eq!(input, "input");
-        assert_matches!(matcher, Matcher::Not(matcher) => {
-            assert_matches!(*matcher, Matcher::Single {extractor, filter} => {
-                assert_eq!(
-                    format!("{:?}", extractor),
-                    format!("{:?}", EventCodeExtractor::new())
-                );
-                assert_eq!(
-                    format!("{:?}", filter),
-                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab)))
-                );
-            });
-        });
+        assert_matches!(
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Block { block, .. })) => {
+                assert_matches!(block, BlockMatch::Not { extractor: Some(extractor), filter, .. } => {
+                    assert_eq!(extractor.name.value, "EventCodeExtractor");
+                    assert_eq!(filter.name.value, "ValueEqualToConstFilter");
+                });
+            }
+        );
     }
 
     #[test]
     fn block_or_test() {
-        let constants = BTreeMap::new();
-        let (input, matcher) = match_statement(&constants)(
-            "match or {
+        let text = "match or {
                 {
                     EventCodeExtractor();
                     ValueEqualToConstFilter(0xabab~u16);
@@ -292,41 +350,24 @@ mod tests {
                     EventCodeExtractor();
                     ValueEqualToConstFilter(0x0123~u16);
                 }
-            }input",
-        )
-        .unwrap();
-
-        assert_eq!(input, "input");
-        assert_matches!(matcher, Matcher::Or(matcher1, matcher2) => {
-            assert_matches!(*matcher1, Matcher::Single {extractor, filter} => {
-                assert_eq!(
-                    format!("{:?}", extractor),
-                    format!("{:?}", EventCodeExtractor::new())
-                );
-                assert_eq!(
-                    format!("{:?}", filter),
-                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab)))
-                );
-            });
+            }input";
 
-            assert_matches!(*matcher2, Matcher::Single {extractor, filter} => {
-                assert_eq!(
-                    format!("{:?}", extractor),
-                    format!("{:?}", EventCodeExtractor::new())
-                );
-                assert_eq!(
-                    format!("{:?}", filter),
-                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0x0123)))
-                );
-            });
-        });
+        assert_matches!(
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Block { block, .. })) => {
+                assert_matches!(block, BlockMatch::Or { children, .. } => {
+                    assert_matches!(
+                        children.as_slice(),
+                        [BlockMatch::Single { .. }, BlockMatch::Single { .. }]
+                    );
+                });
+            }
+        );
     }
 
     #[test]
     fn block_and_test() {
-        let constants = BTreeMap::new();
-        let (input, matcher) = match_statement(&constants)(
-            "match and {
+        let text = "match and {
                 {
                     EventCodeExtractor();
                     ValueEqualToConstFilter(0xabab~u16);
@@ -335,41 +376,24 @@ mod tests {
                     EventCodeExtractor();
                     ValueEqualToConstFilter(0x0123~u16);
                 }
-            }input",
-        )
-        .unwrap();
+            }input";
 
-        assert_eq!(input, "input");
-        assert_matches!(matcher, Matcher::And(matcher1, matcher2) => {
-            assert_matches!(*matcher1, Matcher::Single {extractor, filter} => {
-                assert_eq!(
-                    format!("{:?}", extractor),
-                    format!("{:?}", EventCodeExtractor::new())
-                );
-                assert_eq!(
-                    format!("{:?}", filter),
-                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab)))
-                );
-            });
-
-            assert_matches!(*matcher2, Matcher::Single {extractor, filter} => {
-                assert_eq!(
-                    format!("{:?}", extractor),
-                    format!("{:?}", EventCodeExtractor::new())
-                );
-                assert_eq!(
-                    format!("{:?}", filter),
-                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0x0123)))
-                );
-            });
-        });
+        assert_matches!(
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Block { block, .. })) => {
+                assert_matches!(block, BlockMatch::And { children, .. } => {
+                    assert_matches!(
+                        children.as_slice(),
+                        [BlockMatch::Single { .. }, BlockMatch::Single { .. }]
+                    );
+                });
+            }
+        );
     }
 
     #[test]
     fn block_nested_logic_test() {
-        let constants = BTreeMap::new();
-        let (input, matcher) = match_statement(&constants)(
-            "match and {
+        let text = "match and {
                 or {
                     {
                         EventCodeExtractor();
@@ -384,85 +408,61 @@ mod tests {
                     EventProducerAddressExtractor();
                     ValueEqualToConstFilter(0xffff~u16);
                 }
-            }input",
-        )
-        .unwrap();
-
-        assert_eq!(input, "input");
-        assert_matches!(matcher, Matcher::And(matcher1, matcher2) => {
-            assert_matches!(*matcher1, Matcher::Or(matcher1, matcher2) => {
-                assert_matches!(*matcher1, Matcher::Single {extractor, filter} => {
-                    assert_eq!(
-                        format!("{:?}", extractor),
-                        format!("{:?}", EventCodeExtractor::new())
-                    );
-                    assert_eq!(
-                        format!("{:?}", filter),
-                        format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab)))
-                    );
-                });
+            }input";
 
-                assert_matches!(*matcher2, Matcher::Single {extractor, filter} => {
-                    assert_eq!(
-                        format!("{:?}", extractor),
-                        format!("{:?}", EventCodeExtractor::new())
-                    );
-                    assert_eq!(
-                        format!("{:?}", filter),
-                        format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0x0123)))
+        assert_matches!(
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Block { block, .. })) => {
+                assert_matches!(block, BlockMatch::And { children, .. } => {
+                    assert_matches!(
+                        children.as_slice(),
+                        [BlockMatch::Or { children, .. }, BlockMatch::Single { extractor: Some(extractor), .. }] => {
+                            assert_eq!(children.len(), 2);
+                            assert_eq!(extractor.name.value, "EventProducerAddressExtractor");
+                        }
                     );
                 });
-            });
-
-            assert_matches!(*matcher2, Matcher::Single {extractor, filter} => {
-                assert_eq!(
-                    format!("{:?}", extractor),
-                    format!("{:?}", EventProducerAddressExtractor::new())
-                );
-                assert_eq!(
-                    format!("{:?}", filter),
-                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xffff)))
-                );
-            });
-        });
+            }
+        );
     }
 
     #[test]
     fn event_test() {
-        let constants = BTreeMap::new();
-        let (input, matcher) = match_statement(&constants)("match event 0xabab~u16;input").unwrap();
-
-        assert_eq!(input, "input");
-        assert_matches!(matcher, Matcher::Single {extractor, filter} => {
-            assert_eq!(
-                format!("{:?}", extractor),
-                format!("{:?}", EventCodeExtractor::new())
-            );
-            assert_eq!(
-                format!("{:?}", filter),
-                format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab)))
-            );
-        });
+        let text = "match event 0xabab~u16;input";
+
+        assert_matches!(
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Event { event_code, span })) => {
+                assert_eq!(
+                    event_code,
+                    Expression::Literal {
+                        value: Literal::U16(0xabab),
+                        span: Span { start: 12, end: 22 },
+                    }
+                );
+                assert_eq!(span, Span { start: 0, end: 23 });
+            }
+        );
     }
 
     #[test]
-    fn event_invalid_literal_test() {
-        let constants = BTreeMap::new();
+    fn event_constant_test() {
+        let text = "match event button_pressed;input";
+
         assert_matches!(
-            match_statement(&constants)("match event 0xabababab~u32;input"),
-            Err(NomErr::Failure(ParserError::Base {
-                location: _,
-                kind: ErrorKind::CastFromToNotAllowed("u32", "u16"),
-                child: None,
-            }))
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Event { event_code: Expression::Constant(name), .. })) => {
+                assert_eq!(name.value, "button_pressed");
+            }
         );
     }
 
     #[test]
     fn event_missing_semicolon_test() {
-        let constants = BTreeMap::new();
+        let text = "match event 0xabab~u16";
+
         assert_matches!(
-            match_statement(&constants)("match event 0xabab~u16"),
+            match_statement(text)(text),
             Err(NomErr::Error(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Nom(NomErrorKind::Alt),
@@ -473,43 +473,58 @@ mod tests {
 
     #[test]
     fn producer_test() {
-        let constants = BTreeMap::new();
-        let (input, matcher) =
-            match_statement(&constants)("match producer 0xabab~u16;input").unwrap();
-
-        assert_eq!(input, "input");
+        let text = "match producer 0xabab~u16;input";
 
-        assert_eq!(input, "input");
-        assert_matches!(matcher, Matcher::Single {extractor, filter} => {
-            assert_eq!(
-                format!("{:?}", extractor),
-                format!("{:?}", EventProducerAddressExtractor::new())
-            );
-            assert_eq!(
-                format!("{:?}", filter),
-                format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab)))
-            );
-        });
+        assert_matches!(
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Producer { address, span })) => {
+                assert_eq!(
+                    address,
+                    Expression::Literal {
+                        value: Literal::U16(0xabab),
+                        span: Span { start: 15, end: 25 },
+                    }
+                );
+                assert_eq!(span, Span { start: 0, end: 26 });
+            }
+        );
     }
 
     #[test]
-    fn producer_invalid_literal_test() {
-        let constants = BTreeMap::new();
+    fn producer_missing_semicolon_test() {
+        let text = "match producer 0xabab~u16";
+
         assert_matches!(
-            match_statement(&constants)("match producer 0xabababab~u32;input"),
-            Err(NomErr::Failure(ParserError::Base {
+            match_statement(text)(text),
+            Err(NomErr::Error(ParserError::Base {
                 location: _,
-                kind: ErrorKind::CastFromToNotAllowed("u32", "u16"),
-                child: None,
+                kind: ErrorKind::Nom(NomErrorKind::Alt),
+                child: Some(_),
             }))
         );
     }
 
     #[test]
-    fn producer_missing_semicolon_test() {
-        let constants = BTreeMap::new();
+    fn tick_test() {
+        let text = "match tick;input";
+
         assert_matches!(
-            match_statement(&constants)("match producer 0xabab~u16"),
+            match_statement(text)(text),
+            Ok((
+                "input",
+                MatchExpr::Tick {
+                    span: Span { start: 0, end: 11 }
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn tick_missing_semicolon_test() {
+        let text = "match tick";
+
+        assert_matches!(
+            match_statement(text)(text),
             Err(NomErr::Error(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Nom(NomErrorKind::Alt),
@@ -519,35 +534,108 @@ mod tests {
     }
 
     #[test]
-    fn tick_test() {
-        let constants = BTreeMap::new();
-        let (input, matcher) = match_statement(&constants)("match tick;input").unwrap();
+    fn block_three_children_test() {
+        let text = "match and {
+                { FlipStateFilter(0~u32); },
+                not { FlipStateFilter(1~u32); },
+                or { { FlipStateFilter(2~u32); }, { FlipStateFilter(3~u32); }, { FlipStateFilter(4~u32); } }
+            }input";
+
+        assert_matches!(
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Block { block, .. })) => {
+                assert_matches!(block, BlockMatch::And { children, .. } => {
+                    assert_matches!(
+                        children.as_slice(),
+                        [
+                            BlockMatch::Single { .. },
+                            BlockMatch::Not { .. },
+                            BlockMatch::Or { children, .. },
+                        ] => {
+                            assert_eq!(children.len(), 3);
+                        }
+                    );
+                });
+            }
+        );
+    }
 
-        assert_eq!(input, "input");
+    #[test]
+    fn operator_test() {
+        let text = "match (event 0xabab~u16 && producer 0x0001~u16) || !tick;input";
 
-        assert_eq!(input, "input");
-        assert_matches!(matcher, Matcher::Single {extractor, filter} => {
-            assert_eq!(
-                format!("{:?}", extractor),
-                format!("{:?}", EventCodeExtractor::new())
-            );
-            assert_eq!(
-                format!("{:?}", filter),
-                format!("{:?}", ValueEqualToConstFilter::new(Value::U16(INTERNAL_SYSTEM_TICK_EVENT_CODE)))
-            );
-        });
+        assert_matches!(
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Or { left, right, span })) => {
+                assert_eq!(span, Span { start: 0, end: 57 });
+                assert_matches!(*left, MatchExpr::Parenthesized { match_expr, span } => {
+                    assert_eq!(span, Span { start: 6, end: 47 });
+                    assert_matches!(*match_expr, MatchExpr::And { left, right, .. } => {
+                        assert_matches!(
+                            *left,
+                            MatchExpr::Event {
+                                span: Span { start: 7, end: 23 },
+                                ..
+                            }
+                        );
+                        assert_matches!(
+                            *right,
+                            MatchExpr::Producer {
+                                span: Span { start: 27, end: 46 },
+                                ..
+                            }
+                        );
+                    });
+                });
+                assert_matches!(*right, MatchExpr::Not { operand, span } => {
+                    assert_eq!(span, Span { start: 51, end: 56 });
+                    assert_matches!(*operand, MatchExpr::Tick { .. });
+                });
+            }
+        );
     }
 
     #[test]
-    fn tick_missing_semicolon_test() {
-        let constants = BTreeMap::new();
+    fn operator_precedence_test() {
+        let text = "match tick || event a && producer b || tick;input";
+
         assert_matches!(
-            match_statement(&constants)("match tick"),
-            Err(NomErr::Error(ParserError::Base {
-                location: _,
+            match_statement(text)(text),
+            Ok(("input", MatchExpr::Or { left, right, .. })) => {
+                assert_matches!(*left, MatchExpr::Or { left, right, .. } => {
+                    assert_matches!(*left, MatchExpr::Tick { .. });
+                    assert_matches!(*right, MatchExpr::And { .. });
+                });
+                assert_matches!(*right, MatchExpr::Tick { .. });
+            }
+        );
+    }
+
+    #[test]
+    fn operator_missing_operand_test() {
+        let text = "match tick && ;input";
+
+        assert_matches!(
+            match_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location: ";input",
                 kind: ErrorKind::Nom(NomErrorKind::Alt),
                 child: Some(_),
             }))
         );
     }
+
+    #[test]
+    fn operator_missing_parenthesis_test() {
+        let text = "match (tick || event a;input";
+
+        assert_matches!(
+            match_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location: ";input",
+                kind: ErrorKind::Expected(Expectation::Symbol(')')),
+                child: None,
+            }))
+        );
+    }
 }
@@ -1,8 +1,14 @@
+pub mod attribute_statement;
 pub mod const_statement;
+pub mod device_statement;
 pub mod do_statement;
+pub mod event_statement;
 pub mod fire_statement;
+pub mod import_statement;
 pub mod let_statement;
 pub mod match_statement;
 pub mod peripheral_statement;
 pub mod send_statement;
 pub mod set_statement;
+pub mod test_statement;
+pub mod toggle_statement;
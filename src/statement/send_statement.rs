@@ -1,99 +1,76 @@
 use nom::branch::alt;
 use nom::character::complete::{multispace0, multispace1};
-use nom::combinator::{cut, map, map_res};
+use nom::combinator::{cut, map};
 use nom::sequence::{preceded, separated_pair, terminated, tuple};
 use nom::IResult;
-use std::collections::BTreeMap;
-use std::convert::TryInto;
-
-use ross_config::creator::Creator;
-use ross_config::event_processor::EventProcessor;
-use ross_config::extractor::{EventCodeExtractor, EventProducerAddressExtractor, PacketExtractor};
-use ross_config::filter::ValueEqualToConstFilter;
-use ross_config::matcher::Matcher;
-use ross_config::producer::PacketProducer;
-use ross_config::Value;
 
+use crate::ast::{Expression, SendStatement, Span};
 use crate::error::ParserError;
 use crate::keyword::{from_keyword, if_keyword, send_keyword, to_keyword};
-use crate::literal::{literal_or_constant, Literal};
+use crate::parser::expression;
 use crate::statement::match_statement::match_statement;
 use crate::symbol::semicolon;
 
 pub fn send_statement<'a>(
-    constants: &'a BTreeMap<&str, Literal>,
-) -> impl FnMut(&str) -> IResult<&str, EventProcessor, ParserError<&str>> + 'a {
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, SendStatement, ParserError<&'a str>> {
     move |text| {
         let if_match_parser = {
-            let additional_matcher_parser = cut(preceded(multispace1, match_statement(constants)));
+            let additional_matcher_parser = cut(preceded(multispace1, match_statement(source)));
             let pair_parser = separated_pair(
-                terminated(base_syntax_parser(constants), multispace1),
+                terminated(base_syntax_parser(source), multispace1),
                 if_keyword,
                 additional_matcher_parser,
             );
 
-            map(pair_parser, |((matcher, creators), additional_matcher)| {
-                (
-                    Matcher::And(Box::new(additional_matcher), Box::new(matcher)),
-                    creators,
-                )
+            map(pair_parser, |(addresses, condition)| {
+                (addresses, Some(condition))
             })
         };
 
-        let normal_syntax_parser = terminated(
-            base_syntax_parser(constants),
-            preceded(multispace0, semicolon),
+        let normal_syntax_parser = map(
+            terminated(base_syntax_parser(source), preceded(multispace0, semicolon)),
+            |addresses| (addresses, None),
         );
 
-        let (input, (matcher, creators)) = alt((if_match_parser, normal_syntax_parser))(text)?;
-
-        Ok((input, EventProcessor { matcher, creators }))
+        let (input, ((event_code, from_address, to_address), condition)) =
+            alt((if_match_parser, normal_syntax_parser))(text)?;
+
+        Ok((
+            input,
+            SendStatement {
+                event_code,
+                from_address,
+                to_address,
+                condition,
+                span: Span::new(source, text, input),
+            },
+        ))
     }
 }
 
+type BaseSyntax = (Expression, Expression, Expression);
+
 fn base_syntax_parser<'a>(
-    constants: &'a BTreeMap<&str, Literal>,
-) -> impl FnMut(&str) -> IResult<&str, (Matcher, Vec<Creator>), ParserError<&str>> + 'a {
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, BaseSyntax, ParserError<&'a str>> {
     move |text| {
         let tuple_parser = tuple((
-            literal_or_constant(constants),
+            expression(source),
             multispace1,
             from_keyword,
             multispace1,
-            literal_or_constant(constants),
+            expression(source),
             multispace1,
             to_keyword,
             multispace1,
-            literal_or_constant(constants),
+            expression(source),
         ));
 
-        let content_parser = map_res::<_, _, _, _, ParserError<&str>, _, _>(
+        let content_parser = map(
             tuple_parser,
             |(event_code, _, _, _, from_address, _, _, _, to_address)| {
-                let event_code = event_code.try_into()?;
-                let from_address = from_address.try_into()?;
-                let to_address = to_address.try_into()?;
-
-                let event_matcher = Matcher::Single {
-                    extractor: Box::new(EventCodeExtractor::new()),
-                    filter: Box::new(ValueEqualToConstFilter::new(Value::U16(event_code))),
-                };
-
-                let producer_matcher = Matcher::Single {
-                    extractor: Box::new(EventProducerAddressExtractor::new()),
-                    filter: Box::new(ValueEqualToConstFilter::new(Value::U16(from_address))),
-                };
-
-                let combined_matcher =
-                    Matcher::And(Box::new(event_matcher), Box::new(producer_matcher));
-
-                let packet_creator = Creator {
-                    extractor: Box::new(PacketExtractor::new()),
-                    producer: Box::new(PacketProducer::new(to_address)),
-                    matcher: None,
-                };
-
-                Ok((combined_matcher, vec![packet_creator]))
+                (event_code, from_address, to_address)
             },
         );
 
@@ -109,59 +86,47 @@ mod tests {
     use nom::error::ErrorKind as NomErrorKind;
     use nom::Err as NomErr;
 
+    use crate::ast::MatchExpr;
     use crate::error::{ErrorKind, Expectation};
+    use crate::literal::Literal;
 
     #[test]
     fn normal_syntax_test() {
-        let constants = BTreeMap::new();
-        let (input, event_processor) =
-            send_statement(&constants)("send 0xabab~u16 from 0x0123~u16 to 0xffff~u16;input")
-                .unwrap();
-
-        assert_eq!(input, "input");
+        let text = "send 0xabab~u16 from 0x0123~u16 to receiver_address;input";
 
-        assert_matches!(event_processor.matcher, Matcher::And(matcher1, matcher2) => {
-            assert_matches!(*matcher1, Matcher::Single {extractor, filter} => {
-                assert_eq!(
-                    format!("{:?}", extractor),
-                    format!("{:?}", EventCodeExtractor::new()),
-                );
-                assert_eq!(
-                    format!("{:?}", filter),
-                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab))),
-                );
-            });
-
-            assert_matches!(*matcher2, Matcher::Single {extractor, filter} => {
+        assert_matches!(
+            send_statement(text)(text),
+            Ok(("input", statement)) => {
                 assert_eq!(
-                    format!("{:?}", extractor),
-                    format!("{:?}", EventProducerAddressExtractor::new()),
+                    statement.event_code,
+                    Expression::Literal {
+                        value: Literal::U16(0xabab),
+                        span: Span { start: 5, end: 15 },
+                    }
                 );
                 assert_eq!(
-                    format!("{:?}", filter),
-                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0x0123))),
+                    statement.from_address,
+                    Expression::Literal {
+                        value: Literal::U16(0x0123),
+                        span: Span { start: 21, end: 31 },
+                    }
                 );
-            });
-        });
-
-        let creators = event_processor.creators;
-
-        assert_eq!(creators.len(), 1);
-        assert_eq!(
-            format!("{:?}", creators[0].extractor),
-            format!("{:?}", PacketExtractor::new()),
-        );
-        assert_eq!(
-            format!("{:?}", creators[0].producer),
-            format!("{:?}", PacketProducer::new(0xffff)),
+                assert_matches!(statement.to_address, Expression::Constant(name) => {
+                    assert_eq!(name.value, "receiver_address");
+                    assert_eq!(name.span, Span { start: 35, end: 51 });
+                });
+                assert_eq!(statement.condition, None);
+                assert_eq!(statement.span, Span { start: 0, end: 52 });
+            }
         );
     }
 
     #[test]
     fn normal_syntax_missing_semicolon_test() {
-        let constants = BTreeMap::new();
+        let text = "send 0xabab~u16 from 0x0123~u16 to 0xffff~u16";
+
         assert_matches!(
-            send_statement(&constants)("send 0xabab~u16 from 0x0123~u16 to 0xffff~u16"),
+            send_statement(text)(text),
             Err(NomErr::Error(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Nom(NomErrorKind::Alt),
@@ -172,9 +137,10 @@ mod tests {
 
     #[test]
     fn normal_syntax_missing_from_keyword_test() {
-        let constants = BTreeMap::new();
+        let text = "send 0xabab~u16 0x0123~u16 to 0xffff~u16;";
+
         assert_matches!(
-            send_statement(&constants)("send 0xabab~u16 0x0123~u16 to 0xffff~u16;"),
+            send_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Expected(Expectation::Keyword("from")),
@@ -185,9 +151,10 @@ mod tests {
 
     #[test]
     fn normal_syntax_missing_to_keyword_test() {
-        let constants = BTreeMap::new();
+        let text = "send 0xabab~u16 from 0x0123~u16 0xffff~u16;";
+
         assert_matches!(
-            send_statement(&constants)("send 0xabab~u16 from 0x0123~u16 0xffff~u16;"),
+            send_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Expected(Expectation::Keyword("to")),
@@ -198,9 +165,10 @@ mod tests {
 
     #[test]
     fn normal_syntax_empty_test() {
-        let constants = BTreeMap::new();
+        let text = "";
+
         assert_matches!(
-            send_statement(&constants)(""),
+            send_statement(text)(text),
             Err(NomErr::Error(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Nom(NomErrorKind::Alt),
@@ -211,71 +179,29 @@ mod tests {
 
     #[test]
     fn if_match_event_test() {
-        let constants = BTreeMap::new();
-        let (input, event_processor) = send_statement(&constants)(
-            "send 0xabab~u16 from 0x0123~u16 to 0xffff~u16 if match event 0xbaba~u16;input",
-        )
-        .unwrap();
-
-        assert_eq!(input, "input");
+        let text = "send 0xabab~u16 from 0x0123~u16 to 0xffff~u16 if match event 0xbaba~u16;input";
 
-        assert_matches!(event_processor.matcher, Matcher::And(matcher1, matcher2) => {
-            assert_matches!(*matcher1, Matcher::Single {extractor, filter} => {
-                assert_eq!(
-                    format!("{:?}", extractor),
-                    format!("{:?}", EventCodeExtractor::new()),
-                );
-                assert_eq!(
-                    format!("{:?}", filter),
-                    format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xbaba))),
+        assert_matches!(
+            send_statement(text)(text),
+            Ok(("input", statement)) => {
+                assert_matches!(
+                    statement.condition,
+                    Some(MatchExpr::Event {
+                        event_code: Expression::Literal { value: Literal::U16(0xbaba), .. },
+                        ..
+                    })
                 );
-            });
-
-            assert_matches!(*matcher2, Matcher::And(matcher1, matcher2) => {
-                assert_matches!(*matcher1, Matcher::Single {extractor, filter} => {
-                    assert_eq!(
-                        format!("{:?}", extractor),
-                        format!("{:?}", EventCodeExtractor::new()),
-                    );
-                    assert_eq!(
-                        format!("{:?}", filter),
-                        format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab))),
-                    );
-                });
-
-                assert_matches!(*matcher2, Matcher::Single {extractor, filter} => {
-                    assert_eq!(
-                        format!("{:?}", extractor),
-                        format!("{:?}", EventProducerAddressExtractor::new()),
-                    );
-                    assert_eq!(
-                        format!("{:?}", filter),
-                        format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0x0123))),
-                    );
-                });
-            });
-        });
-
-        let creators = event_processor.creators;
-
-        assert_eq!(creators.len(), 1);
-        assert_eq!(
-            format!("{:?}", creators[0].extractor),
-            format!("{:?}", PacketExtractor::new()),
-        );
-        assert_eq!(
-            format!("{:?}", creators[0].producer),
-            format!("{:?}", PacketProducer::new(0xffff)),
+                assert_eq!(statement.span, Span { start: 0, end: 72 });
+            }
         );
     }
 
     #[test]
     fn if_match_event_missing_semicolon_test() {
-        let constants = BTreeMap::new();
+        let text = "send 0xabab~u16 from 0x0123~u16 to 0xffff~u16 if match event 0xbaba~u16";
+
         assert_matches!(
-            send_statement(&constants)(
-                "send 0xabab~u16 from 0x0123~u16 to 0xffff~u16 if match event 0xbaba~u16",
-            ),
+            send_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Nom(NomErrorKind::Alt),
@@ -286,11 +212,10 @@ mod tests {
 
     #[test]
     fn if_match_event_missing_from_keyword_test() {
-        let constants = BTreeMap::new();
+        let text = "send 0xabab~u16 0x0123~u16 to 0xffff~u16 if match event 0xbaba~u16;";
+
         assert_matches!(
-            send_statement(&constants)(
-                "send 0xabab~u16 0x0123~u16 to 0xffff~u16 if match event 0xbaba~u16;"
-            ),
+            send_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Expected(Expectation::Keyword("from")),
@@ -301,11 +226,10 @@ mod tests {
 
     #[test]
     fn if_match_event_missing_to_keyword_test() {
-        let constants = BTreeMap::new();
+        let text = "send 0xabab~u16 from 0x0123~u16 0xffff~u16 if match event 0xbaba~u16;";
+
         assert_matches!(
-            send_statement(&constants)(
-                "send 0xabab~u16 from 0x0123~u16 0xffff~u16 if match event 0xbaba~u16;",
-            ),
+            send_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Expected(Expectation::Keyword("to")),
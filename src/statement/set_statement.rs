@@ -1,124 +1,81 @@
 use nom::branch::alt;
 use nom::character::complete::{multispace0, multispace1};
-use nom::combinator::{cut, map, map_res};
+use nom::combinator::{cut, map};
 use nom::sequence::{preceded, separated_pair, terminated, tuple};
 use nom::IResult;
-use std::collections::BTreeMap;
-use std::convert::TryInto;
-
-use ross_config::creator::Creator;
-use ross_config::event_processor::EventProcessor;
-use ross_config::extractor::{EventCodeExtractor, EventProducerAddressExtractor, NoneExtractor};
-use ross_config::filter::{SetStateToConstFilter, ValueEqualToConstFilter};
-use ross_config::matcher::Matcher;
-use ross_config::Value;
 
+use crate::ast::{Expression, Name, SetStatement, Span};
 use crate::error::ParserError;
 use crate::keyword::{from_keyword, if_keyword, on_keyword, set_keyword, to_keyword};
-use crate::literal::{literal_or_constant, state_variable, Literal};
+use crate::parser::{expression, name};
 use crate::statement::match_statement::match_statement;
 use crate::symbol::semicolon;
 
 pub fn set_statement<'a>(
-    constants: &'a BTreeMap<&str, Literal>,
-    state_variables: &'a BTreeMap<&str, u32>,
-) -> impl FnMut(&str) -> IResult<&str, EventProcessor, ParserError<&str>> + 'a {
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, SetStatement, ParserError<&'a str>> {
     move |text| {
         let if_match_parser = {
-            let additional_matcher_parser = cut(preceded(multispace1, match_statement(constants)));
+            let additional_matcher_parser = cut(preceded(multispace1, match_statement(source)));
             let pair_parser = separated_pair(
-                terminated(base_syntax_parser(constants, state_variables), multispace1),
+                terminated(base_syntax_parser(source), multispace1),
                 if_keyword,
                 additional_matcher_parser,
             );
 
-            map(
-                pair_parser,
-                |(((combined_matcher, set_matcher), creators), additional_matcher)| {
-                    (
-                        Matcher::And(
-                            Box::new(combined_matcher),
-                            Box::new(Matcher::And(
-                                Box::new(additional_matcher),
-                                Box::new(set_matcher),
-                            )),
-                        ),
-                        creators,
-                    )
-                },
-            )
-        };
-
-        let normal_syntax_parser = {
-            let pair_parser = terminated(
-                base_syntax_parser(constants, state_variables),
-                preceded(multispace0, semicolon),
-            );
-
-            map(
-                pair_parser,
-                |((combined_matcher, set_matcher), creators)| {
-                    (
-                        Matcher::And(Box::new(combined_matcher), Box::new(set_matcher)),
-                        creators,
-                    )
-                },
-            )
+            map(pair_parser, |(base_syntax, condition)| {
+                (base_syntax, Some(condition))
+            })
         };
 
-        let (input, (matcher, creators)) = alt((if_match_parser, normal_syntax_parser))(text)?;
+        let normal_syntax_parser = map(
+            terminated(base_syntax_parser(source), preceded(multispace0, semicolon)),
+            |base_syntax| (base_syntax, None),
+        );
 
-        Ok((input, EventProcessor { matcher, creators }))
+        let (input, ((state_variable, value, event_code, from_address), condition)) =
+            alt((if_match_parser, normal_syntax_parser))(text)?;
+
+        Ok((
+            input,
+            SetStatement {
+                state_variable,
+                value,
+                event_code,
+                from_address,
+                condition,
+                span: Span::new(source, text, input),
+            },
+        ))
     }
 }
 
+type BaseSyntax = (Name, Expression, Expression, Expression);
+
 fn base_syntax_parser<'a>(
-    constants: &'a BTreeMap<&str, Literal>,
-    state_variables: &'a BTreeMap<&str, u32>,
-) -> impl FnMut(&str) -> IResult<&str, ((Matcher, Matcher), Vec<Creator>), ParserError<&str>> + 'a {
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, BaseSyntax, ParserError<&'a str>> {
     move |text| {
         let tuple_parser = tuple((
-            state_variable(state_variables),
+            name(source),
             multispace1,
             to_keyword,
             multispace1,
-            literal_or_constant(constants),
+            expression(source),
             multispace1,
             on_keyword,
             multispace1,
-            literal_or_constant(constants),
+            expression(source),
             multispace1,
             from_keyword,
             multispace1,
-            literal_or_constant(constants),
+            expression(source),
         ));
 
-        let content_parser = map_res::<_, _, _, _, ParserError<&str>, _, _>(
+        let content_parser = map(
             tuple_parser,
-            |(state_index, _, _, _, target_value, _, _, _, event_code, _, _, _, from_address)| {
-                let target_value = target_value.try_into()?;
-                let event_code = event_code.try_into()?;
-                let from_address = from_address.try_into()?;
-
-                let event_matcher = Matcher::Single {
-                    extractor: Box::new(EventCodeExtractor::new()),
-                    filter: Box::new(ValueEqualToConstFilter::new(Value::U16(event_code))),
-                };
-
-                let producer_matcher = Matcher::Single {
-                    extractor: Box::new(EventProducerAddressExtractor::new()),
-                    filter: Box::new(ValueEqualToConstFilter::new(Value::U16(from_address))),
-                };
-
-                let set_matcher = Matcher::Single {
-                    extractor: Box::new(NoneExtractor::new()),
-                    filter: Box::new(SetStateToConstFilter::new(state_index, target_value)),
-                };
-
-                let combined_matcher =
-                    Matcher::And(Box::new(event_matcher), Box::new(producer_matcher));
-
-                Ok(((combined_matcher, set_matcher), vec![]))
+            |(state_variable, _, _, _, value, _, _, _, event_code, _, _, _, from_address)| {
+                (state_variable, value, event_code, from_address)
             },
         );
 
@@ -134,71 +91,50 @@ mod tests {
     use nom::error::ErrorKind as NomErrorKind;
     use nom::Err as NomErr;
 
+    use crate::ast::MatchExpr;
     use crate::error::{ErrorKind, Expectation};
+    use crate::literal::Literal;
 
     #[test]
     fn normal_syntax_test() {
-        let constants = BTreeMap::new();
-        let mut state_variables = BTreeMap::new();
-        state_variables.insert("button_pressed", 0);
-
-        let (input, event_processor) = set_statement(&constants, &state_variables)(
-            "set button_pressed to true on 0xabab~u16 from 0x0123~u16;input",
-        )
-        .unwrap();
-
-        assert_eq!(input, "input");
-
-        assert_matches!(event_processor.matcher, Matcher::And(matcher1, matcher2) => {
-            assert_matches!(*matcher1, Matcher::And(matcher1, matcher2) => {
-                assert_matches!(*matcher1, Matcher::Single {extractor, filter} => {
-                    assert_eq!(
-                        format!("{:?}", extractor),
-                        format!("{:?}", EventCodeExtractor::new()),
-                    );
-                    assert_eq!(
-                        format!("{:?}", filter),
-                        format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab))),
-                    );
-                });
-
-                assert_matches!(*matcher2, Matcher::Single {extractor, filter} => {
-                    assert_eq!(
-                        format!("{:?}", extractor),
-                        format!("{:?}", EventProducerAddressExtractor::new()),
-                    );
-                    assert_eq!(
-                        format!("{:?}", filter),
-                        format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0x0123))),
-                    );
-                });
-            });
-
-            assert_matches!(*matcher2, Matcher::Single {extractor, filter} => {
-                assert_eq!(
-                    format!("{:?}", extractor),
-                    format!("{:?}", NoneExtractor::new()),
-                );
+        let text = "set button_pressed to true on 0xabab~u16 from 0x0123~u16;input";
+
+        assert_matches!(
+            set_statement(text)(text),
+            Ok(("input", statement)) => {
                 assert_eq!(
-                    format!("{:?}", filter),
-                    format!("{:?}", SetStateToConstFilter::new(0, Value::Bool(true))),
+                    statement,
+                    SetStatement {
+                        state_variable: Name {
+                            value: "button_pressed".to_string(),
+                            span: Span { start: 4, end: 18 },
+                        },
+                        value: Expression::Literal {
+                            value: Literal::Bool(true),
+                            span: Span { start: 22, end: 26 },
+                        },
+                        event_code: Expression::Literal {
+                            value: Literal::U16(0xabab),
+                            span: Span { start: 30, end: 40 },
+                        },
+                        from_address: Expression::Literal {
+                            value: Literal::U16(0x0123),
+                            span: Span { start: 46, end: 56 },
+                        },
+                        condition: None,
+                        span: Span { start: 0, end: 57 },
+                    }
                 );
-            });
-        });
-
-        assert_eq!(event_processor.creators.len(), 0);
+            }
+        );
     }
 
     #[test]
     fn normal_syntax_missing_semicolon_test() {
-        let constants = BTreeMap::new();
-        let mut state_variables = BTreeMap::new();
-        state_variables.insert("button_pressed", 0);
+        let text = "set button_pressed to true on 0xabab~u16 from 0x0123~u16";
 
         assert_matches!(
-            set_statement(&constants, &state_variables)(
-                "set button_pressed to true on 0xabab~u16 from 0x0123~u16"
-            ),
+            set_statement(text)(text),
             Err(NomErr::Error(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Nom(NomErrorKind::Alt),
@@ -209,14 +145,10 @@ mod tests {
 
     #[test]
     fn normal_syntax_missing_from_keyword_test() {
-        let constants = BTreeMap::new();
-        let mut state_variables = BTreeMap::new();
-        state_variables.insert("button_pressed", 0);
+        let text = "set button_pressed to true on 0xabab~u16 0x0123~u16;";
 
         assert_matches!(
-            set_statement(&constants, &state_variables)(
-                "set button_pressed to true on 0xabab~u16 0x0123~u16;"
-            ),
+            set_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Expected(Expectation::Keyword("from")),
@@ -227,14 +159,10 @@ mod tests {
 
     #[test]
     fn normal_syntax_missing_to_keyword_test() {
-        let constants = BTreeMap::new();
-        let mut state_variables = BTreeMap::new();
-        state_variables.insert("button_pressed", 0);
+        let text = "set button_pressed true on 0xabab~u16 from 0x0123~u16;";
 
         assert_matches!(
-            set_statement(&constants, &state_variables)(
-                "set button_pressed true on 0xabab~u16 from 0x0123~u16;"
-            ),
+            set_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Expected(Expectation::Keyword("to")),
@@ -245,14 +173,10 @@ mod tests {
 
     #[test]
     fn normal_syntax_missing_on_keyword_test() {
-        let constants = BTreeMap::new();
-        let mut state_variables = BTreeMap::new();
-        state_variables.insert("button_pressed", 0);
+        let text = "set button_pressed to true 0xabab~u16 from 0x0123~u16;";
 
         assert_matches!(
-            set_statement(&constants, &state_variables)(
-                "set button_pressed to true 0xabab~u16 from 0x0123~u16;"
-            ),
+            set_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Expected(Expectation::Keyword("on")),
@@ -263,11 +187,10 @@ mod tests {
 
     #[test]
     fn normal_syntax_empty_test() {
-        let constants = BTreeMap::new();
-        let state_variables = BTreeMap::new();
+        let text = "";
 
         assert_matches!(
-            set_statement(&constants, &state_variables)(""),
+            set_statement(text)(text),
             Err(NomErr::Error(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Nom(NomErrorKind::Alt),
@@ -278,80 +201,30 @@ mod tests {
 
     #[test]
     fn if_match_event_test() {
-        let constants = BTreeMap::new();
-        let mut state_variables = BTreeMap::new();
-        state_variables.insert("button_pressed", 0);
-
-        let (input, event_processor) = set_statement(&constants, &state_variables)(
-            "set button_pressed to true on 0xabab~u16 from 0x0123~u16 if match event 0xbaba~u16;input",
-        )
-        .unwrap();
-
-        assert_eq!(input, "input");
-
-        assert_matches!(event_processor.matcher, Matcher::And(matcher1, matcher2) => {
-            assert_matches!(*matcher1, Matcher::And(matcher1, matcher2) => {
-                assert_matches!(*matcher1, Matcher::Single {extractor, filter} => {
-                    assert_eq!(
-                        format!("{:?}", extractor),
-                        format!("{:?}", EventCodeExtractor::new()),
-                    );
-                    assert_eq!(
-                        format!("{:?}", filter),
-                        format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xabab))),
-                    );
-                });
-
-                assert_matches!(*matcher2, Matcher::Single {extractor, filter} => {
-                    assert_eq!(
-                        format!("{:?}", extractor),
-                        format!("{:?}", EventProducerAddressExtractor::new()),
-                    );
-                    assert_eq!(
-                        format!("{:?}", filter),
-                        format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0x0123))),
-                    );
-                });
-            });
-
-            assert_matches!(*matcher2, Matcher::And(matcher1, matcher2) => {
-                assert_matches!(*matcher1, Matcher::Single {extractor, filter} => {
-                    assert_eq!(
-                        format!("{:?}", extractor),
-                        format!("{:?}", EventCodeExtractor::new()),
-                    );
-                    assert_eq!(
-                        format!("{:?}", filter),
-                        format!("{:?}", ValueEqualToConstFilter::new(Value::U16(0xbaba))),
-                    );
-                });
-
-                assert_matches!(*matcher2, Matcher::Single {extractor, filter} => {
-                    assert_eq!(
-                        format!("{:?}", extractor),
-                        format!("{:?}", NoneExtractor::new()),
-                    );
-                    assert_eq!(
-                        format!("{:?}", filter),
-                        format!("{:?}", SetStateToConstFilter::new(0, Value::Bool(true))),
-                    );
-                });
-            });
-        });
-
-        assert_eq!(event_processor.creators.len(), 0);
+        let text = "set button_pressed to true on 0xabab~u16 from 0x0123~u16 if match event 0xbaba~u16;input";
+
+        assert_matches!(
+            set_statement(text)(text),
+            Ok(("input", statement)) => {
+                assert_eq!(statement.state_variable.value, "button_pressed");
+                assert_matches!(
+                    statement.condition,
+                    Some(MatchExpr::Event {
+                        event_code: Expression::Literal { value: Literal::U16(0xbaba), .. },
+                        ..
+                    })
+                );
+            }
+        );
     }
 
     #[test]
     fn if_match_event_missing_semicolon_test() {
-        let constants = BTreeMap::new();
-        let mut state_variables = BTreeMap::new();
-        state_variables.insert("button_pressed", 0);
+        let text =
+            "set button_pressed to true on 0xabab~u16 from 0x0123~u16 if match event 0xbaba~u16";
 
         assert_matches!(
-            set_statement(&constants, &state_variables)(
-                "set button_pressed to true on 0xabab~u16 from 0x0123~u16 if match event 0xbaba~u16",
-            ),
+            set_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Nom(NomErrorKind::Alt),
@@ -362,14 +235,10 @@ mod tests {
 
     #[test]
     fn if_match_event_missing_from_keyword_test() {
-        let constants = BTreeMap::new();
-        let mut state_variables = BTreeMap::new();
-        state_variables.insert("button_pressed", 0);
+        let text = "set button_pressed to true on 0xabab~u16 0x0123~u16 if match event 0xbaba~u16;";
 
         assert_matches!(
-            set_statement(&constants, &state_variables)(
-                "set button_pressed to true on 0xabab~u16 0x0123~u16 if match event 0xbaba~u16;"
-            ),
+            set_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Expected(Expectation::Keyword("from")),
@@ -380,14 +249,11 @@ mod tests {
 
     #[test]
     fn if_match_event_missing_to_keyword_test() {
-        let constants = BTreeMap::new();
-        let mut state_variables = BTreeMap::new();
-        state_variables.insert("button_pressed", 0);
+        let text =
+            "set button_pressed true on 0xabab~u16 from 0x0123~u16 if match event 0xbaba~u16;";
 
         assert_matches!(
-            set_statement(&constants, &state_variables)(
-                "set button_pressed true on 0xabab~u16 from 0x0123~u16 if match event 0xbaba~u16;",
-            ),
+            set_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Expected(Expectation::Keyword("to")),
@@ -398,14 +264,11 @@ mod tests {
 
     #[test]
     fn if_match_event_missing_on_keyword_test() {
-        let constants = BTreeMap::new();
-        let mut state_variables = BTreeMap::new();
-        state_variables.insert("button_pressed", 0);
+        let text =
+            "set button_pressed to true  0xabab~u16 from 0x0123~u16 if match event 0xbaba~u16;";
 
         assert_matches!(
-            set_statement(&constants, &state_variables)(
-                "set button_pressed to true  0xabab~u16 from 0x0123~u16 if match event 0xbaba~u16;",
-            ),
+            set_statement(text)(text),
             Err(NomErr::Failure(ParserError::Base {
                 location: _,
                 kind: ErrorKind::Expected(Expectation::Keyword("on")),
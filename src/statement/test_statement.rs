@@ -0,0 +1,261 @@
+use nom::branch::alt;
+use nom::bytes::complete::take_until;
+use nom::character::complete::{multispace0, multispace1};
+use nom::combinator::{consumed, cut, map, opt};
+use nom::multi::{many0, separated_list1};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use nom::IResult;
+
+use crate::ast::{Span, TestStatement, TestStep};
+use crate::error::ParserError;
+use crate::expression::constant_expression;
+use crate::item::item_call;
+use crate::keyword::{
+    at_keyword, expect_keyword, from_keyword, inject_keyword, state_keyword, test_keyword,
+    with_keyword,
+};
+use crate::parser::{expression, name};
+use crate::symbol::{close_brace, comma, double_quote, equal_sign, open_brace, semicolon};
+
+pub fn test_statement<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, TestStatement, ParserError<&'a str>> {
+    move |text| {
+        let name_parser = consumed(delimited(double_quote, take_until("\""), double_quote));
+        let content_parser = delimited(
+            open_brace,
+            many0(preceded(multispace0, test_step(source))),
+            preceded(multispace0, close_brace),
+        );
+        let address_parser = preceded(
+            preceded(multispace1, at_keyword),
+            cut(preceded(multispace1, expression(source))),
+        );
+        let mut keyword_parser = preceded(
+            test_keyword,
+            cut(tuple((
+                preceded(multispace1, name_parser),
+                opt(address_parser),
+                preceded(multispace0, content_parser),
+            ))),
+        );
+
+        let (input, ((quoted_name, name), device_address, steps)) = keyword_parser(text)?;
+
+        Ok((
+            input,
+            TestStatement {
+                name: name.to_string(),
+                name_span: Span::of(source, quoted_name),
+                device_address,
+                steps,
+                span: Span::new(source, text, input),
+            },
+        ))
+    }
+}
+
+fn test_step<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, TestStep, ParserError<&'a str>> {
+    move |text| {
+        let data_parser = preceded(
+            preceded(multispace1, with_keyword),
+            cut(separated_list1(
+                preceded(multispace0, comma),
+                preceded(multispace0, expression(source)),
+            )),
+        );
+        let inject_parser = map(
+            terminated(
+                preceded(
+                    inject_keyword,
+                    cut(tuple((
+                        preceded(multispace1, expression(source)),
+                        preceded(multispace1, from_keyword),
+                        preceded(multispace1, expression(source)),
+                        opt(data_parser),
+                    ))),
+                ),
+                cut(preceded(multispace0, semicolon)),
+            ),
+            |(event_code, _, from_address, data)| TestStep::Inject {
+                event_code,
+                from_address,
+                data: data.unwrap_or_default(),
+                span: Span::default(),
+            },
+        );
+
+        let state_parser = map(
+            terminated(
+                preceded(
+                    state_keyword,
+                    cut(tuple((
+                        preceded(multispace1, name(source)),
+                        preceded(multispace0, pair(equal_sign, equal_sign)),
+                        preceded(multispace0, constant_expression(source)),
+                    ))),
+                ),
+                cut(preceded(multispace0, semicolon)),
+            ),
+            |(state_variable, _, value)| TestStep::ExpectState {
+                state_variable,
+                value,
+                span: Span::default(),
+            },
+        );
+        let packet_parser = map(item_call(source), |producer| TestStep::ExpectPacket {
+            producer,
+            span: Span::default(),
+        });
+        let expect_parser = preceded(
+            expect_keyword,
+            cut(preceded(multispace1, alt((state_parser, packet_parser)))),
+        );
+
+        let (input, step) = alt((inject_parser, expect_parser))(text)?;
+
+        Ok((input, step.with_span(Span::new(source, text, input))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+    use nom::Err as NomErr;
+
+    use crate::ast::Expression;
+    use crate::error::{ErrorKind, Expectation};
+    use crate::literal::Literal;
+
+    #[test]
+    fn inject_and_expect_test() {
+        let text = "test \"toggle light\" {
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0002~u16;
+                expect BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0xff~u8);
+                expect state active == true;
+            }input";
+
+        assert_matches!(
+            test_statement(text)(text),
+            Ok(("input", statement)) => {
+                assert_eq!(statement.name, "toggle light");
+                assert_eq!(statement.name_span, Span { start: 5, end: 19 });
+                assert_matches!(
+                    statement.steps.as_slice(),
+                    [
+                        TestStep::Inject {
+                            event_code: Expression::Constant(event_code),
+                            from_address: Expression::Literal { value: Literal::U16(0x0002), .. },
+                            data,
+                            span: inject_span,
+                        },
+                        TestStep::ExpectPacket { producer, .. },
+                        TestStep::ExpectState {
+                            state_variable,
+                            value: Expression::Literal { value: Literal::Bool(true), .. },
+                            span: state_span,
+                        },
+                    ] => {
+                        assert_eq!(event_code.value, "BUTTON_PRESSED_EVENT_CODE");
+                        assert!(data.is_empty());
+                        assert_eq!(*inject_span, Span { start: 38, end: 87 });
+                        assert_eq!(producer.name.value, "BcmChangeBrightnessProducer");
+                        assert_eq!(state_variable.value, "active");
+                        assert_eq!(*state_span, Span { start: 183, end: 211 });
+                    }
+                );
+                assert_eq!(statement.span, Span { start: 0, end: 225 });
+            }
+        );
+    }
+
+    #[test]
+    fn empty_test() {
+        let text = "test \"nothing\" {}input";
+
+        assert_matches!(
+            test_statement(text)(text),
+            Ok(("input", statement)) => {
+                assert_eq!(statement.name, "nothing");
+                assert!(statement.steps.is_empty());
+            }
+        );
+    }
+
+    #[test]
+    fn device_address_and_data_test() {
+        let text = "test \"message\" at 0x0001~u16 {
+                inject MESSAGE_EVENT_CODE from 0x0002~u16 with 0x0100~u16 , 0x01~u8;
+            }input";
+
+        assert_matches!(
+            test_statement(text)(text),
+            Ok(("input", statement)) => {
+                assert_matches!(
+                    statement.device_address,
+                    Some(Expression::Literal { value: Literal::U16(0x0001), .. })
+                );
+                assert_matches!(
+                    statement.steps.as_slice(),
+                    [TestStep::Inject { data, .. }] => {
+                        assert_matches!(
+                            data.as_slice(),
+                            [
+                                Expression::Literal { value: Literal::U16(0x0100), .. },
+                                Expression::Literal { value: Literal::U8(0x01), .. },
+                            ]
+                        );
+                    }
+                );
+            }
+        );
+    }
+
+    #[test]
+    fn missing_data_test() {
+        let text = "test \"a\" { inject BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 with; }input";
+
+        assert_matches!(
+            test_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base { location, .. })) => {
+                assert_eq!(location, "; }input");
+            }
+        );
+    }
+
+    #[test]
+    fn missing_from_test() {
+        let text = "test \"a\" { inject BUTTON_PRESSED_EVENT_CODE 0x0002~u16; }input";
+
+        assert_matches!(
+            test_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind: ErrorKind::Expected(Expectation::Keyword("from")),
+                ..
+            })) => {
+                assert_eq!(location, "0x0002~u16; }input");
+            }
+        );
+    }
+
+    #[test]
+    fn missing_comparison_test() {
+        let text = "test \"a\" { expect state active true; }input";
+
+        assert_matches!(
+            test_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind: ErrorKind::Expected(Expectation::Symbol('=')),
+                ..
+            })) => {
+                assert_eq!(location, "true; }input");
+            }
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use nom::character::complete::{multispace0, multispace1};
+use nom::combinator::cut;
+use nom::sequence::{delimited, pair, preceded, tuple};
+use nom::{Err as NomErr, IResult};
+
+use crate::ast::{FireExpr, Span, ToggleStatement};
+use crate::error::{ErrorKind, ParserError};
+use crate::keyword::{from_keyword, off_keyword, on_keyword, toggle_keyword};
+use crate::parser::{expression, name};
+use crate::statement::fire_statement::fire_statement;
+use crate::symbol::{close_brace, colon, open_brace, semicolon};
+
+type KeywordParser = fn(&str) -> IResult<&str, &str, ParserError<&str>>;
+
+pub fn toggle_statement<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, ToggleStatement, ParserError<&'a str>> {
+    move |text| {
+        let branches_parser = delimited(
+            open_brace,
+            pair(
+                preceded(multispace0, branch(source, on_keyword)),
+                preceded(multispace0, branch(source, off_keyword)),
+            ),
+            preceded(multispace0, close_brace),
+        );
+        let mut keyword_parser = preceded(
+            toggle_keyword,
+            cut(tuple((
+                preceded(multispace1, name(source)),
+                preceded(multispace1, on_keyword),
+                preceded(multispace1, expression(source)),
+                preceded(multispace1, from_keyword),
+                preceded(multispace1, expression(source)),
+                preceded(multispace0, branches_parser),
+            ))),
+        );
+
+        let (input, (state_variable, _, event_code, _, from_address, (on, off))) =
+            keyword_parser(text)?;
+
+        Ok((
+            input,
+            ToggleStatement {
+                state_variable,
+                event_code,
+                from_address,
+                on,
+                off,
+                span: Span::new(source, text, input),
+            },
+        ))
+    }
+}
+
+/// Parses `keyword: fire {..};`. The fire cannot have a condition, because the state variable
+/// is its condition.
+fn branch<'a>(
+    source: &'a str,
+    keyword_parser: KeywordParser,
+) -> impl FnMut(&'a str) -> IResult<&'a str, FireExpr, ParserError<&'a str>> {
+    move |text| {
+        let mut branch_parser = preceded(
+            keyword_parser,
+            cut(preceded(
+                pair(multispace0, colon),
+                preceded(multispace0, fire_statement(source)),
+            )),
+        );
+
+        let (input, fire_expr) = branch_parser(text)?;
+
+        if let Some(condition) = &fire_expr.condition {
+            return Err(NomErr::Failure(ParserError::Base {
+                location: &source[condition.span().start..],
+                kind: ErrorKind::ToggleCondition,
+                child: None,
+            }));
+        }
+
+        let (input, _) = cut(preceded(multispace0, semicolon))(input)?;
+
+        Ok((input, fire_expr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+
+    use crate::ast::Expression;
+    use crate::error::Expectation;
+    use crate::literal::Literal;
+
+    #[test]
+    fn on_and_off_branches_test() {
+        let text = "toggle active on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 {
+                on: fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0xff~u8); };
+                off: fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0x00~u8); };
+            }input";
+
+        assert_matches!(
+            toggle_statement(text)(text),
+            Ok(("input", statement)) => {
+                assert_eq!(statement.state_variable.value, "active");
+                assert_matches!(statement.event_code, Expression::Constant(event_code) => {
+                    assert_eq!(event_code.value, "BUTTON_PRESSED_EVENT_CODE");
+                });
+                assert_matches!(
+                    statement.from_address,
+                    Expression::Literal { value: Literal::U16(0x0002), .. }
+                );
+                assert_matches!(
+                    statement.on.producer.arguments.as_slice(),
+                    [_, _, Expression::Literal { value: Literal::U8(0xff), .. }]
+                );
+                assert_eq!(statement.on.span, Span { start: 81, end: 145 });
+                assert_matches!(
+                    statement.off.producer.arguments.as_slice(),
+                    [_, _, Expression::Literal { value: Literal::U8(0x00), .. }]
+                );
+                assert_eq!(statement.span, Span { start: 0, end: 247 });
+            }
+        );
+    }
+
+    #[test]
+    fn missing_off_test() {
+        let text = "toggle active on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 {
+                on: fire { NoneProducer(); };
+            }input";
+
+        assert_matches!(
+            toggle_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind: ErrorKind::Expected(Expectation::Keyword("off")),
+                ..
+            })) => {
+                assert_eq!(location, "}input");
+            }
+        );
+    }
+
+    #[test]
+    fn missing_colon_test() {
+        let text = "toggle active on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 {
+                on fire { NoneProducer(); };
+                off: fire { NoneProducer(); };
+            }input";
+
+        assert_matches!(
+            toggle_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind: ErrorKind::Expected(Expectation::Symbol(':')),
+                ..
+            })) => {
+                assert!(location.starts_with("fire"));
+            }
+        );
+    }
+
+    #[test]
+    fn condition_test() {
+        let text = "toggle active on BUTTON_PRESSED_EVENT_CODE from 0x0002~u16 {
+                on: fire { NoneProducer(); } if match tick;
+                off: fire { NoneProducer(); };
+            }input";
+
+        assert_matches!(
+            toggle_statement(text)(text),
+            Err(NomErr::Failure(ParserError::Base {
+                location,
+                kind: ErrorKind::ToggleCondition,
+                ..
+            })) => {
+                assert!(location.starts_with("match tick;"));
+            }
+        );
+    }
+}
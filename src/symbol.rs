@@ -65,10 +65,14 @@ macro_rules! implement_symbol_parser {
 implement_symbol_parser!(semicolon, ';');
 implement_symbol_parser!(tilde, '~');
 implement_symbol_parser!(comma, ',');
+implement_symbol_parser!(colon, ':');
 implement_symbol_parser!(open_parenthesis, '(');
 implement_symbol_parser!(close_parenthesis, ')');
 implement_symbol_parser!(open_brace, '{');
 implement_symbol_parser!(close_brace, '}');
+implement_symbol_parser!(open_bracket, '[');
+implement_symbol_parser!(close_bracket, ']');
 implement_symbol_parser!(equal_sign, '=');
 implement_symbol_parser!(double_quote, '"');
 implement_symbol_parser!(hashtag, '#');
+implement_symbol_parser!(exclamation_mark, '!');
@@ -0,0 +1,324 @@
+use std::fmt::{Display, Formatter};
+
+use ross_config::config::Config;
+use ross_config::producer::Producer;
+use ross_config::Value;
+
+use crate::ast::Span;
+use crate::decompiler::value_to_literal;
+use crate::diagnostic::Diagnostic;
+use crate::simulator::{Event, Match, Packet, SimulationError, Simulator};
+
+/// The address the device under test has, unless its test sets one with `at`.
+pub(crate) const DEFAULT_DEVICE_ADDRESS: u16 = 0x0000;
+
+/// A lowered `test` statement.
+#[derive(Debug)]
+pub struct Test {
+    pub name: String,
+    pub span: Span,
+    pub device_address: u16,
+    pub actions: Vec<Action>,
+}
+
+#[derive(Debug)]
+pub enum Action {
+    Inject {
+        event: Event,
+        span: Span,
+    },
+    ExpectPacket {
+        producer: Box<dyn Producer>,
+        span: Span,
+    },
+    ExpectState {
+        state_index: u32,
+        value: Value,
+        span: Span,
+    },
+}
+
+/// Why a step of a test failed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FailureKind {
+    /// None of the packets the last injected event sent is the expected one.
+    PacketNotSent,
+    /// The state variable has another value, which is held as source.
+    UnexpectedState(String),
+    Simulation(SimulationError),
+}
+
+impl Display for FailureKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FailureKind::PacketNotSent => write!(f, "expected packet was not sent"),
+            FailureKind::UnexpectedState(value) => write!(f, "state variable is {}", value),
+            FailureKind::Simulation(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// A step of a test that failed, located at the step.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TestFailure {
+    pub span: Span,
+    pub kind: FailureKind,
+}
+
+impl TestFailure {
+    /// Renders the failure against `source`, which is the text of `file`, the way diagnostics
+    /// are rendered.
+    pub fn render(&self, file: Option<&str>, source: &str) -> String {
+        Diagnostic::error(self.span.location(file, source), self.kind.to_string())
+            .with_span(self.span)
+            .render(source)
+    }
+}
+
+#[derive(Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub span: Span,
+    /// One error for each `expect` that failed, located at it. A test stops at the first event
+    /// that cannot be simulated.
+    pub failures: Vec<TestFailure>,
+    /// The event processors that matched the injected events, in order.
+    pub matches: Vec<Match>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+pub fn run_tests(config: &Config, tests: &[Test]) -> Vec<TestResult> {
+    tests.iter().map(|test| run_test(config, test)).collect()
+}
+
+/// Runs `test` against a device that starts with the initial state of `config`.
+pub fn run_test(config: &Config, test: &Test) -> TestResult {
+    let mut simulator = Simulator::new(config, test.device_address);
+    let mut injected: Option<(&Event, Vec<Packet>)> = None;
+    let mut failures = vec![];
+    let mut matches = vec![];
+
+    for action in test.actions.iter() {
+        let action_failure = match action {
+            Action::Inject { event, span } => match simulator.step_matches(event) {
+                Ok(event_matches) => {
+                    let packets = event_matches
+                        .iter()
+                        .flat_map(|matched| matched.packets.iter().cloned())
+                        .collect();
+
+                    injected = Some((event, packets));
+                    matches.extend(event_matches);
+                    None
+                }
+                Err(err) => {
+                    failures.push(failure(*span, FailureKind::Simulation(err)));
+                    break;
+                }
+            },
+            Action::ExpectPacket { producer, span } => {
+                let sent = match &injected {
+                    Some((event, packets)) => match simulator.expected_packet(producer, event) {
+                        Ok(Some(packet)) => packets.contains(&packet),
+                        Ok(None) => packets.is_empty(),
+                        Err(err) => {
+                            failures.push(failure(*span, FailureKind::Simulation(err)));
+                            break;
+                        }
+                    },
+                    None => false,
+                };
+
+                if sent {
+                    None
+                } else {
+                    Some(failure(*span, FailureKind::PacketNotSent))
+                }
+            }
+            Action::ExpectState {
+                state_index,
+                value,
+                span,
+            } => match simulator.state().get(state_index) {
+                Some(state_value) if state_value == value => None,
+                Some(state_value) => Some(failure(
+                    *span,
+                    FailureKind::UnexpectedState(value_to_literal(state_value).to_string()),
+                )),
+                None => Some(failure(
+                    *span,
+                    FailureKind::UnexpectedState("unset".to_string()),
+                )),
+            },
+        };
+
+        failures.extend(action_failure);
+    }
+
+    TestResult {
+        name: test.name.clone(),
+        span: test.span,
+        failures,
+        matches,
+    }
+}
+
+fn failure(span: Span, kind: FailureKind) -> TestFailure {
+    TestFailure { span, kind }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+
+    use crate::parser::{ParseOptions, Parser};
+
+    const TOGGLE: &str = "let active = false;
+        do {
+            match event BUTTON_PRESSED_EVENT_CODE;
+            match producer 0x0002~u16;
+            match { FlipStateFilter(active); }
+            fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0xff~u8); } if match {
+                StateEqualToConstFilter(active, true);
+            }
+        }
+        ";
+
+    fn run(tests: &str) -> Vec<TestResult> {
+        let text = format!("{}{}", TOGGLE, tests);
+        let parsed = Parser::parse_with(ParseOptions::text(&text).with_tests()).unwrap();
+
+        run_tests(&parsed.config, &parsed.tests.unwrap())
+    }
+
+    #[test]
+    fn passed_test() {
+        let results = run("test \"toggle light\" {
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0002;
+                expect BcmChangeBrightnessProducer(0x0003, 0, 0xff);
+                expect state active == true;
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0002;
+                expect NoneProducer();
+                expect state active == false;
+            }");
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "toggle light");
+        assert!(results[0].passed());
+    }
+
+    #[test]
+    fn failed_test() {
+        let tests = "test \"wrong device\" {
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0004;
+                expect BcmChangeBrightnessProducer(0x0003, 0, 0xff);
+                expect state active == true;
+            }";
+        let results = run(tests);
+        let offset = TOGGLE.len();
+
+        assert!(!results[0].passed());
+        assert_matches!(
+            results[0].failures.as_slice(),
+            [
+                TestFailure {
+                    span: packet_span,
+                    kind: FailureKind::PacketNotSent,
+                },
+                TestFailure {
+                    span: state_span,
+                    kind: FailureKind::UnexpectedState(value),
+                },
+            ] => {
+                assert_eq!(packet_span.start - offset, tests.find("expect Bcm").unwrap());
+                assert_eq!(state_span.start - offset, tests.find("expect state").unwrap());
+                assert_eq!(value, "false");
+            }
+        );
+    }
+
+    #[test]
+    fn name_with_slashes_test() {
+        let results = run("test \"on // off\" {
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0002;
+                expect state active == true;
+            }");
+
+        assert_eq!(results[0].name, "on // off");
+        assert!(results[0].passed());
+    }
+
+    #[test]
+    fn device_address_test() {
+        let results = run("test \"default\" {
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0002;
+            }
+            test \"kitchen\" at 0x0005 {
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0002;
+            }");
+        let producer_addresses: Vec<_> = results
+            .iter()
+            .map(|result| result.matches[0].packets[0].event.producer_address)
+            .collect();
+
+        assert_eq!(producer_addresses, vec![0x0000, 0x0005]);
+    }
+
+    #[test]
+    fn event_data_test() {
+        let text = "do {
+                match event BUTTON_PRESSED_EVENT_CODE;
+                match { ButtonIndexExtractor(); ValueEqualToConstFilter(0x01~u8); }
+                fire { MessageProducer(0x0003~u16, 0x0001~u16, true); }
+            }
+            test \"second button\" {
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0002 with 0x01~u8;
+                expect MessageProducer(0x0003, 0x0001~u16, true);
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0002 with 0x00~u8;
+                expect NoneProducer();
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0002;
+                expect NoneProducer();
+            }";
+        let parsed = Parser::parse_with(ParseOptions::text(text).with_tests()).unwrap();
+        let results = run_tests(&parsed.config, &parsed.tests.unwrap());
+
+        assert!(results[0].passed(), "{:?}", results[0].failures);
+    }
+
+    #[test]
+    fn expect_before_inject_test() {
+        let results = run("test \"nothing injected\" { expect NoneProducer(); }");
+
+        assert_matches!(
+            results[0].failures.as_slice(),
+            [TestFailure {
+                kind: FailureKind::PacketNotSent,
+                ..
+            }]
+        );
+    }
+
+    #[test]
+    fn render_failure_test() {
+        let tests = "test \"wrong device\" {
+                inject BUTTON_PRESSED_EVENT_CODE from 0x0004;
+                expect state active == true;
+            }";
+        let text = format!("{}{}", TOGGLE, tests);
+        let rendered = run(tests)[0].failures[0].render(Some("toggle.ross"), &text);
+
+        assert!(
+            rendered.starts_with("error: state variable is false\n  --> toggle.ross:12:17\n"),
+            "{}",
+            rendered
+        );
+        assert!(rendered.contains("expect state active == true;"));
+    }
+}
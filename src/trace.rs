@@ -0,0 +1,341 @@
+use nom::character::complete::space1;
+use nom::combinator::consumed;
+use nom::multi::separated_list1;
+use nom::Err as NomErr;
+use std::convert::{TryFrom, TryInto};
+
+use ross_config::config::Config;
+use ross_protocol::event::event_code::*;
+
+use crate::ast::Span;
+use crate::error::{ErrorKind, Expectation, ParserError};
+use crate::literal::{literal, Literal};
+use crate::location::Location;
+use crate::lower::SourceMap;
+use crate::simulator::{Event, EventData, Packet, SimulationError, Simulator};
+
+/// An event captured on the bus, `timestamp` milliseconds after the capture started.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TraceEntry {
+    pub timestamp: u32,
+    pub event: Event,
+}
+
+/// What the config did with an event of a trace.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReplayedEntry {
+    pub timestamp: u32,
+    pub event: Event,
+    pub matches: Vec<ReplayedMatch>,
+}
+
+/// An event processor that matched an event of a trace.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReplayedMatch {
+    /// The span of the statement the event processor comes from.
+    pub span: Span,
+    pub event_processor: usize,
+    /// The indices of the creators that ran.
+    pub creators: Vec<usize>,
+    pub packets: Vec<Packet>,
+}
+
+/// Parses a trace, which has an event on each line:
+///
+/// ```text
+/// timestamp event_code producer_address payload...
+/// ```
+///
+/// Fields are literals separated by spaces, with the timestamp in milliseconds. The payload
+/// depends on the event code:
+///
+/// - `BUTTON_PRESSED_EVENT_CODE` and `BUTTON_RELEASED_EVENT_CODE`: `index`
+/// - `MESSAGE_EVENT_CODE`: `code value`, where `value` needs a type
+/// - `BCM_CHANGE_BRIGHTNESS_EVENT_CODE`: `index value`
+/// - `BCM_ANIMATE_BRIGHTNESS_EVENT_CODE`: `index duration value`
+/// - `RELAY_SET_VALUE_EVENT_CODE`: `index value`
+///
+/// Other events have no payload. Empty lines and `//` comments are ignored.
+pub fn parse_trace(text: &str) -> Result<Vec<TraceEntry>, ParserError<Location>> {
+    parse_trace_lines(text)
+        .map_err(|err| err.map_location(&mut |location| Location::from_slice(None, text, location)))
+}
+
+/// Feeds the events of `trace` in order to a device at `device_address` that runs `config`,
+/// returning the event processors that matched each of them. `source_map` has to come from
+/// parsing `config`.
+pub fn replay(
+    config: &Config,
+    source_map: &SourceMap,
+    device_address: u16,
+    trace: &[TraceEntry],
+) -> Result<Vec<ReplayedEntry>, SimulationError> {
+    let mut simulator = Simulator::new(config, device_address);
+
+    trace
+        .iter()
+        .map(|entry| {
+            let matches = simulator
+                .step_matches(&entry.event)?
+                .into_iter()
+                .map(|matched| ReplayedMatch {
+                    span: source_map.event_processors[matched.event_processor],
+                    event_processor: matched.event_processor,
+                    creators: matched.creators,
+                    packets: matched.packets,
+                })
+                .collect();
+
+            Ok(ReplayedEntry {
+                timestamp: entry.timestamp,
+                event: entry.event.clone(),
+                matches,
+            })
+        })
+        .collect()
+}
+
+fn parse_trace_lines(text: &str) -> Result<Vec<TraceEntry>, ParserError<&str>> {
+    let mut entries = vec![];
+
+    for line in text.lines() {
+        let line = match line.find("//") {
+            Some(comment_start) => &line[..comment_start],
+            None => line,
+        };
+        let line = line.trim();
+
+        if !line.is_empty() {
+            entries.push(parse_entry(line)?);
+        }
+    }
+
+    Ok(entries)
+}
+
+fn parse_entry(line: &str) -> Result<TraceEntry, ParserError<&str>> {
+    let (input, fields) =
+        separated_list1(space1, consumed(literal))(line).map_err(|err| match err {
+            NomErr::Error(err) | NomErr::Failure(err) => err,
+            NomErr::Incomplete(_) => ParserError::Base {
+                location: line,
+                kind: ErrorKind::Expected(Expectation::Literal),
+                child: None,
+            },
+        })?;
+
+    if !input.is_empty() {
+        return Err(ParserError::Base {
+            location: input.trim_start(),
+            kind: ErrorKind::Expected(Expectation::Literal),
+            child: None,
+        });
+    }
+
+    let (timestamp, event_code, producer_address, payload) = match fields.as_slice() {
+        [timestamp, event_code, producer_address, payload @ ..] => {
+            (timestamp, event_code, producer_address, payload)
+        }
+        _ => {
+            return Err(ParserError::Base {
+                location: line,
+                kind: ErrorKind::Expected(Expectation::ArgumentCount(3, fields.len())),
+                child: None,
+            })
+        }
+    };
+    let event_code: u16 = field(event_code)?;
+
+    Ok(TraceEntry {
+        timestamp: field(timestamp)?,
+        event: Event {
+            event_code,
+            producer_address: field(producer_address)?,
+            data: event_data(line, event_code, payload)?,
+        },
+    })
+}
+
+fn event_data<'a>(
+    line: &'a str,
+    event_code: u16,
+    payload: &[(&'a str, Literal)],
+) -> Result<EventData, ParserError<&'a str>> {
+    let payload_length = match event_code {
+        BUTTON_PRESSED_EVENT_CODE | BUTTON_RELEASED_EVENT_CODE => 1,
+        MESSAGE_EVENT_CODE | BCM_CHANGE_BRIGHTNESS_EVENT_CODE | RELAY_SET_VALUE_EVENT_CODE => 2,
+        BCM_ANIMATE_BRIGHTNESS_EVENT_CODE => 3,
+        _ => 0,
+    };
+
+    if payload.len() != payload_length {
+        return Err(ParserError::Base {
+            location: line,
+            kind: ErrorKind::Expected(Expectation::ArgumentCount(
+                payload_length + 3,
+                payload.len() + 3,
+            )),
+            child: None,
+        });
+    }
+
+    let data = match (event_code, payload) {
+        (BUTTON_PRESSED_EVENT_CODE, [index]) | (BUTTON_RELEASED_EVENT_CODE, [index]) => {
+            EventData::Button {
+                index: field(index)?,
+            }
+        }
+        (MESSAGE_EVENT_CODE, [code, value]) => EventData::Message {
+            code: field(code)?,
+            value: field(value)?,
+        },
+        (BCM_CHANGE_BRIGHTNESS_EVENT_CODE, [index, value]) => EventData::BcmChangeBrightness {
+            index: field(index)?,
+            value: field(value)?,
+        },
+        (BCM_ANIMATE_BRIGHTNESS_EVENT_CODE, [index, duration, value]) => {
+            EventData::BcmAnimateBrightness {
+                index: field(index)?,
+                duration: field(duration)?,
+                value: field(value)?,
+            }
+        }
+        (RELAY_SET_VALUE_EVENT_CODE, [index, value]) => EventData::RelaySetValue {
+            index: field(index)?,
+            value: field(value)?,
+        },
+        _ => EventData::None,
+    };
+
+    Ok(data)
+}
+
+fn field<'a, T>((text, value): &(&'a str, Literal)) -> Result<T, ParserError<&'a str>>
+where
+    T: TryFrom<Literal, Error = ParserError<&'static str>>,
+{
+    value
+        .clone()
+        .try_into()
+        .map_err(|err: ParserError<&str>| err.relocate(*text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cool_asserts::assert_matches;
+    use ross_protocol::event::bcm::BcmValue;
+    use ross_protocol::event::message::MessageValue;
+
+    use crate::parser::{ParseOptions, Parser};
+
+    #[test]
+    fn parse_trace_test() {
+        let text = "// captured at the gateway
+            0 0x0007 0x0002 0
+
+            1500 0x000c 0x0004 0x0001 0x20~u8 // a message
+            1750 0x0009 0x0001
+            2000 0x000e 0x0004 1 250 #ff0000";
+
+        assert_eq!(
+            parse_trace(text).unwrap(),
+            vec![
+                TraceEntry {
+                    timestamp: 0,
+                    event: Event::button_pressed(0x0002, 0),
+                },
+                TraceEntry {
+                    timestamp: 1500,
+                    event: Event::message(0x0004, 0x0001, MessageValue::U8(0x20)),
+                },
+                TraceEntry {
+                    timestamp: 1750,
+                    event: Event::tick(0x0001),
+                },
+                TraceEntry {
+                    timestamp: 2000,
+                    event: Event {
+                        event_code: BCM_ANIMATE_BRIGHTNESS_EVENT_CODE,
+                        producer_address: 0x0004,
+                        data: EventData::BcmAnimateBrightness {
+                            index: 1,
+                            duration: 250,
+                            value: BcmValue::Rgb(0xff, 0x00, 0x00),
+                        },
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_trace_payload_length_test() {
+        let text = "0 0x0007 0x0002 0\n10 0x0007 0x0002";
+
+        assert_matches!(
+            parse_trace(text),
+            Err(ParserError::Base {
+                location,
+                kind: ErrorKind::Expected(Expectation::ArgumentCount(4, 3)),
+                ..
+            }) => {
+                assert_eq!(location.line, 2);
+                assert_eq!(location.column, 1);
+            }
+        );
+    }
+
+    #[test]
+    fn parse_trace_invalid_field_test() {
+        let text = "0 0x0007 0x10000 0";
+
+        assert_matches!(
+            parse_trace(text),
+            Err(ParserError::Base {
+                location,
+                kind: ErrorKind::IntegerOutOfRange("u16"),
+                ..
+            }) => {
+                assert_eq!(location.offset, 9);
+            }
+        );
+    }
+
+    #[test]
+    fn replay_test() {
+        let text = "let active = false;
+            send BUTTON_RELEASED_EVENT_CODE from 0x0002~u16 to 0x0003~u16;
+            do {
+                match event BUTTON_PRESSED_EVENT_CODE;
+                match { FlipStateFilter(active); }
+                fire { BcmChangeBrightnessProducer(0x0003~u16, 0~u8, 0xff~u8); } if match {
+                    StateEqualToConstFilter(active, true);
+                }
+            }";
+        let parsed = Parser::parse_with(ParseOptions::text(text).with_source_map()).unwrap();
+        let (config, source_map) = (parsed.config, parsed.source_map.unwrap());
+        let trace =
+            parse_trace("0 0x0007 0x0002 0\n100 0x0007 0x0002 0\n200 0x0008 0x0002 0").unwrap();
+        let replayed = replay(&config, &source_map, 0x0001, &trace).unwrap();
+        let do_span = Span {
+            start: text.find("do {").unwrap(),
+            end: text.len(),
+        };
+
+        assert_eq!(replayed.len(), 3);
+        assert_matches!(replayed[0].matches.as_slice(), [matched] => {
+            assert_eq!(matched.span, do_span);
+            assert_eq!(matched.packets.len(), 1);
+        });
+        assert_matches!(replayed[1].matches.as_slice(), [matched] => {
+            assert_eq!(matched.span, do_span);
+            assert!(matched.packets.is_empty());
+        });
+        assert_matches!(replayed[2].matches.as_slice(), [matched] => {
+            assert_eq!(&text[matched.span.start..matched.span.end], "send BUTTON_RELEASED_EVENT_CODE from 0x0002~u16 to 0x0003~u16;");
+            assert_eq!(matched.packets[0].event, trace[2].event);
+        });
+    }
+}